@@ -0,0 +1,249 @@
+//! Copying and pasting through the system clipboard, see [`Window::clipboard`](crate::window::Window::clipboard).
+//!
+//! Whatever's on the clipboard is offered in one or more formats, named by MIME types such as `text/plain`.
+//! Text and images get their own methods, which pick between the formats that apps commonly use for them.
+
+pub(crate) mod bmp;
+mod png;
+
+use crate::{
+    dpi::PhysicalSize,
+    error::{Error, ErrorKind},
+    window::Window,
+};
+use std::{fmt, time::Duration};
+
+/// The MIME type text is offered as, which every backend translates to and from its own text formats.
+pub(crate) const TEXT_MIME: &str = "text/plain;charset=utf-8";
+
+/// How long another app gets to hand over what's on the clipboard, before giving up on it.
+#[cfg_attr(any(not(target_os = "linux"), feature = "headless"), allow(dead_code))]
+pub(crate) const TRANSFER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The names of text formats from before MIME types, which Linux apps still ask for text as.
+///
+/// `UTF8_STRING` is the only one that's UTF-8, the rest are Latin-1 or meant to be ASCII, so they're only
+/// offered for text that's ASCII.
+#[cfg_attr(any(not(target_os = "linux"), feature = "headless"), allow(dead_code))]
+pub(crate) fn text_aliases(text: &[u8]) -> &'static [&'static str] {
+    if text.is_ascii() {
+        &["UTF8_STRING", "text/plain", "TEXT", "STRING"]
+    } else {
+        &["UTF8_STRING"]
+    }
+}
+
+/// Turns the names of the formats another app offers into MIME types, leaving out those that aren't.
+///
+/// `UTF8_STRING` is listed as [`TEXT_MIME`], which it's converted from.
+#[cfg_attr(any(not(target_os = "linux"), feature = "headless"), allow(dead_code))]
+pub(crate) fn mime_types(names: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut mime_types: Vec<String> = Vec::new();
+    for name in names {
+        let name = if name == "UTF8_STRING" {
+            TEXT_MIME.to_owned()
+        } else {
+            name
+        };
+        if name.contains('/') && !mime_types.contains(&name) {
+            mime_types.push(name);
+        }
+    }
+    mime_types
+}
+
+/// Which of the system's clipboards a [`Clipboard`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Selection {
+    /// The clipboard, which is copied to and pasted from explicitly.
+    Clipboard,
+
+    /// X11's primary selection, which is whatever was last selected, and is pasted with the middle mouse button.
+    Primary,
+}
+
+/// A clipboard, which is borrowed from the window that's used to talk to the system about it.
+///
+/// What's put on the clipboard is kept by the window for other apps to paste, so on Linux it's gone when the
+/// window closes, unless a clipboard manager took a copy. On Windows, it stays on the clipboard.
+///
+/// Getting what another app put on the clipboard waits for it to be handed over, which only takes a while
+/// for a lot of data, or if the app is stuck. If it takes longer than a few seconds, it fails with
+/// [`ErrorKind::TimedOut`].
+#[derive(Clone, Copy)]
+pub struct Clipboard<'a> {
+    window: &'a Window,
+    selection: Selection,
+}
+
+/// Something to put on the clipboard, see [`Clipboard::set`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClipboardData {
+    /// Text, which is offered in every text format there is.
+    Text(String),
+
+    /// An image, which is offered as `image/png` and `image/bmp`.
+    Image(ClipboardImage),
+
+    /// Anything else, as the MIME type it's offered as, and the data.
+    Custom(String, Vec<u8>),
+}
+
+/// An image on the clipboard, as 8-bit RGBA pixels.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct ClipboardImage {
+    rgba: Vec<u8>,
+    size: PhysicalSize,
+}
+
+impl<'a> Clipboard<'a> {
+    pub(crate) fn new(window: &'a Window, selection: Selection) -> Self {
+        Self { window, selection }
+    }
+
+    /// The MIME types of the formats on the clipboard, best first if the app that put them there said.
+    ///
+    /// Text formats that aren't MIME types are listed as `text/plain;charset=utf-8`, which they're converted to.
+    pub fn formats(&self) -> Result<Vec<String>, Error> {
+        self.window.imp.clipboard_formats(self.selection)
+    }
+
+    /// Gets the text on the clipboard, if there is any.
+    pub fn get_text(&self) -> Result<Option<String>, Error> {
+        if let Some(data) = self.get_data(TEXT_MIME)? {
+            return Ok(Some(String::from_utf8_lossy(&data).into_owned()))
+        }
+        // It's meant to be ASCII without a charset, but it's UTF-8 anywhere that matters
+        Ok(self
+            .get_data("text/plain")?
+            .map(|data| String::from_utf8_lossy(&data).into_owned()))
+    }
+
+    /// Gets the image on the clipboard, if there is one in a format that can be decoded.
+    ///
+    /// PNG images that are interlaced can't be.
+    pub fn get_image(&self) -> Result<Option<ClipboardImage>, Error> {
+        let formats = self.formats()?;
+        let image = if formats.iter().any(|x| x == "image/png") {
+            self.get_data("image/png")?.and_then(|data| png::decode(&data))
+        } else {
+            None
+        };
+        match image {
+            Some(image) => Ok(Some(image)),
+            None if formats.iter().any(|x| x == "image/bmp") => {
+                Ok(self.get_data("image/bmp")?.and_then(|data| bmp::decode(&data)))
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Gets the clipboard's contents in the format named by a MIME type, if it's on offer.
+    pub fn get_data(&self, mime: &str) -> Result<Option<Vec<u8>>, Error> {
+        self.window.imp.clipboard_get(self.selection, mime)
+    }
+
+    /// Puts text on the clipboard, replacing whatever was on it.
+    pub fn set_text(&self, text: impl Into<String>) -> Result<(), Error> {
+        self.set(Some(ClipboardData::Text(text.into())))
+    }
+
+    /// Puts an image on the clipboard, replacing whatever was on it.
+    pub fn set_image(&self, image: ClipboardImage) -> Result<(), Error> {
+        self.set(Some(ClipboardData::Image(image)))
+    }
+
+    /// Puts data in the format named by a MIME type on the clipboard, replacing whatever was on it.
+    pub fn set_data(&self, mime: impl Into<String>, data: impl Into<Vec<u8>>) -> Result<(), Error> {
+        self.set(Some(ClipboardData::Custom(mime.into(), data.into())))
+    }
+
+    /// Puts the same thing on the clipboard in several formats at once, replacing whatever was on it.
+    ///
+    /// Apps pasting it pick whichever format suits them best, so they should be in order of preference.
+    /// If a MIME type is given more than once, the first one wins.
+    pub fn set(&self, data: impl IntoIterator<Item = ClipboardData>) -> Result<(), Error> {
+        let mut formats: Vec<(String, Vec<u8>)> = Vec::new();
+        for data in data {
+            let new: Vec<(String, Vec<u8>)> = match data {
+                ClipboardData::Text(text) => vec![(TEXT_MIME.into(), text.into_bytes())],
+                ClipboardData::Image(image) => vec![
+                    ("image/png".into(), png::encode(&image)),
+                    ("image/bmp".into(), bmp::encode(&image)),
+                ],
+                ClipboardData::Custom(mime, data) => {
+                    if mime.is_empty() {
+                        return Err(Error::new(
+                            ErrorKind::InvalidInput,
+                            "clipboard formats need a MIME type",
+                        ))
+                    }
+                    vec![(mime, data)]
+                },
+            };
+            for (mime, data) in new {
+                if !formats.iter().any(|x| x.0 == mime) {
+                    formats.push((mime, data));
+                }
+            }
+        }
+        if formats.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "nothing to put on the clipboard"))
+        }
+        self.window.imp.clipboard_set(self.selection, formats)
+    }
+
+    /// Empties the clipboard, if what's on it came from this window.
+    pub fn clear(&self) -> Result<(), Error> {
+        self.window.imp.clipboard_set(self.selection, Vec::new())
+    }
+}
+
+impl fmt::Debug for Clipboard<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Clipboard")
+            .field("selection", &self.selection)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ClipboardImage {
+    /// Makes an image from 8-bit RGBA pixels, row by row from the top-left, without premultiplied alpha.
+    ///
+    /// Fails with [`ErrorKind::InvalidInput`] if there isn't exactly one pixel for each point of `size`.
+    pub fn from_rgba(rgba: impl Into<Vec<u8>>, size: PhysicalSize) -> Result<Self, Error> {
+        let rgba = rgba.into();
+        let expected = u64::from(size.width) * u64::from(size.height) * 4;
+        if size.width == 0 || size.height == 0 || rgba.len() as u64 != expected {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "image pixels don't match the size given",
+            ))
+        }
+        Ok(Self { rgba, size })
+    }
+
+    /// The size of the image in pixels.
+    pub fn size(&self) -> PhysicalSize {
+        self.size
+    }
+
+    /// The pixels as 8-bit RGBA.
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    /// Takes the pixels, as 8-bit RGBA.
+    pub fn into_rgba(self) -> Vec<u8> {
+        self.rgba
+    }
+}
+
+// The pixels aren't worth printing
+impl fmt::Debug for ClipboardImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClipboardImage")
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,231 @@
+//! Just enough BMP for clipboard images, which is what Windows has them as, and what some Linux apps offer.
+//!
+//! Files are a 14 byte header followed by a DIB, which is how Win32 passes bitmaps around on its own.
+//! Decoding handles 24 and 32-bit images, which is what anything copied in the last two decades is.
+
+use super::ClipboardImage;
+use crate::dpi::PhysicalSize;
+
+const FILE_HEADER_LEN: usize = 14;
+const V5_HEADER_LEN: usize = 124;
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+
+// Images past this are refused, which is about as big as a screenshot of eight 8K monitors
+const MAX_DIMENSION: usize = 1 << 15;
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Encodes an image as a 32-bit BMP file with a `BITMAPV5HEADER`, which is the only kind that has alpha.
+pub fn encode(image: &ClipboardImage) -> Vec<u8> {
+    let PhysicalSize { width, height } = image.size;
+    let pixels_len = width as usize * height as usize * 4;
+    let mut bmp = Vec::with_capacity(FILE_HEADER_LEN + V5_HEADER_LEN + pixels_len);
+
+    // BITMAPFILEHEADER: magic, file size, two reserved fields, then where the pixels start
+    bmp.extend_from_slice(b"BM");
+    bmp.extend_from_slice(&((FILE_HEADER_LEN + V5_HEADER_LEN + pixels_len) as u32).to_le_bytes());
+    bmp.extend_from_slice(&[0; 4]);
+    bmp.extend_from_slice(&((FILE_HEADER_LEN + V5_HEADER_LEN) as u32).to_le_bytes());
+
+    // BITMAPV5HEADER, with a positive height for bottom-up rows as that's what some apps expect
+    for value in [V5_HEADER_LEN as u32, width, height] {
+        bmp.extend_from_slice(&value.to_le_bytes());
+    }
+    bmp.extend_from_slice(&1u16.to_le_bytes());
+    bmp.extend_from_slice(&32u16.to_le_bytes());
+    for value in [BI_BITFIELDS, pixels_len as u32, 2835, 2835, 0, 0] {
+        bmp.extend_from_slice(&value.to_le_bytes());
+    }
+    // The red, green, blue and alpha masks, then the colour space, which is sRGB ('sRGB' as a `u32`)
+    for value in [0x00FF0000u32, 0x0000FF00, 0x000000FF, 0xFF000000, 0x73524742] {
+        bmp.extend_from_slice(&value.to_le_bytes());
+    }
+    // Endpoints and gamma (unused for sRGB), then the rendering intent (LCS_GM_IMAGES), profile, and reserved
+    bmp.extend_from_slice(&[0; 48]);
+    for value in [4u32, 0, 0, 0] {
+        bmp.extend_from_slice(&value.to_le_bytes());
+    }
+
+    for row in image.rgba.chunks_exact(width as usize * 4).rev() {
+        for pixel in row.chunks_exact(4) {
+            bmp.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+        }
+    }
+    bmp
+}
+
+/// Decodes a BMP file, or `None` if it's broken or uses something that isn't supported.
+pub fn decode(bmp: &[u8]) -> Option<ClipboardImage> {
+    if !bmp.starts_with(b"BM") {
+        return None
+    }
+    let offset = read_u32(bmp, 10)? as usize;
+    let dib = bmp.get(FILE_HEADER_LEN..)?;
+    decode_dib(dib, offset.checked_sub(FILE_HEADER_LEN)?)
+}
+
+/// Turns a DIB into a BMP file by putting the file header in front of it.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub fn file_from_dib(dib: &[u8]) -> Option<Vec<u8>> {
+    let pixels_offset = FILE_HEADER_LEN + pixels_offset(dib)?;
+    let mut bmp = Vec::with_capacity(FILE_HEADER_LEN + dib.len());
+    bmp.extend_from_slice(b"BM");
+    bmp.extend_from_slice(&((FILE_HEADER_LEN + dib.len()) as u32).to_le_bytes());
+    bmp.extend_from_slice(&[0; 4]);
+    bmp.extend_from_slice(&(pixels_offset as u32).to_le_bytes());
+    bmp.extend_from_slice(dib);
+    Some(bmp)
+}
+
+/// Strips the file header off a BMP file, leaving the DIB that Win32 wants.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub fn dib_from_file(bmp: &[u8]) -> Option<&[u8]> {
+    bmp.strip_prefix(b"BM")?.get(FILE_HEADER_LEN - 2..)
+}
+
+/// Works out where the pixels start in a DIB, which is after the header, the bit masks, and the palette.
+///
+/// Masks after the header are only there for `BI_BITFIELDS` with a `BITMAPINFOHEADER`, as newer headers have them.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn pixels_offset(dib: &[u8]) -> Option<usize> {
+    let header_len = read_u32(dib, 0)? as usize;
+    let bit_count = read_u16(dib, 14)?;
+    let compression = read_u32(dib, 16)?;
+    let masks_len = if header_len == 40 && compression == BI_BITFIELDS {
+        12
+    } else {
+        0
+    };
+    let palette_len = match (bit_count, read_u32(dib, 32)?) {
+        (1 | 4 | 8, 0) => 4usize << bit_count,
+        (_, used) => used as usize * 4,
+    };
+    Some(header_len + masks_len + palette_len)
+}
+
+/// Decodes a DIB whose pixels start at `offset`.
+fn decode_dib(dib: &[u8], offset: usize) -> Option<ClipboardImage> {
+    let header_len = read_u32(dib, 0)? as usize;
+    if header_len < 40 {
+        return None
+    }
+    let width = read_u32(dib, 4)? as i32;
+    let height = read_u32(dib, 8)? as i32;
+    let bit_count = read_u16(dib, 14)?;
+    let compression = read_u32(dib, 16)?;
+    let bottom_up = height > 0;
+    let (width, height) = (width.unsigned_abs() as usize, height.unsigned_abs() as usize);
+    if width == 0 || height == 0 || width > MAX_DIMENSION || height > MAX_DIMENSION {
+        return None
+    }
+
+    // Each channel is where its mask says, scaled up to 8 bits if its mask is narrower
+    let masks: [u32; 4] = match (compression, bit_count) {
+        (BI_RGB, 24 | 32) => [0x00FF0000, 0x0000FF00, 0x000000FF, 0],
+        (BI_BITFIELDS, 32) => {
+            // The masks straight after a `BITMAPINFOHEADER` are where newer headers have them anyway,
+            // but only newer headers have room for an alpha mask
+            let alpha = if header_len >= 56 { read_u32(dib, 52)? } else { 0 };
+            [read_u32(dib, 40)?, read_u32(dib, 44)?, read_u32(dib, 48)?, alpha]
+        },
+        _ => return None,
+    };
+    let channel = |value: u32, mask: u32| -> u8 {
+        if mask == 0 {
+            return 0
+        }
+        let bits = (value & mask) >> mask.trailing_zeros();
+        let max = mask >> mask.trailing_zeros();
+        (u64::from(bits) * 255 / u64::from(max)) as u8
+    };
+
+    let bytes_per_pixel = usize::from(bit_count / 8);
+    let stride = (width * bytes_per_pixel).div_ceil(4) * 4;
+    let pixels = dib.get(offset..offset.checked_add(stride * height)?)?;
+    let read_pixel = |row: &[u8], x: usize| -> u32 {
+        let bytes = &row[x * bytes_per_pixel..][..bytes_per_pixel];
+        bytes.iter().rev().fold(0, |value, &byte| value << 8 | u32::from(byte))
+    };
+    // 32-bit images without an alpha mask usually leave the fourth byte empty, but some put alpha there anyway
+    let alpha_mask = match masks[3] {
+        0 if bit_count == 32 &&
+            compression == BI_RGB &&
+            pixels
+                .chunks_exact(stride)
+                .any(|row| row[..width * 4].iter().skip(3).step_by(4).any(|&a| a != 0)) =>
+        {
+            0xFF000000
+        },
+        mask => mask,
+    };
+
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        let y = if bottom_up { height - 1 - y } else { y };
+        let row = &pixels[y * stride..][..stride];
+        for x in 0..width {
+            let value = read_pixel(row, x);
+            let a = if alpha_mask == 0 {
+                0xFF
+            } else {
+                channel(value, alpha_mask)
+            };
+            rgba.extend_from_slice(&[
+                channel(value, masks[0]),
+                channel(value, masks[1]),
+                channel(value, masks[2]),
+                a,
+            ]);
+        }
+    }
+    Some(ClipboardImage {
+        rgba,
+        size: PhysicalSize::new(width as u32, height as u32),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoded_images_decode() {
+        let size = PhysicalSize::new(3, 2);
+        let rgba: Vec<u8> = (0..24).map(|x| x * 10).collect();
+        let image = ClipboardImage::from_rgba(rgba, size).unwrap();
+        let bmp = encode(&image);
+        assert_eq!(decode(&bmp), Some(image.clone()));
+        // What Win32 hands over is the same without the file header
+        let dib = dib_from_file(&bmp).unwrap();
+        assert_eq!(file_from_dib(dib), Some(bmp));
+    }
+
+    #[test]
+    fn old_dibs_decode() {
+        // A top-down 24-bit `BITMAPINFOHEADER` image, with rows padded to 4 bytes
+        let mut dib = Vec::new();
+        for value in [40u32, 2, -2i32 as u32] {
+            dib.extend_from_slice(&value.to_le_bytes());
+        }
+        dib.extend_from_slice(&[1, 0, 24, 0]);
+        dib.extend_from_slice(&[0; 24]);
+        dib.extend_from_slice(&[1, 2, 3, 4, 5, 6, 0, 0, 7, 8, 9, 10, 11, 12, 0, 0]);
+        let image = decode(&file_from_dib(&dib).unwrap()).unwrap();
+        assert_eq!(image.size(), PhysicalSize::new(2, 2));
+        #[rustfmt::skip]
+        assert_eq!(image.rgba(), [
+            3, 2, 1, 255,   6, 5, 4, 255,
+            9, 8, 7, 255,   12, 11, 10, 255,
+        ]);
+        assert_eq!(decode(&file_from_dib(&dib[..50]).unwrap()), None);
+    }
+}
@@ -0,0 +1,447 @@
+//! Just enough PNG for clipboard images, which is what most apps offer and look for.
+//!
+//! Encoding doesn't bother compressing, as the image only goes between apps on the same machine.
+//! Decoding handles every colour type at 8 or 16 bits (and palettes at any depth), but not interlacing.
+
+use super::ClipboardImage;
+use crate::dpi::PhysicalSize;
+
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+// Images past this are refused, as the decoded pixels could be far bigger than the file
+const MAX_PIXELS: usize = 1 << 28;
+
+/// Encodes an image as an RGBA PNG, with the pixel data stored rather than compressed.
+pub fn encode(image: &ClipboardImage) -> Vec<u8> {
+    let PhysicalSize { width, height } = image.size;
+    let stride = width as usize * 4;
+
+    // Every row starts with its filter type, which is 0 (none)
+    let mut raw = Vec::with_capacity((stride + 1) * height as usize);
+    for row in image.rgba.chunks_exact(stride) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    // zlib: a header for deflate with a 32K window, stored blocks of up to 65535 bytes, then an Adler-32
+    let mut zlib = Vec::with_capacity(raw.len() + raw.len() / 65535 * 5 + 11);
+    zlib.extend_from_slice(&[0x78, 0x01]);
+    let mut blocks = raw.chunks(0xFFFF).peekable();
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        zlib.push(u8::from(blocks.peek().is_none()));
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut png = SIGNATURE.to_vec();
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per channel, RGBA, and the only compression, filter and interlace methods there are
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib);
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    // Sums can go this long without overflowing before they have to be reduced
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    b << 16 | a
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                0xEDB88320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Decodes a PNG, or `None` if it's broken or uses something that isn't supported.
+///
+/// Checksums aren't checked, as a broken image from the clipboard would only show up as a broken image.
+pub fn decode(png: &[u8]) -> Option<ClipboardImage> {
+    let mut rest = png.strip_prefix(SIGNATURE)?;
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency: &[u8] = &[];
+    let mut zlib = Vec::new();
+    while rest.len() >= 12 {
+        let len = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let kind = &rest[4..8];
+        let data = rest.get(8..8 + len)?;
+        rest = rest.get(12 + len..)?;
+        match kind {
+            b"IHDR" if data.len() == 13 => header = Some(data),
+            b"PLTE" => palette = data,
+            b"tRNS" => transparency = data,
+            b"IDAT" => zlib.extend_from_slice(data),
+            b"IEND" => break,
+            _ => (),
+        }
+    }
+    let header = header?;
+    let width = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+    let height = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+    let (depth, color_type, interlace) = (header[8], header[9], header[12]);
+    if width == 0 || height == 0 || interlace != 0 || width as usize * height as usize > MAX_PIXELS {
+        return None
+    }
+    let channels = match (color_type, depth) {
+        (0, 1 | 2 | 4 | 8 | 16) => 1,
+        (2, 8 | 16) => 3,
+        (3, 1 | 2 | 4 | 8) => 1,
+        (4, 8 | 16) => 2,
+        (6, 8 | 16) => 4,
+        _ => return None,
+    };
+    let (width, height) = (width as usize, height as usize);
+    let bits_per_pixel = channels * usize::from(depth);
+    let stride = (width * bits_per_pixel).div_ceil(8);
+    // Filters work on whole bytes, going back one pixel or one byte if they're smaller than that
+    let filter_step = bits_per_pixel.div_ceil(8);
+
+    // zlib's two byte header, which has to say deflate, then the compressed data
+    let (cmf, flags) = (*zlib.first()?, *zlib.get(1)?);
+    if cmf & 0x0F != 8 || (u16::from(cmf) << 8 | u16::from(flags)) % 31 != 0 || flags & 0x20 != 0 {
+        return None
+    }
+    let raw = inflate(&zlib[2..], (stride + 1) * height)?;
+    if raw.len() < (stride + 1) * height {
+        return None
+    }
+
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    let mut previous = vec![0u8; stride];
+    let mut row = vec![0u8; stride];
+    for line in raw.chunks_exact(stride + 1).take(height) {
+        row.copy_from_slice(&line[1..]);
+        unfilter(line[0], &mut row, &previous, filter_step)?;
+        for x in 0..width {
+            // 16-bit samples only keep their high byte, and smaller ones are scaled up to fill a byte
+            let sample = |i: usize| -> u8 {
+                match depth {
+                    16 => row[(x * channels + i) * 2],
+                    8 => row[x * channels + i],
+                    _ => {
+                        let bit = x * usize::from(depth);
+                        let value = (row[bit / 8] >> (8 - usize::from(depth) - bit % 8)) & ((1 << depth) - 1);
+                        if color_type == 3 {
+                            value
+                        } else {
+                            (u32::from(value) * 255 / ((1 << depth) - 1)) as u8
+                        }
+                    },
+                }
+            };
+            let pixel = match color_type {
+                0 => [sample(0), sample(0), sample(0), 0xFF],
+                2 => [sample(0), sample(1), sample(2), 0xFF],
+                3 => {
+                    let index = usize::from(sample(0));
+                    let color = palette.get(index * 3..index * 3 + 3)?;
+                    [
+                        color[0],
+                        color[1],
+                        color[2],
+                        transparency.get(index).copied().unwrap_or(0xFF),
+                    ]
+                },
+                4 => [sample(0), sample(0), sample(0), sample(1)],
+                _ => [sample(0), sample(1), sample(2), sample(3)],
+            };
+            rgba.extend_from_slice(&pixel);
+        }
+        std::mem::swap(&mut previous, &mut row);
+    }
+    Some(ClipboardImage {
+        rgba,
+        size: PhysicalSize::new(width as u32, height as u32),
+    })
+}
+
+/// Undoes a row's filter in place, given the row above it (which is zeroes for the first one).
+fn unfilter(filter: u8, row: &mut [u8], previous: &[u8], step: usize) -> Option<()> {
+    for i in 0..row.len() {
+        let left = if i >= step { row[i - step] } else { 0 };
+        let up = previous[i];
+        let up_left = if i >= step { previous[i - step] } else { 0 };
+        let predicted = match filter {
+            0 => 0,
+            1 => left,
+            2 => up,
+            3 => ((u16::from(left) + u16::from(up)) / 2) as u8,
+            4 => {
+                let p = i16::from(left) + i16::from(up) - i16::from(up_left);
+                let (pa, pb, pc) = (
+                    (p - i16::from(left)).abs(),
+                    (p - i16::from(up)).abs(),
+                    (p - i16::from(up_left)).abs(),
+                );
+                if pa <= pb && pa <= pc {
+                    left
+                } else if pb <= pc {
+                    up
+                } else {
+                    up_left
+                }
+            },
+            _ => return None,
+        };
+        row[i] = row[i].wrapping_add(predicted);
+    }
+    Some(())
+}
+
+/// Reads a deflate stream one bit at a time, least significant first.
+struct Bits<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl Bits<'_> {
+    fn bit(&mut self) -> Option<u32> {
+        let byte = *self.data.get(self.position / 8)?;
+        let bit = (byte >> (self.position % 8)) & 1;
+        self.position += 1;
+        Some(u32::from(bit))
+    }
+
+    fn bits(&mut self, count: u32) -> Option<u32> {
+        let mut value = 0;
+        for i in 0..count {
+            value |= self.bit()? << i;
+        }
+        Some(value)
+    }
+}
+
+/// A canonical Huffman code, as the number of codes of each length and the symbols in order of their codes.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[usize::from(len)] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0u16; 16];
+        for len in 1..16 {
+            offsets[len] = offsets[len - 1] + counts[len - 1];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[usize::from(offsets[usize::from(len)])] = symbol as u16;
+                offsets[usize::from(len)] += 1;
+            }
+        }
+        Self { counts, symbols }
+    }
+
+    fn decode(&self, bits: &mut Bits<'_>) -> Option<u16> {
+        // Codes are packed most significant bit first, unlike everything else
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..16 {
+            code |= bits.bit()? as i32;
+            let count = i32::from(self.counts[len]);
+            if code - count < first {
+                return self.symbols.get((index + code - first) as usize).copied()
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        None
+    }
+}
+
+/// Decompresses a deflate stream, giving up if it would come to more than `limit` bytes.
+fn inflate(data: &[u8], limit: usize) -> Option<Vec<u8>> {
+    const LENGTH_BASE: [u16; 29] = [
+        3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227,
+        258,
+    ];
+    const LENGTH_EXTRA: [u8; 29] = [
+        0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+    ];
+    const DISTANCE_BASE: [u16; 30] = [
+        1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097,
+        6145, 8193, 12289, 16385, 24577,
+    ];
+    const DISTANCE_EXTRA: [u8; 30] = [
+        0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+    ];
+    // The order the lengths of the code length code are sent in
+    const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+    let mut bits = Bits { data, position: 0 };
+    let mut out = Vec::new();
+    loop {
+        let last = bits.bit()? == 1;
+        let (literals, distances) = match bits.bits(2)? {
+            0 => {
+                // Stored, from the next byte boundary: length, its complement, then the bytes
+                let start = bits.position.div_ceil(8);
+                let header = data.get(start..start + 4)?;
+                let len = usize::from(u16::from_le_bytes([header[0], header[1]]));
+                if len != usize::from(!u16::from_le_bytes([header[2], header[3]])) || out.len() + len > limit {
+                    return None
+                }
+                out.extend_from_slice(data.get(start + 4..start + 4 + len)?);
+                bits.position = (start + 4 + len) * 8;
+                if last {
+                    return Some(out)
+                }
+                continue
+            },
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+            },
+            2 => {
+                let literal_count = bits.bits(5)? as usize + 257;
+                let distance_count = bits.bits(5)? as usize + 1;
+                let code_length_count = bits.bits(4)? as usize + 4;
+                let mut code_lengths = [0u8; 19];
+                for &i in &CODE_LENGTH_ORDER[..code_length_count] {
+                    code_lengths[i] = bits.bits(3)? as u8;
+                }
+                let code_length_code = Huffman::new(&code_lengths);
+                let mut lengths = Vec::with_capacity(literal_count + distance_count);
+                while lengths.len() < literal_count + distance_count {
+                    let (value, repeat) = match code_length_code.decode(&mut bits)? {
+                        symbol @ 0..=15 => (symbol as u8, 1),
+                        16 => (*lengths.last()?, 3 + bits.bits(2)?),
+                        17 => (0, 3 + bits.bits(3)?),
+                        _ => (0, 11 + bits.bits(7)?),
+                    };
+                    lengths.extend((0..repeat).map(|_| value));
+                }
+                if lengths.len() != literal_count + distance_count {
+                    return None
+                }
+                (
+                    Huffman::new(&lengths[..literal_count]),
+                    Huffman::new(&lengths[literal_count..]),
+                )
+            },
+            _ => return None,
+        };
+        loop {
+            let symbol = literals.decode(&mut bits)?;
+            match symbol {
+                0..=255 => out.push(symbol as u8),
+                256 => break,
+                _ => {
+                    let i = usize::from(symbol - 257);
+                    let len = usize::from(*LENGTH_BASE.get(i)?) + bits.bits(LENGTH_EXTRA[i].into())? as usize;
+                    let i = usize::from(distances.decode(&mut bits)?);
+                    let distance = usize::from(*DISTANCE_BASE.get(i)?) + bits.bits(DISTANCE_EXTRA[i].into())? as usize;
+                    if distance > out.len() {
+                        return None
+                    }
+                    // The copy can overlap what it's copying, repeating it
+                    let start = out.len() - distance;
+                    for i in 0..len {
+                        out.push(out[start + i]);
+                    }
+                },
+            }
+            if out.len() > limit {
+                return None
+            }
+        }
+        if last {
+            return Some(out)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(name: &str) -> Vec<u8> {
+        std::fs::read(format!("{}/testdata/clipboard/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+    }
+
+    #[test]
+    fn encoded_images_decode() {
+        // Big enough to take more than one stored block
+        let size = PhysicalSize::new(200, 100);
+        let rgba: Vec<u8> = (0..size.width * size.height * 4).map(|x| (x % 251) as u8).collect();
+        let image = ClipboardImage::from_rgba(rgba, size).unwrap();
+        assert_eq!(decode(&encode(&image)), Some(image));
+    }
+
+    #[test]
+    fn compressed_images_decode() {
+        // Made with zlib, using every filter and both fixed and dynamic Huffman codes between them
+        #[rustfmt::skip]
+        let expected = [
+            255, 0, 0, 255,   0, 255, 0, 128,
+            0, 0, 255, 0,     255, 255, 255, 255,
+        ];
+        for name in ["rgba-sub-paeth.png", "rgba-up-average.png"] {
+            let image = decode(&read(name)).unwrap();
+            assert_eq!(image.size(), PhysicalSize::new(2, 2));
+            assert_eq!(image.rgba(), expected);
+        }
+        let image = decode(&read("palette.png")).unwrap();
+        assert_eq!(image.size(), PhysicalSize::new(3, 1));
+        assert_eq!(image.rgba(), [10, 20, 30, 255, 40, 50, 60, 0, 10, 20, 30, 255]);
+        let image = decode(&read("gradient.png")).unwrap();
+        let expected: Vec<u8> = (0..64 * 64)
+            .flat_map(|i| {
+                let (x, y) = ((i % 64) as u8, (i / 64) as u8);
+                [x * 4, y * 4, x ^ y, 255]
+            })
+            .collect();
+        assert_eq!(image.rgba(), expected);
+    }
+
+    #[test]
+    fn broken_images_are_rejected() {
+        let data = read("gradient.png");
+        assert_eq!(decode(&data[..data.len() / 2]), None);
+        assert_eq!(decode(&data[1..]), None);
+    }
+}
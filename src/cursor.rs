@@ -0,0 +1,176 @@
+//! Mouse cursors, either one of the system's or made from pixels.
+
+use crate::{
+    dpi::PhysicalSize,
+    error::{Error, ErrorKind},
+};
+use std::{fmt, sync::Arc};
+
+/// What the cursor looks like over a window, see [`Window::set_cursor`](crate::window::Window::set_cursor).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Cursor {
+    /// One of the system's cursors, which follow the user's theme.
+    Shape(CursorShape),
+
+    /// A cursor made from pixels.
+    Custom(CustomCursor),
+}
+
+/// The standard cursors, named after what they're for.
+///
+/// Not every system has a cursor for each of these, in which case the closest one is shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CursorShape {
+    /// The normal arrow, which is the default.
+    Arrow,
+
+    /// A pointing hand, for links and the like.
+    Hand,
+
+    /// An I-beam, for text that can be selected or edited.
+    Text,
+
+    /// Crosshairs, for picking something precisely.
+    Crosshair,
+
+    /// Something is happening, and the window can't be interacted with until it's done.
+    Wait,
+
+    /// Something is happening in the background, but the window can still be used.
+    Progress,
+
+    /// Help is available for what's under the cursor.
+    Help,
+
+    /// What's under the cursor can't be used.
+    NotAllowed,
+
+    /// Something can be moved in any direction.
+    Move,
+
+    /// Something can be resized left & right.
+    ResizeHorizontal,
+
+    /// Something can be resized up & down.
+    ResizeVertical,
+
+    /// Something can be resized from its top-left or bottom-right corner.
+    ResizeTopLeftBottomRight,
+
+    /// Something can be resized from its top-right or bottom-left corner.
+    ResizeTopRightBottomLeft,
+}
+
+/// A cursor made from RGBA pixels, with a hotspot marking the pixel that points.
+///
+/// It's cheap to clone, as the pixels are shared.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct CustomCursor {
+    pub(crate) rgba: Arc<[u8]>,
+    pub(crate) size: PhysicalSize,
+    pub(crate) hotspot: (u32, u32),
+}
+
+impl CustomCursor {
+    /// Makes a cursor from 8-bit RGBA pixels, row by row from the top-left, without premultiplied alpha.
+    ///
+    /// Fails with [`ErrorKind::InvalidInput`] if there isn't exactly one pixel for each point of `size`,
+    /// or the hotspot isn't one of them.
+    pub fn from_rgba(rgba: impl Into<Vec<u8>>, size: PhysicalSize, hotspot: (u32, u32)) -> Result<Self, Error> {
+        let rgba = rgba.into();
+        let expected = u64::from(size.width) * u64::from(size.height) * 4;
+        if size.width == 0 || size.height == 0 || rgba.len() as u64 != expected {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "cursor pixels don't match the size given",
+            ))
+        }
+        if hotspot.0 >= size.width || hotspot.1 >= size.height {
+            return Err(Error::new(ErrorKind::InvalidInput, "cursor hotspot is outside of it"))
+        }
+        Ok(Self {
+            rgba: rgba.into(),
+            size,
+            hotspot,
+        })
+    }
+
+    /// The size of the cursor in pixels.
+    pub fn size(&self) -> PhysicalSize {
+        self.size
+    }
+
+    /// The pixel that points, from the top-left.
+    pub fn hotspot(&self) -> (u32, u32) {
+        self.hotspot
+    }
+
+    /// The pixels as ARGB, with the alpha premultiplied, which is what X11 and Wayland take.
+    #[cfg_attr(any(not(target_os = "linux"), feature = "headless"), allow(dead_code))]
+    pub(crate) fn premultiplied_argb(&self) -> impl Iterator<Item = u32> + '_ {
+        premultiplied_argb(&self.rgba)
+    }
+}
+
+/// Turns RGBA pixels into ARGB with the alpha premultiplied.
+#[cfg_attr(any(not(target_os = "linux"), feature = "headless"), allow(dead_code))]
+pub(crate) fn premultiplied_argb(rgba: &[u8]) -> impl Iterator<Item = u32> + '_ {
+    rgba.chunks_exact(4).map(|pixel| {
+        let alpha = u32::from(pixel[3]);
+        let premultiply = |x: u8| (u32::from(x) * alpha + 127) / 255;
+        alpha << 24 | premultiply(pixel[0]) << 16 | premultiply(pixel[1]) << 8 | premultiply(pixel[2])
+    })
+}
+
+// The pixels aren't worth printing
+impl fmt::Debug for CustomCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomCursor")
+            .field("size", &self.size)
+            .field("hotspot", &self.hotspot)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Cursor {
+    fn default() -> Self {
+        Self::Shape(CursorShape::Arrow)
+    }
+}
+
+impl From<CursorShape> for Cursor {
+    fn from(shape: CursorShape) -> Self {
+        Self::Shape(shape)
+    }
+}
+
+impl From<CustomCursor> for Cursor {
+    fn from(cursor: CustomCursor) -> Self {
+        Self::Custom(cursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CustomCursor;
+    use crate::{dpi::PhysicalSize, error::ErrorKind};
+
+    #[test]
+    fn custom_cursors_are_checked() {
+        let size = PhysicalSize::new(2, 1);
+        assert!(CustomCursor::from_rgba(vec![0; 8], size, (1, 0)).is_ok());
+        let error = CustomCursor::from_rgba(vec![0; 7], size, (0, 0)).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        let error = CustomCursor::from_rgba(vec![0; 8], size, (2, 0)).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn alpha_is_premultiplied() {
+        let cursor =
+            CustomCursor::from_rgba(vec![255, 128, 0, 128, 10, 20, 30, 255], PhysicalSize::new(2, 1), (0, 0)).unwrap();
+        let pixels: Vec<u32> = cursor.premultiplied_argb().collect();
+        assert_eq!(pixels, [0x80804000, 0xFF0A141E]);
+    }
+}
@@ -0,0 +1,176 @@
+//! Geometry types that keep logical and physical units apart.
+//!
+//! Physical units are pixels on the screen. Logical units are physical units divided by the window's scale factor,
+//! so that something given a logical size looks about as big on any screen, no matter its DPI.
+//! A scale factor of `1.0` is 96 DPI, which is what most desktop software was designed for.
+//!
+//! APIs that take a size or position accept both kinds through [`Size`] and [`Position`],
+//! while everything reported back by ramen is physical.
+
+/// A size in physical pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PhysicalSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A size in logical units.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LogicalSize {
+    pub width: f64,
+    pub height: f64,
+}
+
+/// A position in physical pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PhysicalPosition {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// A position in logical units.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LogicalPosition {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// A size in either logical or physical units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Size {
+    Logical(LogicalSize),
+    Physical(PhysicalSize),
+}
+
+/// A position in either logical or physical units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Position {
+    Logical(LogicalPosition),
+    Physical(PhysicalPosition),
+}
+
+impl PhysicalSize {
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    pub fn to_logical(self, scale_factor: f64) -> LogicalSize {
+        LogicalSize::new(
+            f64::from(self.width) / scale_factor,
+            f64::from(self.height) / scale_factor,
+        )
+    }
+}
+
+impl LogicalSize {
+    pub const fn new(width: f64, height: f64) -> Self {
+        Self { width, height }
+    }
+
+    /// Converts to physical pixels, rounding to the nearest one. Negative sizes become zero.
+    pub fn to_physical(self, scale_factor: f64) -> PhysicalSize {
+        // Float to int casts saturate, which also takes care of the negative ones
+        PhysicalSize::new(
+            (self.width * scale_factor).round() as u32,
+            (self.height * scale_factor).round() as u32,
+        )
+    }
+}
+
+impl PhysicalPosition {
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    pub fn to_logical(self, scale_factor: f64) -> LogicalPosition {
+        LogicalPosition::new(f64::from(self.x) / scale_factor, f64::from(self.y) / scale_factor)
+    }
+}
+
+impl LogicalPosition {
+    pub const fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+
+    /// Converts to physical pixels, rounding to the nearest one.
+    pub fn to_physical(self, scale_factor: f64) -> PhysicalPosition {
+        PhysicalPosition::new(
+            (self.x * scale_factor).round() as i32,
+            (self.y * scale_factor).round() as i32,
+        )
+    }
+}
+
+impl Size {
+    pub fn to_logical(self, scale_factor: f64) -> LogicalSize {
+        match self {
+            Self::Logical(size) => size,
+            Self::Physical(size) => size.to_logical(scale_factor),
+        }
+    }
+
+    pub fn to_physical(self, scale_factor: f64) -> PhysicalSize {
+        match self {
+            Self::Logical(size) => size.to_physical(scale_factor),
+            Self::Physical(size) => size,
+        }
+    }
+}
+
+impl Position {
+    pub fn to_logical(self, scale_factor: f64) -> LogicalPosition {
+        match self {
+            Self::Logical(position) => position,
+            Self::Physical(position) => position.to_logical(scale_factor),
+        }
+    }
+
+    pub fn to_physical(self, scale_factor: f64) -> PhysicalPosition {
+        match self {
+            Self::Logical(position) => position.to_physical(scale_factor),
+            Self::Physical(position) => position,
+        }
+    }
+}
+
+impl From<LogicalSize> for Size {
+    fn from(size: LogicalSize) -> Self {
+        Self::Logical(size)
+    }
+}
+
+impl From<PhysicalSize> for Size {
+    fn from(size: PhysicalSize) -> Self {
+        Self::Physical(size)
+    }
+}
+
+impl From<LogicalPosition> for Position {
+    fn from(position: LogicalPosition) -> Self {
+        Self::Logical(position)
+    }
+}
+
+impl From<PhysicalPosition> for Position {
+    fn from(position: PhysicalPosition) -> Self {
+        Self::Physical(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions_round() {
+        let size = LogicalSize::new(800.0, 600.0);
+        assert_eq!(size.to_physical(1.25), PhysicalSize::new(1000, 750));
+        assert_eq!(Size::from(size).to_physical(1.5), PhysicalSize::new(1200, 900));
+        assert_eq!(PhysicalSize::new(1000, 750).to_logical(1.25), size);
+        assert_eq!(LogicalSize::new(-1.0, 0.4).to_physical(1.0), PhysicalSize::new(0, 0));
+        assert_eq!(
+            LogicalPosition::new(-10.0, 3.3).to_physical(1.5),
+            PhysicalPosition::new(-15, 5)
+        );
+    }
+}
@@ -0,0 +1,279 @@
+//! Dragging and dropping things onto windows.
+//!
+//! Something being dragged over a window arrives as [`Event::DragEntered`](crate::event::Event::DragEntered),
+//! listing the formats it can be dropped as, then [`Event::DragMoved`](crate::event::Event::DragMoved) wherever
+//! it goes. Whether it can be dropped is up to the window, which says so for wherever it is at the time with
+//! [`Window::accept_drag`](crate::window::Window::accept_drag). If it's dropped while it's accepted, it arrives as
+//! [`Event::Dropped`](crate::event::Event::Dropped), in the format it was accepted as. Otherwise, the drag ends
+//! with [`Event::DragLeft`](crate::event::Event::DragLeft).
+//!
+//! Going the other way, [`Window::start_drag`](crate::window::Window::start_drag) drags something out of a window,
+//! and how that went comes back as [`Event::DragFinished`](crate::event::Event::DragFinished).
+
+use crate::{clipboard::TEXT_MIME, cursor::CustomCursor};
+use std::path::{Path, PathBuf};
+
+/// The MIME type files are dragged as, which is a list of `file://` URIs.
+#[cfg_attr(feature = "headless", allow(dead_code))]
+pub(crate) const URI_LIST_MIME: &str = "text/uri-list";
+
+/// The names text is offered under, best first.
+///
+/// `text/plain` without a charset is meant to be ASCII, which reads the same as UTF-8.
+#[cfg_attr(feature = "headless", allow(dead_code))]
+const TEXT_NAMES: [&str; 3] = [TEXT_MIME, "UTF8_STRING", "text/plain"];
+
+/// A format that something being dragged can be dropped as, see [`Window::accept_drag`](crate::window::Window::accept_drag).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DragFormat {
+    /// Files, such as from a file manager.
+    Files,
+
+    /// Text.
+    Text,
+
+    /// Anything else, as the MIME type it's offered as.
+    Custom(String),
+}
+
+/// Something that was dropped, in the format it was accepted as.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DragData {
+    /// The paths of files. Anything that isn't a local file is left out.
+    Files(Vec<PathBuf>),
+
+    /// Text.
+    Text(String),
+
+    /// Anything else, as the MIME type it's offered as, and the data.
+    Custom(String, Vec<u8>),
+}
+
+/// What's shown under the cursor while something's dragged out of a window, see
+/// [`Window::start_drag`](crate::window::Window::start_drag).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DragIcon {
+    /// Nothing but the cursor.
+    #[default]
+    None,
+
+    /// A picture of what's being dragged, made like a cursor, with the hotspot being the point of it that's held.
+    ///
+    /// On X11, there's nothing else to show it with, so it's the cursor for as long as it's dragged.
+    Image(CustomCursor),
+}
+
+/// What happens to something when it's dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DropAction {
+    /// It's copied, and the original stays where it was.
+    Copy,
+
+    /// It's moved, so the original is deleted once it's been dropped.
+    Move,
+}
+
+#[cfg_attr(feature = "headless", allow(dead_code))]
+impl DragFormat {
+    /// Lists the formats that something offered under `names` can be dropped as, leaving out names that aren't one.
+    ///
+    /// Names are MIME types, or on X11, atoms such as `UTF8_STRING`, which are only formats if they're text.
+    pub(crate) fn from_names(names: &[String]) -> Vec<Self> {
+        let mut formats: Vec<Self> = Vec::new();
+        for name in names {
+            let format = if name == URI_LIST_MIME {
+                Self::Files
+            } else if TEXT_NAMES.iter().any(|x| x.eq_ignore_ascii_case(name)) {
+                Self::Text
+            } else if name.contains('/') {
+                Self::Custom(name.clone())
+            } else {
+                continue
+            };
+            if !formats.contains(&format) {
+                formats.push(format);
+            }
+        }
+        formats
+    }
+
+    /// Which of `names` to ask for to get something in this format, if it's offered in it.
+    pub(crate) fn pick_name<'a>(&self, names: &'a [String]) -> Option<&'a str> {
+        let name = match self {
+            Self::Files => names.iter().find(|x| *x == URI_LIST_MIME),
+            Self::Text => TEXT_NAMES
+                .iter()
+                .find_map(|text| names.iter().find(|x| x.eq_ignore_ascii_case(text))),
+            Self::Custom(mime) => names.iter().find(|x| *x == mime),
+        };
+        name.map(String::as_str)
+    }
+}
+
+#[cfg_attr(any(not(target_os = "linux"), feature = "headless"), allow(dead_code))]
+impl DragData {
+    /// The MIME types that something being dragged out of a window is offered as, and the data for each.
+    pub(crate) fn into_formats(self) -> Vec<(String, Vec<u8>)> {
+        match self {
+            Self::Files(paths) => vec![(URI_LIST_MIME.to_owned(), uri_list(&paths))],
+            Self::Text(text) => vec![(TEXT_MIME.to_owned(), text.into_bytes())],
+            Self::Custom(mime, data) => vec![(mime, data)],
+        }
+    }
+
+    /// Makes what another app handed over into what was dropped, as the format it was asked for as.
+    pub(crate) fn from_bytes(format: DragFormat, data: Vec<u8>) -> Self {
+        match format {
+            DragFormat::Files => Self::Files(parse_uri_list(&data)),
+            // Some apps count the null terminator as part of the text
+            DragFormat::Text => Self::Text(String::from_utf8_lossy(&data).trim_end_matches('\0').to_owned()),
+            DragFormat::Custom(mime) => Self::Custom(mime, data),
+        }
+    }
+}
+
+/// Reads the local paths out of a `text/uri-list`, which is one URI per line, and comments starting with `#`.
+///
+/// Paths are percent-encoded, and a `file://` URI can name a host, which has to be this one.
+#[cfg_attr(any(not(target_os = "linux"), feature = "headless"), allow(dead_code))]
+fn parse_uri_list(data: &[u8]) -> Vec<PathBuf> {
+    let text = String::from_utf8_lossy(data);
+    let uris = text
+        .lines()
+        .map(str::trim)
+        .filter(|x| !x.is_empty() && !x.starts_with('#'));
+    uris.filter_map(|uri| {
+        let rest = uri.strip_prefix("file:")?;
+        let path = match rest.strip_prefix("//") {
+            Some(rest) => {
+                let (host, path) = rest.split_at(rest.find('/')?);
+                (host.is_empty() || host == "localhost").then_some(path)?
+            },
+            None => rest,
+        };
+        Some(path_from_bytes(percent_decode(path)))
+    })
+    .collect()
+}
+
+/// Makes a `text/uri-list` of `file://` URIs out of paths, which should be absolute.
+#[cfg_attr(any(not(target_os = "linux"), feature = "headless"), allow(dead_code))]
+fn uri_list(paths: &[PathBuf]) -> Vec<u8> {
+    let mut list = Vec::new();
+    for path in paths {
+        list.extend_from_slice(b"file://");
+        // Anything that could be mistaken for part of the URI's syntax is escaped, along with anything non-ASCII
+        for &byte in path_to_bytes(path).iter() {
+            if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+                list.push(byte);
+            } else {
+                list.extend_from_slice(format!("%{:02X}", byte).as_bytes());
+            }
+        }
+        list.extend_from_slice(b"\r\n");
+    }
+    list
+}
+
+/// Decodes `%XX` escapes, leaving anything that isn't one as it is.
+#[cfg_attr(any(not(target_os = "linux"), feature = "headless"), allow(dead_code))]
+fn percent_decode(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes.get(i + 1..i + 3).and_then(|hex| {
+            let hex = std::str::from_utf8(hex).ok()?;
+            u8::from_str_radix(hex, 16).ok()
+        });
+        match escape {
+            Some(byte) if bytes[i] == b'%' => {
+                decoded.push(byte);
+                i += 3;
+            },
+            _ => {
+                decoded.push(bytes[i]);
+                i += 1;
+            },
+        }
+    }
+    decoded
+}
+
+/// Paths are bytes on Unix, and they're not always UTF-8.
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::{ffi::OsString, os::unix::ffi::OsStringExt};
+    PathBuf::from(OsString::from_vec(bytes))
+}
+
+#[cfg(unix)]
+fn path_to_bytes(path: &Path) -> std::borrow::Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().into()
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(not(unix))]
+fn path_to_bytes(path: &Path) -> std::borrow::Cow<'_, [u8]> {
+    path.to_string_lossy().into_owned().into_bytes().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_uri_list, uri_list, DragData, DragFormat};
+    use std::path::PathBuf;
+
+    #[test]
+    fn formats_from_names() {
+        let names = [
+            "UTF8_STRING",
+            "TARGETS",
+            "text/uri-list",
+            "text/plain",
+            "application/x-thing",
+        ];
+        let names: Vec<String> = names.iter().map(|x| x.to_string()).collect();
+        assert_eq!(
+            DragFormat::from_names(&names),
+            [
+                DragFormat::Text,
+                DragFormat::Files,
+                DragFormat::Custom("application/x-thing".into())
+            ]
+        );
+        assert_eq!(DragFormat::Text.pick_name(&names), Some("UTF8_STRING"));
+        assert_eq!(DragFormat::Files.pick_name(&names), Some("text/uri-list"));
+        assert_eq!(DragFormat::Custom("image/png".into()).pick_name(&names), None);
+    }
+
+    #[test]
+    fn uri_lists() {
+        let list = b"# dragged from somewhere\r\nfile:///home/me/a%20level.map\r\nfile://localhost/tmp/b\r\n\
+                     file://elsewhere/tmp/c\r\nhttps://example.com/d\r\nfile:/tmp/e%zz\r\n";
+        assert_eq!(
+            parse_uri_list(list),
+            [
+                PathBuf::from("/home/me/a level.map"),
+                PathBuf::from("/tmp/b"),
+                PathBuf::from("/tmp/e%zz"),
+            ]
+        );
+        assert_eq!(
+            DragData::from_bytes(DragFormat::Text, b"hi\0".to_vec()),
+            DragData::Text("hi".into())
+        );
+    }
+
+    #[test]
+    fn uri_lists_round_trip() {
+        let paths = [PathBuf::from("/home/me/a level#2.map"), PathBuf::from("/tmp/caf\u{e9}")];
+        let list = uri_list(&paths);
+        assert_eq!(list, b"file:///home/me/a%20level%232.map\r\nfile:///tmp/caf%C3%A9\r\n");
+        assert_eq!(parse_uri_list(&list), paths);
+    }
+}
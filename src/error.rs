@@ -1 +1,115 @@
-pub struct Error {}
+//! Error reporting.
+
+use std::{borrow::Cow, error, fmt};
+
+/// An error that occurred while talking to the windowing system.
+///
+/// Besides the general [`ErrorKind`], it keeps the raw error code from the OS where there is one
+/// (`GetLastError` on Win32, the X error code or `errno` on Linux) and a readable description.
+pub struct Error {
+    kind: ErrorKind,
+    code: Option<i32>,
+    message: Cow<'static, str>,
+}
+
+/// The general category of an [`Error`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Registering the window class failed.
+    ClassRegistrationFailed,
+
+    /// Connecting to the display server failed.
+    ConnectionFailed,
+
+    /// The connection to the display server was lost, or it was closed due to a protocol error.
+    ConnectionLost,
+
+    /// Creating the window failed.
+    WindowCreationFailed,
+
+    /// The system or display server doesn't support something that's needed.
+    Unsupported,
+
+    /// The system ran out of memory.
+    OutOfMemory,
+
+    /// An argument was invalid, such as pixels not matching the size they're said to be.
+    InvalidInput,
+
+    /// Creating the window took longer than [`WindowBuilder::creation_timeout`](crate::window::WindowBuilder::creation_timeout) allowed,
+    /// or another app took too long to hand over what's on the clipboard.
+    TimedOut,
+
+    /// Anything else, the message should say what.
+    Other,
+}
+
+impl Error {
+    pub(crate) fn new(kind: ErrorKind, message: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            kind,
+            code: None,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn with_code(kind: ErrorKind, code: i32, message: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            kind,
+            code: Some(code),
+            message: message.into(),
+        }
+    }
+
+    /// The general category of this error.
+    #[inline]
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// A readable description of what went wrong.
+    #[inline]
+    pub fn message(&self) -> &str {
+        self.message.as_ref()
+    }
+
+    /// The raw error code returned by the OS or display server, if there was one.
+    #[inline]
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.code
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Error")
+            .field("kind", &self.kind)
+            .field("code", &self.code)
+            .field("message", &self.message)
+            .finish()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.code {
+            Some(code) => write!(f, "{} (os error {})", self.message, code),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, ErrorKind};
+
+    #[test]
+    fn display_includes_code() {
+        let error = Error::with_code(ErrorKind::WindowCreationFailed, 1407, "failed to create window");
+        assert_eq!(error.to_string(), "failed to create window (os error 1407)");
+        assert_eq!(Error::new(ErrorKind::Unsupported, "nope").to_string(), "nope");
+    }
+}
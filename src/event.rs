@@ -0,0 +1,375 @@
+//! Events sent by windows, and the queue they arrive through.
+
+use crate::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    drag::{DragData, DragFormat, DropAction},
+    keyboard::{Key, KeyCode, Modifiers},
+    monitor::Monitor,
+    sync::{cvar_notify_one, cvar_wait, cvar_wait_for, mutex_lock, Condvar, Mutex},
+};
+use std::{
+    any::Any,
+    collections::VecDeque,
+    fmt,
+    marker::PhantomData,
+    mem,
+    sync::{Arc, Weak},
+    time::Instant,
+};
+
+/// An event sent by a [`Window`](crate::window::Window).
+///
+/// Positions are in physical pixels relative to the top-left corner of the client area, unless noted otherwise.
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Event {
+    /// Something asked for the window to close, such as the user clicking the close button.
+    ///
+    /// This is only a request, the window stays open until it's closed with [`Window::close`](crate::window::Window::close) or dropped.
+    CloseRequested,
+
+    /// The cursor entered the client area.
+    CursorEntered,
+
+    /// The cursor left the client area.
+    CursorLeft,
+
+    /// The cursor moved within the client area, to the contained position.
+    CursorMoved(PhysicalPosition),
+
+    /// Something is being dragged over the window, which can be dropped as any of the contained formats.
+    ///
+    /// It can't be dropped until it's accepted with [`Window::accept_drag`](crate::window::Window::accept_drag),
+    /// see the [`drag`](crate::drag) module.
+    DragEntered(Vec<DragFormat>),
+
+    /// Something being dragged over the window moved to the contained position.
+    DragMoved(PhysicalPosition),
+
+    /// Something being dragged left the window, or was dropped on it without being accepted.
+    DragLeft,
+
+    /// Something was dropped on the window, where it last moved to, in the format it was accepted as.
+    Dropped(DragData),
+
+    /// Something dragged out of the window with [`Window::start_drag`](crate::window::Window::start_drag) was
+    /// dropped, and copied or moved, or `None` if it was cancelled or dropped somewhere that didn't take it.
+    ///
+    /// If it was moved, it's up to the window to delete the original.
+    DragFinished(Option<DropAction>),
+
+    /// The window gained (`true`) or lost (`false`) keyboard focus.
+    Focus(bool),
+
+    /// A key on the keyboard was pressed or released.
+    Key {
+        /// Platform-specific code identifying the key by its position on the keyboard.
+        scancode: u32,
+        /// The key by its position on the keyboard, which is what bindings should use.
+        key_code: KeyCode,
+        /// The key according to the user's keyboard layout, which is what should be shown to them.
+        logical_key: Key,
+        /// Whether the key is now held down.
+        pressed: bool,
+        /// Whether this is an automatic repetition of a held down key, rather than a real press.
+        repeat: bool,
+        /// The modifiers held down and locks turned on when the key was pressed or released.
+        ///
+        /// If this key is a modifier itself, it's only counted on Windows, which reports the state after the event.
+        modifiers: Modifiers,
+    },
+
+    /// A mouse button was pressed or released over the window.
+    MouseButton {
+        /// Which button this is about.
+        button: MouseButton,
+        /// Whether the button is now held down.
+        pressed: bool,
+    },
+
+    /// A monitor was connected to the system, or turned on.
+    MonitorConnected(Monitor),
+
+    /// A monitor was disconnected from the system, or turned off.
+    MonitorDisconnected(Monitor),
+
+    /// The mouse moved, by the contained amount horizontally and vertically. Positive values are right & down.
+    ///
+    /// This is straight from the device, without pointer acceleration, and keeps coming when the cursor
+    /// can't move any further, such as at the edge of the screen or while it's locked with
+    /// [`Window::set_cursor_grab`](crate::window::Window::set_cursor_grab).
+    /// It's only sent while the window is focused, or on Wayland, while the cursor is over it.
+    /// On X11, this needs XInput2.
+    RawMouseMotion(f64, f64),
+
+    /// The mouse wheel was scrolled, or a touchpad or similar was used to scroll.
+    MouseWheel(ScrollDelta),
+
+    /// The window moved, and its top-left corner (including decorations) is now at the contained position
+    /// in desktop coordinates, the same as [`Window::position`](crate::window::Window::position).
+    Moved(PhysicalPosition),
+
+    /// The client area of the window was resized to the contained size.
+    Resized(PhysicalSize),
+
+    /// The scale factor (DPI) of the window changed, usually from being moved to another monitor.
+    ///
+    /// The window is resized to `suggested_size` straight after, which keeps its logical size the same.
+    /// To pick another size instead, call [`Window::set_inner_size`](crate::window::Window::set_inner_size).
+    ScaleFactorChanged {
+        /// The new scale factor, where `1.0` is 96 DPI.
+        scale_factor: f64,
+        /// The physical size of the client area that the window is being resized to.
+        suggested_size: PhysicalSize,
+    },
+
+    /// An input method's pre-edit text changed, which is what's being composed before it's committed.
+    ///
+    /// It should be drawn inline where text is being entered, replacing the previous pre-edit text.
+    /// An empty `text` means composition ended, either because it was committed as [`Event::Text`] or cancelled.
+    Preedit {
+        /// The text being composed.
+        text: String,
+        /// Where the caret is within `text`, as a range of byte offsets, if it should be shown.
+        cursor: Option<(usize, usize)>,
+    },
+
+    /// Text was typed into the window, or committed by an input method.
+    Text(String),
+
+    /// A timer set with [`Window::schedule_timer`](crate::window::Window::schedule_timer) went off, with the id it
+    /// was given.
+    Timer(u64),
+
+    /// A file descriptor registered with
+    /// [`WindowExt::register_fd`](crate::platform::linux::WindowExt::register_fd) is ready, with the token it was
+    /// registered with.
+    FdReady(u64),
+
+    /// A message sent through an [`EventLoopProxy`].
+    User(UserEvent),
+}
+
+/// A button on a mouse.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    /// Any other button, numbered from 4 like on Windows.
+    ///
+    /// Buttons 4 and 5 are the back and forward buttons on the side of most mice,
+    /// and the rest are in the order the platform numbers them.
+    Other(u16),
+}
+
+/// How far something was scrolled, horizontally and vertically. Positive values scroll right & up, respectively.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScrollDelta {
+    /// Notches of a mouse wheel, which usually scroll by a few lines of text each.
+    ///
+    /// Wheels that scroll smoothly send fractions of a notch. On X11 and Windows, touchpads send these too.
+    Lines(f32, f32),
+
+    /// Physical pixels, from touchpads and other devices that scroll precisely, on Wayland.
+    Pixels(f64, f64),
+}
+
+/// A message sent through an [`EventLoopProxy`], which can be any type that's `Send + 'static`.
+///
+/// The message can't be compared, so a `UserEvent` isn't equal to anything, itself included.
+pub struct UserEvent(Box<dyn Any + Send>);
+
+impl UserEvent {
+    /// Whether the message is a `T`.
+    pub fn is<T: Any>(&self) -> bool {
+        (*self.0).is::<T>()
+    }
+
+    /// Borrows the message if it's a `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        (*self.0).downcast_ref()
+    }
+
+    /// Takes the message if it's a `T`, or gives `self` back if it isn't.
+    pub fn downcast<T: Any>(self) -> Result<T, Self> {
+        self.0.downcast::<T>().map(|message| *message).map_err(Self)
+    }
+}
+
+impl PartialEq for UserEvent {
+    fn eq(&self, _other: &Self) -> bool {
+        false
+    }
+}
+
+// There's no telling what's in it
+impl fmt::Debug for UserEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserEvent").finish_non_exhaustive()
+    }
+}
+
+/// Sends messages of type `T` to a [`Window`](crate::window::Window) from any thread, where they arrive
+/// as [`Event::User`]. Made with [`Window::create_proxy`](crate::window::Window::create_proxy).
+///
+/// Sending wakes up the window's owner if it's blocked in [`wait_events`](crate::window::Window::wait_events).
+/// Messages are queued alongside the window's own events, in the order they were sent.
+pub struct EventLoopProxy<T> {
+    events: Weak<EventQueue>,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T: Any + Send> EventLoopProxy<T> {
+    pub(crate) fn new(events: &Arc<EventQueue>) -> Self {
+        Self {
+            events: Arc::downgrade(events),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Sends a message to the window, which arrives as [`Event::User`].
+    ///
+    /// Fails and hands the message back if the window has been closed.
+    pub fn send_event(&self, message: T) -> Result<(), T> {
+        match self.events.upgrade() {
+            Some(events) => {
+                events.push(Event::User(UserEvent(Box::new(message))));
+                Ok(())
+            },
+            None => Err(message),
+        }
+    }
+}
+
+// Derives would need `T: Clone` and `T: Debug`, which the proxy doesn't hold any of
+impl<T> Clone for EventLoopProxy<T> {
+    fn clone(&self) -> Self {
+        Self {
+            events: Weak::clone(&self.events),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for EventLoopProxy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventLoopProxy").finish_non_exhaustive()
+    }
+}
+
+/// An iterator over events, returned by [`Window::poll_events`](crate::window::Window::poll_events)
+/// and [`Window::wait_events`](crate::window::Window::wait_events) and its variants.
+///
+/// Events that aren't iterated over before this is dropped are kept for the next call.
+pub struct Events<'a> {
+    buffer: &'a mut VecDeque<Event>,
+}
+
+impl Iterator for Events<'_> {
+    type Item = Event;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.buffer.pop_front()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.buffer.len(), Some(self.buffer.len()))
+    }
+}
+
+impl ExactSizeIterator for Events<'_> {}
+
+/// Queue of events pushed by a window thread, waiting to be picked up by its [`Window`](crate::window::Window).
+///
+/// Timers are kept here too, as they go off on the thread that waits for events, which sleeps until the next one.
+pub(crate) struct EventQueue {
+    cvar: Condvar,
+    queue: Mutex<Queue>,
+}
+
+struct Queue {
+    events: VecDeque<Event>,
+    // When each timer goes off and its id, soonest first
+    timers: VecDeque<(Instant, u64)>,
+}
+
+impl Queue {
+    /// Queues the timers that have gone off, returning when the next one does.
+    fn fire_timers(&mut self) -> Option<Instant> {
+        let now = Instant::now();
+        while let Some(&(deadline, id)) = self.timers.front() {
+            if deadline > now {
+                return Some(deadline)
+            }
+            let _ = self.timers.pop_front();
+            self.events.push_back(Event::Timer(id));
+        }
+        None
+    }
+}
+
+impl EventQueue {
+    pub(crate) fn new() -> Self {
+        Self {
+            cvar: Condvar::new(),
+            queue: Mutex::new(Queue {
+                events: VecDeque::new(),
+                timers: VecDeque::new(),
+            }),
+        }
+    }
+
+    pub(crate) fn push(&self, event: Event) {
+        mutex_lock(&self.queue).events.push_back(event);
+        cvar_notify_one(&self.cvar);
+    }
+
+    /// Has [`Event::Timer`] sent at `deadline`, after any other timers for the same time.
+    pub(crate) fn schedule_timer(&self, deadline: Instant, id: u64) {
+        let mut queue = mutex_lock(&self.queue);
+        let index = queue.timers.partition_point(|x| x.0 <= deadline);
+        queue.timers.insert(index, (deadline, id));
+        // Whoever's waiting might need to wake up sooner now
+        mem::drop(queue);
+        cvar_notify_one(&self.cvar);
+    }
+
+    /// Moves every queued event into `buffer`, without blocking, and iterates it.
+    pub(crate) fn poll<'a>(&self, buffer: &'a mut VecDeque<Event>) -> Events<'a> {
+        let mut queue = mutex_lock(&self.queue);
+        let _ = queue.fire_timers();
+        buffer.append(&mut queue.events);
+        mem::drop(queue);
+        Events { buffer }
+    }
+
+    /// Moves every queued event into `buffer`, blocking until there's at least one or `deadline` passes, and
+    /// iterates it. Without a deadline, this blocks for as long as it takes.
+    pub(crate) fn wait<'a>(&self, buffer: &'a mut VecDeque<Event>, deadline: Option<Instant>) -> Events<'a> {
+        let mut queue = mutex_lock(&self.queue);
+        loop {
+            let next_timer = queue.fire_timers();
+            if !queue.events.is_empty() || !buffer.is_empty() {
+                break
+            }
+            let now = Instant::now();
+            if deadline.is_some_and(|x| x <= now) {
+                break
+            }
+            match next_timer.into_iter().chain(deadline).min() {
+                Some(wake_at) if wake_at > now => {
+                    let _ = cvar_wait_for(&self.cvar, &mut queue, wake_at - now);
+                },
+                // A timer went off since they were checked
+                Some(_) => (),
+                None => cvar_wait(&self.cvar, &mut queue),
+            }
+        }
+        buffer.append(&mut queue.events);
+        mem::drop(queue);
+        Events { buffer }
+    }
+}
@@ -0,0 +1,263 @@
+//! Window icons, made from RGBA pixels or (with the `ico` feature) decoded from `.ico` files.
+
+use crate::{
+    dpi::PhysicalSize,
+    error::{Error, ErrorKind},
+};
+use std::{fmt, sync::Arc};
+
+/// A window icon, see [`Window::set_icon`](crate::window::Window::set_icon).
+///
+/// It can have images of several sizes, which the system picks between for wherever the icon is shown,
+/// such as the title bar and the task switcher. It's cheap to clone, as the images are shared.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Icon {
+    pub(crate) images: Arc<[IconImage]>,
+}
+
+/// One image of an [`Icon`].
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct IconImage {
+    pub(crate) rgba: Vec<u8>,
+    pub(crate) size: PhysicalSize,
+}
+
+impl Icon {
+    /// Makes an icon out of one or more images, which should each be a different size.
+    ///
+    /// Fails with [`ErrorKind::InvalidInput`] if there aren't any.
+    pub fn new(images: impl IntoIterator<Item = IconImage>) -> Result<Self, Error> {
+        let images: Arc<[IconImage]> = images.into_iter().collect();
+        if images.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "icon has no images"))
+        }
+        Ok(Self { images })
+    }
+
+    /// The images in the icon.
+    pub fn images(&self) -> &[IconImage] {
+        &self.images
+    }
+}
+
+impl IconImage {
+    /// Makes an image from 8-bit RGBA pixels, row by row from the top-left, without premultiplied alpha.
+    ///
+    /// Fails with [`ErrorKind::InvalidInput`] if there isn't exactly one pixel for each point of `size`.
+    pub fn from_rgba(rgba: impl Into<Vec<u8>>, size: PhysicalSize) -> Result<Self, Error> {
+        let rgba = rgba.into();
+        let expected = u64::from(size.width) * u64::from(size.height) * 4;
+        if size.width == 0 || size.height == 0 || rgba.len() as u64 != expected {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "icon pixels don't match the size given",
+            ))
+        }
+        Ok(Self { rgba, size })
+    }
+
+    /// The size of the image in pixels.
+    pub fn size(&self) -> PhysicalSize {
+        self.size
+    }
+
+    /// The pixels as 8-bit RGBA.
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+}
+
+// The pixels aren't worth printing
+impl fmt::Debug for IconImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IconImage")
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl From<IconImage> for Icon {
+    fn from(image: IconImage) -> Self {
+        Self {
+            images: Arc::new([image]),
+        }
+    }
+}
+
+#[cfg(feature = "ico")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "ico")))]
+impl Icon {
+    /// Decodes a Windows `.ico` file, taking every image in it.
+    ///
+    /// Images that are stored as PNG are skipped, as there's no PNG decoder built in.
+    /// Fails with [`ErrorKind::InvalidInput`] if the file is broken or none of its images could be decoded.
+    pub fn from_ico(data: &[u8]) -> Result<Self, Error> {
+        let images = ico::decode(data).ok_or_else(|| Error::new(ErrorKind::InvalidInput, "malformed .ico file"))?;
+        if images.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                ".ico file has no images that can be decoded",
+            ))
+        }
+        Self::new(images)
+    }
+}
+
+/// The `.ico` format, which is a directory of BMP (without the file header) or PNG images.
+#[cfg(feature = "ico")]
+mod ico {
+    use super::IconImage;
+    use crate::dpi::PhysicalSize;
+
+    // Images past this are refused, which is well past the 256x256 that `.ico` files are meant to top out at
+    const MAX_DIMENSION: usize = 1024;
+
+    fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+        let bytes = data.get(offset..offset.checked_add(2)?)?;
+        Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+        let bytes = data.get(offset..offset.checked_add(4)?)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Decodes every BMP image in the file, or `None` if the directory is broken.
+    pub fn decode(data: &[u8]) -> Option<Vec<IconImage>> {
+        // reserved (0), type (1 for icons, 2 for cursors), count, then 16 bytes for each image
+        if read_u16(data, 0)? != 0 || !matches!(read_u16(data, 2)?, 1 | 2) {
+            return None
+        }
+        let count = usize::from(read_u16(data, 4)?);
+        let mut images = Vec::with_capacity(count);
+        for i in 0..count {
+            let entry = 6 + i * 16;
+            let length = read_u32(data, entry + 8)? as usize;
+            let offset = read_u32(data, entry + 12)? as usize;
+            let image = data.get(offset..offset.checked_add(length)?)?;
+            if image.starts_with(b"\x89PNG") {
+                continue
+            }
+            if let Some(image) = decode_dib(image) {
+                images.push(image);
+            }
+        }
+        Some(images)
+    }
+
+    /// Decodes a `BITMAPINFOHEADER` bitmap, which for icons is followed by a 1-bit transparency mask.
+    ///
+    /// The height in the header counts both, so it's twice the image's. Rows are bottom-up and padded to 4 bytes.
+    fn decode_dib(dib: &[u8]) -> Option<IconImage> {
+        const BI_RGB: u32 = 0;
+
+        let header_len = read_u32(dib, 0)? as usize;
+        if header_len < 40 || header_len > dib.len() || read_u32(dib, 16)? != BI_RGB {
+            return None
+        }
+        let width = read_u32(dib, 4)? as i32;
+        let height = read_u32(dib, 8)? as i32 / 2;
+        if width <= 0 || height <= 0 || width as usize > MAX_DIMENSION || height as usize > MAX_DIMENSION {
+            return None
+        }
+        let (width, height) = (width as usize, height as usize);
+        let bit_count = usize::from(read_u16(dib, 14)?);
+        let palette_len = match bit_count {
+            1 | 4 | 8 => match read_u32(dib, 32)? {
+                0 => 1 << bit_count,
+                used => (used as usize).min(1 << bit_count),
+            },
+            24 | 32 => 0,
+            _ => return None,
+        };
+        let palette = dib.get(header_len..header_len + palette_len * 4)?;
+        let stride = (width * bit_count).div_ceil(32) * 4;
+        let pixels_start = header_len + palette.len();
+        let pixels = dib.get(pixels_start..pixels_start + stride * height)?;
+        // Some 32-bit images leave the mask out, as the alpha channel is all that's used
+        let mask_stride = width.div_ceil(32) * 4;
+        let mask_start = pixels_start + pixels.len();
+        let mask = dib.get(mask_start..mask_start + mask_stride * height);
+        let masked =
+            |x: usize, y: usize| mask.is_some_and(|mask| mask[y * mask_stride + x / 8] & (0x80 >> (x % 8)) != 0);
+        // Old 32-bit icons left the alpha channel empty and used the mask, like the other depths
+        let has_alpha = bit_count == 32 &&
+            pixels
+                .chunks_exact(stride)
+                .any(|row| row[..width * 4].iter().skip(3).step_by(4).any(|&a| a != 0));
+
+        let mut rgba = Vec::with_capacity(width * height * 4);
+        for y in (0..height).rev() {
+            let row = &pixels[y * stride..][..stride];
+            for x in 0..width {
+                let (b, g, r) = match bit_count {
+                    32 => (row[x * 4], row[x * 4 + 1], row[x * 4 + 2]),
+                    24 => (row[x * 3], row[x * 3 + 1], row[x * 3 + 2]),
+                    _ => {
+                        // Indices are packed from the most significant bit
+                        let bit = x * bit_count;
+                        let index = (row[bit / 8] >> (8 - bit_count - bit % 8)) & ((1 << bit_count) - 1) as u8;
+                        let color = palette.get(usize::from(index) * 4..usize::from(index) * 4 + 3)?;
+                        (color[0], color[1], color[2])
+                    },
+                };
+                let a = if has_alpha {
+                    row[x * 4 + 3]
+                } else if masked(x, y) {
+                    0
+                } else {
+                    0xFF
+                };
+                rgba.extend_from_slice(&[r, g, b, a]);
+            }
+        }
+        Some(IconImage {
+            rgba,
+            size: PhysicalSize::new(width as u32, height as u32),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Icon, IconImage};
+    use crate::{dpi::PhysicalSize, error::ErrorKind};
+
+    #[test]
+    fn icons_are_checked() {
+        let image = IconImage::from_rgba(vec![0; 16], PhysicalSize::new(2, 2)).unwrap();
+        assert_eq!(Icon::new(vec![image.clone()]).unwrap(), Icon::from(image));
+        let error = IconImage::from_rgba(vec![0; 15], PhysicalSize::new(2, 2)).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        assert_eq!(Icon::new(None).unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+
+    #[cfg(feature = "ico")]
+    #[test]
+    fn ico_files_are_decoded() {
+        let data = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/icon.ico")).unwrap();
+        let icon = Icon::from_ico(&data).unwrap();
+        // The PNG image is skipped
+        let sizes: Vec<PhysicalSize> = icon.images().iter().map(|x| x.size()).collect();
+        assert_eq!(
+            sizes,
+            [
+                PhysicalSize::new(2, 2),
+                PhysicalSize::new(3, 2),
+                PhysicalSize::new(1, 1)
+            ]
+        );
+        #[rustfmt::skip]
+        assert_eq!(icon.images()[0].rgba(), [
+            255, 0, 0, 255,   0, 255, 0, 128,
+            0, 0, 255, 0,     255, 255, 255, 255,
+        ]);
+        #[rustfmt::skip]
+        assert_eq!(icon.images()[1].rgba(), [
+            0, 0, 0, 255,     255, 255, 255, 255,   0, 0, 0, 0,
+            255, 255, 255, 0, 0, 0, 0, 255,         255, 255, 255, 255,
+        ]);
+        assert_eq!(icon.images()[2].rgba(), [10, 20, 30, 255]);
+        assert!(Icon::from_ico(&data[..20]).is_err());
+    }
+}
@@ -0,0 +1,488 @@
+//! Keys on the keyboard, both by position and by what the user's layout says they are.
+//!
+//! Games binding WASD want [`KeyCode`], which is the same key no matter the layout.
+//! Showing the user what to press wants [`Key`], which is what that key is labelled in their layout.
+
+use crate::event::{Event, EventQueue};
+
+/// A key by its position on the keyboard, named after what it is on a US layout.
+///
+/// The values are USB HID usages from the keyboard page (0x07), which is what every platform's scancodes
+/// are mapped to. For example, `KeyCode::KeyA` is the key right of Caps Lock, even on an AZERTY keyboard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+#[repr(u16)]
+pub enum KeyCode {
+    /// A key with no known position, see the `scancode` in [`Event::Key`].
+    Unidentified = 0x00,
+    KeyA = 0x04,
+    KeyB = 0x05,
+    KeyC = 0x06,
+    KeyD = 0x07,
+    KeyE = 0x08,
+    KeyF = 0x09,
+    KeyG = 0x0A,
+    KeyH = 0x0B,
+    KeyI = 0x0C,
+    KeyJ = 0x0D,
+    KeyK = 0x0E,
+    KeyL = 0x0F,
+    KeyM = 0x10,
+    KeyN = 0x11,
+    KeyO = 0x12,
+    KeyP = 0x13,
+    KeyQ = 0x14,
+    KeyR = 0x15,
+    KeyS = 0x16,
+    KeyT = 0x17,
+    KeyU = 0x18,
+    KeyV = 0x19,
+    KeyW = 0x1A,
+    KeyX = 0x1B,
+    KeyY = 0x1C,
+    KeyZ = 0x1D,
+    Digit1 = 0x1E,
+    Digit2 = 0x1F,
+    Digit3 = 0x20,
+    Digit4 = 0x21,
+    Digit5 = 0x22,
+    Digit6 = 0x23,
+    Digit7 = 0x24,
+    Digit8 = 0x25,
+    Digit9 = 0x26,
+    Digit0 = 0x27,
+    Enter = 0x28,
+    Escape = 0x29,
+    Backspace = 0x2A,
+    Tab = 0x2B,
+    Space = 0x2C,
+    Minus = 0x2D,
+    Equal = 0x2E,
+    BracketLeft = 0x2F,
+    BracketRight = 0x30,
+    Backslash = 0x31,
+    Semicolon = 0x33,
+    Quote = 0x34,
+    Backquote = 0x35,
+    Comma = 0x36,
+    Period = 0x37,
+    Slash = 0x38,
+    CapsLock = 0x39,
+    F1 = 0x3A,
+    F2 = 0x3B,
+    F3 = 0x3C,
+    F4 = 0x3D,
+    F5 = 0x3E,
+    F6 = 0x3F,
+    F7 = 0x40,
+    F8 = 0x41,
+    F9 = 0x42,
+    F10 = 0x43,
+    F11 = 0x44,
+    F12 = 0x45,
+    PrintScreen = 0x46,
+    ScrollLock = 0x47,
+    Pause = 0x48,
+    Insert = 0x49,
+    Home = 0x4A,
+    PageUp = 0x4B,
+    Delete = 0x4C,
+    End = 0x4D,
+    PageDown = 0x4E,
+    ArrowRight = 0x4F,
+    ArrowLeft = 0x50,
+    ArrowDown = 0x51,
+    ArrowUp = 0x52,
+    NumLock = 0x53,
+    NumpadDivide = 0x54,
+    NumpadMultiply = 0x55,
+    NumpadSubtract = 0x56,
+    NumpadAdd = 0x57,
+    NumpadEnter = 0x58,
+    Numpad1 = 0x59,
+    Numpad2 = 0x5A,
+    Numpad3 = 0x5B,
+    Numpad4 = 0x5C,
+    Numpad5 = 0x5D,
+    Numpad6 = 0x5E,
+    Numpad7 = 0x5F,
+    Numpad8 = 0x60,
+    Numpad9 = 0x61,
+    Numpad0 = 0x62,
+    NumpadDecimal = 0x63,
+    /// The extra key between left Shift and Z on ISO keyboards.
+    IntlBackslash = 0x64,
+    ContextMenu = 0x65,
+    Power = 0x66,
+    NumpadEqual = 0x67,
+    F13 = 0x68,
+    F14 = 0x69,
+    F15 = 0x6A,
+    F16 = 0x6B,
+    F17 = 0x6C,
+    F18 = 0x6D,
+    F19 = 0x6E,
+    F20 = 0x6F,
+    F21 = 0x70,
+    F22 = 0x71,
+    F23 = 0x72,
+    F24 = 0x73,
+    AudioVolumeMute = 0x7F,
+    AudioVolumeUp = 0x80,
+    AudioVolumeDown = 0x81,
+    NumpadComma = 0x85,
+    IntlRo = 0x87,
+    KanaMode = 0x88,
+    IntlYen = 0x89,
+    Convert = 0x8A,
+    NonConvert = 0x8B,
+    Lang1 = 0x90,
+    Lang2 = 0x91,
+    Lang3 = 0x92,
+    Lang4 = 0x93,
+    Lang5 = 0x94,
+    ControlLeft = 0xE0,
+    ShiftLeft = 0xE1,
+    AltLeft = 0xE2,
+    /// The left Windows, Command or "logo" key.
+    SuperLeft = 0xE3,
+    ControlRight = 0xE4,
+    ShiftRight = 0xE5,
+    AltRight = 0xE6,
+    SuperRight = 0xE7,
+}
+
+/// Every [`KeyCode`], for looking them up by usage.
+const KEY_CODES: &[KeyCode] = {
+    use KeyCode::*;
+    &[
+        Unidentified,
+        KeyA,
+        KeyB,
+        KeyC,
+        KeyD,
+        KeyE,
+        KeyF,
+        KeyG,
+        KeyH,
+        KeyI,
+        KeyJ,
+        KeyK,
+        KeyL,
+        KeyM,
+        KeyN,
+        KeyO,
+        KeyP,
+        KeyQ,
+        KeyR,
+        KeyS,
+        KeyT,
+        KeyU,
+        KeyV,
+        KeyW,
+        KeyX,
+        KeyY,
+        KeyZ,
+        Digit1,
+        Digit2,
+        Digit3,
+        Digit4,
+        Digit5,
+        Digit6,
+        Digit7,
+        Digit8,
+        Digit9,
+        Digit0,
+        Enter,
+        Escape,
+        Backspace,
+        Tab,
+        Space,
+        Minus,
+        Equal,
+        BracketLeft,
+        BracketRight,
+        Backslash,
+        Semicolon,
+        Quote,
+        Backquote,
+        Comma,
+        Period,
+        Slash,
+        CapsLock,
+        F1,
+        F2,
+        F3,
+        F4,
+        F5,
+        F6,
+        F7,
+        F8,
+        F9,
+        F10,
+        F11,
+        F12,
+        PrintScreen,
+        ScrollLock,
+        Pause,
+        Insert,
+        Home,
+        PageUp,
+        Delete,
+        End,
+        PageDown,
+        ArrowRight,
+        ArrowLeft,
+        ArrowDown,
+        ArrowUp,
+        NumLock,
+        NumpadDivide,
+        NumpadMultiply,
+        NumpadSubtract,
+        NumpadAdd,
+        NumpadEnter,
+        Numpad1,
+        Numpad2,
+        Numpad3,
+        Numpad4,
+        Numpad5,
+        Numpad6,
+        Numpad7,
+        Numpad8,
+        Numpad9,
+        Numpad0,
+        NumpadDecimal,
+        IntlBackslash,
+        ContextMenu,
+        Power,
+        NumpadEqual,
+        F13,
+        F14,
+        F15,
+        F16,
+        F17,
+        F18,
+        F19,
+        F20,
+        F21,
+        F22,
+        F23,
+        F24,
+        AudioVolumeMute,
+        AudioVolumeUp,
+        AudioVolumeDown,
+        NumpadComma,
+        IntlRo,
+        KanaMode,
+        IntlYen,
+        Convert,
+        NonConvert,
+        Lang1,
+        Lang2,
+        Lang3,
+        Lang4,
+        Lang5,
+        ControlLeft,
+        ShiftLeft,
+        AltLeft,
+        SuperLeft,
+        ControlRight,
+        ShiftRight,
+        AltRight,
+        SuperRight,
+    ]
+};
+
+impl KeyCode {
+    /// The USB HID usage ID of the key, from the keyboard page.
+    pub fn usb_hid_usage(self) -> u16 {
+        self as u16
+    }
+
+    /// Looks up a key by its USB HID usage ID, which is [`Unidentified`](Self::Unidentified) if it's not known.
+    pub fn from_usb_hid_usage(usage: u16) -> Self {
+        KEY_CODES
+            .iter()
+            .copied()
+            .find(|&x| x as u16 == usage)
+            .unwrap_or(Self::Unidentified)
+    }
+}
+
+/// A key by what the user's keyboard layout says it is.
+///
+/// This ignores modifiers, so Shift+A is still `Character('a')`, which is what you'd show as the key's name.
+/// Which layout is active still counts, so switching from US to Russian turns the same key into `'ф'`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    /// A key that types a character.
+    Character(char),
+    /// A key that doesn't type anything, or only whitespace and control characters.
+    Named(NamedKey),
+    /// A dead key, which changes the next character typed, such as `´` then `e` for `é`.
+    Dead,
+    /// A key the layout doesn't assign anything, or that couldn't be looked up.
+    Unidentified,
+}
+
+/// The name of a key which doesn't type a character, see [`Key::Named`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum NamedKey {
+    Alt,
+    /// The right Alt key on layouts that use it to type more characters.
+    AltGraph,
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+    ArrowUp,
+    AudioVolumeDown,
+    AudioVolumeMute,
+    AudioVolumeUp,
+    Backspace,
+    CapsLock,
+    ContextMenu,
+    Control,
+    Delete,
+    End,
+    Enter,
+    Escape,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+    Home,
+    Insert,
+    NumLock,
+    PageDown,
+    PageUp,
+    Pause,
+    Power,
+    PrintScreen,
+    ScrollLock,
+    Shift,
+    /// The Windows, Command or "logo" key.
+    Super,
+    Tab,
+}
+
+/// Which modifier keys are held down, and which locks are on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    /// The Windows, Command or "logo" key.
+    pub logo: bool,
+    pub caps_lock: bool,
+    pub num_lock: bool,
+}
+
+/// The keys a window has seen pressed and not yet released, as they were pressed.
+///
+/// Keys let go of while another window has focus are never heard about, so every backend releases whatever's
+/// still held when the window loses focus, rather than leaving it stuck down.
+#[cfg_attr(feature = "headless", allow(dead_code))]
+#[derive(Default)]
+pub(crate) struct HeldKeys {
+    keys: Vec<(u32, KeyCode, Key)>,
+}
+
+#[cfg_attr(feature = "headless", allow(dead_code))]
+impl HeldKeys {
+    /// Records a key being pressed or released, returning whether it was already held.
+    pub(crate) fn update(&mut self, scancode: u32, key_code: KeyCode, logical_key: Key, pressed: bool) -> bool {
+        let held = self.keys.iter().position(|x| x.0 == scancode);
+        match (held, pressed) {
+            (None, true) => self.keys.push((scancode, key_code, logical_key)),
+            (Some(i), false) => {
+                let _ = self.keys.remove(i);
+            },
+            _ => (),
+        }
+        held.is_some()
+    }
+
+    /// Sends a release for every key that's held, in the order they were pressed, and forgets about them.
+    ///
+    /// There's no knowing what the modifiers will be by the time the window has focus again, so there are none.
+    pub(crate) fn release_all(&mut self, events: &EventQueue) {
+        for (scancode, key_code, logical_key) in self.keys.drain(..) {
+            events.push(Event::Key {
+                scancode,
+                key_code,
+                logical_key,
+                pressed: false,
+                repeat: false,
+                modifiers: Modifiers::default(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashSet, VecDeque};
+
+    #[test]
+    fn held_keys_are_released() {
+        let mut held = HeldKeys::default();
+        let a = Key::Character('a');
+        assert!(!held.update(30, KeyCode::KeyA, a, true));
+        assert!(held.update(30, KeyCode::KeyA, a, true));
+        assert!(!held.update(42, KeyCode::ShiftLeft, Key::Named(NamedKey::Shift), true));
+        assert!(!held.update(57, KeyCode::Space, Key::Character(' '), false));
+
+        let events = EventQueue::new();
+        held.release_all(&events);
+        held.release_all(&events);
+        let mut buffer = VecDeque::new();
+        let released: Vec<_> = events
+            .poll(&mut buffer)
+            .map(|event| match event {
+                Event::Key {
+                    scancode,
+                    pressed: false,
+                    repeat: false,
+                    modifiers,
+                    ..
+                } if modifiers == Modifiers::default() => scancode,
+                event => panic!("expected a release, got {:?}", event),
+            })
+            .collect();
+        assert_eq!(released, [30, 42]);
+    }
+
+    #[test]
+    fn usb_hid_usages_round_trip() {
+        let usages: HashSet<u16> = KEY_CODES.iter().map(|x| x.usb_hid_usage()).collect();
+        assert_eq!(usages.len(), KEY_CODES.len());
+        for &key_code in KEY_CODES {
+            assert_eq!(KeyCode::from_usb_hid_usage(key_code.usb_hid_usage()), key_code);
+        }
+        assert_eq!(KeyCode::from_usb_hid_usage(0x04), KeyCode::KeyA);
+        assert_eq!(KeyCode::from_usb_hid_usage(0xE7), KeyCode::SuperRight);
+        assert_eq!(KeyCode::from_usb_hid_usage(0xFF), KeyCode::Unidentified);
+    }
+}
@@ -1,6 +1,14 @@
 #![cfg_attr(feature = "nightly-docs", feature(doc_cfg))]
 
+pub mod clipboard;
+pub mod cursor;
+pub mod dpi;
+pub mod drag;
 pub mod error;
+pub mod event;
+pub mod icon;
+pub mod keyboard;
+pub mod monitor;
 pub mod platform;
 pub mod sync;
 pub mod window;
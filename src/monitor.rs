@@ -0,0 +1,186 @@
+//! Monitors connected to the system, and the video modes they support.
+
+use crate::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{Event, EventQueue},
+};
+
+/// A monitor connected to the system, as it was when this was retrieved.
+///
+/// These are returned by [`Window::available_monitors`](crate::window::Window::available_monitors)
+/// and [`Window::current_monitor`](crate::window::Window::current_monitor),
+/// and kept up to date with [`Event::MonitorConnected`] and [`Event::MonitorDisconnected`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Monitor {
+    // Identifies the monitor across snapshots, so that monitors can be told apart when they come and go
+    pub(crate) id: usize,
+    pub(crate) name: String,
+    pub(crate) position: PhysicalPosition,
+    pub(crate) size: PhysicalSize,
+    pub(crate) physical_size_mm: Option<(u32, u32)>,
+    pub(crate) refresh_rate_millihertz: Option<u32>,
+    pub(crate) scale_factor: f64,
+    pub(crate) primary: bool,
+    pub(crate) video_modes: Vec<VideoMode>,
+}
+
+/// A resolution, bit depth and refresh rate which a monitor can be set to.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VideoMode {
+    // Platform-specific handle to the mode, where there is one (such as an XRandR mode ID)
+    pub(crate) id: usize,
+    pub(crate) size: PhysicalSize,
+    pub(crate) bit_depth: u16,
+    pub(crate) refresh_rate_millihertz: u32,
+}
+
+impl Monitor {
+    /// A human-readable name for the monitor, such as `DP-1` or `\\.\DISPLAY1`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The position of the monitor's top-left corner on the desktop.
+    ///
+    /// On Wayland, this is in the compositor's logical coordinates, which are only physical pixels
+    /// if no monitor is scaled.
+    pub fn position(&self) -> PhysicalPosition {
+        self.position
+    }
+
+    /// The resolution of the monitor's current video mode.
+    pub fn size(&self) -> PhysicalSize {
+        self.size
+    }
+
+    /// The width and height of the screen in millimetres, if the monitor reports it.
+    pub fn physical_size_mm(&self) -> Option<(u32, u32)> {
+        self.physical_size_mm
+    }
+
+    /// The refresh rate of the monitor's current video mode, in millihertz, if it's known.
+    pub fn refresh_rate_millihertz(&self) -> Option<u32> {
+        self.refresh_rate_millihertz
+    }
+
+    /// The scale factor of windows on this monitor, see [`Window::scale_factor`](crate::window::Window::scale_factor).
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// Whether the system considers this the main monitor, which there's at most one of.
+    pub fn is_primary(&self) -> bool {
+        self.primary
+    }
+
+    /// Every video mode the monitor supports.
+    ///
+    /// Wayland compositors usually only report the current one.
+    pub fn video_modes(&self) -> &[VideoMode] {
+        &self.video_modes
+    }
+}
+
+impl VideoMode {
+    /// The resolution of the mode.
+    pub fn size(&self) -> PhysicalSize {
+        self.size
+    }
+
+    /// Bits per pixel. This isn't reported on Wayland, where it's assumed to be 32.
+    pub fn bit_depth(&self) -> u16 {
+        self.bit_depth
+    }
+
+    /// The refresh rate of the mode, in millihertz.
+    pub fn refresh_rate_millihertz(&self) -> u32 {
+        self.refresh_rate_millihertz
+    }
+}
+
+/// Picks the monitor that a rectangle on the desktop is mostly on,
+/// or the closest one if it's not on any of them.
+#[cfg_attr(all(target_os = "windows", not(feature = "headless")), allow(dead_code))] // Win32 asks the system
+pub(crate) fn monitor_for_rect(
+    monitors: &[Monitor],
+    position: PhysicalPosition,
+    size: PhysicalSize,
+) -> Option<&Monitor> {
+    let (left, top) = (i64::from(position.x), i64::from(position.y));
+    let (right, bottom) = (left + i64::from(size.width), top + i64::from(size.height));
+    let (center_x, center_y) = ((left + right) / 2, (top + bottom) / 2);
+    monitors.iter().max_by_key(|monitor| {
+        let (m_left, m_top) = (i64::from(monitor.position.x), i64::from(monitor.position.y));
+        let m_right = m_left + i64::from(monitor.size.width);
+        let m_bottom = m_top + i64::from(monitor.size.height);
+        let overlap = (right.min(m_right) - left.max(m_left)).max(0) * (bottom.min(m_bottom) - top.max(m_top)).max(0);
+
+        // With no overlap anywhere, the monitor with the edge nearest to the centre wins
+        let distance_x = (m_left - center_x).max(center_x - m_right).max(0);
+        let distance_y = (m_top - center_y).max(center_y - m_bottom).max(0);
+        (overlap, -(distance_x * distance_x + distance_y * distance_y))
+    })
+}
+
+/// Replaces a list of monitors with a new snapshot, sending events for those that were connected or disconnected.
+pub(crate) fn update_monitors(monitors: &mut Vec<Monitor>, new: Vec<Monitor>, events: &EventQueue) {
+    for monitor in monitors.iter() {
+        if !new.iter().any(|x| x.id == monitor.id) {
+            events.push(Event::MonitorDisconnected(monitor.clone()));
+        }
+    }
+    for monitor in new.iter() {
+        if !monitors.iter().any(|x| x.id == monitor.id) {
+            events.push(Event::MonitorConnected(monitor.clone()));
+        }
+    }
+    *monitors = new;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    fn monitor(id: usize, x: i32, width: u32) -> Monitor {
+        Monitor {
+            id,
+            name: String::new(),
+            position: PhysicalPosition::new(x, 0),
+            size: PhysicalSize::new(width, 1080),
+            physical_size_mm: None,
+            refresh_rate_millihertz: None,
+            scale_factor: 1.0,
+            primary: false,
+            video_modes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn rects_pick_the_most_overlapping_monitor() {
+        let monitors = [monitor(1, 0, 1920), monitor(2, 1920, 1920)];
+        let size = PhysicalSize::new(800, 600);
+        let pick = |x| monitor_for_rect(&monitors, PhysicalPosition::new(x, 100), size).map(|x| x.id);
+        assert_eq!(pick(100), Some(1));
+        assert_eq!(pick(1600), Some(2));
+        assert_eq!(pick(5000), Some(2));
+        assert_eq!(pick(-5000), Some(1));
+        assert_eq!(monitor_for_rect(&[], PhysicalPosition::new(0, 0), size), None);
+    }
+
+    #[test]
+    fn monitor_updates_send_events() {
+        let events = EventQueue::new();
+        let mut monitors = vec![monitor(1, 0, 1920), monitor(2, 1920, 1920)];
+        let new = vec![monitor(1, 0, 2560), monitor(3, 2560, 1920)];
+        update_monitors(&mut monitors, new.clone(), &events);
+        assert_eq!(monitors, new);
+        assert_eq!(
+            events.poll(&mut VecDeque::new()).collect::<Vec<_>>(),
+            [
+                Event::MonitorDisconnected(monitor(2, 1920, 1920)),
+                Event::MonitorConnected(monitor(3, 2560, 1920)),
+            ]
+        );
+    }
+}
@@ -1,6 +1,45 @@
+// With `headless`, the native backends are still built for their public API, but never instantiated
 #[cfg_attr(feature = "nightly-docs", doc(cfg(target_os = "windows")))]
 #[cfg_attr(not(feature = "nightly-docs"), cfg(target_os = "windows"))]
+#[cfg_attr(feature = "headless", allow(dead_code, unused_imports))]
 pub mod win32;
 
-#[cfg(target_os = "windows")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(target_os = "linux")))]
+#[cfg_attr(not(feature = "nightly-docs"), cfg(target_os = "linux"))]
+#[cfg_attr(feature = "headless", allow(dead_code, unused_imports))]
+pub mod wayland;
+
+#[cfg_attr(feature = "nightly-docs", doc(cfg(target_os = "linux")))]
+#[cfg_attr(not(feature = "nightly-docs"), cfg(target_os = "linux"))]
+#[cfg_attr(feature = "headless", allow(dead_code, unused_imports))]
+pub mod x11;
+
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "headless")))]
+#[cfg_attr(not(feature = "nightly-docs"), cfg(feature = "headless"))]
+pub mod headless;
+
+#[cfg(any(target_os = "linux", feature = "nightly-docs"))]
+#[cfg_attr(feature = "headless", allow(dead_code))]
+mod posix;
+
+#[cfg_attr(
+    feature = "nightly-docs",
+    doc(cfg(all(target_os = "linux", not(feature = "headless"))))
+)]
+#[cfg(all(target_os = "linux", not(feature = "headless")))]
+pub mod linux;
+
+#[cfg(any(target_os = "linux", feature = "nightly-docs"))]
+#[cfg_attr(feature = "headless", allow(dead_code))]
+mod xcursor;
+
+#[cfg(any(target_os = "linux", feature = "nightly-docs"))]
+#[cfg_attr(feature = "headless", allow(dead_code))]
+mod xkb;
+
+#[cfg(feature = "headless")]
+pub(crate) use headless as imp;
+#[cfg(all(target_os = "linux", not(feature = "headless")))]
+pub(crate) use linux as imp;
+#[cfg(all(target_os = "windows", not(feature = "headless")))]
 pub(crate) use win32 as imp;
@@ -0,0 +1,835 @@
+//! Headless backend, keeping windows entirely in memory.
+//!
+//! Enabled with the `headless` feature, replacing the native backend on every platform.
+//! This is meant for testing code that uses ramen without needing a display server:
+//! every window has a [`Controller`] which lets tests inspect what was asked of it.
+
+use crate::{
+    clipboard::Selection,
+    cursor::Cursor,
+    dpi::{PhysicalPosition, PhysicalSize},
+    drag::{DragData, DragFormat, DragIcon, DropAction},
+    error::Error,
+    event::{Event, EventQueue},
+    icon::Icon,
+    monitor::{self, Monitor, VideoMode},
+    sync::{mutex_lock, Mutex},
+    window::{CursorGrab, Fullscreen, Window, WindowBuilder},
+};
+use std::sync::Arc;
+
+/// Headless-specific API extensions to [`Window`].
+pub trait WindowExt {
+    /// Returns a [`Controller`] for inspecting the in-memory state of this window.
+    fn controller(&self) -> Controller;
+}
+
+impl WindowExt for Window {
+    fn controller(&self) -> Controller {
+        Controller {
+            events: Arc::clone(&self.imp.events),
+            state: Arc::clone(&self.imp.state),
+        }
+    }
+}
+
+/// Test-side handle to a headless window.
+///
+/// It can be cloned and sent to other threads, and stays valid after the window is dropped.
+#[derive(Clone)]
+pub struct Controller {
+    events: Arc<EventQueue>,
+    state: Arc<Mutex<WindowState>>,
+}
+
+impl Controller {
+    /// Sends an event to the window, as if it came from the display server.
+    ///
+    /// No state is changed as a side effect, so for example sending [`Event::Resized`] does not change the size of
+    /// the window. [`resize`](Self::resize), [`move_to`](Self::move_to), [`set_focus`](Self::set_focus) and
+    /// [`set_scale_factor`](Self::set_scale_factor) do, and send the event to go with it.
+    pub fn push_event(&self, event: Event) {
+        self.events.push(event);
+    }
+
+    /// The class name the window was built with.
+    pub fn class_name(&self) -> String {
+        mutex_lock(&self.state).class_name.clone()
+    }
+
+    /// Whether the [`Window`] still exists, which is no longer the case once it's dropped.
+    pub fn is_open(&self) -> bool {
+        mutex_lock(&self.state).open
+    }
+
+    /// Whether the window is shown, as last set with [`Window::set_visible`].
+    pub fn is_visible(&self) -> bool {
+        mutex_lock(&self.state).visible
+    }
+
+    /// The cursor last set with [`Window::set_cursor`].
+    pub fn cursor(&self) -> Cursor {
+        mutex_lock(&self.state).cursor.clone()
+    }
+
+    /// Where the cursor was last moved with [`Window::set_cursor_position`], if it was.
+    pub fn cursor_position(&self) -> Option<PhysicalPosition> {
+        mutex_lock(&self.state).cursor_position
+    }
+
+    /// Whether the cursor is shown, as last set with [`Window::set_cursor_visible`].
+    pub fn is_cursor_visible(&self) -> bool {
+        mutex_lock(&self.state).cursor_visible
+    }
+
+    /// Starts dragging something over the window, which can be dropped as `formats`.
+    ///
+    /// Like on the native backends, this sends [`Event::DragEntered`] and forgets what was last accepted.
+    pub fn drag_enter(&self, formats: Vec<DragFormat>) {
+        mutex_lock(&self.state).drag_accepted = None;
+        self.events.push(Event::DragEntered(formats));
+    }
+
+    /// What the window last accepted being dragged over it as, with [`Window::accept_drag`].
+    pub fn drag_accepted(&self) -> Option<(DragFormat, DropAction)> {
+        mutex_lock(&self.state).drag_accepted.clone()
+    }
+
+    /// What's being dragged out of the window with [`Window::start_drag`], and its icon.
+    pub fn dragged_out(&self) -> Option<(DragData, DragIcon)> {
+        mutex_lock(&self.state).dragged_out.clone()
+    }
+
+    /// Finishes dragging something out of the window, sending [`Event::DragFinished`] with what was done with it.
+    pub fn finish_drag_out(&self, action: Option<DropAction>) {
+        if mutex_lock(&self.state).dragged_out.take().is_some() {
+            self.events.push(Event::DragFinished(action));
+        }
+    }
+
+    /// The grab last set with [`Window::set_cursor_grab`].
+    pub fn cursor_grab(&self) -> CursorGrab {
+        mutex_lock(&self.state).cursor_grab
+    }
+
+    /// The icon last set with [`Window::set_icon`], or given to the builder.
+    pub fn icon(&self) -> Option<Icon> {
+        mutex_lock(&self.state).icon.clone()
+    }
+
+    /// Whether the window was built with an IME.
+    pub fn ime(&self) -> bool {
+        mutex_lock(&self.state).ime
+    }
+
+    /// The area last set with [`Window::set_ime_cursor_area`], if any.
+    pub fn ime_cursor_area(&self) -> Option<(PhysicalPosition, PhysicalSize)> {
+        mutex_lock(&self.state).ime_cursor_area
+    }
+
+    /// The current size of the window, which has no decorations.
+    pub fn inner_size(&self) -> PhysicalSize {
+        mutex_lock(&self.state).inner_size
+    }
+
+    /// The current position of the window's top-left corner.
+    pub fn position(&self) -> PhysicalPosition {
+        mutex_lock(&self.state).position
+    }
+
+    /// Resizes the window, as if the user dragged its border, sending [`Event::Resized`] if the size changed.
+    pub fn resize(&self, size: PhysicalSize) {
+        mutex_lock(&self.state).set_inner_size(size, &self.events);
+    }
+
+    /// Moves the window, as if the user dragged its title bar, sending [`Event::Moved`] if it moved.
+    pub fn move_to(&self, position: PhysicalPosition) {
+        mutex_lock(&self.state).set_position(position, &self.events);
+    }
+
+    /// Changes the scale factor, as if the window was moved to a monitor with a different DPI.
+    ///
+    /// Like on the native backends, the window is then resized to keep its logical size.
+    pub fn set_scale_factor(&self, scale_factor: f64) {
+        let mut state = mutex_lock(&self.state);
+        if state.scale_factor != scale_factor {
+            let suggested_size = state
+                .inner_size
+                .to_logical(state.scale_factor)
+                .to_physical(scale_factor);
+            state.scale_factor = scale_factor;
+            self.events.push(Event::ScaleFactorChanged {
+                scale_factor,
+                suggested_size,
+            });
+            state.set_inner_size(suggested_size, &self.events);
+        }
+    }
+
+    /// The video mode an exclusive fullscreen window has switched its monitor to, if any.
+    pub fn video_mode(&self) -> Option<VideoMode> {
+        mutex_lock(&self.state).video_mode.clone()
+    }
+
+    /// Gives or takes away focus, sending [`Event::Focus`].
+    ///
+    /// Like on the native backends, an exclusive fullscreen window undoes its video mode while it's not focused.
+    pub fn set_focus(&self, focus: bool) {
+        let mut state = mutex_lock(&self.state);
+        state.video_mode = match (focus, &state.fullscreen) {
+            (true, Some(Fullscreen::Exclusive(_, mode))) => Some(mode.clone()),
+            _ => None,
+        };
+        self.events.push(Event::Focus(focus));
+    }
+
+    /// The current title of the window.
+    pub fn title(&self) -> String {
+        mutex_lock(&self.state).title.clone()
+    }
+
+    /// Connects a monitor with a single 60 Hz video mode, sending [`Event::MonitorConnected`].
+    ///
+    /// Every window starts out with a primary 1920x1080 monitor at the origin.
+    pub fn connect_monitor(&self, name: &str, position: PhysicalPosition, size: PhysicalSize) -> Monitor {
+        let mut state = mutex_lock(&self.state);
+        let id = state.monitors.iter().map(|x| x.id + 1).max().unwrap_or(0);
+        let monitor = headless_monitor(id, name, position, size, state.scale_factor);
+        let mut monitors = state.monitors.clone();
+        monitors.push(monitor.clone());
+        monitor::update_monitors(&mut state.monitors, monitors, &self.events);
+        monitor
+    }
+
+    /// Disconnects a monitor, sending [`Event::MonitorDisconnected`] if it was connected.
+    pub fn disconnect_monitor(&self, monitor: &Monitor) {
+        let mut state = mutex_lock(&self.state);
+        let monitors = state.monitors.iter().filter(|x| x.id != monitor.id).cloned().collect();
+        monitor::update_monitors(&mut state.monitors, monitors, &self.events);
+    }
+}
+
+fn headless_monitor(
+    id: usize,
+    name: &str,
+    position: PhysicalPosition,
+    size: PhysicalSize,
+    scale_factor: f64,
+) -> Monitor {
+    Monitor {
+        id,
+        name: name.into(),
+        position,
+        size,
+        physical_size_mm: None,
+        refresh_rate_millihertz: Some(60_000),
+        scale_factor,
+        primary: id == 0,
+        video_modes: vec![VideoMode {
+            id: 0,
+            size,
+            bit_depth: 32,
+            refresh_rate_millihertz: 60_000,
+        }],
+    }
+}
+
+/// Everything a native backend would keep on the other end of the display server connection.
+struct WindowState {
+    class_name: String,
+    // The clipboard and primary selection, as MIME types and data
+    clipboards: [Vec<(String, Vec<u8>)>; 2],
+    cursor: Cursor,
+    cursor_grab: CursorGrab,
+    cursor_position: Option<PhysicalPosition>,
+    cursor_visible: bool,
+    drag_accepted: Option<(DragFormat, DropAction)>,
+    dragged_out: Option<(DragData, DragIcon)>,
+    fullscreen: Option<Fullscreen>,
+    icon: Option<Icon>,
+    ime: bool,
+    ime_cursor_area: Option<(PhysicalPosition, PhysicalSize)>,
+    inner_size: PhysicalSize,
+    monitors: Vec<Monitor>,
+    open: bool,
+    position: PhysicalPosition,
+    scale_factor: f64,
+    title: String,
+    video_mode: Option<VideoMode>,
+    visible: bool,
+
+    // Where the window was before it went fullscreen, to put it back after
+    windowed: Option<(PhysicalPosition, PhysicalSize)>,
+}
+
+impl WindowState {
+    fn set_inner_size(&mut self, size: PhysicalSize, events: &EventQueue) {
+        if self.inner_size != size {
+            self.inner_size = size;
+            events.push(Event::Resized(size));
+        }
+    }
+
+    fn set_position(&mut self, position: PhysicalPosition, events: &EventQueue) {
+        if self.position != position {
+            self.position = position;
+            events.push(Event::Moved(position));
+        }
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: Option<Fullscreen>, events: &EventQueue) {
+        let (position, size) = match &fullscreen {
+            Some(Fullscreen::Borderless(monitor)) => {
+                match monitor
+                    .as_ref()
+                    .or_else(|| monitor::monitor_for_rect(&self.monitors, self.position, self.inner_size))
+                {
+                    Some(monitor) => (monitor.position, monitor.size),
+                    None => return,
+                }
+            },
+            Some(Fullscreen::Exclusive(monitor, mode)) => (monitor.position, mode.size),
+            None => match self.windowed.take() {
+                Some(windowed) => windowed,
+                None => return,
+            },
+        };
+        if fullscreen.is_some() && self.windowed.is_none() {
+            self.windowed = Some((self.position, self.inner_size));
+        }
+        self.video_mode = match &fullscreen {
+            Some(Fullscreen::Exclusive(_, mode)) => Some(mode.clone()),
+            _ => None,
+        };
+        self.fullscreen = fullscreen;
+        self.set_position(position, events);
+        self.set_inner_size(size, events);
+    }
+}
+
+pub(crate) struct WindowImpl {
+    events: Arc<EventQueue>,
+    state: Arc<Mutex<WindowState>>,
+}
+
+impl WindowImpl {
+    pub(crate) fn new(builder: &WindowBuilder) -> Result<Self, Error> {
+        let events = Arc::new(EventQueue::new());
+        let mut state = WindowState {
+            class_name: builder.class_name.clone().into_owned(),
+            clipboards: Default::default(),
+            cursor: Cursor::default(),
+            cursor_grab: CursorGrab::None,
+            cursor_position: None,
+            cursor_visible: true,
+            drag_accepted: None,
+            dragged_out: None,
+            fullscreen: None,
+            icon: builder.icon.clone(),
+            ime: builder.ime,
+            ime_cursor_area: None,
+            inner_size: builder.inner_size.to_physical(1.0),
+            monitors: vec![headless_monitor(
+                0,
+                "headless",
+                PhysicalPosition::new(0, 0),
+                PhysicalSize::new(1920, 1080),
+                1.0,
+            )],
+            open: true,
+            position: builder.position.map_or_else(Default::default, |x| x.to_physical(1.0)),
+            scale_factor: 1.0,
+            title: builder.title.clone().into_owned(),
+            video_mode: None,
+            visible: true,
+            windowed: None,
+        };
+        state.set_fullscreen(builder.fullscreen.clone(), &events);
+        Ok(Self {
+            events,
+            state: Arc::new(Mutex::new(state)),
+        })
+    }
+
+    #[cfg(feature = "raw-window-handle")]
+    pub(crate) fn raw_window_handle(
+        &self,
+    ) -> Result<raw_window_handle::RawWindowHandle, raw_window_handle::HandleError> {
+        // There's nothing to render to
+        Err(raw_window_handle::HandleError::NotSupported)
+    }
+
+    #[cfg(feature = "raw-window-handle")]
+    pub(crate) fn raw_display_handle(
+        &self,
+    ) -> Result<raw_window_handle::RawDisplayHandle, raw_window_handle::HandleError> {
+        Err(raw_window_handle::HandleError::NotSupported)
+    }
+
+    #[inline]
+    pub(crate) fn events(&self) -> &Arc<EventQueue> {
+        &self.events
+    }
+
+    pub(crate) fn available_monitors(&self) -> Vec<Monitor> {
+        mutex_lock(&self.state).monitors.clone()
+    }
+
+    pub(crate) fn current_monitor(&self) -> Option<Monitor> {
+        let state = mutex_lock(&self.state);
+        monitor::monitor_for_rect(&state.monitors, state.position, state.inner_size).cloned()
+    }
+
+    pub(crate) fn fullscreen(&self) -> Option<Fullscreen> {
+        mutex_lock(&self.state).fullscreen.clone()
+    }
+
+    pub(crate) fn inner_size(&self) -> PhysicalSize {
+        mutex_lock(&self.state).inner_size
+    }
+
+    pub(crate) fn outer_size(&self) -> PhysicalSize {
+        self.inner_size()
+    }
+
+    pub(crate) fn position(&self) -> Option<PhysicalPosition> {
+        Some(mutex_lock(&self.state).position)
+    }
+
+    pub(crate) fn scale_factor(&self) -> f64 {
+        mutex_lock(&self.state).scale_factor
+    }
+
+    // Like a real display server, changes are reported back with events
+
+    pub(crate) fn accept_drag(&self, accept: Option<(DragFormat, DropAction)>) {
+        mutex_lock(&self.state).drag_accepted = accept;
+    }
+
+    pub(crate) fn start_drag(&self, data: DragData, icon: DragIcon) -> Result<(), Error> {
+        mutex_lock(&self.state).dragged_out = Some((data, icon));
+        Ok(())
+    }
+
+    pub(crate) fn clipboard_formats(&self, selection: Selection) -> Result<Vec<String>, Error> {
+        let state = mutex_lock(&self.state);
+        Ok(state.clipboards[selection as usize]
+            .iter()
+            .map(|x| x.0.clone())
+            .collect())
+    }
+
+    pub(crate) fn clipboard_get(&self, selection: Selection, mime: &str) -> Result<Option<Vec<u8>>, Error> {
+        let state = mutex_lock(&self.state);
+        let clipboard = &state.clipboards[selection as usize];
+        Ok(clipboard.iter().find(|x| x.0 == mime).map(|x| x.1.clone()))
+    }
+
+    pub(crate) fn clipboard_set(&self, selection: Selection, data: Vec<(String, Vec<u8>)>) -> Result<(), Error> {
+        mutex_lock(&self.state).clipboards[selection as usize] = data;
+        Ok(())
+    }
+
+    pub(crate) fn set_cursor(&self, cursor: Cursor) {
+        mutex_lock(&self.state).cursor = cursor;
+    }
+
+    pub(crate) fn set_cursor_grab(&self, grab: CursorGrab) {
+        mutex_lock(&self.state).cursor_grab = grab;
+    }
+
+    pub(crate) fn set_cursor_position(&self, position: PhysicalPosition) {
+        mutex_lock(&self.state).cursor_position = Some(position);
+    }
+
+    pub(crate) fn set_cursor_visible(&self, visible: bool) {
+        mutex_lock(&self.state).cursor_visible = visible;
+    }
+
+    pub(crate) fn set_fullscreen(&self, fullscreen: Option<Fullscreen>) {
+        mutex_lock(&self.state).set_fullscreen(fullscreen, &self.events);
+    }
+
+    pub(crate) fn set_icon(&self, icon: Option<Icon>) {
+        mutex_lock(&self.state).icon = icon;
+    }
+
+    pub(crate) fn set_ime_cursor_area(&self, position: PhysicalPosition, size: PhysicalSize) {
+        let mut state = mutex_lock(&self.state);
+        if state.ime {
+            state.ime_cursor_area = Some((position, size));
+        }
+    }
+
+    pub(crate) fn set_inner_size(&self, size: PhysicalSize) {
+        mutex_lock(&self.state).set_inner_size(size, &self.events);
+    }
+
+    pub(crate) fn set_outer_size(&self, size: PhysicalSize) {
+        self.set_inner_size(size);
+    }
+
+    pub(crate) fn set_position(&self, position: PhysicalPosition) {
+        mutex_lock(&self.state).set_position(position, &self.events);
+    }
+
+    pub(crate) fn set_title(&self, title: &str) {
+        mutex_lock(&self.state).title = title.into();
+    }
+
+    pub(crate) fn set_visible(&self, visible: bool) {
+        mutex_lock(&self.state).visible = visible;
+    }
+}
+
+impl Drop for WindowImpl {
+    fn drop(&mut self) {
+        let mut state = mutex_lock(&self.state);
+        state.open = false;
+        state.video_mode = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WindowExt;
+    use crate::{
+        clipboard::{ClipboardData, ClipboardImage},
+        cursor::{Cursor, CursorShape},
+        dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize},
+        drag::{DragData, DragFormat, DragIcon, DropAction},
+        event::Event,
+        icon::{Icon, IconImage},
+        window::{CursorGrab, Fullscreen, Window},
+    };
+    use std::{
+        sync::mpsc,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    #[test]
+    fn controller_reflects_window() {
+        let icon = Icon::from(IconImage::from_rgba(vec![0; 4], PhysicalSize::new(1, 1)).unwrap());
+        let mut builder = Window::builder();
+        builder
+            .title("headless")
+            .class_name("ramen_headless")
+            .icon(Some(icon.clone()));
+        let window = builder.build().unwrap();
+        let controller = window.controller();
+        assert_eq!(controller.title(), "headless");
+        assert_eq!(controller.class_name(), "ramen_headless");
+        assert_eq!(controller.icon(), Some(icon));
+        window.set_icon(None);
+        assert_eq!(controller.icon(), None);
+        window.set_title("renamed");
+        assert_eq!(controller.title(), "renamed");
+        assert!(controller.is_visible());
+        window.set_visible(false);
+        assert!(!controller.is_visible());
+        assert!(controller.is_open());
+        drop(window);
+        assert!(!controller.is_open());
+    }
+
+    #[test]
+    fn cursor_state_is_recorded() {
+        let window = Window::builder().build().unwrap();
+        let controller = window.controller();
+        assert_eq!(controller.cursor(), Cursor::Shape(CursorShape::Arrow));
+        assert_eq!(controller.cursor_grab(), CursorGrab::None);
+        assert!(controller.is_cursor_visible());
+        window.set_cursor(CursorShape::Hand);
+        window.set_cursor_grab(CursorGrab::Locked);
+        window.set_cursor_visible(false);
+        assert_eq!(controller.cursor(), Cursor::Shape(CursorShape::Hand));
+        assert_eq!(controller.cursor_grab(), CursorGrab::Locked);
+        assert!(!controller.is_cursor_visible());
+
+        controller.set_scale_factor(2.0);
+        window.set_cursor_position(LogicalPosition::new(10.0, 20.0));
+        assert_eq!(controller.cursor_position(), Some(PhysicalPosition::new(20, 40)));
+    }
+
+    #[test]
+    fn clipboard_round_trips() {
+        let window = Window::builder().build().unwrap();
+        let clipboard = window.clipboard();
+        assert_eq!(clipboard.get_text().unwrap(), None);
+        clipboard.set_text("héllo").unwrap();
+        assert_eq!(clipboard.get_text().unwrap().as_deref(), Some("héllo"));
+        assert_eq!(window.primary_selection().get_text().unwrap(), None);
+
+        // Images are offered as PNG and BMP, and whatever else is given stays first in line
+        let image = ClipboardImage::from_rgba(vec![10, 20, 30, 40], PhysicalSize::new(1, 1)).unwrap();
+        clipboard
+            .set([
+                ClipboardData::Custom("application/x-thing".into(), vec![1, 2, 3]),
+                ClipboardData::Image(image.clone()),
+            ])
+            .unwrap();
+        assert_eq!(
+            clipboard.formats().unwrap(),
+            ["application/x-thing", "image/png", "image/bmp"]
+        );
+        assert_eq!(clipboard.get_image().unwrap(), Some(image));
+        assert_eq!(clipboard.get_data("application/x-thing").unwrap(), Some(vec![1, 2, 3]));
+        assert_eq!(clipboard.get_text().unwrap(), None);
+
+        assert!(clipboard.set(None).is_err());
+        clipboard.clear().unwrap();
+        assert_eq!(clipboard.formats().unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn drags_start_out_rejected() {
+        let mut window = Window::builder().build().unwrap();
+        let controller = window.controller();
+        window.accept_drag(Some((DragFormat::Text, DropAction::Copy)));
+        controller.drag_enter(vec![DragFormat::Files]);
+        assert_eq!(controller.drag_accepted(), None);
+        assert_eq!(
+            window.poll_events().collect::<Vec<_>>(),
+            [Event::DragEntered(vec![DragFormat::Files])]
+        );
+        window.accept_drag(Some((DragFormat::Files, DropAction::Move)));
+        assert_eq!(controller.drag_accepted(), Some((DragFormat::Files, DropAction::Move)));
+    }
+
+    #[test]
+    fn drags_out_finish_once() {
+        let mut window = Window::builder().build().unwrap();
+        let controller = window.controller();
+        let data = DragData::Text("hi".into());
+        window.start_drag(data.clone(), DragIcon::None).unwrap();
+        assert_eq!(controller.dragged_out(), Some((data, DragIcon::None)));
+        controller.finish_drag_out(Some(DropAction::Move));
+        controller.finish_drag_out(None);
+        assert_eq!(controller.dragged_out(), None);
+        assert_eq!(
+            window.poll_events().collect::<Vec<_>>(),
+            [Event::DragFinished(Some(DropAction::Move))]
+        );
+    }
+
+    #[test]
+    fn pushed_events_arrive_in_order() {
+        let mut window = Window::builder().build().unwrap();
+        let controller = window.controller();
+        assert_eq!(window.poll_events().count(), 0);
+        controller.push_event(Event::Focus(true));
+        controller.push_event(Event::CloseRequested);
+        assert_eq!(
+            window.wait_events().collect::<Vec<_>>(),
+            [Event::Focus(true), Event::CloseRequested]
+        );
+    }
+
+    #[test]
+    fn timers_go_off_in_order() {
+        let mut window = Window::builder().build().unwrap();
+        let start = Instant::now();
+        window.schedule_timer(start + Duration::from_millis(20), 2);
+        window.schedule_timer(start, 1);
+        assert_eq!(window.poll_events().collect::<Vec<_>>(), [Event::Timer(1)]);
+        assert_eq!(window.wait_events().collect::<Vec<_>>(), [Event::Timer(2)]);
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert_eq!(window.wait_events_timeout(Duration::from_millis(5)).count(), 0);
+    }
+
+    #[test]
+    fn proxies_wake_the_window() {
+        let mut window = Window::builder().build().unwrap();
+        let proxy = window.create_proxy::<String>();
+        let sender = thread::spawn(move || proxy.send_event("loaded".into()));
+        let event = window.wait_events().next();
+        sender.join().unwrap().unwrap();
+        match event {
+            Some(Event::User(message)) => {
+                assert!(!message.is::<u32>());
+                assert_eq!(message.downcast::<String>().unwrap(), "loaded");
+            },
+            event => panic!("expected a user event, got {:?}", event),
+        }
+
+        // Messages only need to be `Send`, like the receiving end of a channel
+        let proxy = window.create_proxy::<mpsc::Receiver<u32>>();
+        let (sender, receiver) = mpsc::channel();
+        proxy.send_event(receiver).unwrap();
+        sender.send(7).unwrap();
+        match window.poll_events().next() {
+            Some(Event::User(message)) => {
+                let message = message.downcast::<String>().unwrap_err();
+                assert_eq!(message.downcast::<mpsc::Receiver<u32>>().unwrap().recv(), Ok(7));
+            },
+            event => panic!("expected a user event, got {:?}", event),
+        }
+
+        let proxy = window.create_proxy::<u32>();
+        window.close();
+        assert_eq!(proxy.send_event(1), Err(1));
+    }
+
+    #[test]
+    fn geometry_changes_send_events() {
+        let mut builder = Window::builder();
+        builder
+            .inner_size(PhysicalSize::new(640, 480))
+            .position(PhysicalPosition::new(10, 20));
+        let mut window = builder.build().unwrap();
+        let controller = window.controller();
+        assert_eq!(window.inner_size(), PhysicalSize::new(640, 480));
+        assert_eq!(window.position(), Some(PhysicalPosition::new(10, 20)));
+        window.set_outer_size(PhysicalSize::new(800, 600));
+        window.set_position(PhysicalPosition::new(10, 20));
+        assert_eq!(controller.inner_size(), PhysicalSize::new(800, 600));
+        assert_eq!(
+            window.poll_events().collect::<Vec<_>>(),
+            [Event::Resized(PhysicalSize::new(800, 600))]
+        );
+    }
+
+    #[test]
+    fn user_changes_update_the_window() {
+        let mut window = Window::builder().build().unwrap();
+        let controller = window.controller();
+        controller.resize(PhysicalSize::new(300, 200));
+        controller.move_to(PhysicalPosition::new(50, 60));
+        controller.move_to(PhysicalPosition::new(50, 60));
+        assert_eq!(window.inner_size(), PhysicalSize::new(300, 200));
+        assert_eq!(window.outer_size(), PhysicalSize::new(300, 200));
+        assert_eq!(window.position(), Some(PhysicalPosition::new(50, 60)));
+        assert_eq!(
+            window.poll_events().collect::<Vec<_>>(),
+            [
+                Event::Resized(PhysicalSize::new(300, 200)),
+                Event::Moved(PhysicalPosition::new(50, 60)),
+            ]
+        );
+    }
+
+    #[test]
+    fn logical_sizes_follow_the_scale_factor() {
+        let mut builder = Window::builder();
+        builder.inner_size(LogicalSize::new(400.0, 300.0));
+        let mut window = builder.build().unwrap();
+        let controller = window.controller();
+        controller.set_scale_factor(2.0);
+        assert_eq!(window.scale_factor(), 2.0);
+        assert_eq!(window.inner_size(), PhysicalSize::new(800, 600));
+        assert_eq!(
+            window.poll_events().next(),
+            Some(Event::ScaleFactorChanged {
+                scale_factor: 2.0,
+                suggested_size: PhysicalSize::new(800, 600),
+            })
+        );
+        window.set_inner_size(LogicalSize::new(100.0, 50.0));
+        assert_eq!(window.inner_size(), PhysicalSize::new(200, 100));
+    }
+
+    #[test]
+    fn monitors_can_be_connected() {
+        let mut builder = Window::builder();
+        builder
+            .inner_size(PhysicalSize::new(800, 600))
+            .position(PhysicalPosition::new(100, 100));
+        let mut window = builder.build().unwrap();
+        let controller = window.controller();
+        let primary = window.current_monitor().unwrap();
+        assert!(primary.is_primary());
+        let monitor =
+            controller.connect_monitor("right", PhysicalPosition::new(1920, 0), PhysicalSize::new(1280, 1024));
+        assert_eq!(window.available_monitors(), [primary.clone(), monitor.clone()]);
+        window.set_position(PhysicalPosition::new(2000, 100));
+        assert_eq!(window.current_monitor(), Some(monitor.clone()));
+        controller.disconnect_monitor(&monitor);
+        assert_eq!(window.current_monitor(), Some(primary));
+        assert_eq!(
+            window.poll_events().collect::<Vec<_>>(),
+            [
+                Event::MonitorConnected(monitor.clone()),
+                Event::Moved(PhysicalPosition::new(2000, 100)),
+                Event::MonitorDisconnected(monitor),
+            ]
+        );
+    }
+
+    #[test]
+    fn fullscreen_restores_the_window() {
+        let mut builder = Window::builder();
+        builder
+            .inner_size(PhysicalSize::new(800, 600))
+            .position(PhysicalPosition::new(100, 100));
+        let mut window = builder.build().unwrap();
+        let controller = window.controller();
+        let monitor = window.current_monitor().unwrap();
+        let mode = monitor.video_modes()[0].clone();
+        window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+        assert_eq!(window.inner_size(), monitor.size());
+        assert_eq!(controller.video_mode(), None);
+        window.set_fullscreen(Some(Fullscreen::Exclusive(monitor.clone(), mode.clone())));
+        assert_eq!(controller.video_mode(), Some(mode.clone()));
+        controller.set_focus(false);
+        assert_eq!(controller.video_mode(), None);
+        controller.set_focus(true);
+        assert_eq!(controller.video_mode(), Some(mode));
+        window.set_fullscreen(None);
+        assert_eq!(window.fullscreen(), None);
+        assert_eq!(controller.video_mode(), None);
+        assert_eq!(
+            window.poll_events().collect::<Vec<_>>(),
+            [
+                Event::Moved(PhysicalPosition::new(0, 0)),
+                Event::Resized(monitor.size()),
+                Event::Focus(false),
+                Event::Focus(true),
+                Event::Moved(PhysicalPosition::new(100, 100)),
+                Event::Resized(PhysicalSize::new(800, 600)),
+            ]
+        );
+    }
+
+    #[test]
+    fn ime_cursor_area_follows_the_scale_factor() {
+        let window = Window::builder().build().unwrap();
+        let controller = window.controller();
+        assert!(controller.ime());
+        controller.set_scale_factor(2.0);
+        window.set_ime_cursor_area(LogicalPosition::new(10.0, 20.0), LogicalSize::new(1.0, 16.0));
+        assert_eq!(
+            controller.ime_cursor_area(),
+            Some((PhysicalPosition::new(20, 40), PhysicalSize::new(2, 32)))
+        );
+
+        let mut builder = Window::builder();
+        builder.ime(false);
+        let window = builder.build().unwrap();
+        let controller = window.controller();
+        window.set_ime_cursor_area(PhysicalPosition::new(10, 20), PhysicalSize::new(1, 16));
+        assert!(!controller.ime());
+        assert_eq!(controller.ime_cursor_area(), None);
+    }
+
+    #[test]
+    fn close_requests_can_be_ignored() {
+        let mut window = Window::builder().build().unwrap();
+        let controller = window.controller();
+        controller.push_event(Event::CloseRequested);
+        assert_eq!(window.poll_events().next(), Some(Event::CloseRequested));
+        assert!(controller.is_open());
+        window.close();
+        assert!(!controller.is_open());
+    }
+
+    #[cfg(feature = "raw-window-handle")]
+    #[test]
+    fn raw_handles_are_not_supported() {
+        use raw_window_handle::{HandleError, HasDisplayHandle, HasWindowHandle};
+
+        let window = Window::builder().build().unwrap();
+        assert!(matches!(window.window_handle(), Err(HandleError::NotSupported)));
+        assert!(matches!(window.display_handle(), Err(HandleError::NotSupported)));
+    }
+}
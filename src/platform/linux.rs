@@ -0,0 +1,214 @@
+//! Linux-specific API extensions, which work the same with X11 and Wayland, and the glue picking between
+//! them at runtime.
+
+use crate::{
+    clipboard::Selection,
+    cursor::Cursor,
+    dpi::{PhysicalPosition, PhysicalSize},
+    drag::{DragData, DragFormat, DragIcon, DropAction},
+    error::Error,
+    event::EventQueue,
+    icon::Icon,
+    monitor::Monitor,
+    platform::{posix, wayland, x11},
+    window::{CursorGrab, Fullscreen, Window, WindowBuilder},
+};
+use std::{env, os::unix::io::RawFd, sync::Arc};
+
+/// What a file descriptor is watched for, see [`WindowExt::register_fd`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Interest {
+    /// It can be read from without blocking.
+    Readable,
+
+    /// It can be written to without blocking.
+    Writable,
+
+    /// Either of those.
+    Both,
+}
+
+/// Linux-specific API extensions to [`Window`].
+pub trait WindowExt {
+    /// Watches a file descriptor, such as a socket or an eventfd, on the window's thread, sending
+    /// [`Event::FdReady`](crate::event::Event::FdReady) with `token` once it's ready. That wakes up
+    /// [`wait_events`](Window::wait_events), so the thread waiting for events can wait on other things too.
+    ///
+    /// It's only sent the once, after which the file descriptor's no longer watched, so it has to be registered
+    /// again once it's been read or written. Registering a token that's already in use replaces what it was
+    /// watching. Errors and hangups count as ready, as they're found out about by reading or writing.
+    ///
+    /// The file descriptor has to stay open for as long as it's watched. It's still the caller's, and isn't closed.
+    fn register_fd(&self, fd: RawFd, interest: Interest, token: u64);
+
+    /// Stops watching whatever was registered with `token`, if it hasn't been sent already.
+    fn deregister_fd(&self, token: u64);
+}
+
+impl WindowExt for Window {
+    fn register_fd(&self, fd: RawFd, interest: Interest, token: u64) {
+        let events = match interest {
+            Interest::Readable => posix::POLLIN,
+            Interest::Writable => posix::POLLOUT,
+            Interest::Both => posix::POLLIN | posix::POLLOUT,
+        };
+        self.imp.register_fd(fd, events, token)
+    }
+
+    fn deregister_fd(&self, token: u64) {
+        self.imp.deregister_fd(token)
+    }
+}
+
+pub(crate) enum WindowImpl {
+    Wayland(wayland::WindowImpl),
+    X11(x11::WindowImpl),
+}
+
+/// Forwards a method call to whichever backend is in use.
+macro_rules! dispatch {
+    ($self:ident.$method:ident($($arg:expr),*)) => {
+        match $self {
+            WindowImpl::Wayland(imp) => imp.$method($($arg),*),
+            WindowImpl::X11(imp) => imp.$method($($arg),*),
+        }
+    };
+}
+
+impl WindowImpl {
+    pub(crate) fn new(builder: &WindowBuilder) -> Result<Self, Error> {
+        // Wayland sessions usually run Xwayland too, so only prefer X11 if there's no compositor around
+        // Whichever one is preferred, the other is a fallback for when it fails
+        let prefer_wayland = env::var_os("WAYLAND_DISPLAY").is_some_and(|x| !x.is_empty());
+        let wayland = || wayland::WindowImpl::new(builder).map(Self::Wayland);
+        let x11 = || x11::WindowImpl::new(builder).map(Self::X11);
+        if prefer_wayland {
+            wayland().or_else(|err| x11().map_err(|_| err))
+        } else {
+            x11().or_else(|err| wayland().map_err(|_| err))
+        }
+    }
+
+    #[inline]
+    pub(crate) fn events(&self) -> &Arc<EventQueue> {
+        dispatch!(self.events())
+    }
+
+    fn register_fd(&self, fd: RawFd, events: posix::c_short, token: u64) {
+        dispatch!(self.register_fd(fd, events, token))
+    }
+
+    fn deregister_fd(&self, token: u64) {
+        dispatch!(self.deregister_fd(token))
+    }
+
+    pub(crate) fn available_monitors(&self) -> Vec<Monitor> {
+        dispatch!(self.available_monitors())
+    }
+
+    pub(crate) fn current_monitor(&self) -> Option<Monitor> {
+        dispatch!(self.current_monitor())
+    }
+
+    pub(crate) fn fullscreen(&self) -> Option<Fullscreen> {
+        dispatch!(self.fullscreen())
+    }
+
+    pub(crate) fn inner_size(&self) -> PhysicalSize {
+        dispatch!(self.inner_size())
+    }
+
+    pub(crate) fn outer_size(&self) -> PhysicalSize {
+        dispatch!(self.outer_size())
+    }
+
+    pub(crate) fn position(&self) -> Option<PhysicalPosition> {
+        dispatch!(self.position())
+    }
+
+    pub(crate) fn scale_factor(&self) -> f64 {
+        dispatch!(self.scale_factor())
+    }
+
+    #[cfg(feature = "raw-window-handle")]
+    pub(crate) fn raw_window_handle(
+        &self,
+    ) -> Result<raw_window_handle::RawWindowHandle, raw_window_handle::HandleError> {
+        dispatch!(self.raw_window_handle())
+    }
+
+    #[cfg(feature = "raw-window-handle")]
+    pub(crate) fn raw_display_handle(
+        &self,
+    ) -> Result<raw_window_handle::RawDisplayHandle, raw_window_handle::HandleError> {
+        dispatch!(self.raw_display_handle())
+    }
+
+    pub(crate) fn accept_drag(&self, accept: Option<(DragFormat, DropAction)>) {
+        dispatch!(self.accept_drag(accept))
+    }
+
+    pub(crate) fn start_drag(&self, data: DragData, icon: DragIcon) -> Result<(), Error> {
+        dispatch!(self.start_drag(data, icon))
+    }
+
+    pub(crate) fn clipboard_formats(&self, selection: Selection) -> Result<Vec<String>, Error> {
+        dispatch!(self.clipboard_formats(selection))
+    }
+
+    pub(crate) fn clipboard_get(&self, selection: Selection, mime: &str) -> Result<Option<Vec<u8>>, Error> {
+        dispatch!(self.clipboard_get(selection, mime))
+    }
+
+    pub(crate) fn clipboard_set(&self, selection: Selection, data: Vec<(String, Vec<u8>)>) -> Result<(), Error> {
+        dispatch!(self.clipboard_set(selection, data))
+    }
+
+    pub(crate) fn set_cursor(&self, cursor: Cursor) {
+        dispatch!(self.set_cursor(cursor))
+    }
+
+    pub(crate) fn set_cursor_grab(&self, grab: CursorGrab) {
+        dispatch!(self.set_cursor_grab(grab))
+    }
+
+    pub(crate) fn set_cursor_position(&self, position: PhysicalPosition) {
+        dispatch!(self.set_cursor_position(position))
+    }
+
+    pub(crate) fn set_cursor_visible(&self, visible: bool) {
+        dispatch!(self.set_cursor_visible(visible))
+    }
+
+    pub(crate) fn set_fullscreen(&self, fullscreen: Option<Fullscreen>) {
+        dispatch!(self.set_fullscreen(fullscreen))
+    }
+
+    pub(crate) fn set_icon(&self, icon: Option<Icon>) {
+        dispatch!(self.set_icon(icon))
+    }
+
+    pub(crate) fn set_ime_cursor_area(&self, position: PhysicalPosition, size: PhysicalSize) {
+        dispatch!(self.set_ime_cursor_area(position, size))
+    }
+
+    pub(crate) fn set_inner_size(&self, size: PhysicalSize) {
+        dispatch!(self.set_inner_size(size))
+    }
+
+    pub(crate) fn set_outer_size(&self, size: PhysicalSize) {
+        dispatch!(self.set_outer_size(size))
+    }
+
+    pub(crate) fn set_position(&self, position: PhysicalPosition) {
+        dispatch!(self.set_position(position))
+    }
+
+    pub(crate) fn set_title(&self, title: &str) {
+        dispatch!(self.set_title(title))
+    }
+
+    pub(crate) fn set_visible(&self, visible: bool) {
+        dispatch!(self.set_visible(visible))
+    }
+}
@@ -0,0 +1,342 @@
+//! Bindings to the bits of POSIX shared between the Unix backends.
+
+#![allow(bad_style)]
+
+use crate::{
+    error::{Error, ErrorKind},
+    event::{Event, EventQueue},
+};
+pub use std::os::raw::{c_char, c_int, c_long, c_short, c_uint, c_ulong, c_void};
+use std::{
+    ffi::CString,
+    fs::File,
+    io::{self, Read, Write},
+    os::unix::io::FromRawFd,
+    time::{Duration, Instant},
+};
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct pollfd {
+    pub fd: c_int,
+    pub events: c_short,
+    pub revents: c_short,
+}
+
+pub const ENOMEM: c_int = 12;
+pub const LC_CTYPE: c_int = 0;
+pub const MAP_FAILED: *mut c_void = !0 as *mut c_void;
+pub const MAP_PRIVATE: c_int = 0x02;
+pub const MAP_SHARED: c_int = 0x01;
+pub const MFD_CLOEXEC: c_uint = 0x0001;
+pub const O_CLOEXEC: c_int = 0o2000000;
+pub const O_NONBLOCK: c_int = 0o4000;
+pub const POLLIN: c_short = 0x001;
+pub const POLLOUT: c_short = 0x004;
+pub const PROT_READ: c_int = 0x1;
+pub const PROT_WRITE: c_int = 0x2;
+pub const RTLD_LAZY: c_int = 0x001;
+pub const RTLD_LOCAL: c_int = 0;
+
+extern "C" {
+    pub fn close(fd: c_int) -> c_int;
+    pub fn ftruncate(fd: c_int, length: c_long) -> c_int;
+    pub fn memfd_create(name: *const c_char, flags: c_uint) -> c_int;
+    pub fn mmap(addr: *mut c_void, length: usize, prot: c_int, flags: c_int, fd: c_int, offset: c_long) -> *mut c_void;
+    pub fn munmap(addr: *mut c_void, length: usize) -> c_int;
+    pub fn pipe2(pipefd: *mut c_int, flags: c_int) -> c_int;
+    pub fn poll(fds: *mut pollfd, nfds: c_ulong, timeout: c_int) -> c_int;
+    pub fn read(fd: c_int, buf: *mut c_void, count: usize) -> isize;
+    pub fn setlocale(category: c_int, locale: *const c_char) -> *mut c_char;
+    pub fn write(fd: c_int, buf: *const c_void, count: usize) -> isize;
+}
+
+// Merged into libc as of glibc 2.34, but older versions still need the library
+#[link(name = "dl")]
+extern "C" {
+    pub fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
+    pub fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+}
+
+/// Declares a struct of functions from a shared library, with a `load` function which `dlopen`s it.
+///
+/// Loading returns `None` if the library or any of the functions are missing.
+macro_rules! dynamic_library {
+    (
+        $(#[$attr:meta])*
+        pub struct $lib:ident($soname:literal) {
+            $(fn $name:ident($($params:tt)*) $(-> $ret:ty)?;)*
+        }
+    ) => {
+        $(#[$attr])*
+        pub struct $lib {
+            $(pub $name: unsafe extern "C" fn($($params)*) $(-> $ret)?,)*
+        }
+
+        impl $lib {
+            pub unsafe fn load() -> Option<Self> {
+                use $crate::platform::posix::{dlopen, dlsym, RTLD_LAZY, RTLD_LOCAL};
+                let lib = dlopen(concat!($soname, "\0").as_ptr().cast(), RTLD_LAZY | RTLD_LOCAL);
+                if lib.is_null() {
+                    return None
+                }
+                Some(Self {$(
+                    $name: {
+                        let sym = dlsym(lib, concat!(stringify!($name), "\0").as_ptr().cast());
+                        if sym.is_null() {
+                            return None
+                        }
+                        ::std::mem::transmute::<*mut ::core::ffi::c_void, unsafe extern "C" fn($($params)*) $(-> $ret)?>(sym)
+                    },
+                )*})
+            }
+        }
+    };
+}
+pub(crate) use dynamic_library;
+
+/// Declares a library with [`dynamic_library!`], a static it's loaded into on first use, and a function calling
+/// into it for each of its functions, so that they can be called as if the library was linked.
+///
+/// The functions are only for once the static has been checked to be `Some`. Variadic ones don't get one,
+/// as they can't be declared in Rust, so they're called through the static.
+macro_rules! dynamic_library_fns {
+    (
+        $(#[$attr:meta])*
+        pub struct $lib:ident($soname:literal) in $static:ident {
+            $(fn $name:ident($($params:tt)*) $(-> $ret:ty)?;)*
+        }
+    ) => {
+        $crate::platform::posix::dynamic_library! {
+            $(#[$attr])*
+            pub struct $lib($soname) {
+                $(fn $name($($params)*) $(-> $ret)?;)*
+            }
+        }
+
+        pub static $static: $crate::sync::LazyCell<Option<$lib>> =
+            $crate::sync::LazyCell::new(|| unsafe { $lib::load() });
+
+        $($crate::platform::posix::dynamic_library_fns!(@fn $static $name($($params)*) $(-> $ret)?);)*
+    };
+    (@fn $static:ident $name:ident($($arg:ident: $ty:ty),* $(,)?) $(-> $ret:ty)?) => {
+        /// Calls into the library. Must only be used once it's been loaded.
+        #[inline]
+        #[allow(clippy::too_many_arguments)]
+        pub unsafe fn $name($($arg: $ty),*) $(-> $ret)? {
+            match &*$static {
+                Some(lib) => (lib.$name)($($arg),*),
+                None => unreachable!(),
+            }
+        }
+    };
+    (@fn $($variadic:tt)*) => {};
+}
+pub(crate) use dynamic_library_fns;
+
+/// Creates an [`Error`] from the current value of `errno`.
+pub fn errno_error(kind: ErrorKind, message: &'static str) -> Error {
+    let code = io::Error::last_os_error().raw_os_error().unwrap_or(0);
+    let kind = if code == ENOMEM { ErrorKind::OutOfMemory } else { kind };
+    Error::with_code(kind, code, message)
+}
+
+/// Converts a `&str` to a null terminated C string, replacing any interior nulls with spaces.
+pub fn str_to_cstring(src: &str) -> CString {
+    if src.bytes().any(|x| x == 0x00) {
+        // `unwrap` can't fail as every null was just replaced
+        CString::new(src.replace('\0', " ")).unwrap()
+    } else {
+        CString::new(src).unwrap()
+    }
+}
+
+/// Waits until a pipe can be read from or written to (`events` being [`POLLIN`] or [`POLLOUT`]), or `deadline`.
+fn poll_pipe(fd: c_int, events: c_short, deadline: Instant) -> Result<(), Error> {
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let mut pollfd = pollfd { fd, events, revents: 0 };
+        let ready = unsafe { poll(&mut pollfd, 1, remaining.as_millis().min(c_int::MAX as u128) as c_int) };
+        if ready > 0 {
+            return Ok(())
+        } else if ready == 0 {
+            return Err(Error::new(ErrorKind::TimedOut, "timed out waiting for another app"))
+        } else if io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+            return Err(errno_error(ErrorKind::Other, "failed to wait on a pipe"))
+        }
+    }
+}
+
+/// Reads a pipe to the end and closes it, giving up with [`ErrorKind::TimedOut`] if nothing arrives for `timeout`.
+///
+/// This is how other apps hand over data on Wayland, which is written by them as they please.
+pub fn read_pipe(fd: c_int, timeout: Duration) -> Result<Vec<u8>, Error> {
+    // `File` closes the pipe whatever happens
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    let mut data = Vec::new();
+    let mut buffer = [0; 8192];
+    let mut deadline = Instant::now() + timeout;
+    loop {
+        poll_pipe(fd, POLLIN, deadline)?;
+        match file.read(&mut buffer) {
+            Ok(0) => return Ok(data),
+            Ok(count) => {
+                data.extend_from_slice(&buffer[..count]);
+                deadline = Instant::now() + timeout;
+            },
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => {
+                let code = err.raw_os_error().unwrap_or(0);
+                return Err(Error::with_code(
+                    ErrorKind::Other,
+                    code,
+                    "failed to read data from another app",
+                ))
+            },
+        }
+    }
+}
+
+/// Writes all of `data` to a pipe and closes it, giving up if the other end doesn't make room for `timeout`.
+pub fn write_pipe(fd: c_int, data: &[u8], timeout: Duration) -> Result<(), Error> {
+    // A pipe that polls as writable has room for at least a page, which is written without blocking
+    const PAGE: usize = 4096;
+
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    for chunk in data.chunks(PAGE) {
+        poll_pipe(fd, POLLOUT, Instant::now() + timeout)?;
+        if let Err(err) = file.write_all(chunk) {
+            let code = err.raw_os_error().unwrap_or(0);
+            return Err(Error::with_code(
+                ErrorKind::Other,
+                code,
+                "failed to write data for another app",
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// A self-pipe, used to wake up a window thread that's blocked in [`poll`].
+///
+/// Both ends are non-blocking, so waking a thread that's already awake never stalls the caller.
+pub struct WakePipe {
+    read: c_int,
+    write: c_int,
+}
+
+impl WakePipe {
+    pub fn new() -> Result<Self, Error> {
+        let mut fds = [-1 as c_int; 2];
+        if unsafe { pipe2(fds.as_mut_ptr(), O_CLOEXEC | O_NONBLOCK) } == 0 {
+            Ok(Self {
+                read: fds[0],
+                write: fds[1],
+            })
+        } else {
+            Err(errno_error(ErrorKind::Other, "failed to create wake pipe"))
+        }
+    }
+
+    /// The file descriptor to poll for readability.
+    #[inline]
+    pub fn fd(&self) -> c_int {
+        self.read
+    }
+
+    /// Empties the pipe, so that the next [`poll`] blocks again.
+    pub fn drain(&self) {
+        let mut buf = [0u8; 64];
+        while unsafe { read(self.read, buf.as_mut_ptr().cast(), buf.len()) } > 0 {}
+    }
+
+    /// Wakes up the thread polling [`WakePipe::fd`].
+    pub fn wake(&self) {
+        // If the pipe is full (`EAGAIN`) the thread has plenty of wakeups pending already
+        let _ = unsafe { write(self.write, [0u8].as_ptr().cast(), 1) };
+    }
+}
+
+impl Drop for WakePipe {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = close(self.read);
+            let _ = close(self.write);
+        }
+    }
+}
+
+/// File descriptors that a window thread polls for the user, along with its own.
+///
+/// Each is watched until it's ready once, as otherwise polling would spin until the user got around to it.
+#[derive(Default)]
+pub struct WatchedFds(Vec<(c_int, c_short, u64)>);
+
+impl WatchedFds {
+    /// Watches `fd` for `events` ([`POLLIN`] and/or [`POLLOUT`]), in place of whatever `token` was watching.
+    pub fn register(&mut self, fd: c_int, events: c_short, token: u64) {
+        self.deregister(token);
+        self.0.push((fd, events, token));
+    }
+
+    pub fn deregister(&mut self, token: u64) {
+        self.0.retain(|x| x.2 != token);
+    }
+
+    /// Adds everything that's watched to `fds`, returning their tokens in the same order.
+    pub fn extend_pollfds(&self, fds: &mut Vec<pollfd>) -> Vec<u64> {
+        fds.extend(self.0.iter().map(|&(fd, events, _)| pollfd { fd, events, revents: 0 }));
+        self.0.iter().map(|x| x.2).collect()
+    }
+
+    /// Sends [`Event::FdReady`] for the ones that polled as ready, which stop being watched.
+    ///
+    /// Errors and hangups count as ready, so the user finds out about them when they go to read or write.
+    pub fn dispatch(&mut self, polled: &[pollfd], tokens: &[u64], events: &EventQueue) {
+        for (pollfd, &token) in polled.iter().zip(tokens) {
+            if pollfd.revents == 0 {
+                continue
+            }
+            // They could have changed since polling started
+            if let Some(index) = self.0.iter().position(|x| x.0 == pollfd.fd && x.2 == token) {
+                let _ = self.0.remove(index);
+                events.push(Event::FdReady(token));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{close, pipe2, poll, pollfd, write, WatchedFds, O_CLOEXEC, POLLIN};
+    use crate::event::{Event, EventQueue};
+    use std::collections::VecDeque;
+
+    #[test]
+    fn deregistered_fds_never_fire() {
+        let mut pipe = [-1; 2];
+        assert_eq!(unsafe { pipe2(pipe.as_mut_ptr(), O_CLOEXEC) }, 0);
+        let _ = unsafe { write(pipe[1], [0u8].as_ptr().cast(), 1) };
+
+        let mut watched = WatchedFds::default();
+        watched.register(pipe[0], POLLIN, 1);
+        watched.register(pipe[0], POLLIN, 2);
+        let mut fds: Vec<pollfd> = Vec::new();
+        let tokens = watched.extend_pollfds(&mut fds);
+        // Deregistering while it's being polled still counts
+        watched.deregister(2);
+        assert_eq!(unsafe { poll(fds.as_mut_ptr(), fds.len() as _, 0) }, 2);
+        let events = EventQueue::new();
+        watched.dispatch(&fds, &tokens, &events);
+        // Once it's been sent, it's not watched any more
+        fds.clear();
+        assert!(watched.extend_pollfds(&mut fds).is_empty());
+
+        let mut buffer = VecDeque::new();
+        assert_eq!(events.poll(&mut buffer).collect::<Vec<_>>(), [Event::FdReady(1)]);
+        unsafe {
+            let _ = close(pipe[0]);
+            let _ = close(pipe[1]);
+        }
+    }
+}
@@ -0,0 +1,13 @@
+//! Wayland-specific definitions and API extensions.
+
+mod cursor;
+mod ffi;
+mod imp;
+mod protocol;
+mod util;
+
+// public re-exports
+pub use self::ffi::{wl_display, wl_proxy};
+
+// platform `imp` glue
+pub(crate) use imp::WindowImpl;
@@ -0,0 +1,102 @@
+//! Cursors, either named for the compositor to draw with `wp_cursor_shape_v1` or made from pixels in shared memory.
+//!
+//! Without `wp_cursor_shape_v1`, shapes are drawn from the user's cursor theme like custom cursors are.
+
+use crate::{
+    cursor::{Cursor, CursorShape},
+    dpi::PhysicalSize,
+    platform::{
+        wayland::{ffi::*, util},
+        xcursor,
+    },
+};
+use std::time::Duration;
+
+/// A cursor drawn by us, which has several frames if it's animated.
+pub struct Frames {
+    pub frames: Vec<Frame>,
+
+    /// The buffer scale to draw them at, as theme cursors are made at the pixel size for the output scale.
+    pub buffer_scale: i32,
+}
+
+/// One image of a cursor drawn by us.
+pub struct Frame {
+    pub buffer: *mut wl_proxy,
+    pub size: PhysicalSize,
+    pub hotspot: (u32, u32),
+    pub delay: Duration,
+}
+
+/// Makes the buffers for a cursor, which there's none of if it couldn't be made.
+///
+/// Shapes are looked up in the theme, at the size it'd be for `scale`.
+pub unsafe fn create_frames(shm: *mut wl_proxy, cursor: &Cursor, scale: i32) -> Frames {
+    let mut frames = Vec::new();
+    let mut buffer_scale = 1;
+    match cursor {
+        Cursor::Shape(shape) => {
+            let size = xcursor::default_size() * scale as u32;
+            let images = xcursor::load(*shape, size).unwrap_or_default();
+            // Buffers have to be a whole number of surface-local pixels, which themes don't always stick to
+            if images
+                .iter()
+                .all(|x| x.width % scale as u32 == 0 && x.height % scale as u32 == 0)
+            {
+                buffer_scale = scale;
+            }
+            for image in images {
+                let size = PhysicalSize::new(image.width, image.height);
+                let buffer = util::create_buffer(shm, size, image.pixels.into_iter());
+                if buffer.is_null() {
+                    break
+                }
+                frames.push(Frame {
+                    buffer,
+                    size,
+                    hotspot: image.hotspot,
+                    // Zero would have the animation spin as fast as it can
+                    delay: Duration::from_millis(u64::from(image.delay.max(1))),
+                });
+            }
+        },
+        Cursor::Custom(custom) => {
+            let buffer = util::create_buffer(shm, custom.size, custom.premultiplied_argb());
+            if !buffer.is_null() {
+                frames.push(Frame {
+                    buffer,
+                    size: custom.size,
+                    hotspot: custom.hotspot,
+                    delay: Duration::default(),
+                });
+            }
+        },
+    }
+    Frames { frames, buffer_scale }
+}
+
+/// Destroys the buffers of a cursor, which should no longer be attached to anything.
+pub unsafe fn destroy_frames(frames: Frames) {
+    for frame in frames.frames {
+        util::request_destroy(frame.buffer, 0);
+    }
+}
+
+/// The `wp_cursor_shape_device_v1` shape that's closest to one of ours.
+pub fn shape_id(shape: CursorShape) -> u32 {
+    match shape {
+        CursorShape::Arrow => 1,                     // default
+        CursorShape::Help => 3,                      // help
+        CursorShape::Hand => 4,                      // pointer
+        CursorShape::Progress => 5,                  // progress
+        CursorShape::Wait => 6,                      // wait
+        CursorShape::Crosshair => 8,                 // crosshair
+        CursorShape::Text => 9,                      // text
+        CursorShape::Move => 13,                     // move
+        CursorShape::NotAllowed => 15,               // not-allowed
+        CursorShape::ResizeHorizontal => 26,         // ew-resize
+        CursorShape::ResizeVertical => 27,           // ns-resize
+        CursorShape::ResizeTopRightBottomLeft => 28, // nesw-resize
+        CursorShape::ResizeTopLeftBottomRight => 29, // nwse-resize
+    }
+}
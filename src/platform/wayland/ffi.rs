@@ -0,0 +1,120 @@
+//! Bindings to `libwayland-client`.
+//!
+//! The library is loaded at runtime, so that X11-only systems don't need it installed to run ramen.
+
+#![allow(bad_style, dead_code)]
+
+use crate::platform::posix::dynamic_library_fns;
+
+pub use core::ffi::c_void;
+pub use std::os::raw::{c_char, c_int};
+
+/* opaque types */
+
+/// Opaque handle to a connection to a Wayland compositor.
+pub enum wl_display {}
+/// Opaque handle to a client-side protocol object, such as a `wl_surface`.
+pub enum wl_proxy {}
+
+/* other libwayland types */
+
+/// Fixed-point number with 8 bits of fraction, as used in the wire protocol.
+pub type wl_fixed_t = i32;
+
+pub type wl_dispatcher_func_t = unsafe extern "C" fn(
+    implementation: *const c_void,
+    target: *mut c_void,
+    opcode: u32,
+    msg: *const wl_message,
+    args: *mut wl_argument,
+) -> c_int;
+
+/* structs */
+
+#[repr(C)]
+pub struct wl_array {
+    pub size: usize,
+    pub alloc: usize,
+    pub data: *mut c_void,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union wl_argument {
+    pub i: i32,
+    pub u: u32,
+    pub f: wl_fixed_t,
+    pub s: *const c_char,
+    pub o: *mut wl_proxy,
+    pub n: u32,
+    pub a: *mut wl_array,
+    pub h: i32,
+}
+
+/// Describes a protocol interface, normally generated by `wayland-scanner` (see `protocol.rs`).
+#[repr(C)]
+pub struct wl_interface {
+    pub name: *const c_char,
+    pub version: c_int,
+    pub method_count: c_int,
+    pub methods: *const wl_message,
+    pub event_count: c_int,
+    pub events: *const wl_message,
+}
+unsafe impl Sync for wl_interface {}
+
+#[repr(C)]
+pub struct wl_message {
+    pub name: *const c_char,
+    pub signature: *const c_char,
+    pub types: *const *const wl_interface,
+}
+unsafe impl Sync for wl_message {}
+
+/* constants */
+
+pub const WL_MARSHAL_FLAG_DESTROY: u32 = 1 << 0;
+
+/* dynamically loaded functions */
+
+dynamic_library_fns! {
+    pub struct WaylandClient("libwayland-client.so.0") in WAYLAND_CLIENT {
+        // Connection management
+        fn wl_display_connect(name: *const c_char) -> *mut wl_display;
+        fn wl_display_disconnect(display: *mut wl_display);
+        fn wl_display_get_fd(display: *mut wl_display) -> c_int;
+        fn wl_display_flush(display: *mut wl_display) -> c_int;
+        fn wl_display_roundtrip(display: *mut wl_display) -> c_int;
+        fn wl_display_get_error(display: *mut wl_display) -> c_int;
+
+        // Event queue
+        fn wl_display_prepare_read(display: *mut wl_display) -> c_int;
+        fn wl_display_read_events(display: *mut wl_display) -> c_int;
+        fn wl_display_cancel_read(display: *mut wl_display);
+        fn wl_display_dispatch_pending(display: *mut wl_display) -> c_int;
+
+        // Proxies
+        fn wl_proxy_add_dispatcher(
+            proxy: *mut wl_proxy,
+            dispatcher_func: wl_dispatcher_func_t,
+            dispatcher_data: *const c_void,
+            data: *mut c_void,
+        ) -> c_int;
+        fn wl_proxy_destroy(proxy: *mut wl_proxy);
+        fn wl_proxy_get_id(proxy: *mut wl_proxy) -> u32;
+        fn wl_proxy_get_version(proxy: *mut wl_proxy) -> u32;
+        fn wl_proxy_marshal_array_flags(
+            proxy: *mut wl_proxy,
+            opcode: u32,
+            interface: *const wl_interface,
+            version: u32,
+            flags: u32,
+            args: *mut wl_argument,
+        ) -> *mut wl_proxy;
+    }
+}
+
+/// Whether `libwayland-client` (1.20 or newer) could be loaded.
+pub fn is_available() -> bool {
+    WAYLAND_CLIENT.is_some()
+}
@@ -0,0 +1,2237 @@
+//! The Wayland backend, where every window has a thread of its own with its own compositor connection.
+
+use crate::{
+    clipboard::{self, Selection, TEXT_MIME, TRANSFER_TIMEOUT},
+    cursor::{self, Cursor},
+    dpi::{PhysicalPosition, PhysicalSize},
+    drag::{DragData, DragFormat, DragIcon, DropAction},
+    error::{Error, ErrorKind},
+    event::{Event, EventQueue, MouseButton, ScrollDelta},
+    icon::Icon,
+    keyboard::{HeldKeys, Key},
+    monitor::{self, Monitor, VideoMode},
+    platform::{
+        posix::{self, str_to_cstring, WakePipe, WatchedFds},
+        wayland::{cursor as wayland_cursor, ffi::*, protocol::*, util},
+        xkb,
+    },
+    sync::{self, mutex_lock, Mutex, Sender},
+    window::{CursorGrab, Fullscreen, WindowBuilder},
+};
+use std::{
+    ffi::CStr,
+    mem, ptr, slice,
+    sync::{
+        atomic::{self, AtomicBool},
+        Arc,
+    },
+    thread,
+    time::Instant,
+};
+
+pub(crate) struct WindowImpl {
+    // What `raw-window-handle` hands out
+    #[cfg_attr(not(feature = "raw-window-handle"), allow(dead_code))]
+    display: *mut wl_display,
+    #[cfg_attr(not(feature = "raw-window-handle"), allow(dead_code))]
+    surface: *mut wl_proxy,
+    shared: Arc<WindowImplShared>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+// The display and surface are only ever used by the window thread, they're just handed out for others to render with
+unsafe impl Send for WindowImpl {}
+unsafe impl Sync for WindowImpl {}
+
+/// State shared between a [`WindowImpl`] and its window thread.
+struct WindowImplShared {
+    commands: Mutex<Vec<Command>>,
+    destroy_flag: AtomicBool,
+    events: Arc<EventQueue>,
+    fullscreen: Mutex<Option<Fullscreen>>,
+    monitors: Mutex<Monitors>,
+    scale_factor: Mutex<f64>,
+    size: Mutex<PhysicalSize>,
+    wake: WakePipe,
+    watched_fds: Mutex<WatchedFds>,
+}
+
+/// Every output that's been described in full, and which of them the window is on.
+#[derive(Default)]
+struct Monitors {
+    current: Option<usize>,
+    list: Vec<Monitor>,
+}
+
+/// Requests from the user's thread, carried out by the window thread which owns the connection.
+enum Command {
+    AcceptDrag(Option<(DragFormat, DropAction)>),
+    ClipboardFormats(Sender<Result<Vec<String>, Error>>),
+    ClipboardGet(String, Sender<Result<Option<Paste>, Error>>),
+    ClipboardSet(Vec<(String, Vec<u8>)>, Sender<Result<(), Error>>),
+    // Sent by the thread reading what was dropped, with the offer's ID and what was done with it, if anything
+    FinishDrop(u32, Option<DropAction>),
+    Resize(PhysicalSize),
+    SetCursor(Cursor),
+    SetCursorGrab(CursorGrab),
+    SetCursorVisible(bool),
+    SetFullscreen(Option<Fullscreen>),
+    SetIcon(Option<Icon>),
+    SetImeCursorArea(PhysicalPosition, PhysicalSize),
+    SetTitle(String),
+    StartDrag(DragData, DragIcon, Sender<Result<(), Error>>),
+}
+
+/// Where to get what's on the clipboard from, which is a pipe unless it came from us.
+enum Paste {
+    Owned(Arc<[u8]>),
+    Pipe(c_int),
+}
+
+/// Where text input is at with the compositor's `zwp_text_input_v3`.
+#[derive(Default)]
+struct Ime {
+    // Whether the surface has text input focus, which is when the text input is enabled
+    entered: bool,
+    cursor_area: Option<(PhysicalPosition, PhysicalSize)>,
+    preedit_shown: bool,
+
+    // Everything up to `done` is applied at once
+    pending_preedit: Option<(String, Option<(usize, usize)>)>,
+    pending_commit: Option<String>,
+}
+
+/// A `wl_output`, which is usually a monitor.
+struct Output {
+    proxy: *mut wl_proxy,
+    xdg_output: *mut wl_proxy,
+    // The name of its global, to tell which output `wl_registry.global_remove` is about
+    name: u32,
+    scale: i32,
+    // Whether any part of our surface is on it
+    entered: bool,
+
+    // Built up from events, and only published after the first `done`
+    monitor: Monitor,
+    done: bool,
+    named: bool,
+    logical_position: Option<PhysicalPosition>,
+    transform: i32,
+}
+
+/// State accessible from the dispatchers, living on the thread stack.
+struct WindowImplState {
+    registry: *mut wl_proxy,
+    compositor: *mut wl_proxy,
+    seat: *mut wl_proxy,
+    wm_base: *mut wl_proxy,
+    surface: *mut wl_proxy,
+    xdg_surface: *mut wl_proxy,
+    toplevel: *mut wl_proxy,
+    pointer: *mut wl_proxy,
+    keyboard: *mut wl_proxy,
+    xdg_output_manager: *mut wl_proxy,
+    text_input_manager: *mut wl_proxy,
+    text_input: *mut wl_proxy,
+    relative_pointer_manager: *mut wl_proxy,
+    relative_pointer: *mut wl_proxy,
+    pointer_constraints: *mut wl_proxy,
+    pointer_constraint: *mut wl_proxy,
+    shm: *mut wl_proxy,
+    cursor_shape_manager: *mut wl_proxy,
+    cursor_shape_device: *mut wl_proxy,
+    cursor_surface: *mut wl_proxy,
+    icon_manager: *mut wl_proxy,
+    data_device_manager: *mut wl_proxy,
+    data_device: *mut wl_proxy,
+
+    // Data offers the compositor introduced with the MIME types they're offered as, and which is the clipboard
+    data_offers: Vec<(*mut wl_proxy, Vec<String>)>,
+    selection_offer: *mut wl_proxy,
+
+    // What we put on the clipboard, as given and with the aliases it's offered under
+    data_source: *mut wl_proxy,
+    data_source_formats: Vec<(String, Arc<[u8]>)>,
+    data_source_offered: Vec<(String, Arc<[u8]>)>,
+
+    // The offer being dragged over the window, the serial of it entering, and what it's accepted as
+    drag_offer: *mut wl_proxy,
+    drag_serial: u32,
+    drag_accepted: Option<(DragFormat, DropAction)>,
+
+    // What's being dragged out of the window, with the aliases it's offered under, the surface and buffer of its
+    // icon, and what the compositor last said would be done with it if it was dropped
+    drag_source: *mut wl_proxy,
+    drag_source_offered: Vec<(String, Arc<[u8]>)>,
+    drag_icon: (*mut wl_proxy, *mut wl_proxy),
+    drag_source_action: Option<DropAction>,
+
+    // The clipboard can only be set in response to input, and this is the latest
+    input_serial: Option<u32>,
+    // Dragging out of the window can only start while a button's held, and this is the press, if it still is
+    press_serial: Option<u32>,
+
+    // The buffers of the icon that's set, which have to stay around for as long as it is
+    icon_buffers: Vec<*mut wl_proxy>,
+
+    outputs: Vec<Output>,
+    shared: Arc<WindowImplShared>,
+
+    // The initial outputs aren't news, so events are only sent once the window exists
+    announce_monitors: bool,
+
+    // Surface-local (logical) size, which is multiplied by the buffer scale to get pixels
+    scale: i32,
+    size: (u32, u32),
+
+    // Scrolling since the last `wl_pointer.frame`, in surface-local pixels and in wheel notches
+    scroll: (f64, f64),
+    scroll_discrete: (i32, i32),
+
+    // The compositor's keymap, and the modifiers as of the last `wl_keyboard.modifiers`
+    keymap: Option<xkb::Keymap>,
+    modifiers: u32,
+
+    // What's held down, to release it when focus is lost
+    held_keys: HeldKeys,
+
+    // Whether the window was built with an IME, and its state if the compositor supports one
+    ime_wanted: bool,
+    ime: Ime,
+
+    // The cursor grab as requested, which `pointer_constraint` carries out once there's a pointer
+    cursor_grab: CursorGrab,
+
+    // The cursor as requested, which is set whenever the pointer enters as that's the serial it needs
+    // Shapes are up to the compositor if it has `wp_cursor_shape_v1`, otherwise they're drawn on `cursor_surface`
+    // like custom cursors, with the frames made the first time they're shown and again if the scale changes
+    cursor: Cursor,
+    cursor_visible: bool,
+    cursor_frames: Option<wayland_cursor::Frames>,
+    pointer_serial: Option<u32>,
+
+    // The frame of an animated cursor that's shown, and when to move on to the next one
+    cursor_frame: usize,
+    cursor_frame_deadline: Option<Instant>,
+}
+
+/// Sent to `thread::spawn` as a nice package.
+struct WindowImplThreadParams {
+    builder: WindowBuilder,
+    shared: Arc<WindowImplShared>,
+    response: Sender<Result<WindowImpl, Error>>,
+}
+
+impl WindowImpl {
+    pub(crate) fn new(builder: &WindowBuilder) -> Result<Self, Error> {
+        if !is_available() {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "libwayland-client (1.20 or newer) is not available",
+            ))
+        }
+
+        let (response, receiver) = sync::oneshot();
+        let shared = Arc::new(WindowImplShared {
+            commands: Mutex::new(Vec::new()),
+            destroy_flag: AtomicBool::new(false),
+            events: Arc::new(EventQueue::new()),
+            fullscreen: Mutex::new(builder.fullscreen.clone()),
+            monitors: Mutex::new(Monitors::default()),
+            scale_factor: Mutex::new(1.0),
+            size: Mutex::new(PhysicalSize::default()),
+            wake: WakePipe::new()?,
+            watched_fds: Mutex::new(WatchedFds::default()),
+        });
+
+        let thread_params = WindowImplThreadParams {
+            // Owned by the thread, as the caller might stop waiting on it (see `creation_timeout`)
+            builder: builder.clone(),
+            shared,
+            response,
+        };
+        let thread = thread::spawn(move || unsafe {
+            let WindowImplThreadParams {
+                builder,
+                shared,
+                response,
+            } = thread_params;
+            let respond = |result| {
+                // If the caller gave up waiting, the unclaimed `WindowImpl` is dropped here
+                // That sets the destroy flag, so the event loop exits as soon as it's entered
+                let _ = response.send(result);
+            };
+
+            // Every window gets its own connection, so that each window thread can block on its own socket
+            // `NULL` means "use the `WAYLAND_DISPLAY` environment variable"
+            let display = wl_display_connect(ptr::null());
+            if display.is_null() {
+                return respond(Err(posix::errno_error(
+                    ErrorKind::ConnectionFailed,
+                    "failed to connect to the Wayland compositor",
+                )))
+            }
+
+            // The dispatchers get a pointer to this, so it must not move until everything's destroyed
+            let mut state = WindowImplState {
+                registry: ptr::null_mut(),
+                compositor: ptr::null_mut(),
+                seat: ptr::null_mut(),
+                wm_base: ptr::null_mut(),
+                surface: ptr::null_mut(),
+                xdg_surface: ptr::null_mut(),
+                toplevel: ptr::null_mut(),
+                pointer: ptr::null_mut(),
+                keyboard: ptr::null_mut(),
+                xdg_output_manager: ptr::null_mut(),
+                text_input_manager: ptr::null_mut(),
+                text_input: ptr::null_mut(),
+                relative_pointer_manager: ptr::null_mut(),
+                relative_pointer: ptr::null_mut(),
+                pointer_constraints: ptr::null_mut(),
+                pointer_constraint: ptr::null_mut(),
+                shm: ptr::null_mut(),
+                cursor_shape_manager: ptr::null_mut(),
+                cursor_shape_device: ptr::null_mut(),
+                cursor_surface: ptr::null_mut(),
+                icon_manager: ptr::null_mut(),
+                data_device_manager: ptr::null_mut(),
+                data_device: ptr::null_mut(),
+                data_offers: Vec::new(),
+                selection_offer: ptr::null_mut(),
+                data_source: ptr::null_mut(),
+                data_source_formats: Vec::new(),
+                data_source_offered: Vec::new(),
+                drag_offer: ptr::null_mut(),
+                drag_serial: 0,
+                drag_source: ptr::null_mut(),
+                drag_source_offered: Vec::new(),
+                drag_icon: (ptr::null_mut(), ptr::null_mut()),
+                drag_source_action: None,
+                drag_accepted: None,
+                input_serial: None,
+                press_serial: None,
+                icon_buffers: Vec::new(),
+                outputs: Vec::new(),
+                shared: Arc::clone(&shared),
+                announce_monitors: false,
+                scale: 1,
+                size: (0, 0),
+                scroll: (0.0, 0.0),
+                scroll_discrete: (0, 0),
+                keymap: None,
+                modifiers: 0,
+                held_keys: HeldKeys::default(),
+                ime_wanted: builder.ime,
+                ime: Ime::default(),
+                cursor_grab: CursorGrab::None,
+                cursor: Cursor::default(),
+                cursor_visible: true,
+                cursor_frames: None,
+                pointer_serial: None,
+                cursor_frame: 0,
+                cursor_frame_deadline: None,
+            };
+            let state_ptr = &mut state as *mut WindowImplState;
+
+            // `wl_display.get_registry`, then a roundtrip to receive every `wl_registry.global` there is
+            let display_proxy = display.cast::<wl_proxy>();
+            state.registry = util::request_new(display_proxy, 1, &WL_REGISTRY, &mut [wl_argument { n: 0 }]);
+            let _ = wl_proxy_add_dispatcher(state.registry, registry_dispatch, state_ptr.cast(), ptr::null_mut());
+            let _ = wl_display_roundtrip(display);
+            let missing_global = if state.compositor.is_null() {
+                Some("the compositor does not advertise wl_compositor")
+            } else if state.wm_base.is_null() {
+                Some("the compositor does not support xdg-shell (xdg_wm_base)")
+            } else {
+                None
+            };
+            if let Some(message) = missing_global {
+                destroy_state(&mut state);
+                wl_display_disconnect(display);
+                return respond(Err(Error::new(ErrorKind::Unsupported, message)))
+            }
+
+            // Until the surface enters an output, the densest output is the best guess for the scale
+            // Their `scale` events were only sent in response to binding them, hence another roundtrip
+            let _ = wl_display_roundtrip(display);
+            state.scale = state.outputs.iter().map(|x| x.scale).max().unwrap_or(1);
+            let size = builder.inner_size.to_logical(f64::from(state.scale));
+            state.size = (size.width.ceil().max(1.0) as u32, size.height.ceil().max(1.0) as u32);
+            *mutex_lock(&shared.scale_factor) = f64::from(state.scale);
+            *mutex_lock(&shared.size) = physical_size(state.size, state.scale);
+
+            // A `wl_surface` is just a rectangle of pixels, it's xdg-shell that gives it a role as a window
+            state.surface = util::request_new(state.compositor, 0, &WL_SURFACE, &mut [wl_argument { n: 0 }]);
+            let _ = wl_proxy_add_dispatcher(state.surface, surface_dispatch, state_ptr.cast(), ptr::null_mut());
+            set_buffer_scale(&state);
+            state.xdg_surface = util::request_new(
+                state.wm_base,
+                2,
+                &XDG_SURFACE,
+                &mut [wl_argument { n: 0 }, wl_argument { o: state.surface }],
+            );
+            let _ = wl_proxy_add_dispatcher(
+                state.xdg_surface,
+                xdg_surface_dispatch,
+                state_ptr.cast(),
+                ptr::null_mut(),
+            );
+            state.toplevel = util::request_new(state.xdg_surface, 1, &XDG_TOPLEVEL, &mut [wl_argument { n: 0 }]);
+            let _ = wl_proxy_add_dispatcher(state.toplevel, toplevel_dispatch, state_ptr.cast(), ptr::null_mut());
+            let title = str_to_cstring(builder.title.as_ref());
+            util::request(state.toplevel, 2, &mut [wl_argument { s: title.as_ptr() }]);
+            // The app ID is what `WM_CLASS` is on X11, and is matched against `.desktop` files
+            let app_id = str_to_cstring(builder.class_name.as_ref());
+            util::request(state.toplevel, 3, &mut [wl_argument { s: app_id.as_ptr() }]);
+            if builder.fullscreen.is_some() {
+                set_fullscreen(&state, builder.fullscreen.as_ref());
+            }
+            if builder.icon.is_some() {
+                set_icon(&mut state, builder.icon.as_ref());
+            }
+
+            // Committing without a buffer attached asks the compositor for the initial `configure`
+            // Note that the window doesn't actually appear until the user presents something to the surface
+            util::request(state.surface, 6, &mut []);
+            let _ = wl_display_roundtrip(display);
+            let code = wl_display_get_error(display);
+            if code != 0 {
+                destroy_state(&mut state);
+                wl_display_disconnect(display);
+                return respond(Err(Error::with_code(
+                    ErrorKind::WindowCreationFailed,
+                    code,
+                    "the compositor closed the connection while creating the window",
+                )))
+            }
+
+            state.announce_monitors = true;
+            respond(Ok(WindowImpl {
+                display,
+                surface: state.surface,
+                shared: Arc::clone(&shared),
+                thread: None,
+            }));
+
+            // The connection and the wake pipe come first, then whatever the user's watching
+            let own_fds = [
+                posix::pollfd {
+                    fd: wl_display_get_fd(display),
+                    events: posix::POLLIN,
+                    revents: 0,
+                },
+                posix::pollfd {
+                    fd: shared.wake.fd(),
+                    events: posix::POLLIN,
+                    revents: 0,
+                },
+            ];
+            let mut fds = Vec::with_capacity(own_fds.len());
+            loop {
+                // The dance to read events from multiple threads (or alongside other fds) safely
+                // `prepare_read` fails if there's still events queued, which have to be dispatched first
+                while wl_display_prepare_read(display) != 0 {
+                    let _ = wl_display_dispatch_pending(display);
+                }
+                let _ = wl_display_flush(display);
+                if shared.destroy_flag.load(atomic::Ordering::Acquire) || wl_display_get_error(display) != 0 {
+                    wl_display_cancel_read(display);
+                    break
+                }
+                // Animated cursors wake up for their next frame
+                let timeout = state.cursor_frame_deadline.map_or(-1, |deadline| {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    (timeout.as_nanos() as f64 / 1e6).ceil().min(f64::from(i32::MAX)) as c_int
+                });
+                fds.clear();
+                fds.extend_from_slice(&own_fds);
+                let tokens = mutex_lock(&shared.watched_fds).extend_pollfds(&mut fds);
+                let _ = posix::poll(fds.as_mut_ptr(), fds.len() as _, timeout);
+                mutex_lock(&shared.watched_fds).dispatch(&fds[own_fds.len()..], &tokens, &shared.events);
+                if fds[0].revents != 0 {
+                    let _ = wl_display_read_events(display);
+                } else {
+                    wl_display_cancel_read(display);
+                }
+                let _ = wl_display_dispatch_pending(display);
+                if state.cursor_frame_deadline.is_some_and(|x| x <= Instant::now()) {
+                    state.cursor_frame += 1;
+                    update_cursor(&mut state);
+                }
+                if fds[1].revents != 0 {
+                    shared.wake.drain();
+                    let commands = mem::take(&mut *mutex_lock(&shared.commands));
+                    for command in commands {
+                        match command {
+                            Command::AcceptDrag(accept) => {
+                                state.drag_accepted = accept;
+                                update_drag_accepted(&state);
+                            },
+                            Command::ClipboardFormats(reply) => {
+                                let _ = reply.send(clipboard_formats(&state));
+                            },
+                            Command::ClipboardGet(mime, reply) => {
+                                let _ = reply.send(clipboard_get(&state, &mime));
+                            },
+                            Command::ClipboardSet(formats, reply) => {
+                                let _ = reply.send(clipboard_set(&mut state, formats, state_ptr.cast()));
+                            },
+                            Command::FinishDrop(id, action) => {
+                                let offer = state
+                                    .data_offers
+                                    .iter()
+                                    .map(|x| x.0)
+                                    .find(|&x| wl_proxy_get_id(x) == id);
+                                if let Some(offer) = offer {
+                                    // `finish` is from version 3, before it the offer's just destroyed
+                                    if action.is_some() && wl_proxy_get_version(offer) >= 3 {
+                                        util::request(offer, 3, &mut []);
+                                    }
+                                    destroy_data_offer(&mut state, offer);
+                                }
+                            },
+                            // We draw no decorations, and without a buffer attached there's nothing to resize,
+                            // so this is just the size that's reported back
+                            Command::Resize(size) => {
+                                let size = size.to_logical(f64::from(state.scale));
+                                set_size(&mut state, (size.width.ceil() as u32, size.height.ceil() as u32));
+                            },
+                            Command::SetCursor(cursor) => {
+                                state.cursor = cursor;
+                                reload_cursor(&mut state);
+                            },
+                            Command::SetCursorVisible(visible) => {
+                                state.cursor_visible = visible;
+                                update_cursor(&mut state);
+                            },
+                            Command::SetCursorGrab(grab) => {
+                                state.cursor_grab = grab;
+                                update_cursor_grab(&mut state);
+                            },
+                            Command::SetFullscreen(fullscreen) => set_fullscreen(&state, fullscreen.as_ref()),
+                            Command::SetIcon(icon) => set_icon(&mut state, icon.as_ref()),
+                            Command::SetImeCursorArea(position, size) => {
+                                state.ime.cursor_area = Some((position, size));
+                                if state.ime.entered {
+                                    set_ime_cursor_area(&state);
+                                    util::request(state.text_input, 7, &mut []);
+                                }
+                            },
+                            Command::SetTitle(title) => {
+                                let title = str_to_cstring(&title);
+                                util::request(state.toplevel, 2, &mut [wl_argument { s: title.as_ptr() }]);
+                            },
+                            Command::StartDrag(drag, icon, reply) => {
+                                let _ = reply.send(start_drag(&mut state, drag, icon, state_ptr.cast()));
+                            },
+                        }
+                    }
+                }
+            }
+
+            destroy_state(&mut state);
+            let _ = wl_display_flush(display);
+            wl_display_disconnect(display);
+        });
+
+        /* Wait for the thread to return the window or an error */
+        let (mut window, thread) = receiver.recv_from_thread(thread, builder.creation_timeout)?;
+        window.thread = Some(thread);
+        Ok(window)
+    }
+
+    #[cfg(feature = "raw-window-handle")]
+    pub(crate) fn raw_window_handle(
+        &self,
+    ) -> Result<raw_window_handle::RawWindowHandle, raw_window_handle::HandleError> {
+        let surface = ptr::NonNull::new(self.surface.cast()).ok_or(raw_window_handle::HandleError::Unavailable)?;
+        Ok(raw_window_handle::WaylandWindowHandle::new(surface).into())
+    }
+
+    #[cfg(feature = "raw-window-handle")]
+    pub(crate) fn raw_display_handle(
+        &self,
+    ) -> Result<raw_window_handle::RawDisplayHandle, raw_window_handle::HandleError> {
+        let display = ptr::NonNull::new(self.display.cast()).ok_or(raw_window_handle::HandleError::Unavailable)?;
+        Ok(raw_window_handle::WaylandDisplayHandle::new(display).into())
+    }
+
+    #[inline]
+    pub(crate) fn events(&self) -> &Arc<EventQueue> {
+        &self.shared.events
+    }
+
+    pub(crate) fn register_fd(&self, fd: c_int, events: posix::c_short, token: u64) {
+        mutex_lock(&self.shared.watched_fds).register(fd, events, token);
+        self.shared.wake.wake();
+    }
+
+    pub(crate) fn deregister_fd(&self, token: u64) {
+        mutex_lock(&self.shared.watched_fds).deregister(token);
+        // The window thread stops polling it straight away, so it can be closed
+        self.shared.wake.wake();
+    }
+
+    pub(crate) fn available_monitors(&self) -> Vec<Monitor> {
+        mutex_lock(&self.shared.monitors).list.clone()
+    }
+
+    pub(crate) fn current_monitor(&self) -> Option<Monitor> {
+        let monitors = mutex_lock(&self.shared.monitors);
+        let current = monitors.current?;
+        monitors.list.iter().find(|x| x.id == current).cloned()
+    }
+
+    pub(crate) fn fullscreen(&self) -> Option<Fullscreen> {
+        mutex_lock(&self.shared.fullscreen).clone()
+    }
+
+    pub(crate) fn inner_size(&self) -> PhysicalSize {
+        *mutex_lock(&self.shared.size)
+    }
+
+    pub(crate) fn outer_size(&self) -> PhysicalSize {
+        // Decorations are up to the client on Wayland, and we don't draw any
+        self.inner_size()
+    }
+
+    pub(crate) fn position(&self) -> Option<PhysicalPosition> {
+        // Wayland clients don't get to know where their windows are
+        None
+    }
+
+    pub(crate) fn scale_factor(&self) -> f64 {
+        *mutex_lock(&self.shared.scale_factor)
+    }
+
+    pub(crate) fn accept_drag(&self, accept: Option<(DragFormat, DropAction)>) {
+        mutex_lock(&self.shared.commands).push(Command::AcceptDrag(accept));
+        self.shared.wake.wake();
+    }
+
+    pub(crate) fn start_drag(&self, data: DragData, icon: DragIcon) -> Result<(), Error> {
+        let (reply, receiver) = sync::oneshot();
+        mutex_lock(&self.shared.commands).push(Command::StartDrag(data, icon, reply));
+        self.shared.wake.wake();
+        receiver.recv_reply()
+    }
+
+    pub(crate) fn clipboard_formats(&self, selection: Selection) -> Result<Vec<String>, Error> {
+        check_selection(selection)?;
+        let (reply, receiver) = sync::oneshot();
+        mutex_lock(&self.shared.commands).push(Command::ClipboardFormats(reply));
+        self.shared.wake.wake();
+        receiver.recv_reply()
+    }
+
+    pub(crate) fn clipboard_get(&self, selection: Selection, mime: &str) -> Result<Option<Vec<u8>>, Error> {
+        check_selection(selection)?;
+        let (reply, receiver) = sync::oneshot();
+        mutex_lock(&self.shared.commands).push(Command::ClipboardGet(mime.into(), reply));
+        self.shared.wake.wake();
+        match receiver.recv_reply()? {
+            Some(Paste::Owned(data)) => Ok(Some(data.to_vec())),
+            Some(Paste::Pipe(fd)) => posix::read_pipe(fd, TRANSFER_TIMEOUT).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) fn clipboard_set(&self, selection: Selection, data: Vec<(String, Vec<u8>)>) -> Result<(), Error> {
+        check_selection(selection)?;
+        let (reply, receiver) = sync::oneshot();
+        mutex_lock(&self.shared.commands).push(Command::ClipboardSet(data, reply));
+        self.shared.wake.wake();
+        receiver.recv_reply()
+    }
+
+    pub(crate) fn set_cursor(&self, cursor: Cursor) {
+        mutex_lock(&self.shared.commands).push(Command::SetCursor(cursor));
+        self.shared.wake.wake();
+    }
+
+    pub(crate) fn set_cursor_grab(&self, grab: CursorGrab) {
+        mutex_lock(&self.shared.commands).push(Command::SetCursorGrab(grab));
+        self.shared.wake.wake();
+    }
+
+    pub(crate) fn set_cursor_position(&self, _position: PhysicalPosition) {
+        // Wayland doesn't let clients move the cursor
+    }
+
+    pub(crate) fn set_cursor_visible(&self, visible: bool) {
+        mutex_lock(&self.shared.commands).push(Command::SetCursorVisible(visible));
+        self.shared.wake.wake();
+    }
+
+    pub(crate) fn set_fullscreen(&self, fullscreen: Option<Fullscreen>) {
+        *mutex_lock(&self.shared.fullscreen) = fullscreen.clone();
+        mutex_lock(&self.shared.commands).push(Command::SetFullscreen(fullscreen));
+        self.shared.wake.wake();
+    }
+
+    pub(crate) fn set_icon(&self, icon: Option<Icon>) {
+        mutex_lock(&self.shared.commands).push(Command::SetIcon(icon));
+        self.shared.wake.wake();
+    }
+
+    pub(crate) fn set_ime_cursor_area(&self, position: PhysicalPosition, size: PhysicalSize) {
+        mutex_lock(&self.shared.commands).push(Command::SetImeCursorArea(position, size));
+        self.shared.wake.wake();
+    }
+
+    pub(crate) fn set_inner_size(&self, size: PhysicalSize) {
+        let size = PhysicalSize::new(size.width.max(1), size.height.max(1));
+        mutex_lock(&self.shared.commands).push(Command::Resize(size));
+        self.shared.wake.wake();
+    }
+
+    pub(crate) fn set_outer_size(&self, size: PhysicalSize) {
+        self.set_inner_size(size);
+    }
+
+    pub(crate) fn set_position(&self, _position: PhysicalPosition) {
+        // Nor do they get to choose
+    }
+
+    pub(crate) fn set_title(&self, title: &str) {
+        mutex_lock(&self.shared.commands).push(Command::SetTitle(title.into()));
+        self.shared.wake.wake();
+    }
+
+    pub(crate) fn set_visible(&self, _visible: bool) {
+        // A surface is only hidden by taking away its buffer, which is up to whatever the user presents with
+    }
+}
+
+fn physical_size((width, height): (u32, u32), scale: i32) -> PhysicalSize {
+    PhysicalSize::new(width * scale as u32, height * scale as u32)
+}
+
+/// Updates the surface-local size of the window, sending `Resized` if it changed.
+fn set_size(state: &mut WindowImplState, size: (u32, u32)) {
+    state.size = size;
+    let size = physical_size(size, state.scale);
+    let mut shared_size = mutex_lock(&state.shared.size);
+    if *shared_size != size {
+        *shared_size = size;
+        state.shared.events.push(Event::Resized(size));
+    }
+}
+
+/// Tells the compositor that our buffers are drawn at the current scale, rather than having it upscale them.
+unsafe fn set_buffer_scale(state: &WindowImplState) {
+    // `wl_surface.set_buffer_scale` is from version 3, and it's only applied when the user next commits
+    if wl_proxy_get_version(state.surface) >= 3 {
+        util::request(state.surface, 8, &mut [wl_argument { i: state.scale }]);
+    }
+}
+
+/// Asks the compositor to make the window fullscreen on a monitor, or on whichever it likes, or not at all.
+unsafe fn set_fullscreen(state: &WindowImplState, fullscreen: Option<&Fullscreen>) {
+    let monitor = match fullscreen {
+        // Clients can't change video modes, so exclusive fullscreen is the same as borderless
+        Some(Fullscreen::Borderless(Some(monitor)) | Fullscreen::Exclusive(monitor, _)) => Some(monitor),
+        Some(Fullscreen::Borderless(None)) => None,
+        None => return util::request(state.toplevel, 12, &mut []),
+    };
+    let output = monitor
+        .and_then(|monitor| state.outputs.iter().find(|x| x.name as usize == monitor.id))
+        .map_or(ptr::null_mut(), |x| x.proxy);
+    util::request(state.toplevel, 11, &mut [wl_argument { o: output }]);
+}
+
+/// Sets the toplevel's icon with `xdg-toplevel-icon-v1`, or unsets it with `None`.
+///
+/// Only square images are taken. The icon object can go as soon as it's set, but not its buffers.
+unsafe fn set_icon(state: &mut WindowImplState, icon: Option<&Icon>) {
+    if state.icon_manager.is_null() || state.shm.is_null() {
+        return
+    }
+    let old_buffers = mem::take(&mut state.icon_buffers);
+    let mut icon_object = ptr::null_mut();
+    if let Some(icon) = icon {
+        icon_object = util::request_new(
+            state.icon_manager,
+            1,
+            &XDG_TOPLEVEL_ICON_V1,
+            &mut [wl_argument { n: 0 }],
+        );
+        for image in icon.images().iter().filter(|x| x.size().width == x.size().height) {
+            let buffer = util::create_buffer(state.shm, image.size(), cursor::premultiplied_argb(image.rgba()));
+            if !buffer.is_null() {
+                util::request(icon_object, 2, &mut [wl_argument { o: buffer }, wl_argument { i: 1 }]);
+                state.icon_buffers.push(buffer);
+            }
+        }
+        // An icon with nothing in it isn't allowed, so it's unset instead
+        if state.icon_buffers.is_empty() {
+            util::request_destroy(icon_object, 0);
+            icon_object = ptr::null_mut();
+        }
+    }
+    util::request(
+        state.icon_manager,
+        2,
+        &mut [wl_argument { o: state.toplevel }, wl_argument { o: icon_object }],
+    );
+    if !icon_object.is_null() {
+        util::request_destroy(icon_object, 0);
+    }
+    for buffer in old_buffers {
+        util::request_destroy(buffer, 0);
+    }
+}
+
+/// Picks the scale of the densest output the surface is on, sending `ScaleFactorChanged` if it changed.
+///
+/// That output is also what's considered the current monitor.
+unsafe fn update_scale(state: &mut WindowImplState) {
+    let current = state.outputs.iter().filter(|x| x.entered).max_by_key(|x| x.scale);
+    mutex_lock(&state.shared.monitors).current = current.map(|x| x.name as usize);
+    let scale = current.map_or(state.scale, |x| x.scale);
+    if scale != state.scale {
+        // The surface-local size stays the same, so the suggested size is what it'll be resized to anyway
+        state.scale = scale;
+        set_buffer_scale(state);
+        *mutex_lock(&state.shared.scale_factor) = f64::from(scale);
+        state.shared.events.push(Event::ScaleFactorChanged {
+            scale_factor: f64::from(scale),
+            suggested_size: physical_size(state.size, scale),
+        });
+        set_size(state, state.size);
+        if let Cursor::Shape(_) = state.cursor {
+            reload_cursor(state);
+        }
+    }
+}
+
+impl Output {
+    fn new(proxy: *mut wl_proxy, name: u32) -> Self {
+        Self {
+            proxy,
+            xdg_output: ptr::null_mut(),
+            name,
+            scale: 1,
+            entered: false,
+            monitor: Monitor {
+                id: name as usize,
+                name: String::new(),
+                position: PhysicalPosition::default(),
+                size: PhysicalSize::default(),
+                physical_size_mm: None,
+                refresh_rate_millihertz: None,
+                scale_factor: 1.0,
+                primary: false,
+                video_modes: Vec::new(),
+            },
+            done: false,
+            named: false,
+            logical_position: None,
+            transform: 0,
+        }
+    }
+
+    /// The output as a [`Monitor`], accounting for the details that come in separately.
+    fn to_monitor(&self) -> Monitor {
+        let mut monitor = self.monitor.clone();
+        monitor.scale_factor = f64::from(self.scale);
+        if let Some(position) = self.logical_position {
+            monitor.position = position;
+        }
+
+        // The mode is in the output's own orientation, so rotating it by 90 or 270 degrees swaps its sides
+        const WL_OUTPUT_TRANSFORM_90: i32 = 1;
+        if self.transform & WL_OUTPUT_TRANSFORM_90 != 0 {
+            let PhysicalSize { width, height } = monitor.size;
+            monitor.size = PhysicalSize::new(height, width);
+        }
+        monitor
+    }
+}
+
+/// Publishes every output that's been described in full, sending events for ones that came or went.
+fn update_monitors(state: &mut WindowImplState) {
+    let monitors = state
+        .outputs
+        .iter()
+        .filter(|x| x.done)
+        .map(Output::to_monitor)
+        .collect();
+    let mut shared_monitors = mutex_lock(&state.shared.monitors);
+    if state.announce_monitors {
+        monitor::update_monitors(&mut shared_monitors.list, monitors, &state.shared.events);
+    } else {
+        shared_monitors.list = monitors;
+    }
+}
+
+/// Asks for an output's `xdg_output`, which has its name and position on the desktop.
+unsafe fn create_xdg_output(manager: *mut wl_proxy, output: &mut Output, data: *const c_void) {
+    output.xdg_output = util::request_new(
+        manager,
+        1,
+        &ZXDG_OUTPUT_V1,
+        &mut [wl_argument { n: 0 }, wl_argument { o: output.proxy }],
+    );
+    let _ = wl_proxy_add_dispatcher(output.xdg_output, xdg_output_dispatch, data, ptr::null_mut());
+}
+
+impl Drop for WindowImpl {
+    fn drop(&mut self) {
+        self.shared.destroy_flag.store(true, atomic::Ordering::Release);
+        self.shared.wake.wake();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Destroys every object in `state` that was created, children first.
+unsafe fn destroy_state(state: &mut WindowImplState) {
+    // Objects from before `release` requests existed can only be destroyed client-side
+    let input_destructor = |proxy| {
+        if wl_proxy_get_version(proxy) >= 3 {
+            Some(1)
+        } else {
+            None
+        }
+    };
+    let pointer_destructor = (!state.pointer.is_null())
+        .then(|| input_destructor(state.pointer))
+        .flatten();
+    let keyboard_destructor = (!state.keyboard.is_null())
+        .then(|| input_destructor(state.keyboard))
+        .flatten();
+    let seat_destructor = (!state.seat.is_null() && wl_proxy_get_version(state.seat) >= 5).then_some(3);
+    let data_device_destructor =
+        (!state.data_device.is_null() && wl_proxy_get_version(state.data_device) >= 2).then_some(2);
+    for (offer, _) in state.data_offers.drain(..) {
+        util::request_destroy(offer, 2);
+    }
+    state.selection_offer = ptr::null_mut();
+    state.drag_offer = ptr::null_mut();
+    destroy_data_source(state);
+    destroy_drag_source(state);
+    for output in state.outputs.drain(..) {
+        destroy_output(output);
+    }
+    if let Some(frames) = state.cursor_frames.take() {
+        wayland_cursor::destroy_frames(frames);
+    }
+    for buffer in state.icon_buffers.drain(..) {
+        util::request_destroy(buffer, 0);
+    }
+    for (proxy, destructor) in [
+        (&mut state.text_input, Some(0)),
+        (&mut state.pointer_constraint, Some(0)),
+        (&mut state.relative_pointer, Some(0)),
+        (&mut state.cursor_shape_device, Some(0)),
+        (&mut state.pointer, pointer_destructor),
+        (&mut state.cursor_surface, Some(0)),
+        (&mut state.keyboard, keyboard_destructor),
+        (&mut state.data_device, data_device_destructor),
+        (&mut state.toplevel, Some(0)),
+        (&mut state.xdg_surface, Some(0)),
+        (&mut state.surface, Some(0)),
+        (&mut state.wm_base, Some(0)),
+        (&mut state.seat, seat_destructor),
+        (&mut state.xdg_output_manager, Some(0)),
+        (&mut state.text_input_manager, Some(0)),
+        (&mut state.relative_pointer_manager, Some(0)),
+        (&mut state.pointer_constraints, Some(0)),
+        (&mut state.cursor_shape_manager, Some(0)),
+        (&mut state.icon_manager, Some(0)),
+        (&mut state.data_device_manager, None),
+        (&mut state.shm, None),
+        (&mut state.compositor, None),
+        (&mut state.registry, None),
+    ] {
+        if !proxy.is_null() {
+            match destructor {
+                Some(opcode) => util::request_destroy(*proxy, opcode),
+                None => wl_proxy_destroy(*proxy),
+            }
+            *proxy = ptr::null_mut();
+        }
+    }
+}
+
+/// Makes the text input for the seat, once both it and the text input manager have been bound.
+unsafe fn create_text_input(state: &mut WindowImplState, data: *const c_void) {
+    if state.text_input.is_null() && !state.seat.is_null() && !state.text_input_manager.is_null() {
+        state.text_input = util::request_new(
+            state.text_input_manager,
+            1,
+            &ZWP_TEXT_INPUT_V3,
+            &mut [wl_argument { n: 0 }, wl_argument { o: state.seat }],
+        );
+        let _ = wl_proxy_add_dispatcher(state.text_input, text_input_dispatch, data, ptr::null_mut());
+    }
+}
+
+/// Makes the relative pointer for the pointer, once both it and the relative pointer manager have been bound.
+unsafe fn create_relative_pointer(state: &mut WindowImplState, data: *const c_void) {
+    if state.relative_pointer.is_null() && !state.pointer.is_null() && !state.relative_pointer_manager.is_null() {
+        state.relative_pointer = util::request_new(
+            state.relative_pointer_manager,
+            1,
+            &ZWP_RELATIVE_POINTER_V1,
+            &mut [wl_argument { n: 0 }, wl_argument { o: state.pointer }],
+        );
+        let _ = wl_proxy_add_dispatcher(state.relative_pointer, relative_pointer_dispatch, data, ptr::null_mut());
+    }
+}
+
+/// Makes the cursor shape device for the pointer, once both it and the cursor shape manager have been bound.
+unsafe fn create_cursor_shape_device(state: &mut WindowImplState) {
+    if state.cursor_shape_device.is_null() && !state.pointer.is_null() && !state.cursor_shape_manager.is_null() {
+        state.cursor_shape_device = util::request_new(
+            state.cursor_shape_manager,
+            1,
+            &WP_CURSOR_SHAPE_DEVICE_V1,
+            &mut [wl_argument { n: 0 }, wl_argument { o: state.pointer }],
+        );
+    }
+}
+
+/// Makes the data device for the seat, once both it and the data device manager have been bound.
+unsafe fn create_data_device(state: &mut WindowImplState, data: *const c_void) {
+    if state.data_device.is_null() && !state.seat.is_null() && !state.data_device_manager.is_null() {
+        state.data_device = util::request_new(
+            state.data_device_manager,
+            1,
+            &WL_DATA_DEVICE,
+            &mut [wl_argument { n: 0 }, wl_argument { o: state.seat }],
+        );
+        let _ = wl_proxy_add_dispatcher(state.data_device, data_device_dispatch, data, ptr::null_mut());
+    }
+}
+
+/// Remakes the frames for [`WindowImplState::cursor`] and shows it.
+unsafe fn reload_cursor(state: &mut WindowImplState) {
+    // The old buffers are only let go of once they've been replaced on the surface
+    let old_frames = state.cursor_frames.take();
+    state.cursor_frame = 0;
+    update_cursor(state);
+    if let Some(frames) = old_frames {
+        wayland_cursor::destroy_frames(frames);
+    }
+}
+
+/// Sets the cursor for the pointer, if it's over the surface.
+///
+/// Animated cursors set [`WindowImplState::cursor_frame_deadline`] for when this should be called again.
+unsafe fn update_cursor(state: &mut WindowImplState) {
+    state.cursor_frame_deadline = None;
+    let serial = match state.pointer_serial {
+        Some(serial) if !state.pointer.is_null() => serial,
+        _ => return,
+    };
+    let pointer = state.pointer;
+    let set_cursor = move |surface, (x, y): (u32, u32)| {
+        util::request(
+            pointer,
+            0,
+            &mut [
+                wl_argument { u: serial },
+                wl_argument { o: surface },
+                wl_argument { i: x as i32 },
+                wl_argument { i: y as i32 },
+            ],
+        );
+    };
+    match &state.cursor {
+        _ if !state.cursor_visible => return set_cursor(ptr::null_mut(), (0, 0)),
+        Cursor::Shape(shape) if !state.cursor_shape_device.is_null() => {
+            let shape = wayland_cursor::shape_id(*shape);
+            util::request(
+                state.cursor_shape_device,
+                1,
+                &mut [wl_argument { u: serial }, wl_argument { u: shape }],
+            );
+            return
+        },
+        _ => (),
+    }
+
+    if state.shm.is_null() {
+        return
+    }
+    let (shm, scale) = (state.shm, state.scale);
+    let cursor = &state.cursor;
+    let frames = state
+        .cursor_frames
+        .get_or_insert_with(|| wayland_cursor::create_frames(shm, cursor, scale));
+    if frames.frames.is_empty() {
+        return
+    }
+    if state.cursor_surface.is_null() {
+        state.cursor_surface = util::request_new(state.compositor, 0, &WL_SURFACE, &mut [wl_argument { n: 0 }]);
+    }
+    let surface = state.cursor_surface;
+    state.cursor_frame %= frames.frames.len();
+    let frame = &frames.frames[state.cursor_frame];
+
+    // Everything's in surface-local coordinates, which is the buffer's size divided by its scale
+    // `wl_surface.set_buffer_scale` is from version 3, without it the cursor's just bigger
+    let buffer_scale = if wl_proxy_get_version(surface) >= 3 {
+        util::request(surface, 8, &mut [wl_argument { i: frames.buffer_scale }]);
+        frames.buffer_scale as u32
+    } else {
+        1
+    };
+    util::request(
+        surface,
+        1,
+        &mut [
+            wl_argument { o: frame.buffer },
+            wl_argument { i: 0 },
+            wl_argument { i: 0 },
+        ],
+    );
+    util::request(
+        surface,
+        2,
+        &mut [
+            wl_argument { i: 0 },
+            wl_argument { i: 0 },
+            wl_argument {
+                i: (frame.size.width / buffer_scale) as i32,
+            },
+            wl_argument {
+                i: (frame.size.height / buffer_scale) as i32,
+            },
+        ],
+    );
+    util::request(surface, 6, &mut []);
+    set_cursor(
+        surface,
+        (frame.hotspot.0 / buffer_scale, frame.hotspot.1 / buffer_scale),
+    );
+    if frames.frames.len() > 1 {
+        state.cursor_frame_deadline = Some(Instant::now() + frame.delay);
+    }
+}
+
+/// Replaces the pointer constraint with one for [`WindowImplState::cursor_grab`], if it can be made yet.
+///
+/// Constraints are persistent, so the compositor turns them on and off as the window gains and loses focus.
+/// Their events only say when that happens, so they're left without a dispatcher.
+unsafe fn update_cursor_grab(state: &mut WindowImplState) {
+    const ZWP_POINTER_CONSTRAINTS_V1_LIFETIME_PERSISTENT: u32 = 2;
+
+    if !state.pointer_constraint.is_null() {
+        util::request_destroy(state.pointer_constraint, 0);
+        state.pointer_constraint = ptr::null_mut();
+    }
+    if state.pointer.is_null() || state.pointer_constraints.is_null() || state.surface.is_null() {
+        return
+    }
+    let (opcode, interface) = match state.cursor_grab {
+        CursorGrab::None => return,
+        CursorGrab::Locked => (1, &ZWP_LOCKED_POINTER_V1),
+        CursorGrab::Confined => (2, &ZWP_CONFINED_POINTER_V1),
+    };
+    state.pointer_constraint = util::request_new(
+        state.pointer_constraints,
+        opcode,
+        interface,
+        &mut [
+            wl_argument { n: 0 },
+            wl_argument { o: state.surface },
+            wl_argument { o: state.pointer },
+            wl_argument { o: ptr::null_mut() },
+            wl_argument {
+                u: ZWP_POINTER_CONSTRAINTS_V1_LIFETIME_PERSISTENT,
+            },
+        ],
+    );
+}
+
+/// Sends the IME cursor area in surface-local coordinates, which still needs a `commit` after.
+unsafe fn set_ime_cursor_area(state: &WindowImplState) {
+    if let Some((position, size)) = state.ime.cursor_area {
+        let logical = |x: i64| (x as f64 / f64::from(state.scale)).round() as i32;
+        util::request(
+            state.text_input,
+            6,
+            &mut [
+                wl_argument {
+                    i: logical(position.x.into()),
+                },
+                wl_argument {
+                    i: logical(position.y.into()),
+                },
+                wl_argument {
+                    i: logical(size.width.into()),
+                },
+                wl_argument {
+                    i: logical(size.height.into()),
+                },
+            ],
+        );
+    }
+}
+
+/// Fails for the primary selection, which isn't supported on Wayland.
+fn check_selection(selection: Selection) -> Result<(), Error> {
+    match selection {
+        Selection::Clipboard => Ok(()),
+        Selection::Primary => Err(Error::new(
+            ErrorKind::Unsupported,
+            "the primary selection is only supported on X11",
+        )),
+    }
+}
+
+/// Fails if there's no clipboard, which is when the compositor doesn't support it or there's no seat.
+fn check_data_device(state: &WindowImplState) -> Result<(), Error> {
+    if state.data_device.is_null() {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "the compositor does not support the clipboard",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// The MIME types of the offer that's on the clipboard, if there is one.
+fn selection_mime_types(state: &WindowImplState) -> Option<&[String]> {
+    let offer = state.data_offers.iter().find(|x| x.0 == state.selection_offer)?;
+    Some(&offer.1)
+}
+
+fn clipboard_formats(state: &WindowImplState) -> Result<Vec<String>, Error> {
+    check_data_device(state)?;
+    if !state.data_source.is_null() {
+        return Ok(state.data_source_formats.iter().map(|x| x.0.clone()).collect())
+    }
+    let mime_types = selection_mime_types(state).unwrap_or_default();
+    Ok(clipboard::mime_types(mime_types.iter().cloned()))
+}
+
+/// Asks for what's on the clipboard as `mime`, which the app that put it there writes to a pipe.
+unsafe fn clipboard_get(state: &WindowImplState, mime: &str) -> Result<Option<Paste>, Error> {
+    check_data_device(state)?;
+    if !state.data_source.is_null() {
+        let data = state.data_source_formats.iter().find(|x| x.0 == mime);
+        return Ok(data.map(|x| Paste::Owned(Arc::clone(&x.1))))
+    }
+    let mime_types = selection_mime_types(state).unwrap_or_default();
+    let mime = match mime_types.iter().find(|x| *x == mime) {
+        Some(mime) => mime.as_str(),
+        // Text that's only offered as `UTF8_STRING` is listed as `TEXT_MIME`
+        None if mime == TEXT_MIME && mime_types.iter().any(|x| x == "UTF8_STRING") => "UTF8_STRING",
+        None => return Ok(None),
+    };
+    let mut fds = [-1 as c_int; 2];
+    if posix::pipe2(fds.as_mut_ptr(), posix::O_CLOEXEC) != 0 {
+        return Err(posix::errno_error(ErrorKind::Other, "failed to create a pipe"))
+    }
+    // libwayland sends a copy of the write end, so ours is closed to see the end of the data when theirs is
+    let mime = str_to_cstring(mime);
+    util::request(
+        state.selection_offer,
+        1,
+        &mut [wl_argument { s: mime.as_ptr() }, wl_argument { h: fds[1] }],
+    );
+    let _ = posix::close(fds[1]);
+    Ok(Some(Paste::Pipe(fds[0])))
+}
+
+/// Puts data on the clipboard, or takes ours off it if there's nothing to put there.
+unsafe fn clipboard_set(
+    state: &mut WindowImplState,
+    formats: Vec<(String, Vec<u8>)>,
+    data: *const c_void,
+) -> Result<(), Error> {
+    check_data_device(state)?;
+    // Destroying the source that's on the clipboard empties it
+    if formats.is_empty() {
+        destroy_data_source(state);
+        return Ok(())
+    }
+    let serial = state.input_serial.ok_or_else(|| {
+        Error::new(
+            ErrorKind::Other,
+            "the clipboard can only be set once the window has had input",
+        )
+    })?;
+
+    let formats: Vec<(String, Arc<[u8]>)> = formats.into_iter().map(|(mime, data)| (mime, data.into())).collect();
+    let offered = with_text_aliases(&formats);
+    let source = create_data_source(state, &offered, data);
+    util::request(
+        state.data_device,
+        1,
+        &mut [wl_argument { o: source }, wl_argument { u: serial }],
+    );
+    destroy_data_source(state);
+    state.data_source = source;
+    state.data_source_formats = formats;
+    state.data_source_offered = offered;
+    Ok(())
+}
+
+/// Adds the aliases that text is offered under to the formats that something's offered as.
+fn with_text_aliases(formats: &[(String, Arc<[u8]>)]) -> Vec<(String, Arc<[u8]>)> {
+    let mut offered = formats.to_vec();
+    if let Some((_, text)) = formats.iter().find(|x| x.0 == TEXT_MIME) {
+        for alias in clipboard::text_aliases(text) {
+            if !offered.iter().any(|x| x.0 == *alias) {
+                offered.push(((*alias).to_owned(), Arc::clone(text)));
+            }
+        }
+    }
+    offered
+}
+
+/// Makes a data source offering the MIME types of `offered`, which is handed its data as it's asked for.
+unsafe fn create_data_source(
+    state: &WindowImplState,
+    offered: &[(String, Arc<[u8]>)],
+    data: *const c_void,
+) -> *mut wl_proxy {
+    let source = util::request_new(
+        state.data_device_manager,
+        0,
+        &WL_DATA_SOURCE,
+        &mut [wl_argument { n: 0 }],
+    );
+    let _ = wl_proxy_add_dispatcher(source, data_source_dispatch, data, ptr::null_mut());
+    for (mime, _) in offered {
+        let mime = str_to_cstring(mime);
+        util::request(source, 0, &mut [wl_argument { s: mime.as_ptr() }]);
+    }
+    source
+}
+
+/// Starts dragging something out of the window, which the compositor takes from there.
+unsafe fn start_drag(
+    state: &mut WindowImplState,
+    drag: DragData,
+    icon: DragIcon,
+    data: *const c_void,
+) -> Result<(), Error> {
+    check_data_device(state)?;
+    // Drag and drop actions are from version 3, before which there'd be no saying whether it was moved
+    if wl_proxy_get_version(state.data_device_manager) < 3 {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            "the compositor does not support dragging out of windows",
+        ))
+    }
+    if !state.drag_source.is_null() {
+        return Err(Error::new(
+            ErrorKind::Other,
+            "something is already being dragged out of the window",
+        ))
+    }
+    let serial = state.press_serial.ok_or_else(|| {
+        Error::new(
+            ErrorKind::Other,
+            "dragging out of the window can only start while a mouse button is held",
+        )
+    })?;
+
+    let formats: Vec<(String, Arc<[u8]>)> = drag
+        .into_formats()
+        .into_iter()
+        .map(|(mime, data)| (mime, data.into()))
+        .collect();
+    let offered = with_text_aliases(&formats);
+    let source = create_data_source(state, &offered, data);
+    // Which of these it is is up to the compositor, usually going by the modifiers that are held
+    const DND_ACTION_COPY: u32 = 1;
+    const DND_ACTION_MOVE: u32 = 2;
+    util::request(
+        source,
+        2,
+        &mut [wl_argument {
+            u: DND_ACTION_COPY | DND_ACTION_MOVE,
+        }],
+    );
+
+    // The icon's surface is positioned relative to the pointer, so the hotspot is put under it
+    let (mut surface, mut buffer) = (ptr::null_mut(), ptr::null_mut());
+    if let DragIcon::Image(image) = &icon {
+        if !state.shm.is_null() {
+            buffer = util::create_buffer(state.shm, image.size, image.premultiplied_argb());
+        }
+        if !buffer.is_null() {
+            surface = util::request_new(state.compositor, 0, &WL_SURFACE, &mut [wl_argument { n: 0 }]);
+            let (x, y) = image.hotspot;
+            util::request(
+                surface,
+                1,
+                &mut [
+                    wl_argument { o: buffer },
+                    wl_argument { i: -(x as i32) },
+                    wl_argument { i: -(y as i32) },
+                ],
+            );
+            util::request(surface, 6, &mut []);
+        }
+    }
+    util::request(
+        state.data_device,
+        0,
+        &mut [
+            wl_argument { o: source },
+            wl_argument { o: state.surface },
+            wl_argument { o: surface },
+            wl_argument { u: serial },
+        ],
+    );
+    state.drag_source = source;
+    state.drag_source_offered = offered;
+    state.drag_icon = (surface, buffer);
+    state.drag_source_action = None;
+    Ok(())
+}
+
+/// Destroys what's being dragged out of the window, if anything, and its icon.
+unsafe fn destroy_drag_source(state: &mut WindowImplState) {
+    if !state.drag_source.is_null() {
+        util::request_destroy(state.drag_source, 1);
+        state.drag_source = ptr::null_mut();
+    }
+    let (surface, buffer) = mem::replace(&mut state.drag_icon, (ptr::null_mut(), ptr::null_mut()));
+    if !surface.is_null() {
+        util::request_destroy(surface, 0);
+    }
+    if !buffer.is_null() {
+        util::request_destroy(buffer, 0);
+    }
+    state.drag_source_offered.clear();
+    state.drag_source_action = None;
+}
+
+/// Destroys what we put on the clipboard, if anything.
+unsafe fn destroy_data_source(state: &mut WindowImplState) {
+    if !state.data_source.is_null() {
+        util::request_destroy(state.data_source, 1);
+        state.data_source = ptr::null_mut();
+    }
+    state.data_source_formats.clear();
+    state.data_source_offered.clear();
+}
+
+/// The MIME type to ask for what's being dragged as, if it's accepted as a format it's offered in.
+fn drag_mime_type(state: &WindowImplState) -> Option<&str> {
+    let (format, _) = state.drag_accepted.as_ref()?;
+    let offer = state.data_offers.iter().find(|x| x.0 == state.drag_offer)?;
+    format.pick_name(&offer.1)
+}
+
+/// Tells the compositor whether what's being dragged over the window would be accepted, and what would be done with it.
+unsafe fn update_drag_accepted(state: &WindowImplState) {
+    if state.drag_offer.is_null() {
+        return
+    }
+    let mime = drag_mime_type(state).map(str_to_cstring);
+    util::request(
+        state.drag_offer,
+        0,
+        &mut [
+            wl_argument { u: state.drag_serial },
+            wl_argument {
+                s: mime.as_ref().map_or(ptr::null(), |x| x.as_ptr()),
+            },
+        ],
+    );
+    // Without picking an action, it can't be dropped from version 3 on, where copy is 1 and move is 2
+    if wl_proxy_get_version(state.drag_offer) >= 3 {
+        let action = match state.drag_accepted {
+            Some((_, DropAction::Copy)) if mime.is_some() => 1,
+            Some((_, DropAction::Move)) if mime.is_some() => 2,
+            _ => 0,
+        };
+        util::request(
+            state.drag_offer,
+            4,
+            &mut [wl_argument { u: action }, wl_argument { u: action }],
+        );
+    }
+}
+
+/// Asks for what was dropped, which is read from a pipe on another thread as the app it's from writes it.
+///
+/// That thread sends the event, then has the offer finished and destroyed, so it's kept around until then.
+/// If it wasn't accepted, it's just left.
+unsafe fn receive_drop(state: &mut WindowImplState) {
+    let mime = drag_mime_type(state).map(str_to_cstring);
+    let offer = mem::replace(&mut state.drag_offer, ptr::null_mut());
+    let mut fds = [-1 as c_int; 2];
+    let accepted = match (mime, state.drag_accepted.clone()) {
+        (Some(mime), Some(accepted)) if posix::pipe2(fds.as_mut_ptr(), posix::O_CLOEXEC) == 0 => {
+            util::request(
+                offer,
+                1,
+                &mut [wl_argument { s: mime.as_ptr() }, wl_argument { h: fds[1] }],
+            );
+            let _ = posix::close(fds[1]);
+            accepted
+        },
+        _ => {
+            destroy_data_offer(state, offer);
+            state.shared.events.push(Event::DragLeft);
+            return
+        },
+    };
+    let (id, shared) = (wl_proxy_get_id(offer), Arc::clone(&state.shared));
+    let _ = thread::spawn(move || {
+        let (format, action) = accepted;
+        let action = match posix::read_pipe(fds[0], TRANSFER_TIMEOUT) {
+            Ok(data) => {
+                shared.events.push(Event::Dropped(DragData::from_bytes(format, data)));
+                Some(action)
+            },
+            Err(_) => {
+                shared.events.push(Event::DragLeft);
+                None
+            },
+        };
+        mutex_lock(&shared.commands).push(Command::FinishDrop(id, action));
+        shared.wake.wake();
+    });
+}
+
+/// Destroys a data offer that's no longer needed, if it isn't null.
+unsafe fn destroy_data_offer(state: &mut WindowImplState, offer: *mut wl_proxy) {
+    if let Some(index) = state.data_offers.iter().position(|x| x.0 == offer) {
+        let _ = state.data_offers.remove(index);
+        util::request_destroy(offer, 2);
+    }
+}
+
+unsafe fn destroy_output(output: Output) {
+    if !output.xdg_output.is_null() {
+        util::request_destroy(output.xdg_output, 0);
+    }
+    if wl_proxy_get_version(output.proxy) >= 3 {
+        util::request_destroy(output.proxy, 0);
+    } else {
+        wl_proxy_destroy(output.proxy);
+    }
+}
+
+unsafe extern "C" fn registry_dispatch(
+    data: *const c_void,
+    _target: *mut c_void,
+    opcode: u32,
+    _msg: *const wl_message,
+    args: *mut wl_argument,
+) -> c_int {
+    let state = &mut *(data as *mut WindowImplState);
+    if opcode == 0 {
+        // global(name: uint, interface: string, version: uint)
+        let name = (*args).u;
+        let interface = CStr::from_ptr((*args.add(1)).s).to_bytes();
+        let version = (*args.add(2)).u;
+        match interface {
+            b"wl_compositor" if state.compositor.is_null() => {
+                state.compositor = util::bind(state.registry, name, &WL_COMPOSITOR, version);
+            },
+            b"wl_seat" if state.seat.is_null() => {
+                state.seat = util::bind(state.registry, name, &WL_SEAT, version);
+                let _ = wl_proxy_add_dispatcher(state.seat, seat_dispatch, data, ptr::null_mut());
+                create_text_input(state, data);
+                create_data_device(state, data);
+            },
+            b"wl_data_device_manager" if state.data_device_manager.is_null() => {
+                state.data_device_manager = util::bind(state.registry, name, &WL_DATA_DEVICE_MANAGER, version);
+                create_data_device(state, data);
+            },
+            b"xdg_wm_base" if state.wm_base.is_null() => {
+                state.wm_base = util::bind(state.registry, name, &XDG_WM_BASE, version);
+                let _ = wl_proxy_add_dispatcher(state.wm_base, wm_base_dispatch, data, ptr::null_mut());
+            },
+            b"wl_output" => {
+                let proxy = util::bind(state.registry, name, &WL_OUTPUT, version);
+                let _ = wl_proxy_add_dispatcher(proxy, output_dispatch, data, ptr::null_mut());
+                let mut output = Output::new(proxy, name);
+                if !state.xdg_output_manager.is_null() {
+                    create_xdg_output(state.xdg_output_manager, &mut output, data);
+                }
+                state.outputs.push(output);
+            },
+            b"wl_shm" if state.shm.is_null() => {
+                state.shm = util::bind(state.registry, name, &WL_SHM, version);
+            },
+            b"wp_cursor_shape_manager_v1" if state.cursor_shape_manager.is_null() => {
+                state.cursor_shape_manager = util::bind(state.registry, name, &WP_CURSOR_SHAPE_MANAGER_V1, version);
+                create_cursor_shape_device(state);
+            },
+            b"xdg_toplevel_icon_manager_v1" if state.icon_manager.is_null() => {
+                state.icon_manager = util::bind(state.registry, name, &XDG_TOPLEVEL_ICON_MANAGER_V1, version);
+            },
+            b"zwp_pointer_constraints_v1" if state.pointer_constraints.is_null() => {
+                state.pointer_constraints = util::bind(state.registry, name, &ZWP_POINTER_CONSTRAINTS_V1, version);
+            },
+            b"zwp_relative_pointer_manager_v1" if state.relative_pointer_manager.is_null() => {
+                let manager = util::bind(state.registry, name, &ZWP_RELATIVE_POINTER_MANAGER_V1, version);
+                state.relative_pointer_manager = manager;
+                create_relative_pointer(state, data);
+            },
+            b"zwp_text_input_manager_v3" if state.ime_wanted && state.text_input_manager.is_null() => {
+                state.text_input_manager = util::bind(state.registry, name, &ZWP_TEXT_INPUT_MANAGER_V3, version);
+                create_text_input(state, data);
+            },
+            b"zxdg_output_manager_v1" if state.xdg_output_manager.is_null() => {
+                let manager = util::bind(state.registry, name, &ZXDG_OUTPUT_MANAGER_V1, version);
+                state.xdg_output_manager = manager;
+                for output in &mut state.outputs {
+                    create_xdg_output(manager, output, data);
+                }
+            },
+            _ => (),
+        }
+    } else if opcode == 1 {
+        // global_remove(name: uint), only outputs are expected to come and go
+        let name = (*args).u;
+        if let Some(index) = state.outputs.iter().position(|x| x.name == name) {
+            destroy_output(state.outputs.remove(index));
+            update_monitors(state);
+            update_scale(state);
+        }
+    }
+    0
+}
+
+unsafe extern "C" fn output_dispatch(
+    data: *const c_void,
+    target: *mut c_void,
+    opcode: u32,
+    _msg: *const wl_message,
+    args: *mut wl_argument,
+) -> c_int {
+    const WL_OUTPUT_MODE_CURRENT: u32 = 1;
+
+    let state = &mut *(data as *mut WindowImplState);
+    let output = match state.outputs.iter_mut().find(|x| x.proxy == target.cast()) {
+        Some(output) => output,
+        None => return 0,
+    };
+    match opcode {
+        0 => {
+            // geometry(x: int, y: int, physical_width: int, physical_height: int, subpixel: int,
+            //          make: string, model: string, transform: int)
+            let monitor = &mut output.monitor;
+            monitor.position = PhysicalPosition::new((*args).i, (*args.add(1)).i);
+            let (mm_width, mm_height) = ((*args.add(2)).i, (*args.add(3)).i);
+            monitor.physical_size_mm = (mm_width > 0 && mm_height > 0).then_some((mm_width as u32, mm_height as u32));
+            if !output.named {
+                let make = CStr::from_ptr((*args.add(5)).s).to_string_lossy();
+                let model = CStr::from_ptr((*args.add(6)).s).to_string_lossy();
+                monitor.name = format!("{} {}", make, model);
+            }
+            output.transform = (*args.add(7)).i;
+        },
+        1 => {
+            // mode(flags: uint, width: int, height: int, refresh: int)
+            let size = PhysicalSize::new((*args.add(1)).i.max(0) as u32, (*args.add(2)).i.max(0) as u32);
+            let refresh_rate_millihertz = (*args.add(3)).i.max(0) as u32;
+            let monitor = &mut output.monitor;
+            if (*args).u & WL_OUTPUT_MODE_CURRENT != 0 {
+                monitor.size = size;
+                monitor.refresh_rate_millihertz = (refresh_rate_millihertz != 0).then_some(refresh_rate_millihertz);
+            }
+            let mode = VideoMode {
+                id: 0,
+                size,
+                bit_depth: 32,
+                refresh_rate_millihertz,
+            };
+            if !monitor.video_modes.contains(&mode) {
+                monitor.video_modes.push(mode);
+            }
+
+            // Version 1 has no `done`, but the mode is the last thing sent about an output
+            if wl_proxy_get_version(target.cast()) < 2 {
+                output.done = true;
+                update_monitors(state);
+            }
+        },
+        2 => {
+            // done(), after a batch of changes to the output
+            output.done = true;
+            update_monitors(state);
+            update_scale(state);
+        },
+        3 => {
+            // scale(factor: int)
+            output.scale = (*args).i.max(1);
+        },
+        4 => {
+            // name(name: string), such as "DP-1"
+            output.monitor.name = CStr::from_ptr((*args).s).to_string_lossy().into_owned();
+            output.named = true;
+        },
+        _ => (),
+    }
+    0
+}
+
+unsafe extern "C" fn xdg_output_dispatch(
+    data: *const c_void,
+    target: *mut c_void,
+    opcode: u32,
+    _msg: *const wl_message,
+    args: *mut wl_argument,
+) -> c_int {
+    let state = &mut *(data as *mut WindowImplState);
+    let output = match state.outputs.iter_mut().find(|x| x.xdg_output == target.cast()) {
+        Some(output) => output,
+        None => return 0,
+    };
+    match opcode {
+        0 => {
+            // logical_position(x: int, y: int), where the output is on the desktop
+            output.logical_position = Some(PhysicalPosition::new((*args).i, (*args.add(1)).i));
+        },
+        // done(), which was replaced by `wl_output.done` in version 3
+        2 if wl_proxy_get_version(target.cast()) < 3 && output.done => {
+            update_monitors(state);
+        },
+        // name(name: string), only used if the `wl_output` doesn't have a name itself
+        3 if !output.named || wl_proxy_get_version(output.proxy) < 4 => {
+            output.monitor.name = CStr::from_ptr((*args).s).to_string_lossy().into_owned();
+            output.named = true;
+        },
+        _ => (),
+    }
+    0
+}
+
+unsafe extern "C" fn surface_dispatch(
+    data: *const c_void,
+    _target: *mut c_void,
+    opcode: u32,
+    _msg: *const wl_message,
+    args: *mut wl_argument,
+) -> c_int {
+    // enter(output: object), leave(output: object)
+    let state = &mut *(data as *mut WindowImplState);
+    let proxy = (*args).o;
+    if let Some(output) = state.outputs.iter_mut().find(|x| x.proxy == proxy) {
+        output.entered = opcode == 0;
+        update_scale(state);
+    }
+    0
+}
+
+unsafe extern "C" fn wm_base_dispatch(
+    _data: *const c_void,
+    target: *mut c_void,
+    opcode: u32,
+    _msg: *const wl_message,
+    args: *mut wl_argument,
+) -> c_int {
+    if opcode == 0 {
+        // ping(serial: uint), the compositor checking that we're not frozen
+        util::request(target.cast(), 3, &mut [*args]);
+    }
+    0
+}
+
+unsafe extern "C" fn xdg_surface_dispatch(
+    _data: *const c_void,
+    target: *mut c_void,
+    opcode: u32,
+    _msg: *const wl_message,
+    args: *mut wl_argument,
+) -> c_int {
+    if opcode == 0 {
+        // configure(serial: uint), sent after a batch of role-specific configure events
+        util::request(target.cast(), 4, &mut [*args]);
+    }
+    0
+}
+
+unsafe extern "C" fn toplevel_dispatch(
+    data: *const c_void,
+    _target: *mut c_void,
+    opcode: u32,
+    _msg: *const wl_message,
+    args: *mut wl_argument,
+) -> c_int {
+    let state = &mut *(data as *mut WindowImplState);
+    match opcode {
+        0 => {
+            // configure(width: int, height: int, states: array)
+            // A size of 0 means the compositor leaves it up to us, in which case we keep what we have
+            let (width, height) = ((*args).i, (*args.add(1)).i);
+            if width > 0 && height > 0 {
+                set_size(state, (width as u32, height as u32));
+            }
+        },
+        1 => {
+            // close()
+            state.shared.events.push(Event::CloseRequested);
+        },
+        _ => (),
+    }
+    0
+}
+
+unsafe extern "C" fn seat_dispatch(
+    data: *const c_void,
+    target: *mut c_void,
+    opcode: u32,
+    _msg: *const wl_message,
+    args: *mut wl_argument,
+) -> c_int {
+    const WL_SEAT_CAPABILITY_POINTER: u32 = 1;
+    const WL_SEAT_CAPABILITY_KEYBOARD: u32 = 2;
+
+    let state = &mut *(data as *mut WindowImplState);
+    if opcode == 0 {
+        // capabilities(capabilities: uint), sent on bind and whenever devices are plugged in or out
+        let capabilities = (*args).u;
+        let has_pointer = capabilities & WL_SEAT_CAPABILITY_POINTER != 0;
+        if has_pointer && state.pointer.is_null() {
+            state.pointer = util::request_new(target.cast(), 0, &WL_POINTER, &mut [wl_argument { n: 0 }]);
+            let _ = wl_proxy_add_dispatcher(state.pointer, pointer_dispatch, data, ptr::null_mut());
+            create_relative_pointer(state, data);
+            create_cursor_shape_device(state);
+            update_cursor_grab(state);
+        } else if !has_pointer && !state.pointer.is_null() {
+            // What was made for the pointer goes with it
+            state.pointer_serial = None;
+            for proxy in [
+                &mut state.pointer_constraint,
+                &mut state.relative_pointer,
+                &mut state.cursor_shape_device,
+            ] {
+                if !proxy.is_null() {
+                    util::request_destroy(*proxy, 0);
+                    *proxy = ptr::null_mut();
+                }
+            }
+            wl_proxy_destroy(state.pointer);
+            state.pointer = ptr::null_mut();
+        }
+        let has_keyboard = capabilities & WL_SEAT_CAPABILITY_KEYBOARD != 0;
+        if has_keyboard && state.keyboard.is_null() {
+            state.keyboard = util::request_new(target.cast(), 1, &WL_KEYBOARD, &mut [wl_argument { n: 0 }]);
+            let _ = wl_proxy_add_dispatcher(state.keyboard, keyboard_dispatch, data, ptr::null_mut());
+        } else if !has_keyboard && !state.keyboard.is_null() {
+            wl_proxy_destroy(state.keyboard);
+            state.keyboard = ptr::null_mut();
+        }
+    }
+    0
+}
+
+unsafe extern "C" fn pointer_dispatch(
+    data: *const c_void,
+    target: *mut c_void,
+    opcode: u32,
+    _msg: *const wl_message,
+    args: *mut wl_argument,
+) -> c_int {
+    // From `linux/input-event-codes.h`
+    const BTN_LEFT: u32 = 0x110;
+    const BTN_RIGHT: u32 = 0x111;
+    const BTN_MIDDLE: u32 = 0x112;
+    const BTN_SIDE: u32 = 0x113;
+
+    let state = &mut *(data as *mut WindowImplState);
+    if opcode == 0 {
+        // The cursor is set with the serial of `enter`, every time it enters
+        state.pointer_serial = Some((*args).u);
+        update_cursor(state);
+    } else if opcode == 3 {
+        // Setting the clipboard needs the serial of something the user did, and dragging needs a button held
+        let serial = (*args).u;
+        state.input_serial = Some(serial);
+        state.press_serial = ((*args.add(3)).u == 1).then_some(serial);
+    }
+    let events = &state.shared.events;
+    match opcode {
+        0 | 2 => {
+            // enter(serial: uint, surface: object, x: fixed, y: fixed), motion(time: uint, x: fixed, y: fixed)
+            let (x, y) = if opcode == 0 {
+                events.push(Event::CursorEntered);
+                ((*args.add(2)).f, (*args.add(3)).f)
+            } else {
+                ((*args.add(1)).f, (*args.add(2)).f)
+            };
+            let scale = state.scale;
+            events.push(Event::CursorMoved(PhysicalPosition::new(
+                x * scale / 256,
+                y * scale / 256,
+            )));
+        },
+        1 => {
+            // leave(serial: uint, surface: object)
+            state.pointer_serial = None;
+            state.cursor_frame_deadline = None;
+            events.push(Event::CursorLeft);
+        },
+        3 => {
+            // button(serial: uint, time: uint, button: uint, state: uint)
+            // The side buttons and everything after them are numbered like X11 does, minus its scroll buttons
+            let button = match (*args.add(2)).u {
+                BTN_LEFT => MouseButton::Left,
+                BTN_RIGHT => MouseButton::Right,
+                BTN_MIDDLE => MouseButton::Middle,
+                other => MouseButton::Other(other.saturating_sub(BTN_SIDE).saturating_add(4) as u16),
+            };
+            events.push(Event::MouseButton {
+                button,
+                pressed: (*args.add(3)).u == 1,
+            });
+        },
+        4 => {
+            // axis(time: uint, axis: uint, value: fixed)
+            // The value is in surface-local pixels, positive values are down and right
+            let value = f64::from((*args.add(2)).f) / 256.0;
+            if (*args.add(1)).u == 0 {
+                state.scroll.1 += value;
+            } else {
+                state.scroll.0 += value;
+            }
+            // Before version 5 there's no `frame` to wait for
+            if wl_proxy_get_version(target.cast()) < 5 {
+                flush_scroll(state);
+            }
+        },
+        5 => {
+            // frame(), which ends a group of events that happened at the same time
+            flush_scroll(state);
+        },
+        8 => {
+            // axis_discrete(axis: uint, discrete: int), sent along with `axis` for mouse wheels
+            let discrete = (*args.add(1)).i;
+            if (*args).u == 0 {
+                state.scroll_discrete.1 += discrete;
+            } else {
+                state.scroll_discrete.0 += discrete;
+            }
+        },
+        _ => (),
+    }
+    0
+}
+
+unsafe extern "C" fn relative_pointer_dispatch(
+    data: *const c_void,
+    _target: *mut c_void,
+    opcode: u32,
+    _msg: *const wl_message,
+    args: *mut wl_argument,
+) -> c_int {
+    let state = &mut *(data as *mut WindowImplState);
+    if opcode == 0 {
+        // relative_motion(utime_hi: uint, utime_lo: uint, dx: fixed, dy: fixed, dx_unaccel: fixed, dy_unaccel: fixed)
+        let x = f64::from((*args.add(4)).f) / 256.0;
+        let y = f64::from((*args.add(5)).f) / 256.0;
+        state.shared.events.push(Event::RawMouseMotion(x, y));
+    }
+    0
+}
+
+/// Sends the scrolling from a pointer frame, which is in notches if it came from a mouse wheel.
+fn flush_scroll(state: &mut WindowImplState) {
+    // Vertical values are positive downwards, which is the opposite of ramen's convention
+    let (x, y) = mem::take(&mut state.scroll);
+    let (notches_x, notches_y) = mem::take(&mut state.scroll_discrete);
+    let delta = if (notches_x, notches_y) != (0, 0) {
+        ScrollDelta::Lines(notches_x as f32, -notches_y as f32)
+    } else if (x, y) != (0.0, 0.0) {
+        let scale = f64::from(state.scale);
+        ScrollDelta::Pixels(x * scale, -y * scale)
+    } else {
+        return
+    };
+    state.shared.events.push(Event::MouseWheel(delta));
+}
+
+unsafe extern "C" fn keyboard_dispatch(
+    data: *const c_void,
+    _target: *mut c_void,
+    opcode: u32,
+    _msg: *const wl_message,
+    args: *mut wl_argument,
+) -> c_int {
+    let state = &mut *(data as *mut WindowImplState);
+    if opcode == 1 || opcode == 3 {
+        // Setting the clipboard needs the serial of something the user did, which includes focusing the window
+        state.input_serial = Some((*args).u);
+    }
+    let events = &state.shared.events;
+    match opcode {
+        0 => {
+            // keymap(format: uint, fd: fd, size: uint), we own the fd now
+            // The only format there is (besides "none") is 1, XKB's text format, which ends in a null
+            let (format, fd, size) = ((*args).u, (*args.add(1)).h, (*args.add(2)).u as usize);
+            state.keymap = None;
+            if format == 1 {
+                let map = posix::mmap(ptr::null_mut(), size, posix::PROT_READ, posix::MAP_PRIVATE, fd, 0);
+                if map != posix::MAP_FAILED {
+                    state.keymap = xkb::Keymap::from_text(slice::from_raw_parts(map.cast(), size));
+                    let _ = posix::munmap(map, size);
+                }
+            }
+            let _ = posix::close(fd);
+        },
+        1 => {
+            // enter(serial: uint, surface: object, keys: array)
+            events.push(Event::Focus(true));
+        },
+        2 => {
+            // leave(serial: uint, surface: object)
+            state.held_keys.release_all(events);
+            events.push(Event::Focus(false));
+        },
+        3 => {
+            // key(serial: uint, time: uint, key: uint, state: uint)
+            // The key is an evdev code, which X11 offsets by 8, so the Linux backends agree on scancodes
+            let scancode = (*args.add(2)).u + 8;
+            let pressed = (*args.add(3)).u == 1;
+            let key_code = xkb::key_code(scancode);
+            let logical_key = state
+                .keymap
+                .as_ref()
+                .map_or(Key::Unidentified, |keymap| keymap.logical_key(scancode));
+            let _ = state.held_keys.update(scancode, key_code, logical_key, pressed);
+            events.push(Event::Key {
+                scancode,
+                key_code,
+                logical_key,
+                pressed,
+                repeat: false,
+                modifiers: xkb::modifiers(state.modifiers),
+            });
+
+            // Keys that an input method takes for composing text never get here, so these are typed directly
+            if let (true, Some(keymap)) = (pressed, &state.keymap) {
+                if let Some(ch) = keymap.text(scancode) {
+                    events.push(Event::Text(ch.to_string()));
+                }
+            }
+        },
+        4 => {
+            // modifiers(serial: uint, mods_depressed: uint, mods_latched: uint, mods_locked: uint, group: uint)
+            let (depressed, latched, locked) = ((*args.add(1)).u, (*args.add(2)).u, (*args.add(3)).u);
+            state.modifiers = depressed | latched | locked;
+            if let Some(keymap) = &mut state.keymap {
+                keymap.update_mask(depressed, latched, locked, (*args.add(4)).u);
+            }
+        },
+        _ => (),
+    }
+    0
+}
+
+unsafe extern "C" fn text_input_dispatch(
+    data: *const c_void,
+    target: *mut c_void,
+    opcode: u32,
+    _msg: *const wl_message,
+    args: *mut wl_argument,
+) -> c_int {
+    let state = &mut *(data as *mut WindowImplState);
+    let text_input = target.cast();
+    let string = |arg: wl_argument| (!arg.s.is_null()).then(|| CStr::from_ptr(arg.s).to_string_lossy().into_owned());
+    match opcode {
+        0 => {
+            // enter(surface: object), which is when text input can be turned on for the surface
+            state.ime.entered = true;
+            util::request(text_input, 1, &mut []);
+            set_ime_cursor_area(state);
+            util::request(text_input, 7, &mut []);
+        },
+        1 => {
+            // leave(surface: object)
+            state.ime.entered = false;
+            util::request(text_input, 2, &mut []);
+            util::request(text_input, 7, &mut []);
+        },
+        2 => {
+            // preedit_string(text: string?, cursor_begin: int, cursor_end: int), where -1s mean a hidden cursor
+            let (begin, end) = ((*args.add(1)).i, (*args.add(2)).i);
+            let cursor = (begin >= 0 && end >= 0).then_some((begin as usize, end as usize));
+            state.ime.pending_preedit = string(*args).map(|text| (text, cursor));
+        },
+        3 => {
+            // commit_string(text: string?)
+            state.ime.pending_commit = string(*args);
+        },
+        5 => {
+            // done(serial: uint), the old pre-edit text is replaced by the commit, then the new pre-edit text
+            let events = &state.shared.events;
+            let ime = &mut state.ime;
+            let commit = ime.pending_commit.take().filter(|x| !x.is_empty());
+            let preedit = ime.pending_preedit.take().filter(|(text, _)| !text.is_empty());
+            if ime.preedit_shown && (commit.is_some() || preedit.is_none()) {
+                ime.preedit_shown = false;
+                events.push(Event::Preedit {
+                    text: String::new(),
+                    cursor: None,
+                });
+            }
+            if let Some(text) = commit {
+                events.push(Event::Text(text));
+            }
+            if let Some((text, cursor)) = preedit {
+                ime.preedit_shown = true;
+                events.push(Event::Preedit { text, cursor });
+            }
+        },
+        _ => (),
+    }
+    0
+}
+
+unsafe extern "C" fn data_device_dispatch(
+    data: *const c_void,
+    _target: *mut c_void,
+    opcode: u32,
+    _msg: *const wl_message,
+    args: *mut wl_argument,
+) -> c_int {
+    let state = &mut *(data as *mut WindowImplState);
+    match opcode {
+        0 => {
+            // data_offer(id: new_id), introducing an offer, whose MIME types follow before it's used
+            let offer = (*args).o;
+            let _ = wl_proxy_add_dispatcher(offer, data_offer_dispatch, data, ptr::null_mut());
+            state.data_offers.push((offer, Vec::new()));
+        },
+        1 => {
+            // enter(serial: uint, surface: object, x: fixed, y: fixed, id: object), with the offer being dragged
+            // That's null for drags within another client that aren't offering anything, which can't be dropped
+            let offer = (*args.add(4)).o;
+            if offer.is_null() {
+                return 0
+            }
+            state.drag_offer = offer;
+            state.drag_serial = (*args).u;
+            state.drag_accepted = None;
+            let formats = match state.data_offers.iter().find(|x| x.0 == offer) {
+                Some((_, mime_types)) => DragFormat::from_names(mime_types),
+                None => Vec::new(),
+            };
+            state.shared.events.push(Event::DragEntered(formats));
+            drag_moved(state, (*args.add(2)).f, (*args.add(3)).f);
+            update_drag_accepted(state);
+        },
+        // leave(), which also comes after a drop, when the offer's kept until what was dropped is read
+        2 if !state.drag_offer.is_null() => {
+            destroy_data_offer(state, state.drag_offer);
+            state.drag_offer = ptr::null_mut();
+            state.shared.events.push(Event::DragLeft);
+        },
+        // motion(time: uint, x: fixed, y: fixed)
+        3 if !state.drag_offer.is_null() => drag_moved(state, (*args.add(1)).f, (*args.add(2)).f),
+        // drop()
+        4 if !state.drag_offer.is_null() => receive_drop(state),
+        5 => {
+            // selection(id: object), the offer that's on the clipboard now, or null if nothing is
+            let offer = (*args).o;
+            if offer != state.selection_offer {
+                destroy_data_offer(state, state.selection_offer);
+                state.selection_offer = offer;
+            }
+        },
+        _ => (),
+    }
+    0
+}
+
+/// Sends where what's being dragged moved to, from surface-local coordinates.
+fn drag_moved(state: &WindowImplState, x: i32, y: i32) {
+    let scale = state.scale;
+    let position = PhysicalPosition::new(x * scale / 256, y * scale / 256);
+    state.shared.events.push(Event::DragMoved(position));
+}
+
+unsafe extern "C" fn data_offer_dispatch(
+    data: *const c_void,
+    target: *mut c_void,
+    opcode: u32,
+    _msg: *const wl_message,
+    args: *mut wl_argument,
+) -> c_int {
+    let state = &mut *(data as *mut WindowImplState);
+    if opcode == 0 {
+        // offer(mime_type: string)
+        let mime = CStr::from_ptr((*args).s).to_string_lossy().into_owned();
+        if let Some(offer) = state.data_offers.iter_mut().find(|x| x.0 == target.cast()) {
+            offer.1.push(mime);
+        }
+    }
+    0
+}
+
+unsafe extern "C" fn data_source_dispatch(
+    data: *const c_void,
+    target: *mut c_void,
+    opcode: u32,
+    _msg: *const wl_message,
+    args: *mut wl_argument,
+) -> c_int {
+    let state = &mut *(data as *mut WindowImplState);
+    match opcode {
+        1 => {
+            // send(mime_type: string, fd: fd), we own the fd now
+            // The other app reads it at its own pace, so it's written on another thread
+            let fd = (*args.add(1)).h;
+            let mime = CStr::from_ptr((*args).s).to_bytes();
+            let offered = if target.cast() == state.drag_source {
+                &state.drag_source_offered
+            } else {
+                &state.data_source_offered
+            };
+            match offered.iter().find(|x| x.0.as_bytes() == mime) {
+                Some((_, data)) => {
+                    let data = Arc::clone(data);
+                    let _ = thread::spawn(move || posix::write_pipe(fd, &data, TRANSFER_TIMEOUT));
+                },
+                None => {
+                    let _ = posix::close(fd);
+                },
+            }
+        },
+        // cancelled(), something else went on the clipboard
+        2 if target.cast() == state.data_source => destroy_data_source(state),
+        // cancelled(), what was dragged out of the window wasn't taken, dnd_finished(), it was
+        2 | 4 if target.cast() == state.drag_source => {
+            let action = state.drag_source_action.filter(|_| opcode == 4);
+            destroy_drag_source(state);
+            state.shared.events.push(Event::DragFinished(action));
+        },
+        // action(dnd_action: uint), what would be done with it if it was dropped, where the last one is what was
+        5 if target.cast() == state.drag_source => {
+            state.drag_source_action = match (*args).u {
+                1 => Some(DropAction::Copy),
+                2 => Some(DropAction::Move),
+                _ => None,
+            };
+        },
+        _ => (),
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WindowImpl;
+    use crate::window::Window;
+    use std::env;
+
+    // Needs a compositor, such as `weston --backend=headless-backend.so --socket=ramen-test`
+    #[test]
+    fn create_and_drop() {
+        if env::var_os("WAYLAND_DISPLAY").is_none() {
+            return
+        }
+        let builder = Window::builder();
+        assert!(WindowImpl::new(&builder).is_ok());
+    }
+}
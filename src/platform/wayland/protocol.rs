@@ -0,0 +1,469 @@
+//! Protocol interface descriptions, as `wayland-scanner` would generate them from the XML.
+//!
+//! Object arguments of interfaces ramen doesn't describe are left untyped (`X`),
+//! which only makes libwayland skip checking them.
+//!
+//! The core interfaces are exported by `libwayland-client` as well, but since it's loaded at runtime
+//! they can't be referenced from statics. This is fine, as interfaces are compared by name.
+//!
+//! The opcode of a request or event is its index in the list of its interface.
+
+use super::ffi::{wl_interface, wl_message};
+
+macro_rules! count {
+    () => { 0usize };
+    ($head:tt $($tail:tt)*) => { 1usize + count!($($tail)*) };
+}
+
+macro_rules! messages {
+    ($($name:literal($sig:literal $(, $ty:ident)*);)*) => {{
+        static MESSAGES: [wl_message; count!($($name)*)] = [$(
+            wl_message {
+                name: concat!($name, "\0").as_ptr().cast(),
+                signature: concat!($sig, "\0").as_ptr().cast(),
+                types: {
+                    static TYPES: [Option<&wl_interface>; count!($($ty)*)] = [$(interface!($ty)),*];
+                    &TYPES as *const _ as *const *const wl_interface
+                },
+            },
+        )*];
+        &MESSAGES as *const _ as *const wl_message
+    }};
+}
+
+macro_rules! interface {
+    (X) => {
+        None
+    };
+    ($ty:ident) => {
+        Some(&$ty)
+    };
+}
+
+macro_rules! interfaces {
+    ($(
+        $ident:ident($name:literal, $version:literal) {
+            requests { $($rname:literal($rsig:literal $(, $rty:ident)*);)* }
+            events { $($ename:literal($esig:literal $(, $ety:ident)*);)* }
+        }
+    )*) => {$(
+        pub static $ident: wl_interface = wl_interface {
+            name: concat!($name, "\0").as_ptr().cast(),
+            version: $version,
+            method_count: count!($($rname)*) as _,
+            methods: messages! { $($rname($rsig $(, $rty)*);)* },
+            event_count: count!($($ename)*) as _,
+            events: messages! { $($ename($esig $(, $ety)*);)* },
+        };
+    )*};
+}
+
+// Signature characters:
+// `i` int, `u` uint, `f` fixed, `s` string, `o` object, `n` new_id, `a` array, `h` fd,
+// `?` makes the next argument nullable, and a leading number is the version it was added in.
+// Untyped `new_id`s (only `wl_registry.bind`) are sent as `sun`: interface name, version, id.
+
+interfaces! {
+    WL_REGISTRY("wl_registry", 1) {
+        requests {
+            "bind"("usun", X, X, X, X);
+        }
+        events {
+            "global"("usu", X, X, X);
+            "global_remove"("u", X);
+        }
+    }
+
+    WL_CALLBACK("wl_callback", 1) {
+        requests {}
+        events {
+            "done"("u", X);
+        }
+    }
+
+    WL_COMPOSITOR("wl_compositor", 4) {
+        requests {
+            "create_surface"("n", WL_SURFACE);
+            "create_region"("n", WL_REGION);
+        }
+        events {}
+    }
+
+    WL_SURFACE("wl_surface", 4) {
+        requests {
+            "destroy"("");
+            "attach"("?oii", X, X, X);
+            "damage"("iiii", X, X, X, X);
+            "frame"("n", WL_CALLBACK);
+            "set_opaque_region"("?o", WL_REGION);
+            "set_input_region"("?o", WL_REGION);
+            "commit"("");
+            "set_buffer_transform"("2i", X);
+            "set_buffer_scale"("3i", X);
+            "damage_buffer"("4iiii", X, X, X, X);
+        }
+        events {
+            "enter"("o", WL_OUTPUT);
+            "leave"("o", WL_OUTPUT);
+        }
+    }
+
+    WL_OUTPUT("wl_output", 4) {
+        requests {
+            "release"("3");
+        }
+        events {
+            "geometry"("iiiiissi", X, X, X, X, X, X, X, X);
+            "mode"("uiii", X, X, X, X);
+            "done"("2");
+            "scale"("2i", X);
+            "name"("4s", X);
+            "description"("4s", X);
+        }
+    }
+
+    WL_REGION("wl_region", 1) {
+        requests {
+            "destroy"("");
+            "add"("iiii", X, X, X, X);
+            "subtract"("iiii", X, X, X, X);
+        }
+        events {}
+    }
+
+    WL_SHM("wl_shm", 1) {
+        requests {
+            "create_pool"("nhi", WL_SHM_POOL, X, X);
+        }
+        events {
+            "format"("u", X);
+        }
+    }
+
+    WL_SHM_POOL("wl_shm_pool", 1) {
+        requests {
+            "create_buffer"("niiiiu", WL_BUFFER, X, X, X, X, X);
+            "destroy"("");
+            "resize"("i", X);
+        }
+        events {}
+    }
+
+    WL_BUFFER("wl_buffer", 1) {
+        requests {
+            "destroy"("");
+        }
+        events {
+            "release"("");
+        }
+    }
+
+    WL_SEAT("wl_seat", 5) {
+        requests {
+            "get_pointer"("n", WL_POINTER);
+            "get_keyboard"("n", WL_KEYBOARD);
+            "get_touch"("n", X);
+            "release"("5");
+        }
+        events {
+            "capabilities"("u", X);
+            "name"("2s", X);
+        }
+    }
+
+    WL_POINTER("wl_pointer", 5) {
+        requests {
+            "set_cursor"("u?oii", X, WL_SURFACE, X, X);
+            "release"("3");
+        }
+        events {
+            "enter"("uoff", X, WL_SURFACE, X, X);
+            "leave"("uo", X, WL_SURFACE);
+            "motion"("uff", X, X, X);
+            "button"("uuuu", X, X, X, X);
+            "axis"("uuf", X, X, X);
+            "frame"("5");
+            "axis_source"("5u", X);
+            "axis_stop"("5uu", X, X);
+            "axis_discrete"("5ui", X, X);
+        }
+    }
+
+    WL_KEYBOARD("wl_keyboard", 5) {
+        requests {
+            "release"("3");
+        }
+        events {
+            "keymap"("uhu", X, X, X);
+            "enter"("uoa", X, WL_SURFACE, X);
+            "leave"("uo", X, WL_SURFACE);
+            "key"("uuuu", X, X, X, X);
+            "modifiers"("uuuuu", X, X, X, X, X);
+            "repeat_info"("4ii", X, X);
+        }
+    }
+
+    WL_DATA_DEVICE_MANAGER("wl_data_device_manager", 3) {
+        requests {
+            "create_data_source"("n", WL_DATA_SOURCE);
+            "get_data_device"("no", WL_DATA_DEVICE, WL_SEAT);
+        }
+        events {}
+    }
+
+    WL_DATA_SOURCE("wl_data_source", 3) {
+        requests {
+            "offer"("s", X);
+            "destroy"("");
+            "set_actions"("3u", X);
+        }
+        events {
+            "target"("?s", X);
+            "send"("sh", X, X);
+            "cancelled"("");
+            "dnd_drop_performed"("3");
+            "dnd_finished"("3");
+            "action"("3u", X);
+        }
+    }
+
+    WL_DATA_DEVICE("wl_data_device", 3) {
+        requests {
+            "start_drag"("?oo?ou", WL_DATA_SOURCE, WL_SURFACE, WL_SURFACE, X);
+            "set_selection"("?ou", WL_DATA_SOURCE, X);
+            "release"("2");
+        }
+        events {
+            "data_offer"("n", WL_DATA_OFFER);
+            "enter"("uoff?o", X, WL_SURFACE, X, X, WL_DATA_OFFER);
+            "leave"("");
+            "motion"("uff", X, X, X);
+            "drop"("");
+            "selection"("?o", WL_DATA_OFFER);
+        }
+    }
+
+    WL_DATA_OFFER("wl_data_offer", 3) {
+        requests {
+            "accept"("u?s", X, X);
+            "receive"("sh", X, X);
+            "destroy"("");
+            "finish"("3");
+            "set_actions"("3uu", X, X);
+        }
+        events {
+            "offer"("s", X);
+            "source_actions"("3u", X);
+            "action"("3u", X);
+        }
+    }
+
+    WP_CURSOR_SHAPE_MANAGER_V1("wp_cursor_shape_manager_v1", 1) {
+        requests {
+            "destroy"("");
+            "get_pointer"("no", WP_CURSOR_SHAPE_DEVICE_V1, WL_POINTER);
+            "get_tablet_tool_v2"("no", WP_CURSOR_SHAPE_DEVICE_V1, X);
+        }
+        events {}
+    }
+
+    WP_CURSOR_SHAPE_DEVICE_V1("wp_cursor_shape_device_v1", 1) {
+        requests {
+            "destroy"("");
+            "set_shape"("uu", X, X);
+        }
+        events {}
+    }
+
+    ZWP_POINTER_CONSTRAINTS_V1("zwp_pointer_constraints_v1", 1) {
+        requests {
+            "destroy"("");
+            "lock_pointer"("noo?ou", ZWP_LOCKED_POINTER_V1, WL_SURFACE, WL_POINTER, WL_REGION, X);
+            "confine_pointer"("noo?ou", ZWP_CONFINED_POINTER_V1, WL_SURFACE, WL_POINTER, WL_REGION, X);
+        }
+        events {}
+    }
+
+    ZWP_LOCKED_POINTER_V1("zwp_locked_pointer_v1", 1) {
+        requests {
+            "destroy"("");
+            "set_cursor_position_hint"("ff", X, X);
+            "set_region"("?o", WL_REGION);
+        }
+        events {
+            "locked"("");
+            "unlocked"("");
+        }
+    }
+
+    ZWP_CONFINED_POINTER_V1("zwp_confined_pointer_v1", 1) {
+        requests {
+            "destroy"("");
+            "set_region"("?o", WL_REGION);
+        }
+        events {
+            "confined"("");
+            "unconfined"("");
+        }
+    }
+
+    ZWP_RELATIVE_POINTER_MANAGER_V1("zwp_relative_pointer_manager_v1", 1) {
+        requests {
+            "destroy"("");
+            "get_relative_pointer"("no", ZWP_RELATIVE_POINTER_V1, WL_POINTER);
+        }
+        events {}
+    }
+
+    ZWP_RELATIVE_POINTER_V1("zwp_relative_pointer_v1", 1) {
+        requests {
+            "destroy"("");
+        }
+        events {
+            "relative_motion"("uuffff", X, X, X, X, X, X);
+        }
+    }
+
+    ZWP_TEXT_INPUT_MANAGER_V3("zwp_text_input_manager_v3", 1) {
+        requests {
+            "destroy"("");
+            "get_text_input"("no", ZWP_TEXT_INPUT_V3, WL_SEAT);
+        }
+        events {}
+    }
+
+    ZWP_TEXT_INPUT_V3("zwp_text_input_v3", 1) {
+        requests {
+            "destroy"("");
+            "enable"("");
+            "disable"("");
+            "set_surrounding_text"("sii", X, X, X);
+            "set_text_change_cause"("u", X);
+            "set_content_type"("uu", X, X);
+            "set_cursor_rectangle"("iiii", X, X, X, X);
+            "commit"("");
+        }
+        events {
+            "enter"("o", WL_SURFACE);
+            "leave"("o", WL_SURFACE);
+            "preedit_string"("?sii", X, X, X);
+            "commit_string"("?s", X);
+            "delete_surrounding_text"("uu", X, X);
+            "done"("u", X);
+        }
+    }
+
+    ZXDG_OUTPUT_MANAGER_V1("zxdg_output_manager_v1", 3) {
+        requests {
+            "destroy"("");
+            "get_xdg_output"("no", ZXDG_OUTPUT_V1, WL_OUTPUT);
+        }
+        events {}
+    }
+
+    ZXDG_OUTPUT_V1("zxdg_output_v1", 3) {
+        requests {
+            "destroy"("");
+        }
+        events {
+            "logical_position"("ii", X, X);
+            "logical_size"("ii", X, X);
+            "done"("");
+            "name"("2s", X);
+            "description"("2s", X);
+        }
+    }
+
+    XDG_WM_BASE("xdg_wm_base", 1) {
+        requests {
+            "destroy"("");
+            "create_positioner"("n", XDG_POSITIONER);
+            "get_xdg_surface"("no", XDG_SURFACE, WL_SURFACE);
+            "pong"("u", X);
+        }
+        events {
+            "ping"("u", X);
+        }
+    }
+
+    XDG_POSITIONER("xdg_positioner", 1) {
+        requests {
+            "destroy"("");
+            "set_size"("ii", X, X);
+            "set_anchor_rect"("iiii", X, X, X, X);
+            "set_anchor"("u", X);
+            "set_gravity"("u", X);
+            "set_constraint_adjustment"("u", X);
+            "set_offset"("ii", X, X);
+        }
+        events {}
+    }
+
+    XDG_SURFACE("xdg_surface", 1) {
+        requests {
+            "destroy"("");
+            "get_toplevel"("n", XDG_TOPLEVEL);
+            "get_popup"("n?oo", XDG_POPUP, XDG_SURFACE, XDG_POSITIONER);
+            "set_window_geometry"("iiii", X, X, X, X);
+            "ack_configure"("u", X);
+        }
+        events {
+            "configure"("u", X);
+        }
+    }
+
+    XDG_TOPLEVEL("xdg_toplevel", 1) {
+        requests {
+            "destroy"("");
+            "set_parent"("?o", XDG_TOPLEVEL);
+            "set_title"("s", X);
+            "set_app_id"("s", X);
+            "show_window_menu"("ouii", X, X, X, X);
+            "move"("ou", X, X);
+            "resize"("ouu", X, X, X);
+            "set_max_size"("ii", X, X);
+            "set_min_size"("ii", X, X);
+            "set_maximized"("");
+            "unset_maximized"("");
+            "set_fullscreen"("?o", X);
+            "unset_fullscreen"("");
+            "set_minimized"("");
+        }
+        events {
+            "configure"("iia", X, X, X);
+            "close"("");
+        }
+    }
+
+    XDG_TOPLEVEL_ICON_MANAGER_V1("xdg_toplevel_icon_manager_v1", 1) {
+        requests {
+            "destroy"("");
+            "create_icon"("n", XDG_TOPLEVEL_ICON_V1);
+            "set_icon"("o?o", XDG_TOPLEVEL, XDG_TOPLEVEL_ICON_V1);
+        }
+        events {
+            "icon_size"("i", X);
+            "done"("");
+        }
+    }
+
+    XDG_TOPLEVEL_ICON_V1("xdg_toplevel_icon_v1", 1) {
+        requests {
+            "destroy"("");
+            "set_name"("s", X);
+            "add_buffer"("oi", WL_BUFFER, X);
+        }
+        events {}
+    }
+
+    XDG_POPUP("xdg_popup", 1) {
+        requests {
+            "destroy"("");
+            "grab"("ou", X, X);
+        }
+        events {
+            "configure"("iiii", X, X, X, X);
+            "popup_done"("");
+        }
+    }
+}
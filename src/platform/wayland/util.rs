@@ -0,0 +1,132 @@
+//! Utility functions for sending requests through `libwayland-client`.
+
+use crate::{
+    dpi::PhysicalSize,
+    platform::{
+        posix,
+        wayland::{ffi::*, protocol::*},
+    },
+};
+use std::{ptr, slice};
+
+/// Sends a request which doesn't create an object.
+#[inline]
+pub unsafe fn request(proxy: *mut wl_proxy, opcode: u32, args: &mut [wl_argument]) {
+    let version = wl_proxy_get_version(proxy);
+    let _ = wl_proxy_marshal_array_flags(proxy, opcode, ptr::null(), version, 0, args.as_mut_ptr());
+}
+
+/// Sends a request which creates an object of type `interface`.
+///
+/// The `new_id` argument(s) in `args` should be left as a placeholder `wl_argument { n: 0 }`.
+#[inline]
+pub unsafe fn request_new(
+    proxy: *mut wl_proxy,
+    opcode: u32,
+    interface: &'static wl_interface,
+    args: &mut [wl_argument],
+) -> *mut wl_proxy {
+    let version = wl_proxy_get_version(proxy);
+    wl_proxy_marshal_array_flags(proxy, opcode, interface, version, 0, args.as_mut_ptr())
+}
+
+/// Sends a destructor request, and destroys the proxy along with it.
+#[inline]
+pub unsafe fn request_destroy(proxy: *mut wl_proxy, opcode: u32) {
+    let version = wl_proxy_get_version(proxy);
+    let _ = wl_proxy_marshal_array_flags(
+        proxy,
+        opcode,
+        ptr::null(),
+        version,
+        WL_MARSHAL_FLAG_DESTROY,
+        ptr::null_mut(),
+    );
+}
+
+/// Binds a global advertised by `wl_registry.global`, at most at the version ramen describes.
+pub unsafe fn bind(
+    registry: *mut wl_proxy,
+    name: u32,
+    interface: &'static wl_interface,
+    version: u32,
+) -> *mut wl_proxy {
+    let version = version.min(interface.version as u32);
+    let mut args = [
+        wl_argument { u: name },
+        wl_argument { s: interface.name },
+        wl_argument { u: version },
+        wl_argument { n: 0 },
+    ];
+    wl_proxy_marshal_array_flags(registry, 0, interface, version, 0, args.as_mut_ptr())
+}
+
+/// Makes a `wl_buffer` holding premultiplied ARGB pixels, or null if the memory for it couldn't be made.
+pub unsafe fn create_buffer(
+    shm: *mut wl_proxy,
+    size: PhysicalSize,
+    pixels: impl Iterator<Item = u32>,
+) -> *mut wl_proxy {
+    const WL_SHM_FORMAT_ARGB8888: u32 = 0;
+
+    // The compositor maps the same memory, which is passed over as a file descriptor
+    let length = size.width as usize * size.height as usize * 4;
+    let fd = posix::memfd_create(b"ramen-cursor\0".as_ptr().cast(), posix::MFD_CLOEXEC);
+    if fd < 0 {
+        return ptr::null_mut()
+    }
+    if posix::ftruncate(fd, length as posix::c_long) != 0 {
+        let _ = posix::close(fd);
+        return ptr::null_mut()
+    }
+    let map = posix::mmap(
+        ptr::null_mut(),
+        length,
+        posix::PROT_READ | posix::PROT_WRITE,
+        posix::MAP_SHARED,
+        fd,
+        0,
+    );
+    if map == posix::MAP_FAILED {
+        let _ = posix::close(fd);
+        return ptr::null_mut()
+    }
+    // `wl_shm` formats are little-endian
+    let memory = slice::from_raw_parts_mut(map.cast::<u32>(), length / 4);
+    for (dest, pixel) in memory.iter_mut().zip(pixels) {
+        *dest = pixel.to_le();
+    }
+    let _ = posix::munmap(map, length);
+
+    // The pool can go as soon as the buffer's made, the buffer keeps the memory alive
+    let pool = request_new(
+        shm,
+        0,
+        &WL_SHM_POOL,
+        &mut [
+            wl_argument { n: 0 },
+            wl_argument { h: fd },
+            wl_argument { i: length as i32 },
+        ],
+    );
+    let buffer = request_new(
+        pool,
+        0,
+        &WL_BUFFER,
+        &mut [
+            wl_argument { n: 0 },
+            wl_argument { i: 0 },
+            wl_argument { i: size.width as i32 },
+            wl_argument { i: size.height as i32 },
+            wl_argument {
+                i: size.width as i32 * 4,
+            },
+            wl_argument {
+                u: WL_SHM_FORMAT_ARGB8888,
+            },
+        ],
+    );
+    request_destroy(pool, 1);
+    let _ = posix::close(fd);
+    buffer
+}
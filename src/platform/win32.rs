@@ -1,7 +1,10 @@
 //! Win32-specific definitions and API extensions.
 
+mod clipboard;
+mod dnd;
 mod ffi;
 mod imp;
+mod keyboard;
 mod util;
 
 // public re-exports
@@ -0,0 +1,183 @@
+//! The clipboard, which any thread can use, as long as it's opened first and closed soon after.
+//!
+//! Formats are numbered, with constants for the standard ones and the rest registered by name, which is what
+//! MIME types are used as. Text and BMP images are standard formats, and PNG images go by the name `PNG`.
+
+use crate::{
+    clipboard::{bmp, TEXT_MIME},
+    error::{Error, ErrorKind},
+    platform::win32::{ffi::*, util},
+};
+use std::{iter, ptr, slice};
+
+/// The clipboard being open, which it is until this is dropped.
+struct Open;
+
+impl Open {
+    unsafe fn new(hwnd: HWND) -> Result<Self, Error> {
+        // Other apps only have it open for a moment, so it's worth trying again a few times
+        for _ in 0..10 {
+            if OpenClipboard(hwnd) != FALSE {
+                return Ok(Self)
+            }
+            Sleep(10);
+        }
+        Err(util::last_error(ErrorKind::Other, "failed to open the clipboard"))
+    }
+}
+
+impl Drop for Open {
+    fn drop(&mut self) {
+        let _ = unsafe { CloseClipboard() };
+    }
+}
+
+/// The MIME types of the formats on the clipboard, in the order they were put there.
+pub unsafe fn formats(hwnd: HWND) -> Result<Vec<String>, Error> {
+    let _open = Open::new(hwnd)?;
+    let mut formats: Vec<String> = Vec::new();
+    let mut format = 0;
+    loop {
+        format = EnumClipboardFormats(format);
+        if format == 0 {
+            break
+        }
+        if let Some(mime) = mime_type(format) {
+            if !formats.contains(&mime) {
+                formats.push(mime);
+            }
+        }
+    }
+    Ok(formats)
+}
+
+/// Gets the clipboard's contents as a MIME type, if it's on there.
+pub unsafe fn get(hwnd: HWND, mime: &str) -> Result<Option<Vec<u8>>, Error> {
+    let _open = Open::new(hwnd)?;
+    Ok(match mime {
+        TEXT_MIME => read(CF_UNICODETEXT).map(|data| text_from_utf16(&data).into_bytes()),
+        // Both of these are a BMP file without the header, and whichever was put there, the other's made from it
+        "image/bmp" => read(CF_DIBV5)
+            .or_else(|| read(CF_DIB))
+            .and_then(|dib| bmp::file_from_dib(&dib)),
+        _ => match registered_format(mime) {
+            0 => None,
+            format => read(format),
+        },
+    })
+}
+
+/// Puts data on the clipboard as each of the MIME types given, or empties it if it came from this window.
+pub unsafe fn set(hwnd: HWND, formats: Vec<(String, Vec<u8>)>) -> Result<(), Error> {
+    let _open = Open::new(hwnd)?;
+    if formats.is_empty() {
+        if GetClipboardOwner() == hwnd && EmptyClipboard() == FALSE {
+            return Err(util::last_error(ErrorKind::Other, "failed to empty the clipboard"))
+        }
+        return Ok(())
+    }
+    if EmptyClipboard() == FALSE {
+        return Err(util::last_error(ErrorKind::Other, "failed to empty the clipboard"))
+    }
+    for (mime, data) in formats {
+        let (format, data) = match mime.as_str() {
+            TEXT_MIME => {
+                let text = String::from_utf8_lossy(&data);
+                let units = text.encode_utf16().chain(iter::once(0));
+                (CF_UNICODETEXT, units.flat_map(WCHAR::to_le_bytes).collect())
+            },
+            "image/bmp" => match bmp::dib_from_file(&data) {
+                // Only the newer header has room for alpha, apps that know to look for it use `CF_DIBV5`
+                Some(dib) if dib.get(..4) == Some(&124u32.to_le_bytes()) => (CF_DIBV5, dib.to_vec()),
+                Some(dib) => (CF_DIB, dib.to_vec()),
+                None => return Err(Error::new(ErrorKind::InvalidInput, "image/bmp data isn't a BMP file")),
+            },
+            _ => match registered_format(&mime) {
+                0 => {
+                    return Err(util::last_error(
+                        ErrorKind::Other,
+                        "failed to register a clipboard format",
+                    ))
+                },
+                format => (format, data),
+            },
+        };
+
+        // The clipboard takes the memory, unless setting it fails
+        let memory = GlobalAlloc(GMEM_MOVEABLE, data.len().max(1));
+        if memory.is_null() {
+            return Err(util::last_error(
+                ErrorKind::OutOfMemory,
+                "failed to allocate clipboard memory",
+            ))
+        }
+        let pointer = GlobalLock(memory);
+        if !pointer.is_null() {
+            ptr::copy_nonoverlapping(data.as_ptr(), pointer.cast(), data.len());
+            let _ = GlobalUnlock(memory);
+        }
+        if pointer.is_null() || SetClipboardData(format, memory).is_null() {
+            let error = util::last_error(ErrorKind::Other, "failed to put data on the clipboard");
+            let _ = GlobalFree(memory);
+            return Err(error)
+        }
+    }
+    Ok(())
+}
+
+/// Copies a format off the clipboard, if it's there.
+unsafe fn read(format: UINT) -> Option<Vec<u8>> {
+    if IsClipboardFormatAvailable(format) == FALSE {
+        return None
+    }
+    global_bytes(GetClipboardData(format))
+}
+
+/// Copies the contents of global memory, which is what the clipboard and OLE hand data over in.
+pub unsafe fn global_bytes(memory: HGLOBAL) -> Option<Vec<u8>> {
+    if memory.is_null() {
+        return None
+    }
+    let pointer = GlobalLock(memory);
+    if pointer.is_null() {
+        return None
+    }
+    let data = slice::from_raw_parts(pointer.cast::<u8>(), GlobalSize(memory)).to_vec();
+    let _ = GlobalUnlock(memory);
+    Some(data)
+}
+
+/// Decodes `CF_UNICODETEXT`, which is null-terminated UTF-16 in memory that's often bigger than the text.
+pub fn text_from_utf16(data: &[u8]) -> String {
+    let units: Vec<WCHAR> = data
+        .chunks_exact(2)
+        .map(|x| WCHAR::from_le_bytes([x[0], x[1]]))
+        .collect();
+    let length = units.iter().position(|&x| x == 0).unwrap_or(units.len());
+    String::from_utf16_lossy(&units[..length])
+}
+
+/// The format a MIME type that isn't a standard format goes by, or 0 if it couldn't be registered.
+unsafe fn registered_format(mime: &str) -> UINT {
+    let name = if mime == "image/png" { "PNG" } else { mime };
+    let mut buffer = Vec::new();
+    RegisterClipboardFormatW(util::str_to_wstr(name, &mut buffer))
+}
+
+/// The MIME type a format is listed as, if it has one.
+pub unsafe fn mime_type(format: UINT) -> Option<String> {
+    match format {
+        CF_UNICODETEXT => Some(TEXT_MIME.to_owned()),
+        CF_DIB | CF_DIBV5 => Some("image/bmp".to_owned()),
+        _ => {
+            let mut buffer = [0 as WCHAR; 256];
+            let length = GetClipboardFormatNameW(format, buffer.as_mut_ptr(), buffer.len() as c_int);
+            let name = String::from_utf16_lossy(&buffer[..length.max(0) as usize]);
+            match name.as_str() {
+                "PNG" => Some("image/png".to_owned()),
+                _ if name.contains('/') => Some(name),
+                _ => None,
+            }
+        },
+    }
+}
@@ -0,0 +1,282 @@
+//! Drag and drop, which Win32 does through OLE.
+//!
+//! Windows that take drops register an `IDropTarget`, which OLE calls into on the window thread as something's
+//! dragged over it, and which answers straight away with what would be done if it was dropped there. What's being
+//! dragged is an `IDataObject`, which has it in each of the clipboard formats it's offered as.
+
+use crate::{
+    clipboard::bmp,
+    dpi::PhysicalPosition,
+    drag::{DragData, DragFormat, DropAction, URI_LIST_MIME},
+    event::{Event, EventQueue},
+    platform::win32::{clipboard, ffi::*},
+    sync::{mutex_lock, Mutex},
+};
+use std::{
+    cell::{Cell, RefCell},
+    ffi::OsString,
+    mem,
+    os::windows::ffi::OsStringExt,
+    path::PathBuf,
+    ptr,
+    sync::Arc,
+};
+
+/// The drop target of one window, which OLE owns once it's registered.
+#[repr(C)]
+struct DropTarget {
+    // This has to come first, as it's what makes it an `IDropTarget`
+    vtbl: *const IDropTargetVtbl,
+    refs: Cell<ULONG>,
+
+    hwnd: HWND,
+    events: Arc<EventQueue>,
+    accepted: Arc<Mutex<Option<(DragFormat, DropAction)>>>,
+
+    // What's being dragged over the window, as the names of the formats it's offered in and their numbers
+    names: RefCell<Vec<String>>,
+    formats: RefCell<Vec<UINT>>,
+    position: Cell<Option<PhysicalPosition>>,
+}
+
+static DROP_TARGET_VTBL: IDropTargetVtbl = IDropTargetVtbl {
+    QueryInterface: query_interface,
+    AddRef: add_ref,
+    Release: release,
+    DragEnter: drag_enter,
+    DragOver: drag_over,
+    DragLeave: drag_leave,
+    Drop: drag_drop,
+};
+
+/// Has a window take drops, for which OLE has to be initialised on the window thread.
+///
+/// What's dropped goes to `events`, if it's accepted in `accepted` at the time. `RevokeDragDrop` undoes this.
+pub unsafe fn register(hwnd: HWND, events: Arc<EventQueue>, accepted: Arc<Mutex<Option<(DragFormat, DropAction)>>>) {
+    let target = Box::into_raw(Box::new(DropTarget {
+        vtbl: &DROP_TARGET_VTBL,
+        refs: Cell::new(1),
+        hwnd,
+        events,
+        accepted,
+        names: RefCell::new(Vec::new()),
+        formats: RefCell::new(Vec::new()),
+        position: Cell::new(None),
+    }));
+    let _ = RegisterDragDrop(hwnd, target.cast());
+    // OLE holds on to it if it was registered, and otherwise this frees it
+    let _ = release(target.cast());
+}
+
+impl DropTarget {
+    /// How what's being dragged is accepted, if it's offered in the format and `allowed` has the action in it.
+    ///
+    /// That's the format, the number it's read as, and the effect to tell OLE.
+    fn accepted(&self, allowed: DWORD) -> Option<(DragFormat, UINT, DWORD)> {
+        let (format, action) = mutex_lock(&self.accepted).clone()?;
+        let names = self.names.borrow();
+        let name = format.pick_name(&names)?;
+        let index = names.iter().position(|x| x == name)?;
+        let effect = match action {
+            DropAction::Copy => DROPEFFECT_COPY,
+            DropAction::Move => DROPEFFECT_MOVE,
+        };
+        (allowed & effect != 0).then(|| (format, self.formats.borrow()[index], effect))
+    }
+
+    /// Sends where it's been dragged to, in client coordinates, if that's changed.
+    unsafe fn moved(&self, point: POINTL) {
+        let mut point = POINT { x: point.x, y: point.y };
+        let _ = ScreenToClient(self.hwnd, &mut point);
+        let position = PhysicalPosition::new(point.x, point.y);
+        if self.position.get() != Some(position) {
+            self.position.set(Some(position));
+            self.events.push(Event::DragMoved(position));
+        }
+    }
+
+    fn clear(&self) {
+        self.names.borrow_mut().clear();
+        self.formats.borrow_mut().clear();
+        self.position.set(None);
+    }
+}
+
+unsafe extern "system" fn query_interface(
+    this: *mut IDropTarget,
+    riid: *const GUID,
+    object: *mut *mut c_void,
+) -> HRESULT {
+    if *riid == IID_IUnknown || *riid == IID_IDropTarget {
+        *object = this.cast();
+        let _ = add_ref(this);
+        S_OK
+    } else {
+        *object = ptr::null_mut();
+        E_NOINTERFACE
+    }
+}
+
+// OLE only calls these on the window thread, so the count needn't be atomic
+
+unsafe extern "system" fn add_ref(this: *mut IDropTarget) -> ULONG {
+    let target = &*this.cast::<DropTarget>();
+    target.refs.set(target.refs.get() + 1);
+    target.refs.get()
+}
+
+unsafe extern "system" fn release(this: *mut IDropTarget) -> ULONG {
+    let refs = {
+        let target = &*this.cast::<DropTarget>();
+        target.refs.set(target.refs.get() - 1);
+        target.refs.get()
+    };
+    if refs == 0 {
+        mem::drop(Box::from_raw(this.cast::<DropTarget>()));
+    }
+    refs
+}
+
+unsafe extern "system" fn drag_enter(
+    this: *mut IDropTarget,
+    data: *mut IDataObject,
+    _key_state: DWORD,
+    point: POINTL,
+    effect: *mut DWORD,
+) -> HRESULT {
+    let target = &*this.cast::<DropTarget>();
+    let (names, formats) = data_formats(data);
+    *mutex_lock(&target.accepted) = None;
+    target.events.push(Event::DragEntered(DragFormat::from_names(&names)));
+    *target.names.borrow_mut() = names;
+    *target.formats.borrow_mut() = formats;
+    target.position.set(None);
+    target.moved(point);
+    *effect = target.accepted(*effect).map_or(DROPEFFECT_NONE, |(_, _, x)| x);
+    S_OK
+}
+
+unsafe extern "system" fn drag_over(
+    this: *mut IDropTarget,
+    _key_state: DWORD,
+    point: POINTL,
+    effect: *mut DWORD,
+) -> HRESULT {
+    let target = &*this.cast::<DropTarget>();
+    target.moved(point);
+    *effect = target.accepted(*effect).map_or(DROPEFFECT_NONE, |(_, _, x)| x);
+    S_OK
+}
+
+unsafe extern "system" fn drag_leave(this: *mut IDropTarget) -> HRESULT {
+    let target = &*this.cast::<DropTarget>();
+    target.clear();
+    target.events.push(Event::DragLeft);
+    S_OK
+}
+
+unsafe extern "system" fn drag_drop(
+    this: *mut IDropTarget,
+    data: *mut IDataObject,
+    _key_state: DWORD,
+    point: POINTL,
+    effect: *mut DWORD,
+) -> HRESULT {
+    let target = &*this.cast::<DropTarget>();
+    target.moved(point);
+    let dropped = target
+        .accepted(*effect)
+        .and_then(|(format, number, effect)| Some((read_data(data, format, number)?, effect)));
+    target.clear();
+    match dropped {
+        Some((data, dropped_effect)) => {
+            target.events.push(Event::Dropped(data));
+            *effect = dropped_effect;
+        },
+        None => {
+            target.events.push(Event::DragLeft);
+            *effect = DROPEFFECT_NONE;
+        },
+    }
+    S_OK
+}
+
+/// The names of the formats something's offered in, and their numbers, leaving out ones without a name.
+///
+/// Files are offered as `CF_HDROP`, which goes by the name they're dragged as on Linux.
+unsafe fn data_formats(data: *mut IDataObject) -> (Vec<String>, Vec<UINT>) {
+    let (mut names, mut formats) = (Vec::new(), Vec::new());
+    let mut enumerator: *mut IEnumFORMATETC = ptr::null_mut();
+    if ((*(*data).lpVtbl).EnumFormatEtc)(data, DATADIR_GET, &mut enumerator) != S_OK || enumerator.is_null() {
+        return (names, formats)
+    }
+    let mut format_etc: FORMATETC = mem::zeroed();
+    while ((*(*enumerator).lpVtbl).Next)(enumerator, 1, &mut format_etc, ptr::null_mut()) == S_OK {
+        if !format_etc.ptd.is_null() {
+            CoTaskMemFree(format_etc.ptd);
+        }
+        if format_etc.tymed & TYMED_HGLOBAL == 0 {
+            continue
+        }
+        let format = UINT::from(format_etc.cfFormat);
+        let name = match format {
+            CF_HDROP => Some(URI_LIST_MIME.to_owned()),
+            _ => clipboard::mime_type(format),
+        };
+        if let Some(name) = name {
+            names.push(name);
+            formats.push(format);
+        }
+    }
+    let _ = ((*(*enumerator).lpVtbl).Release)(enumerator);
+    (names, formats)
+}
+
+/// Reads what was dropped as a format, which is offered as its clipboard format `number`.
+unsafe fn read_data(data: *mut IDataObject, format: DragFormat, number: UINT) -> Option<DragData> {
+    let format_etc = FORMATETC {
+        cfFormat: number as CLIPFORMAT,
+        ptd: ptr::null_mut(),
+        dwAspect: DVASPECT_CONTENT,
+        lindex: -1,
+        tymed: TYMED_HGLOBAL,
+    };
+    let mut medium: STGMEDIUM = mem::zeroed();
+    if ((*(*data).lpVtbl).GetData)(data, &format_etc, &mut medium) != S_OK {
+        return None
+    }
+    let dropped = if medium.tymed == TYMED_HGLOBAL {
+        match format {
+            DragFormat::Files => Some(DragData::Files(paths_from_hdrop(medium.hGlobal))),
+            DragFormat::Text => {
+                clipboard::global_bytes(medium.hGlobal).map(|x| DragData::Text(clipboard::text_from_utf16(&x)))
+            },
+            DragFormat::Custom(mime) => {
+                let bytes = clipboard::global_bytes(medium.hGlobal);
+                // Bitmaps come without the file header, the same as on the clipboard
+                let bytes = match number {
+                    CF_DIB | CF_DIBV5 => bytes.and_then(|x| bmp::file_from_dib(&x)),
+                    _ => bytes,
+                };
+                bytes.map(|x| DragData::Custom(mime, x))
+            },
+        }
+    } else {
+        None
+    };
+    ReleaseStgMedium(&mut medium);
+    dropped
+}
+
+unsafe fn paths_from_hdrop(hdrop: HDROP) -> Vec<PathBuf> {
+    // Asking for the file at index `0xFFFFFFFF` gives how many there are
+    let count = DragQueryFileW(hdrop, 0xFFFFFFFF, ptr::null_mut(), 0);
+    (0..count)
+        .map(|i| {
+            let length = DragQueryFileW(hdrop, i, ptr::null_mut(), 0);
+            let mut buffer = vec![0 as WCHAR; length as usize + 1];
+            let length = DragQueryFileW(hdrop, i, buffer.as_mut_ptr(), buffer.len() as UINT);
+            PathBuf::from(OsString::from_wide(&buffer[..length as usize]))
+        })
+        .collect()
+}
@@ -21,6 +21,7 @@ pub type wchar_t = u16;
 pub type BOOL = c_int;
 pub type BYTE = c_uchar;
 pub type CHAR = c_char;
+pub type DPI_AWARENESS_CONTEXT = isize;
 pub type DWORD = c_ulong;
 pub type INT = c_int;
 pub type LONG = c_long;
@@ -28,6 +29,7 @@ pub type LONG_PTR = isize;
 pub type SHORT = c_short;
 pub type UINT = c_uint;
 pub type UINT_PTR = usize;
+pub type ULONG = c_ulong;
 pub type ULONG_PTR = usize;
 pub type USHORT = c_ushort;
 pub type WCHAR = wchar_t;
@@ -35,18 +37,33 @@ pub type WORD = c_ushort;
 
 /* opaque types */
 
+pub type HBITMAP = *mut HBITMAP__;
+pub enum HBITMAP__ {}
 pub type HBRUSH = *mut HBRUSH__;
 pub enum HBRUSH__ {}
 pub type HCURSOR = HICON;
+pub type HDC = *mut HDC__;
+pub type HDROP = HANDLE;
+pub enum HDC__ {}
+pub type HGDIOBJ = *mut c_void;
 pub type HHOOK = *mut HHOOK__;
 pub enum HHOOK__ {}
 pub type HICON = *mut HICON__;
 pub enum HICON__ {}
+/// Opaque handle to an input method context.
+pub type HIMC = *mut HIMC__;
+pub enum HIMC__ {}
 /// Opaque handle to a module in memory.
 pub type HINSTANCE = *mut HINSTANCE__;
 pub enum HINSTANCE__ {}
 pub type HMENU = *mut HMENU__;
 pub enum HMENU__ {}
+pub type HMONITOR = *mut HMONITOR__;
+pub enum HMONITOR__ {}
+pub type HANDLE = *mut c_void;
+pub type HGLOBAL = HANDLE;
+pub type HRAWINPUT = *mut HRAWINPUT__;
+pub enum HRAWINPUT__ {}
 /// Opaque handle to a window.
 pub type HWND = *mut HWND__;
 pub enum HWND__ {}
@@ -54,9 +71,12 @@ pub enum HWND__ {}
 /* other winapi types */
 
 pub type ATOM = WORD;
+pub type CLIPFORMAT = WORD;
 pub type HOOKPROC = unsafe extern "system" fn(c_int, WPARAM, LPARAM) -> LRESULT;
+pub type HRESULT = c_long;
 pub type LPARAM = LONG_PTR;
 pub type LRESULT = LONG_PTR;
+pub type MONITORENUMPROC = unsafe extern "system" fn(HMONITOR, HDC, *mut RECT, LPARAM) -> BOOL;
 pub type WPARAM = UINT_PTR;
 
 /// A user-defined application window callback function.
@@ -64,6 +84,110 @@ pub type WNDPROC = unsafe extern "system" fn(HWND, UINT, WPARAM, LPARAM) -> LRES
 
 /* structs */
 
+#[repr(C)]
+pub struct BITMAPINFO {
+    pub bmiHeader: BITMAPINFOHEADER,
+    pub bmiColors: [RGBQUAD; 1],
+}
+
+#[repr(C)]
+pub struct BITMAPINFOHEADER {
+    pub biSize: DWORD,
+    pub biWidth: LONG,
+    pub biHeight: LONG,
+    pub biPlanes: WORD,
+    pub biBitCount: WORD,
+    pub biCompression: DWORD,
+    pub biSizeImage: DWORD,
+    pub biXPelsPerMeter: LONG,
+    pub biYPelsPerMeter: LONG,
+    pub biClrUsed: DWORD,
+    pub biClrImportant: DWORD,
+}
+
+#[repr(C)]
+pub struct CANDIDATEFORM {
+    pub dwIndex: DWORD,
+    pub dwStyle: DWORD,
+    pub ptCurrentPos: POINT,
+    pub rcArea: RECT,
+}
+
+#[repr(C)]
+pub struct COMPOSITIONFORM {
+    pub dwStyle: DWORD,
+    pub ptCurrentPos: POINT,
+    pub rcArea: RECT,
+}
+
+#[repr(C)]
+pub struct CREATESTRUCTW {
+    pub lpCreateParams: *mut c_void,
+    pub hInstance: HINSTANCE,
+    pub hMenu: HMENU,
+    pub hwndParent: HWND,
+    pub cy: c_int,
+    pub cx: c_int,
+    pub y: c_int,
+    pub x: c_int,
+    pub style: LONG,
+    pub lpszName: *const WCHAR,
+    pub lpszClass: *const WCHAR,
+    pub dwExStyle: DWORD,
+}
+
+/// The display device variant, as the union in the middle is only used for printers otherwise.
+#[repr(C)]
+pub struct DEVMODEW {
+    pub dmDeviceName: [WCHAR; 32],
+    pub dmSpecVersion: WORD,
+    pub dmDriverVersion: WORD,
+    pub dmSize: WORD,
+    pub dmDriverExtra: WORD,
+    pub dmFields: DWORD,
+    pub dmPosition: POINT,
+    pub dmDisplayOrientation: DWORD,
+    pub dmDisplayFixedOutput: DWORD,
+    pub dmColor: c_short,
+    pub dmDuplex: c_short,
+    pub dmYResolution: c_short,
+    pub dmTTOption: c_short,
+    pub dmCollate: c_short,
+    pub dmFormName: [WCHAR; 32],
+    pub dmLogPixels: WORD,
+    pub dmBitsPerPel: DWORD,
+    pub dmPelsWidth: DWORD,
+    pub dmPelsHeight: DWORD,
+    pub dmDisplayFlags: DWORD,
+    pub dmDisplayFrequency: DWORD,
+    pub dmICMMethod: DWORD,
+    pub dmICMIntent: DWORD,
+    pub dmMediaType: DWORD,
+    pub dmDitherType: DWORD,
+    pub dmReserved1: DWORD,
+    pub dmReserved2: DWORD,
+    pub dmPanningWidth: DWORD,
+    pub dmPanningHeight: DWORD,
+}
+
+#[repr(C)]
+pub struct FORMATETC {
+    pub cfFormat: CLIPFORMAT,
+    pub ptd: *mut c_void,
+    pub dwAspect: DWORD,
+    pub lindex: LONG,
+    pub tymed: DWORD,
+}
+
+#[repr(C)]
+#[derive(PartialEq, Eq)]
+pub struct GUID {
+    pub Data1: c_ulong,
+    pub Data2: c_ushort,
+    pub Data3: c_ushort,
+    pub Data4: [c_uchar; 8],
+}
+
 #[repr(C)]
 pub struct IMAGE_DOS_HEADER {
     pub e_magic: u16,
@@ -87,6 +211,15 @@ pub struct IMAGE_DOS_HEADER {
     pub e_lfanew: i32,
 }
 
+#[repr(C)]
+pub struct MONITORINFOEXW {
+    pub cbSize: DWORD,
+    pub rcMonitor: RECT,
+    pub rcWork: RECT,
+    pub dwFlags: DWORD,
+    pub szDevice: [WCHAR; 32],
+}
+
 #[repr(C)]
 pub struct MSG {
     pub hwnd: HWND,
@@ -98,12 +231,72 @@ pub struct MSG {
 }
 
 #[repr(C)]
+#[derive(Clone, Copy)]
 pub struct POINT {
     pub x: LONG,
     pub y: LONG,
 }
 
 #[repr(C)]
+#[derive(Clone, Copy)]
+pub struct POINTL {
+    pub x: LONG,
+    pub y: LONG,
+}
+
+#[repr(C)]
+pub struct ICONINFO {
+    pub fIcon: BOOL,
+    pub xHotspot: DWORD,
+    pub yHotspot: DWORD,
+    pub hbmMask: HBITMAP,
+    pub hbmColor: HBITMAP,
+}
+
+#[repr(C)]
+pub struct RAWINPUTDEVICE {
+    pub usUsagePage: USHORT,
+    pub usUsage: USHORT,
+    pub dwFlags: DWORD,
+    pub hwndTarget: HWND,
+}
+
+#[repr(C)]
+pub struct RAWINPUTHEADER {
+    pub dwType: DWORD,
+    pub dwSize: DWORD,
+    pub hDevice: HANDLE,
+    pub wParam: WPARAM,
+}
+
+/// Only the mouse is described, as the other members of the union are never read (and are smaller).
+#[repr(C)]
+pub struct RAWINPUT {
+    pub header: RAWINPUTHEADER,
+    pub mouse: RAWMOUSE,
+}
+
+/// `ulButtons` is in a union with `usButtonFlags` & `usButtonData`, which aren't needed.
+#[repr(C)]
+pub struct RAWMOUSE {
+    pub usFlags: USHORT,
+    pub ulButtons: ULONG,
+    pub ulRawButtons: ULONG,
+    pub lLastX: LONG,
+    pub lLastY: LONG,
+    pub ulExtraInformation: ULONG,
+}
+
+#[repr(C)]
+pub struct RGBQUAD {
+    pub rgbBlue: BYTE,
+    pub rgbGreen: BYTE,
+    pub rgbRed: BYTE,
+    pub rgbReserved: BYTE,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
 pub struct RECT {
     pub left: LONG,
     pub top: LONG,
@@ -111,6 +304,22 @@ pub struct RECT {
     pub bottom: LONG,
 }
 
+/// `hGlobal` is in a union with the other kinds of storage, which are never asked for.
+#[repr(C)]
+pub struct STGMEDIUM {
+    pub tymed: DWORD,
+    pub hGlobal: HGLOBAL,
+    pub pUnkForRelease: *mut c_void,
+}
+
+#[repr(C)]
+pub struct TRACKMOUSEEVENT {
+    pub cbSize: DWORD,
+    pub dwFlags: DWORD,
+    pub hwndTrack: HWND,
+    pub dwHoverTime: DWORD,
+}
+
 #[repr(C)]
 pub struct WNDCLASSEXW {
     pub cbSize: UINT,
@@ -127,15 +336,212 @@ pub struct WNDCLASSEXW {
     pub hIconSm: HICON,
 }
 
+/* COM interfaces */
+
+// Methods that are never called are left as pointers, and only their place in the vtable matters
+
+#[repr(C)]
+pub struct IDataObject {
+    pub lpVtbl: *const IDataObjectVtbl,
+}
+
+#[repr(C)]
+pub struct IDataObjectVtbl {
+    pub QueryInterface: *const c_void,
+    pub AddRef: *const c_void,
+    pub Release: *const c_void,
+    pub GetData: unsafe extern "system" fn(*mut IDataObject, *const FORMATETC, *mut STGMEDIUM) -> HRESULT,
+    pub GetDataHere: *const c_void,
+    pub QueryGetData: *const c_void,
+    pub GetCanonicalFormatEtc: *const c_void,
+    pub SetData: *const c_void,
+    pub EnumFormatEtc: unsafe extern "system" fn(*mut IDataObject, DWORD, *mut *mut IEnumFORMATETC) -> HRESULT,
+    pub DAdvise: *const c_void,
+    pub DUnadvise: *const c_void,
+    pub EnumDAdvise: *const c_void,
+}
+
+#[repr(C)]
+pub struct IDropTarget {
+    pub lpVtbl: *const IDropTargetVtbl,
+}
+
+#[repr(C)]
+pub struct IDropTargetVtbl {
+    pub QueryInterface: unsafe extern "system" fn(*mut IDropTarget, *const GUID, *mut *mut c_void) -> HRESULT,
+    pub AddRef: unsafe extern "system" fn(*mut IDropTarget) -> ULONG,
+    pub Release: unsafe extern "system" fn(*mut IDropTarget) -> ULONG,
+    pub DragEnter: unsafe extern "system" fn(*mut IDropTarget, *mut IDataObject, DWORD, POINTL, *mut DWORD) -> HRESULT,
+    pub DragOver: unsafe extern "system" fn(*mut IDropTarget, DWORD, POINTL, *mut DWORD) -> HRESULT,
+    pub DragLeave: unsafe extern "system" fn(*mut IDropTarget) -> HRESULT,
+    pub Drop: unsafe extern "system" fn(*mut IDropTarget, *mut IDataObject, DWORD, POINTL, *mut DWORD) -> HRESULT,
+}
+
+#[repr(C)]
+pub struct IEnumFORMATETC {
+    pub lpVtbl: *const IEnumFORMATETCVtbl,
+}
+
+#[repr(C)]
+pub struct IEnumFORMATETCVtbl {
+    pub QueryInterface: *const c_void,
+    pub AddRef: *const c_void,
+    pub Release: unsafe extern "system" fn(*mut IEnumFORMATETC) -> ULONG,
+    pub Next: unsafe extern "system" fn(*mut IEnumFORMATETC, ULONG, *mut FORMATETC, *mut ULONG) -> HRESULT,
+    pub Skip: *const c_void,
+    pub Reset: *const c_void,
+    pub Clone: *const c_void,
+}
+
+pub const IID_IUnknown: GUID = GUID {
+    Data1: 0x00000000,
+    Data2: 0x0000,
+    Data3: 0x0000,
+    Data4: [0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46],
+};
+pub const IID_IDropTarget: GUID = GUID {
+    Data1: 0x00000122,
+    Data2: 0x0000,
+    Data3: 0x0000,
+    Data4: [0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46],
+};
+
 /* constants */
 
+pub const BI_RGB: DWORD = 0;
+pub const CDS_FULLSCREEN: DWORD = 0x00000004;
+pub const CF_DIB: UINT = 8;
+pub const CF_UNICODETEXT: UINT = 13;
+pub const CF_HDROP: UINT = 15;
+pub const CF_DIBV5: UINT = 17;
+pub const CFS_POINT: DWORD = 0x0002;
+pub const CFS_EXCLUDE: DWORD = 0x0080;
 pub const CP_UTF8: DWORD = 65001;
 pub const CS_OWNDC: UINT = 0x0020;
+pub const CW_USEDEFAULT: c_int = 0x80000000_u32 as c_int;
+pub const DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE: DPI_AWARENESS_CONTEXT = -3;
+pub const DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2: DPI_AWARENESS_CONTEXT = -4;
+pub const DATADIR_GET: DWORD = 1;
+pub const DIB_RGB_COLORS: UINT = 0;
+pub const DISP_CHANGE_SUCCESSFUL: LONG = 0;
+pub const DM_BITSPERPEL: DWORD = 0x00040000;
+pub const DM_PELSWIDTH: DWORD = 0x00080000;
+pub const DM_PELSHEIGHT: DWORD = 0x00100000;
+pub const DM_DISPLAYFREQUENCY: DWORD = 0x00400000;
+pub const DROPEFFECT_NONE: DWORD = 0;
+pub const DROPEFFECT_COPY: DWORD = 1;
+pub const DROPEFFECT_MOVE: DWORD = 2;
+pub const DVASPECT_CONTENT: DWORD = 1;
+pub const E_NOINTERFACE: HRESULT = 0x80004002_u32 as HRESULT;
+pub const ENUM_CURRENT_SETTINGS: DWORD = 0xFFFFFFFF;
 pub const ERROR_SUCCESS: DWORD = 0; // lol
+pub const ERROR_NOT_ENOUGH_MEMORY: DWORD = 8;
+pub const ERROR_OUTOFMEMORY: DWORD = 14;
 pub const FALSE: BOOL = 0;
+pub const FORMAT_MESSAGE_FROM_SYSTEM: DWORD = 0x00001000;
+pub const FORMAT_MESSAGE_IGNORE_INSERTS: DWORD = 0x00000200;
 pub const GCL_CBCLSEXTRA: c_int = -20;
+pub const GMEM_MOVEABLE: UINT = 0x0002;
+pub const GCS_COMPSTR: DWORD = 0x0008;
+pub const GCS_CURSORPOS: DWORD = 0x0080;
+pub const GCS_RESULTSTR: DWORD = 0x0800;
+pub const GWL_EXSTYLE: c_int = -20;
+pub const GWL_STYLE: c_int = -16;
 pub const HCBT_DESTROYWND: c_int = 4;
+pub const HORZSIZE: c_int = 4;
+pub const HTCLIENT: LRESULT = 1;
+pub const ICON_SMALL: WPARAM = 0;
+pub const ICON_BIG: WPARAM = 1;
+pub const IDC_ARROW: *const WCHAR = 32512 as *const WCHAR;
+pub const IDC_IBEAM: *const WCHAR = 32513 as *const WCHAR;
+pub const IDC_WAIT: *const WCHAR = 32514 as *const WCHAR;
+pub const IDC_CROSS: *const WCHAR = 32515 as *const WCHAR;
+pub const IDC_SIZENWSE: *const WCHAR = 32642 as *const WCHAR;
+pub const IDC_SIZENESW: *const WCHAR = 32643 as *const WCHAR;
+pub const IDC_SIZEWE: *const WCHAR = 32644 as *const WCHAR;
+pub const IDC_SIZENS: *const WCHAR = 32645 as *const WCHAR;
+pub const IDC_SIZEALL: *const WCHAR = 32646 as *const WCHAR;
+pub const IDC_NO: *const WCHAR = 32648 as *const WCHAR;
+pub const IDC_HAND: *const WCHAR = 32649 as *const WCHAR;
+pub const IDC_APPSTARTING: *const WCHAR = 32650 as *const WCHAR;
+pub const IDC_HELP: *const WCHAR = 32651 as *const WCHAR;
+pub const ISC_SHOWUICOMPOSITIONWINDOW: LPARAM = 0x80000000;
+pub const LOAD_LIBRARY_SEARCH_SYSTEM32: DWORD = 0x00000800;
+pub const MAPVK_VK_TO_CHAR: UINT = 2;
+pub const MDT_EFFECTIVE_DPI: c_int = 0;
+pub const MONITOR_DEFAULTTONEAREST: DWORD = 0x00000002;
+pub const MONITORINFOF_PRIMARY: DWORD = 0x00000001;
+pub const MOUSE_MOVE_ABSOLUTE: USHORT = 0x01;
+pub const RID_INPUT: UINT = 0x10000003;
+pub const RIM_TYPEMOUSE: DWORD = 0;
+pub const S_OK: HRESULT = 0;
+pub const SIZE_MINIMIZED: WPARAM = 1;
+pub const SM_CXICON: c_int = 11;
+pub const SM_CXSMICON: c_int = 49;
+pub const SW_MINIMIZE: c_int = 6;
+pub const SWP_NOSIZE: UINT = 0x0001;
+pub const SWP_NOMOVE: UINT = 0x0002;
+pub const SWP_NOZORDER: UINT = 0x0004;
+pub const SWP_NOACTIVATE: UINT = 0x0010;
+pub const SWP_FRAMECHANGED: UINT = 0x0020;
+pub const SWP_SHOWWINDOW: UINT = 0x0040;
+pub const SWP_HIDEWINDOW: UINT = 0x0080;
+pub const SWP_NOOWNERZORDER: UINT = 0x0200;
+pub const SWP_ASYNCWINDOWPOS: UINT = 0x4000;
+pub const TME_LEAVE: DWORD = 0x00000002;
+pub const TRUE: BOOL = 1;
+pub const TYMED_HGLOBAL: DWORD = 1;
+pub const USER_DEFAULT_SCREEN_DPI: UINT = 96;
+pub const VERTSIZE: c_int = 6;
+pub const VK_SHIFT: c_int = 0x10;
+pub const VK_CONTROL: c_int = 0x11;
+pub const VK_MENU: c_int = 0x12;
+pub const VK_CAPITAL: c_int = 0x14;
+pub const VK_LWIN: c_int = 0x5B;
+pub const VK_RWIN: c_int = 0x5C;
+pub const VK_NUMLOCK: c_int = 0x90;
 pub const WH_CBT: c_int = 5;
+pub const WHEEL_DELTA: c_short = 120;
+pub const WS_OVERLAPPEDWINDOW: DWORD = 0x00CF0000;
+pub const WS_POPUP: DWORD = 0x80000000;
+pub const XBUTTON1: WORD = 0x0001;
+pub const XBUTTON2: WORD = 0x0002;
+
+// Window messages
+pub const WM_NCCREATE: UINT = 0x0081;
+pub const WM_DESTROY: UINT = 0x0002;
+pub const WM_MOVE: UINT = 0x0003;
+pub const WM_SIZE: UINT = 0x0005;
+pub const WM_SETFOCUS: UINT = 0x0007;
+pub const WM_KILLFOCUS: UINT = 0x0008;
+pub const WM_CLOSE: UINT = 0x0010;
+pub const WM_SETCURSOR: UINT = 0x0020;
+pub const WM_DISPLAYCHANGE: UINT = 0x007E;
+pub const WM_SETICON: UINT = 0x0080;
+pub const WM_INPUT: UINT = 0x00FF;
+pub const WM_KEYDOWN: UINT = 0x0100;
+pub const WM_KEYUP: UINT = 0x0101;
+pub const WM_CHAR: UINT = 0x0102;
+pub const WM_SYSKEYDOWN: UINT = 0x0104;
+pub const WM_SYSKEYUP: UINT = 0x0105;
+pub const WM_IME_STARTCOMPOSITION: UINT = 0x010D;
+pub const WM_IME_ENDCOMPOSITION: UINT = 0x010E;
+pub const WM_IME_COMPOSITION: UINT = 0x010F;
+pub const WM_MOUSEMOVE: UINT = 0x0200;
+pub const WM_LBUTTONDOWN: UINT = 0x0201;
+pub const WM_LBUTTONUP: UINT = 0x0202;
+pub const WM_RBUTTONDOWN: UINT = 0x0204;
+pub const WM_RBUTTONUP: UINT = 0x0205;
+pub const WM_MBUTTONDOWN: UINT = 0x0207;
+pub const WM_MBUTTONUP: UINT = 0x0208;
+pub const WM_MOUSEWHEEL: UINT = 0x020A;
+pub const WM_XBUTTONDOWN: UINT = 0x020B;
+pub const WM_XBUTTONUP: UINT = 0x020C;
+pub const WM_MOUSEHWHEEL: UINT = 0x020E;
+pub const WM_IME_SETCONTEXT: UINT = 0x0281;
+pub const WM_MOUSELEAVE: UINT = 0x02A3;
+pub const WM_DPICHANGED: UINT = 0x02E0;
+pub const WM_USER: UINT = 0x0400;
 
 /* static linked functions */
 
@@ -144,7 +550,28 @@ extern "system" {
     pub fn GetLastError() -> DWORD;
     pub fn SetLastError(dwErrCode: DWORD);
 
+    pub fn FormatMessageW(
+        dwFlags: DWORD,
+        lpSource: *const c_void,
+        dwMessageId: DWORD,
+        dwLanguageId: DWORD,
+        lpBuffer: *mut WCHAR,
+        nSize: DWORD,
+        Arguments: *mut c_void,
+    ) -> DWORD;
+
     pub fn GetCurrentThreadId() -> DWORD;
+    pub fn Sleep(dwMilliseconds: DWORD);
+
+    pub fn GlobalAlloc(uFlags: UINT, dwBytes: usize) -> HGLOBAL;
+    pub fn GlobalFree(hMem: HGLOBAL) -> HGLOBAL;
+    pub fn GlobalLock(hMem: HGLOBAL) -> *mut c_void;
+    pub fn GlobalSize(hMem: HGLOBAL) -> usize;
+    pub fn GlobalUnlock(hMem: HGLOBAL) -> BOOL;
+
+    pub fn GetModuleHandleW(lpModuleName: *const WCHAR) -> HINSTANCE;
+    pub fn GetProcAddress(hModule: HINSTANCE, lpProcName: *const c_char) -> *mut c_void;
+    pub fn LoadLibraryExW(lpLibFileName: *const WCHAR, hFile: *mut c_void, dwFlags: DWORD) -> HINSTANCE;
 
     pub fn MultiByteToWideChar(
         CodePage: UINT,
@@ -158,6 +585,18 @@ extern "system" {
 
 #[link(name = "user32")]
 extern "system" {
+    // Clipboard
+    pub fn OpenClipboard(hWndNewOwner: HWND) -> BOOL;
+    pub fn CloseClipboard() -> BOOL;
+    pub fn EmptyClipboard() -> BOOL;
+    pub fn GetClipboardOwner() -> HWND;
+    pub fn EnumClipboardFormats(format: UINT) -> UINT;
+    pub fn IsClipboardFormatAvailable(format: UINT) -> BOOL;
+    pub fn GetClipboardData(uFormat: UINT) -> HANDLE;
+    pub fn SetClipboardData(uFormat: UINT, hMem: HANDLE) -> HANDLE;
+    pub fn RegisterClipboardFormatW(lpszFormat: *const WCHAR) -> UINT;
+    pub fn GetClipboardFormatNameW(format: UINT, lpszFormatName: *mut WCHAR, cchMaxCount: c_int) -> c_int;
+
     // Window creation
     pub fn GetClassInfoExW(hinst: HINSTANCE, lpszClass: *const WCHAR, lpwcx: *mut WNDCLASSEXW) -> BOOL;
     pub fn RegisterClassExW(lpWndClass: *const WNDCLASSEXW) -> ATOM;
@@ -188,8 +627,11 @@ extern "system" {
     ) -> BOOL;
     pub fn AdjustWindowRectEx(lpRect: *mut RECT, dwStyle: DWORD, bMenu: BOOL, dwExStyle: DWORD) -> BOOL;
     pub fn ClientToScreen(hWnd: HWND, lpPoint: *mut POINT) -> BOOL;
+    pub fn ScreenToClient(hWnd: HWND, lpPoint: *mut POINT) -> BOOL;
     pub fn GetClientRect(hWnd: HWND, lpRect: *mut RECT) -> BOOL;
     pub fn GetWindowRect(hWnd: HWND, lpRect: *mut RECT) -> BOOL;
+    pub fn IsIconic(hWnd: HWND) -> BOOL;
+    pub fn ShowWindow(hWnd: HWND, nCmdShow: c_int) -> BOOL;
     // pub fn GetTitleBarInfo(hwnd: HWND, pti: *mut TITLEBARINFO) -> BOOL;
     pub fn WindowFromPoint(Point: POINT) -> HWND;
     pub fn DestroyWindow(hWnd: HWND) -> BOOL;
@@ -198,6 +640,7 @@ extern "system" {
     pub fn GetClassLongW(hWnd: HWND, nIndex: c_int) -> DWORD;
     pub fn SetClassLongW(hWnd: HWND, nIndex: c_int, dwNewLong: LONG) -> DWORD;
     pub fn GetWindowLongW(hWnd: HWND, nIndex: c_int) -> LONG;
+    pub fn SetWindowTextW(hWnd: HWND, lpString: *const WCHAR) -> BOOL;
     pub fn SetWindowLongW(hWnd: HWND, nIndex: c_int, dwNewLong: LONG) -> LONG;
     #[cfg(target_pointer_width = "64")]
     pub fn GetClassLongPtrW(hWnd: HWND, nIndex: c_int) -> ULONG_PTR;
@@ -208,11 +651,53 @@ extern "system" {
     #[cfg(target_pointer_width = "64")]
     pub fn SetWindowLongPtrW(hWnd: HWND, nIndex: c_int, dwNewLong: LONG_PTR) -> LONG_PTR;
 
+    // Monitors
+    pub fn EnumDisplayMonitors(hdc: HDC, lprcClip: *const RECT, lpfnEnum: MONITORENUMPROC, dwData: LPARAM) -> BOOL;
+    pub fn EnumDisplaySettingsW(lpszDeviceName: *const WCHAR, iModeNum: DWORD, lpDevMode: *mut DEVMODEW) -> BOOL;
+    pub fn ChangeDisplaySettingsExW(
+        lpszDeviceName: *const WCHAR,
+        lpDevMode: *mut DEVMODEW,
+        hwnd: HWND,
+        dwflags: DWORD,
+        lParam: *mut c_void,
+    ) -> LONG;
+    pub fn GetMonitorInfoW(hMonitor: HMONITOR, lpmi: *mut MONITORINFOEXW) -> BOOL;
+    pub fn MonitorFromWindow(hwnd: HWND, dwFlags: DWORD) -> HMONITOR;
+
+    // Keyboard
+    pub fn GetKeyState(nVirtKey: c_int) -> SHORT;
+    pub fn MapVirtualKeyW(uCode: UINT, uMapType: UINT) -> UINT;
+
+    // Cursors & icons
+    pub fn CreateIconIndirect(piconinfo: *mut ICONINFO) -> HICON;
+    pub fn DestroyIcon(hIcon: HICON) -> BOOL;
+    pub fn GetSystemMetrics(nIndex: c_int) -> c_int;
+    pub fn LoadCursorW(hInstance: HINSTANCE, lpCursorName: *const WCHAR) -> HCURSOR;
+    pub fn SetCursor(hCursor: HCURSOR) -> HCURSOR;
+
+    // Mouse
+    pub fn ClipCursor(lpRect: *const RECT) -> BOOL;
+    pub fn GetCursorPos(lpPoint: *mut POINT) -> BOOL;
+    pub fn GetFocus() -> HWND;
+    pub fn SetCursorPos(X: c_int, Y: c_int) -> BOOL;
+    pub fn TrackMouseEvent(lpEventTrack: *mut TRACKMOUSEEVENT) -> BOOL;
+
+    // Raw input
+    pub fn GetRawInputData(
+        hRawInput: HRAWINPUT,
+        uiCommand: UINT,
+        pData: *mut c_void,
+        pcbSize: *mut UINT,
+        cbSizeHeader: UINT,
+    ) -> UINT;
+    pub fn RegisterRawInputDevices(pRawInputDevices: *const RAWINPUTDEVICE, uiNumDevices: UINT, cbSize: UINT) -> BOOL;
+
     // Window message loop
     pub fn GetMessageW(lpMsg: *mut MSG, hWnd: HWND, wMsgFilterMin: UINT, wMsgFilterMax: UINT) -> BOOL;
     pub fn PostMessageW(hWnd: HWND, Msg: UINT, wParam: WPARAM, lParam: LPARAM) -> BOOL;
     pub fn SendMessageW(hWnd: HWND, Msg: UINT, wParam: WPARAM, lParam: LPARAM) -> LRESULT;
     pub fn DefWindowProcW(hWnd: HWND, Msg: UINT, wParam: WPARAM, lParam: LPARAM) -> LRESULT;
+    pub fn TranslateMessage(lpMsg: *const MSG) -> BOOL;
     pub fn DispatchMessageW(lpmsg: *const MSG) -> LRESULT;
     pub fn PostQuitMessage(nExitCode: c_int);
 
@@ -221,3 +706,56 @@ extern "system" {
     pub fn SetWindowsHookExW(idHook: c_int, lpfn: HOOKPROC, hmod: HINSTANCE, dwThreadId: DWORD) -> HHOOK;
     pub fn UnhookWindowsHookEx(hhk: HHOOK) -> BOOL;
 }
+
+#[link(name = "imm32")]
+extern "system" {
+    pub fn ImmAssociateContextEx(hWnd: HWND, hIMC: HIMC, dwFlags: DWORD) -> BOOL;
+    pub fn ImmGetCompositionStringW(hIMC: HIMC, dwIndex: DWORD, lpBuf: *mut c_void, dwBufLen: DWORD) -> LONG;
+    pub fn ImmGetContext(hWnd: HWND) -> HIMC;
+    pub fn ImmReleaseContext(hWnd: HWND, hIMC: HIMC) -> BOOL;
+    pub fn ImmSetCandidateWindow(hIMC: HIMC, lpCandidate: *mut CANDIDATEFORM) -> BOOL;
+    pub fn ImmSetCompositionWindow(hIMC: HIMC, lpCompForm: *mut COMPOSITIONFORM) -> BOOL;
+}
+
+#[link(name = "ole32")]
+extern "system" {
+    pub fn OleInitialize(pvReserved: *mut c_void) -> HRESULT;
+    pub fn OleUninitialize();
+    pub fn RegisterDragDrop(hwnd: HWND, pDropTarget: *mut IDropTarget) -> HRESULT;
+    pub fn RevokeDragDrop(hwnd: HWND) -> HRESULT;
+    pub fn ReleaseStgMedium(pMedium: *mut STGMEDIUM);
+    pub fn CoTaskMemFree(pv: *mut c_void);
+}
+
+#[link(name = "shell32")]
+extern "system" {
+    pub fn DragQueryFileW(hDrop: HDROP, iFile: UINT, lpszFile: *mut WCHAR, cch: UINT) -> UINT;
+}
+
+#[link(name = "gdi32")]
+extern "system" {
+    pub fn CreateDCW(
+        pszDriver: *const WCHAR,
+        pszDevice: *const WCHAR,
+        pszPort: *const WCHAR,
+        pdm: *const DEVMODEW,
+    ) -> HDC;
+    pub fn DeleteDC(hdc: HDC) -> BOOL;
+    pub fn CreateBitmap(
+        nWidth: c_int,
+        nHeight: c_int,
+        nPlanes: UINT,
+        nBitCount: UINT,
+        lpBits: *const c_void,
+    ) -> HBITMAP;
+    pub fn CreateDIBSection(
+        hdc: HDC,
+        pbmi: *const BITMAPINFO,
+        usage: UINT,
+        ppvBits: *mut *mut c_void,
+        hSection: HANDLE,
+        offset: DWORD,
+    ) -> HBITMAP;
+    pub fn DeleteObject(ho: HGDIOBJ) -> BOOL;
+    pub fn GetDeviceCaps(hdc: HDC, index: c_int) -> c_int;
+}
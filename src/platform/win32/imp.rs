@@ -1,11 +1,19 @@
-//! stuff
+//! The Win32 backend, where every window has a thread of its own running its message loop.
 
 // TODO: unglob
 use crate::{
-    error::Error,
-    platform::win32::{ffi::*, util, WindowBuilderExt},
-    sync::{cvar_notify_one, cvar_wait, mutex_lock, Condvar, LazyCell, Mutex},
-    window::WindowBuilder,
+    clipboard::Selection,
+    cursor::{Cursor, CursorShape},
+    dpi::{PhysicalPosition, PhysicalSize},
+    drag::{DragData, DragFormat, DragIcon, DropAction},
+    error::{Error, ErrorKind},
+    event::{Event, EventQueue, MouseButton, ScrollDelta},
+    icon::Icon,
+    keyboard::HeldKeys,
+    monitor::{self, Monitor, VideoMode},
+    platform::win32::{clipboard, dnd, ffi::*, keyboard, util, WindowBuilderExt},
+    sync::{self, mutex_lock, LazyCell, Mutex, Sender},
+    window::{CursorGrab, Fullscreen, WindowBuilder},
 };
 use std::{
     cell, mem, ptr,
@@ -26,42 +34,141 @@ impl WindowBuilderExt for WindowBuilder {
 }
 
 pub(crate) struct WindowImpl {
+    hwnd: HWND,
+    shared: Arc<WindowImplShared>,
     thread: Option<thread::JoinHandle<()>>,
 }
 
+// `HWND`s can be used from any thread, the window procedure always runs on the window thread
+unsafe impl Send for WindowImpl {}
+unsafe impl Sync for WindowImpl {}
+
+/// State shared between a [`WindowImpl`] and its window thread.
+struct WindowImplShared {
+    cursor: Mutex<(Cursor, bool)>,
+    cursor_grab: Mutex<CursorGrab>,
+    destroy_flag: AtomicBool,
+    drag_accepted: Arc<Mutex<Option<(DragFormat, DropAction)>>>,
+    events: Arc<EventQueue>,
+    fullscreen: Mutex<Option<Fullscreen>>,
+    icon: Mutex<Option<Icon>>,
+    ime_cursor_area: Mutex<Option<(PhysicalPosition, PhysicalSize)>>,
+    title: Mutex<Vec<WCHAR>>,
+}
+
 /// State accessible from `window_proc`, living on the thread stack.
 struct WindowImplUserData {
-    destroy_flag: AtomicBool,
+    shared: Arc<WindowImplShared>,
+
+    // The monitors as of the last `WM_DISPLAYCHANGE`, to tell which ones came and went
+    monitors: Vec<Monitor>,
+
+    // The fullscreen state last carried out, the style and rectangle from before it,
+    // and the device whose video mode it changed
+    fullscreen: Option<Fullscreen>,
+    windowed: Option<(DWORD, RECT)>,
+    video_mode_device: Option<Vec<WCHAR>>,
+
+    // What's held down, to tell repeats apart and to release it when focus is lost
+    held_keys: HeldKeys,
+
+    // `WM_CHAR` sends UTF-16 code units, so characters outside the BMP come in two messages
+    high_surrogate: Option<WCHAR>,
+
+    // Whether there's pre-edit text that needs clearing when composition ends
+    preedit_shown: bool,
+
+    // Whether the cursor is in the client area, as `WM_MOUSELEAVE` has to be asked for every time it enters
+    cursor_inside: bool,
+
+    // Whether the cursor is clipped by us, as the clip is system-wide and shouldn't be undone for anyone else
+    cursor_clipped: bool,
+
+    // The cursor shown in the client area, and whether we made it and have to destroy it
+    cursor: HCURSOR,
+    cursor_custom: bool,
+
+    // The big and small icons set on the window, which are ours to destroy
+    icons: [HICON; 2],
 }
 
 /// Sent to `thread::spawn` as a nice package.
 struct WindowImplThreadParams {
-    builder: *const WindowBuilder,
-    class_name: *const WCHAR,
-    title: *const WCHAR,
-    response: Arc<(Condvar, Mutex<Option<Result<WindowImpl, Error>>>)>,
+    builder: WindowBuilder,
+    class_name: Vec<WCHAR>,
+    title: Vec<WCHAR>,
+    shared: Arc<WindowImplShared>,
+    response: Sender<Result<WindowImpl, Error>>,
 }
-unsafe impl Send for WindowImplThreadParams {}
+
+/// Posted by [`WindowImpl`]'s destructor, as only the window thread can call `DestroyWindow`.
+const RAMEN_WM_DESTROY: UINT = WM_USER;
+
+/// Posted by [`WindowImpl::set_fullscreen`] to have the window thread carry out the new fullscreen state.
+const RAMEN_WM_SET_FULLSCREEN: UINT = WM_USER + 1;
+
+/// Posted by [`WindowImpl::set_ime_cursor_area`], as input contexts can only be used from the window thread.
+const RAMEN_WM_SET_IME_CURSOR_AREA: UINT = WM_USER + 2;
+
+/// Posted by [`WindowImpl::set_cursor_grab`], as the window thread knows whether the window is focused.
+const RAMEN_WM_SET_CURSOR_GRAB: UINT = WM_USER + 3;
+
+/// Posted by [`WindowImpl::set_cursor`] and [`WindowImpl::set_cursor_visible`], as the cursor is per-thread.
+const RAMEN_WM_SET_CURSOR: UINT = WM_USER + 4;
+
+/// Posted by [`WindowImpl::set_icon`], to keep the window thread in charge of destroying the icons.
+const RAMEN_WM_SET_ICON: UINT = WM_USER + 5;
+
+/// Posted by [`WindowImpl::set_title`], as `SetWindowTextW` would wait on the window thread.
+const RAMEN_WM_SET_TITLE: UINT = WM_USER + 6;
 
 /// Marker value stored in `cbClsExtra` to filter user windows in hooking functions
 const RAMEN_WINDOW_MARKER: u32 = u32::from_be_bytes(*b"viri");
 
 impl WindowImpl {
     pub(crate) fn new(builder: &WindowBuilder) -> Result<Self, Error> {
-        let response = Arc::new((Condvar::new(), Mutex::new(None)));
+        let (response, receiver) = sync::oneshot();
+        let shared = Arc::new(WindowImplShared {
+            cursor: Mutex::new((Cursor::default(), true)),
+            cursor_grab: Mutex::new(CursorGrab::None),
+            destroy_flag: AtomicBool::new(false),
+            drag_accepted: Arc::new(Mutex::new(None)),
+            events: Arc::new(EventQueue::new()),
+            fullscreen: Mutex::new(builder.fullscreen.clone()),
+            icon: Mutex::new(builder.icon.clone()),
+            ime_cursor_area: Mutex::new(None),
+            title: Mutex::new(Vec::new()),
+        });
 
         // XXX: no-panic allocator api
         // Allocate these on the main thread to avoid panicking on the window thread.
         let mut buf_class_name = Vec::new();
         let mut buf_title = Vec::new();
+        let _ = util::str_to_wstr(builder.class_name.as_ref(), &mut buf_class_name);
+        let _ = util::str_to_wstr(builder.title.as_ref(), &mut buf_title);
 
         let thread_params = WindowImplThreadParams {
-            builder: builder,
-            class_name: util::str_to_wstr(builder.class_name.as_ref(), &mut buf_class_name),
-            title: util::str_to_wstr(builder.title.as_ref(), &mut buf_title),
-            response: Arc::clone(&response),
+            // Owned by the thread, as the caller might stop waiting on it (see `creation_timeout`)
+            builder: builder.clone(),
+            class_name: buf_class_name,
+            title: buf_title,
+            shared,
+            response,
         };
         let thread = thread::spawn(move || unsafe {
+            let WindowImplThreadParams {
+                builder,
+                class_name,
+                title,
+                shared,
+                response,
+            } = thread_params;
+            let respond = |result| {
+                // If the caller gave up waiting, the unclaimed `WindowImpl` is dropped here
+                // That posts `RAMEN_WM_DESTROY`, so the window is destroyed as soon as the message loop starts
+                let _ = response.send(result);
+            };
+
             static CLASS_REGISTRY_LOCK: LazyCell<Mutex<()>> = LazyCell::new(Default::default);
 
             /* Register the window class (unless it's already been registered) */
@@ -70,18 +177,13 @@ impl WindowImpl {
             let mut class_created_this_thread = false; // see usages below
             let class_registry_lock = mutex_lock(&CLASS_REGISTRY_LOCK);
             (*class_info.as_mut_ptr()).cbSize = mem::size_of_val(&class_info) as DWORD;
-            if GetClassInfoExW(
-                util::base_hinstance(),
-                thread_params.class_name,
-                class_info.as_mut_ptr(),
-            ) == FALSE
-            {
+            if GetClassInfoExW(util::base_hinstance(), class_name.as_ptr(), class_info.as_mut_ptr()) == FALSE {
                 // The window class not existing sets the thread global error flag, but it's okay
                 SetLastError(ERROR_SUCCESS);
 
                 // Fill in & register class (`cbSize` is set above already)
                 let class = &mut *class_info.as_mut_ptr();
-                if (&*thread_params.builder).cs_owndc {
+                if builder.cs_owndc {
                     // See `win32::WindowBuilderExt` for an explanation
                     class.style = CS_OWNDC;
                 } else {
@@ -89,28 +191,87 @@ impl WindowImpl {
                 }
                 class.lpfnWndProc = window_proc;
                 class.cbClsExtra = mem::size_of::<usize>() as c_int;
-                class.cbWndExtra = 0;
+                class.cbWndExtra = mem::size_of::<usize>() as c_int;
                 class.hInstance = util::base_hinstance();
                 class.hIcon = ptr::null_mut();
-                class.hCursor = ptr::null_mut();
+                class.hCursor = LoadCursorW(ptr::null_mut(), IDC_ARROW);
                 class.hbrBackground = ptr::null_mut();
                 class.lpszMenuName = ptr::null_mut();
                 // TODO: Filter reserved class names
-                class.lpszClassName = thread_params.class_name;
+                class.lpszClassName = class_name.as_ptr();
                 class.hIconSm = ptr::null_mut();
 
-                // TODO handle properly
-                let atom = RegisterClassExW(class);
-                assert_ne!(0, atom);
+                if RegisterClassExW(class) == 0 {
+                    let err = util::last_error(ErrorKind::ClassRegistrationFailed, "failed to register window class");
+                    mem::drop(class_registry_lock);
+                    return respond(Err(err))
+                }
                 class_created_this_thread = true;
             }
             mem::drop(class_registry_lock);
 
             let user_data = cell::UnsafeCell::new(WindowImplUserData {
-                destroy_flag: AtomicBool::new(false),
+                shared: Arc::clone(&shared),
+                monitors: enumerate_monitors(),
+                fullscreen: None,
+                windowed: None,
+                video_mode_device: None,
+                held_keys: HeldKeys::default(),
+                high_surrogate: None,
+                preedit_shown: false,
+                cursor_inside: false,
+                cursor_clipped: false,
+                cursor: LoadCursorW(ptr::null_mut(), IDC_ARROW),
+                cursor_custom: false,
+                icons: [ptr::null_mut(); 2],
             });
 
-            let hwnd: HWND = ptr::null_mut();
+            // A guarantee of `Window` is that as long as you own it, the window remains open
+            // However, external requests can be made to destroy our window without asking us first
+            // `WM_DESTROY` is only sent after a lot of state has already been invalidated and you can't stop it
+            // The CBT (not what you think, "computer-based training") hooking APIs added a hook to tamper with this
+            // We attach a hooking procedure that rejects windows being destroyed until we set an internal flag
+            let cbt_hook = SetWindowsHookExW(WH_CBT, hcbt_destroywnd_hookproc, ptr::null_mut(), GetCurrentThreadId());
+
+            // Per-monitor DPI awareness is per thread, so our windows get it without affecting the rest of the process
+            // Version 2 also scales the title bar and such, but it's only in Windows 10 1703 and up
+            if let Some(set_thread_dpi_awareness_context) = util::USER32.set_thread_dpi_awareness_context {
+                if set_thread_dpi_awareness_context(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2) == 0 {
+                    let _ = set_thread_dpi_awareness_context(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE);
+                }
+            }
+
+            // The DPI depends on which monitor the window lands on, so it's created hidden to find out first
+            // `CW_USEDEFAULT` for `x` lets the system pick a position
+            let (style, style_ex) = (WS_OVERLAPPEDWINDOW, 0);
+            let (x, y) = match builder.position {
+                Some(position) => {
+                    let position = position.to_physical(1.0);
+                    (position.x, position.y)
+                },
+                None => (CW_USEDEFAULT, CW_USEDEFAULT),
+            };
+
+            // The user data pointer is passed through `lpParam`, and stored during `WM_NCCREATE`
+            let hwnd = CreateWindowExW(
+                style_ex,
+                class_name.as_ptr(),
+                title.as_ptr(),
+                style,
+                x,
+                y,
+                CW_USEDEFAULT,
+                CW_USEDEFAULT,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                util::base_hinstance(),
+                user_data.get().cast(),
+            );
+            if hwnd.is_null() {
+                let err = util::last_error(ErrorKind::WindowCreationFailed, "failed to create window");
+                let _ = UnhookWindowsHookEx(cbt_hook);
+                return respond(Err(err))
+            }
 
             // If we're the thread that created the class, we have to manipulate the storage a bit
             // Unfortunately the API doesn't allow you to do this until you have a window handle
@@ -118,36 +279,695 @@ impl WindowImpl {
                 let _ = util::set_class_data(hwnd, 0, RAMEN_WINDOW_MARKER as usize);
             }
 
-            // A guarantee of `Window` is that as long as you own it, the window remains open
-            // However, external requests can be made to destroy our window without asking us first
-            // `WM_DESTROY` is only sent after a lot of state has already been invalidated and you can't stop it
-            // The CBT (not what you think, "computer-based training") hooking APIs added a hook to tamper with this
-            // We attach a hooking procedure that rejects windows being destroyed until we set an internal flag
-            let cbt_hook = SetWindowsHookExW(WH_CBT, hcbt_destroywnd_hookproc, ptr::null_mut(), GetCurrentThreadId());
+            // Now that the DPI is known, size it properly and show it - `SetWindowPos` takes the outer size
+            let dpi = util::window_dpi(hwnd);
+            let scale_factor = f64::from(dpi) / f64::from(USER_DEFAULT_SCREEN_DPI);
+            let size = builder.inner_size.to_physical(scale_factor);
+            let (width, height) = util::adjust_window_size(size, style, style_ex, dpi);
+            let (x, y, move_flag) = match builder.position {
+                Some(position) => {
+                    let position = position.to_physical(scale_factor);
+                    (position.x, position.y, 0)
+                },
+                None => (0, 0, SWP_NOMOVE),
+            };
+            let _ = SetWindowPos(
+                hwnd,
+                ptr::null_mut(),
+                x,
+                y,
+                width,
+                height,
+                move_flag | SWP_NOZORDER | SWP_SHOWWINDOW,
+            );
+            if builder.fullscreen.is_some() {
+                set_fullscreen(hwnd, &mut *user_data.get(), builder.fullscreen.clone());
+            }
+            if builder.icon.is_some() {
+                update_icon(hwnd, &mut *user_data.get());
+            }
+
+            // Associating no input context turns IMEs off for the window, leaving just `WM_CHAR`
+            if !builder.ime {
+                let _ = ImmAssociateContextEx(hwnd, ptr::null_mut(), 0);
+            }
+
+            // Raw input is registered for the whole process, and without a target window it follows the focus
+            // That's what's wanted anyway, and it means every window can register it without taking it from the others
+            let device = RAWINPUTDEVICE {
+                usUsagePage: 0x01, // Generic desktop controls
+                usUsage: 0x02,     // Mouse
+                dwFlags: 0,
+                hwndTarget: ptr::null_mut(),
+            };
+            let _ = RegisterRawInputDevices(&device, 1, mem::size_of::<RAWINPUTDEVICE>() as UINT);
+
+            // Drops come through OLE, which has to be initialised on every thread that takes them
+            let ole_initialized = OleInitialize(ptr::null_mut()) >= 0;
+            if ole_initialized {
+                dnd::register(hwnd, Arc::clone(&shared.events), Arc::clone(&shared.drag_accepted));
+            }
+
+            respond(Ok(WindowImpl {
+                hwnd,
+                shared: Arc::clone(&shared),
+                thread: None,
+            }));
 
-            // ...
+            let mut msg = mem::MaybeUninit::<MSG>::uninit();
+            loop {
+                // `GetMessageW` returns 0 for `WM_QUIT`, and -1 on error
+                if GetMessageW(msg.as_mut_ptr(), ptr::null_mut(), 0, 0) <= 0 {
+                    break
+                }
+                let _ = TranslateMessage(msg.as_ptr());
+                let _ = DispatchMessageW(msg.as_ptr());
+            }
 
             let _ = UnhookWindowsHookEx(cbt_hook);
+            if ole_initialized {
+                OleUninitialize();
+            }
         });
 
         /* Wait for the thread to return the window or an error */
-        let (cvar, mutex) = &*response;
-        let mut lock = mutex_lock(&mutex);
-        loop {
-            if let Some(result) = (&mut *lock).take() {
-                break result.map(|mut window| {
-                    window.thread = Some(thread);
-                    window
-                })
-            } else {
-                cvar_wait(&cvar, &mut lock);
+        let (mut window, thread) = receiver.recv_from_thread(thread, builder.creation_timeout)?;
+        window.thread = Some(thread);
+        Ok(window)
+    }
+
+    #[cfg(feature = "raw-window-handle")]
+    pub(crate) fn raw_window_handle(
+        &self,
+    ) -> Result<raw_window_handle::RawWindowHandle, raw_window_handle::HandleError> {
+        let hwnd =
+            std::num::NonZeroIsize::new(self.hwnd as isize).ok_or(raw_window_handle::HandleError::Unavailable)?;
+        let mut handle = raw_window_handle::Win32WindowHandle::new(hwnd);
+        handle.hinstance = std::num::NonZeroIsize::new(util::base_hinstance() as isize);
+        Ok(handle.into())
+    }
+
+    #[cfg(feature = "raw-window-handle")]
+    pub(crate) fn raw_display_handle(
+        &self,
+    ) -> Result<raw_window_handle::RawDisplayHandle, raw_window_handle::HandleError> {
+        Ok(raw_window_handle::WindowsDisplayHandle::new().into())
+    }
+
+    #[inline]
+    pub(crate) fn events(&self) -> &Arc<EventQueue> {
+        &self.shared.events
+    }
+
+    // These don't send any messages, so they're safe to call from any thread
+
+    pub(crate) fn available_monitors(&self) -> Vec<Monitor> {
+        unsafe { enumerate_monitors() }
+    }
+
+    pub(crate) fn current_monitor(&self) -> Option<Monitor> {
+        unsafe { monitor_info(MonitorFromWindow(self.hwnd, MONITOR_DEFAULTTONEAREST)) }
+    }
+
+    pub(crate) fn accept_drag(&self, accept: Option<(DragFormat, DropAction)>) {
+        // The drop target reads this whenever it's asked, so there's nothing to tell the window thread
+        *mutex_lock(&self.shared.drag_accepted) = accept;
+    }
+
+    pub(crate) fn start_drag(&self, _data: DragData, _icon: DragIcon) -> Result<(), Error> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "dragging out of a window is only supported on X11 and Wayland",
+        ))
+    }
+
+    pub(crate) fn clipboard_formats(&self, selection: Selection) -> Result<Vec<String>, Error> {
+        check_selection(selection)?;
+        unsafe { clipboard::formats(self.hwnd) }
+    }
+
+    pub(crate) fn clipboard_get(&self, selection: Selection, mime: &str) -> Result<Option<Vec<u8>>, Error> {
+        check_selection(selection)?;
+        unsafe { clipboard::get(self.hwnd, mime) }
+    }
+
+    pub(crate) fn clipboard_set(&self, selection: Selection, data: Vec<(String, Vec<u8>)>) -> Result<(), Error> {
+        check_selection(selection)?;
+        unsafe { clipboard::set(self.hwnd, data) }
+    }
+
+    pub(crate) fn fullscreen(&self) -> Option<Fullscreen> {
+        mutex_lock(&self.shared.fullscreen).clone()
+    }
+
+    pub(crate) fn inner_size(&self) -> PhysicalSize {
+        let mut rect = RECT {
+            left: 0,
+            top: 0,
+            right: 0,
+            bottom: 0,
+        };
+        let _ = unsafe { GetClientRect(self.hwnd, &mut rect) };
+        PhysicalSize::new(rect.right as u32, rect.bottom as u32)
+    }
+
+    pub(crate) fn outer_size(&self) -> PhysicalSize {
+        let rect = self.window_rect();
+        PhysicalSize::new((rect.right - rect.left) as u32, (rect.bottom - rect.top) as u32)
+    }
+
+    pub(crate) fn position(&self) -> Option<PhysicalPosition> {
+        let rect = self.window_rect();
+        Some(PhysicalPosition::new(rect.left, rect.top))
+    }
+
+    pub(crate) fn scale_factor(&self) -> f64 {
+        let dpi = unsafe { util::window_dpi(self.hwnd) };
+        f64::from(dpi) / f64::from(USER_DEFAULT_SCREEN_DPI)
+    }
+
+    fn window_rect(&self) -> RECT {
+        let mut rect = RECT {
+            left: 0,
+            top: 0,
+            right: 0,
+            bottom: 0,
+        };
+        let _ = unsafe { GetWindowRect(self.hwnd, &mut rect) };
+        rect
+    }
+
+    pub(crate) fn set_cursor(&self, cursor: Cursor) {
+        mutex_lock(&self.shared.cursor).0 = cursor;
+        unsafe {
+            let _ = PostMessageW(self.hwnd, RAMEN_WM_SET_CURSOR, 0, 0);
+        }
+    }
+
+    pub(crate) fn set_cursor_position(&self, position: PhysicalPosition) {
+        let mut point = POINT {
+            x: position.x,
+            y: position.y,
+        };
+        unsafe {
+            if ClientToScreen(self.hwnd, &mut point) != FALSE {
+                let _ = SetCursorPos(point.x, point.y);
             }
         }
     }
+
+    pub(crate) fn set_cursor_visible(&self, visible: bool) {
+        mutex_lock(&self.shared.cursor).1 = visible;
+        unsafe {
+            let _ = PostMessageW(self.hwnd, RAMEN_WM_SET_CURSOR, 0, 0);
+        }
+    }
+
+    pub(crate) fn set_cursor_grab(&self, grab: CursorGrab) {
+        *mutex_lock(&self.shared.cursor_grab) = grab;
+        unsafe {
+            let _ = PostMessageW(self.hwnd, RAMEN_WM_SET_CURSOR_GRAB, 0, 0);
+        }
+    }
+
+    pub(crate) fn set_fullscreen(&self, fullscreen: Option<Fullscreen>) {
+        // The window thread keeps the state from before going fullscreen, so it's the one that carries this out
+        *mutex_lock(&self.shared.fullscreen) = fullscreen;
+        unsafe {
+            let _ = PostMessageW(self.hwnd, RAMEN_WM_SET_FULLSCREEN, 0, 0);
+        }
+    }
+
+    pub(crate) fn set_icon(&self, icon: Option<Icon>) {
+        *mutex_lock(&self.shared.icon) = icon;
+        unsafe {
+            let _ = PostMessageW(self.hwnd, RAMEN_WM_SET_ICON, 0, 0);
+        }
+    }
+
+    pub(crate) fn set_ime_cursor_area(&self, position: PhysicalPosition, size: PhysicalSize) {
+        *mutex_lock(&self.shared.ime_cursor_area) = Some((position, size));
+        unsafe {
+            let _ = PostMessageW(self.hwnd, RAMEN_WM_SET_IME_CURSOR_AREA, 0, 0);
+        }
+    }
+
+    // With `SWP_ASYNCWINDOWPOS`, these are posted to the window thread instead of waiting on it
+
+    pub(crate) fn set_inner_size(&self, size: PhysicalSize) {
+        unsafe {
+            let style = util::get_window_data(self.hwnd, GWL_STYLE) as DWORD;
+            let style_ex = util::get_window_data(self.hwnd, GWL_EXSTYLE) as DWORD;
+            let dpi = util::window_dpi(self.hwnd);
+            let (width, height) = util::adjust_window_size(size, style, style_ex, dpi);
+            self.set_window_pos(0, 0, width, height, SWP_NOMOVE);
+        }
+    }
+
+    pub(crate) fn set_outer_size(&self, size: PhysicalSize) {
+        self.set_window_pos(0, 0, size.width as c_int, size.height as c_int, SWP_NOMOVE);
+    }
+
+    pub(crate) fn set_position(&self, position: PhysicalPosition) {
+        self.set_window_pos(position.x, position.y, 0, 0, SWP_NOSIZE);
+    }
+
+    pub(crate) fn set_title(&self, title: &str) {
+        // Converted here, like the builder's title, so the window thread has nothing to allocate
+        let mut buffer = Vec::new();
+        let _ = util::str_to_wstr(title, &mut buffer);
+        if buffer.is_empty() {
+            buffer.push(0x00);
+        }
+        *mutex_lock(&self.shared.title) = buffer;
+        unsafe {
+            let _ = PostMessageW(self.hwnd, RAMEN_WM_SET_TITLE, 0, 0);
+        }
+    }
+
+    pub(crate) fn set_visible(&self, visible: bool) {
+        // Showing the window activates it, like when it was first shown
+        let flags = if visible {
+            SWP_SHOWWINDOW
+        } else {
+            SWP_HIDEWINDOW | SWP_NOACTIVATE
+        };
+        let flags = flags | SWP_ASYNCWINDOWPOS | SWP_NOMOVE | SWP_NOSIZE | SWP_NOZORDER;
+        let _ = unsafe { SetWindowPos(self.hwnd, ptr::null_mut(), 0, 0, 0, 0, flags) };
+    }
+
+    fn set_window_pos(&self, x: c_int, y: c_int, width: c_int, height: c_int, flags: UINT) {
+        let flags = flags | SWP_ASYNCWINDOWPOS | SWP_NOACTIVATE | SWP_NOZORDER;
+        let _ = unsafe { SetWindowPos(self.hwnd, ptr::null_mut(), x, y, width, height, flags) };
+    }
+}
+
+impl Drop for WindowImpl {
+    fn drop(&mut self) {
+        // This is the only way our window can be destroyed, see `hcbt_destroywnd_hookproc`
+        self.shared.destroy_flag.store(true, atomic::Ordering::Release);
+        unsafe {
+            let _ = PostMessageW(self.hwnd, RAMEN_WM_DESTROY, 0, 0);
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Fails for the primary selection, which only X11 has.
+fn check_selection(selection: Selection) -> Result<(), Error> {
+    match selection {
+        Selection::Clipboard => Ok(()),
+        Selection::Primary => Err(Error::new(
+            ErrorKind::Unsupported,
+            "the primary selection is only supported on X11",
+        )),
+    }
 }
 
-unsafe fn user_data<'a>(hwnd: HWND) -> &'a mut WindowImplUserData {
-    &mut *(util::get_window_data(hwnd, 0) as *mut WindowImplUserData)
+/// Lists every monitor that's part of the desktop.
+unsafe fn enumerate_monitors() -> Vec<Monitor> {
+    unsafe extern "system" fn callback(hmonitor: HMONITOR, _hdc: HDC, _rect: *mut RECT, data: LPARAM) -> BOOL {
+        let monitors = &mut *(data as *mut Vec<Monitor>);
+        monitors.extend(monitor_info(hmonitor));
+        TRUE
+    }
+    let mut monitors = Vec::new();
+    let _ = EnumDisplayMonitors(
+        ptr::null_mut(),
+        ptr::null(),
+        callback,
+        &mut monitors as *mut Vec<Monitor> as LPARAM,
+    );
+    monitors
+}
+
+unsafe fn monitor_info(hmonitor: HMONITOR) -> Option<Monitor> {
+    let mut info: MONITORINFOEXW = mem::zeroed();
+    info.cbSize = mem::size_of::<MONITORINFOEXW>() as DWORD;
+    if hmonitor.is_null() || GetMonitorInfoW(hmonitor, &mut info) == FALSE {
+        return None
+    }
+    let device = info.szDevice.as_ptr();
+    let device_len = info
+        .szDevice
+        .iter()
+        .position(|&x| x == 0)
+        .unwrap_or(info.szDevice.len());
+    let name = String::from_utf16_lossy(&info.szDevice[..device_len]);
+
+    // Mode number 0 and up are every supported mode, with plenty of duplicates for things like scaling options
+    let mut mode: DEVMODEW = mem::zeroed();
+    mode.dmSize = mem::size_of::<DEVMODEW>() as WORD;
+    let video_mode = |mode: &DEVMODEW| VideoMode {
+        id: 0,
+        size: PhysicalSize::new(mode.dmPelsWidth, mode.dmPelsHeight),
+        bit_depth: mode.dmBitsPerPel as u16,
+        refresh_rate_millihertz: mode.dmDisplayFrequency * 1000,
+    };
+    let mut video_modes = Vec::new();
+    for i in 0.. {
+        if EnumDisplaySettingsW(device, i, &mut mode) == FALSE {
+            break
+        }
+        let video_mode = video_mode(&mode);
+        if !video_modes.contains(&video_mode) {
+            video_modes.push(video_mode);
+        }
+    }
+
+    // A frequency of 0 or 1 means the hardware's default, whatever that is
+    let refresh_rate_millihertz = (EnumDisplaySettingsW(device, ENUM_CURRENT_SETTINGS, &mut mode) != FALSE &&
+        mode.dmDisplayFrequency > 1)
+        .then(|| mode.dmDisplayFrequency * 1000);
+
+    let hdc = CreateDCW(device, ptr::null(), ptr::null(), ptr::null());
+    let physical_size_mm = (!hdc.is_null())
+        .then(|| {
+            let size = (GetDeviceCaps(hdc, HORZSIZE), GetDeviceCaps(hdc, VERTSIZE));
+            let _ = DeleteDC(hdc);
+            size
+        })
+        .filter(|&(width, height)| width > 0 && height > 0)
+        .map(|(width, height)| (width as u32, height as u32));
+
+    let mut dpi = (USER_DEFAULT_SCREEN_DPI, USER_DEFAULT_SCREEN_DPI);
+    if let Some(get_dpi_for_monitor) = *util::GET_DPI_FOR_MONITOR {
+        let _ = get_dpi_for_monitor(hmonitor, MDT_EFFECTIVE_DPI, &mut dpi.0, &mut dpi.1);
+    }
+
+    // Device names are `\\.\DISPLAYn`, where the number is what stays the same for a monitor
+    // `HMONITOR`s don't, as they're recreated whenever the display configuration changes
+    let id = name
+        .trim_start_matches(|x: char| !x.is_ascii_digit())
+        .parse()
+        .unwrap_or(hmonitor as usize);
+    let rect = &info.rcMonitor;
+    Some(Monitor {
+        id,
+        name,
+        position: PhysicalPosition::new(rect.left, rect.top),
+        size: PhysicalSize::new((rect.right - rect.left) as u32, (rect.bottom - rect.top) as u32),
+        physical_size_mm,
+        refresh_rate_millihertz,
+        scale_factor: f64::from(dpi.0) / f64::from(USER_DEFAULT_SCREEN_DPI),
+        primary: info.dwFlags & MONITORINFOF_PRIMARY != 0,
+        video_modes,
+    })
+}
+
+/// Makes the window fullscreen or not, switching the video mode for exclusive fullscreen.
+unsafe fn set_fullscreen(hwnd: HWND, user_data: &mut WindowImplUserData, fullscreen: Option<Fullscreen>) {
+    restore_video_mode(user_data);
+    let (monitor, mode) = match &fullscreen {
+        Some(Fullscreen::Borderless(Some(monitor))) => (Some(monitor.clone()), None),
+        Some(Fullscreen::Borderless(None)) => (monitor_info(MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST)), None),
+        Some(Fullscreen::Exclusive(monitor, mode)) => (Some(monitor.clone()), Some(mode)),
+        None => (None, None),
+    };
+    match monitor {
+        Some(monitor) => {
+            if user_data.windowed.is_none() {
+                let style = util::get_window_data(hwnd, GWL_STYLE) as DWORD;
+                let mut rect = RECT {
+                    left: 0,
+                    top: 0,
+                    right: 0,
+                    bottom: 0,
+                };
+                let _ = GetWindowRect(hwnd, &mut rect);
+                user_data.windowed = Some((style, rect));
+            }
+
+            // If the mode can't be set, this carries on as borderless
+            let size = match mode {
+                Some(mode) if set_video_mode(user_data, &monitor, mode) => mode.size,
+                _ => monitor.size,
+            };
+            let style = util::get_window_data(hwnd, GWL_STYLE) as DWORD;
+            let _ = util::set_window_data(hwnd, GWL_STYLE, ((style & !WS_OVERLAPPEDWINDOW) | WS_POPUP) as usize);
+            let _ = SetWindowPos(
+                hwnd,
+                ptr::null_mut(),
+                monitor.position.x,
+                monitor.position.y,
+                size.width as c_int,
+                size.height as c_int,
+                SWP_FRAMECHANGED | SWP_NOOWNERZORDER | SWP_NOZORDER,
+            );
+        },
+        None if fullscreen.is_none() => {
+            if let Some((style, rect)) = user_data.windowed.take() {
+                let _ = util::set_window_data(hwnd, GWL_STYLE, style as usize);
+                let _ = SetWindowPos(
+                    hwnd,
+                    ptr::null_mut(),
+                    rect.left,
+                    rect.top,
+                    rect.right - rect.left,
+                    rect.bottom - rect.top,
+                    SWP_FRAMECHANGED | SWP_NOOWNERZORDER | SWP_NOZORDER,
+                );
+            }
+        },
+        None => (),
+    }
+    user_data.fullscreen = fullscreen;
+}
+
+/// Switches a monitor to one of its video modes, until [`restore_video_mode`] is called.
+unsafe fn set_video_mode(user_data: &mut WindowImplUserData, monitor: &Monitor, mode: &VideoMode) -> bool {
+    let mut device = Vec::new();
+    let _ = util::str_to_wstr(&monitor.name, &mut device);
+    let mut devmode: DEVMODEW = mem::zeroed();
+    devmode.dmSize = mem::size_of::<DEVMODEW>() as WORD;
+    devmode.dmFields = DM_BITSPERPEL | DM_PELSWIDTH | DM_PELSHEIGHT | DM_DISPLAYFREQUENCY;
+    devmode.dmBitsPerPel = mode.bit_depth.into();
+    devmode.dmPelsWidth = mode.size.width;
+    devmode.dmPelsHeight = mode.size.height;
+    devmode.dmDisplayFrequency = mode.refresh_rate_millihertz / 1000;
+
+    // `CDS_FULLSCREEN` makes the change temporary, so it's never saved to the registry
+    let result = ChangeDisplaySettingsExW(
+        device.as_ptr(),
+        &mut devmode,
+        ptr::null_mut(),
+        CDS_FULLSCREEN,
+        ptr::null_mut(),
+    );
+    if result == DISP_CHANGE_SUCCESSFUL {
+        user_data.video_mode_device = Some(device);
+        true
+    } else {
+        false
+    }
+}
+
+/// Puts back the video mode that [`set_video_mode`] changed, if it changed one.
+unsafe fn restore_video_mode(user_data: &mut WindowImplUserData) {
+    if let Some(device) = user_data.video_mode_device.take() {
+        // No mode means the one saved in the registry, which is what it was before
+        let _ = ChangeDisplaySettingsExW(device.as_ptr(), ptr::null_mut(), ptr::null_mut(), 0, ptr::null_mut());
+    }
+}
+
+/// Clips the cursor to the client area as [`WindowImpl::set_cursor_grab`] asked for, if the window is focused.
+///
+/// The clip has to follow the window around, so this is done again whenever it moves or changes size.
+/// Windows can't lock the cursor, so it's clipped to the single pixel it's on instead.
+unsafe fn update_cursor_clip(hwnd: HWND, user_data: &mut WindowImplUserData) {
+    let grab = *mutex_lock(&user_data.shared.cursor_grab);
+    let mut client = RECT {
+        left: 0,
+        top: 0,
+        right: 0,
+        bottom: 0,
+    };
+    if grab == CursorGrab::None || GetFocus() != hwnd || GetClientRect(hwnd, &mut client) == FALSE {
+        if user_data.cursor_clipped {
+            user_data.cursor_clipped = false;
+            let _ = ClipCursor(ptr::null());
+        }
+        return
+    }
+
+    // The client rectangle is relative to itself, so its corners are moved to where it is on the screen
+    let mut top_left = POINT {
+        x: client.left,
+        y: client.top,
+    };
+    let mut bottom_right = POINT {
+        x: client.right,
+        y: client.bottom,
+    };
+    let _ = ClientToScreen(hwnd, &mut top_left);
+    let _ = ClientToScreen(hwnd, &mut bottom_right);
+    let mut rect = RECT {
+        left: top_left.x,
+        top: top_left.y,
+        right: bottom_right.x,
+        bottom: bottom_right.y,
+    };
+    if grab == CursorGrab::Locked {
+        let mut cursor = POINT { x: 0, y: 0 };
+        let inside = GetCursorPos(&mut cursor) != FALSE &&
+            (rect.left..rect.right).contains(&cursor.x) &&
+            (rect.top..rect.bottom).contains(&cursor.y);
+        if !inside {
+            cursor.x = rect.left + (rect.right - rect.left) / 2;
+            cursor.y = rect.top + (rect.bottom - rect.top) / 2;
+            let _ = SetCursorPos(cursor.x, cursor.y);
+        }
+        rect = RECT {
+            left: cursor.x,
+            top: cursor.y,
+            right: cursor.x + 1,
+            bottom: cursor.y + 1,
+        };
+    }
+    if ClipCursor(&rect) != FALSE {
+        user_data.cursor_clipped = true;
+    }
+}
+
+/// Makes the cursor asked for with [`WindowImpl::set_cursor`], showing it straight away if it's over the window.
+unsafe fn update_cursor(user_data: &mut WindowImplUserData) {
+    let cursor = mutex_lock(&user_data.shared.cursor).0.clone();
+    let (hcursor, custom) = match &cursor {
+        Cursor::Shape(shape) => (LoadCursorW(ptr::null_mut(), cursor_resource(*shape)), false),
+        Cursor::Custom(custom) => (
+            util::create_icon(&custom.rgba, custom.size, custom.hotspot, false),
+            true,
+        ),
+    };
+    if hcursor.is_null() {
+        return
+    }
+    let old = mem::replace(&mut user_data.cursor, hcursor);
+    if user_data.cursor_inside {
+        show_cursor(user_data);
+    }
+    if mem::replace(&mut user_data.cursor_custom, custom) {
+        let _ = DestroyIcon(old);
+    }
+}
+
+/// Sets the cursor for the thread, which is done every time it moves over the client area (`WM_SETCURSOR`).
+unsafe fn show_cursor(user_data: &WindowImplUserData) {
+    if mutex_lock(&user_data.shared.cursor).1 {
+        let _ = SetCursor(user_data.cursor);
+    } else {
+        let _ = SetCursor(ptr::null_mut());
+    }
+}
+
+/// The system cursor that's closest to a shape.
+fn cursor_resource(shape: CursorShape) -> *const WCHAR {
+    match shape {
+        CursorShape::Arrow => IDC_ARROW,
+        CursorShape::Hand => IDC_HAND,
+        CursorShape::Text => IDC_IBEAM,
+        CursorShape::Crosshair => IDC_CROSS,
+        CursorShape::Wait => IDC_WAIT,
+        CursorShape::Progress => IDC_APPSTARTING,
+        CursorShape::Help => IDC_HELP,
+        CursorShape::NotAllowed => IDC_NO,
+        CursorShape::Move => IDC_SIZEALL,
+        CursorShape::ResizeHorizontal => IDC_SIZEWE,
+        CursorShape::ResizeVertical => IDC_SIZENS,
+        CursorShape::ResizeTopLeftBottomRight => IDC_SIZENWSE,
+        CursorShape::ResizeTopRightBottomLeft => IDC_SIZENESW,
+    }
+}
+
+/// Sets the big and small icons from the images in [`WindowImpl::set_icon`]'s icon that suit their sizes best.
+///
+/// Without one, the icons are unset, so the window goes back to its class's icons (or Windows' default).
+unsafe fn update_icon(hwnd: HWND, user_data: &mut WindowImplUserData) {
+    let icon = mutex_lock(&user_data.shared.icon).clone();
+    for (i, (kind, metric)) in [(ICON_BIG, SM_CXICON), (ICON_SMALL, SM_CXSMICON)]
+        .iter()
+        .copied()
+        .enumerate()
+    {
+        // The smallest image that's at least as big gets scaled down, otherwise the biggest gets scaled up
+        let size = GetSystemMetrics(metric).max(1) as u32;
+        let image = icon.as_ref().and_then(|icon| {
+            let images = icon.images().iter();
+            let smallest_fit = images
+                .clone()
+                .filter(|x| x.size().width >= size)
+                .min_by_key(|x| x.size().width);
+            smallest_fit.or_else(|| images.max_by_key(|x| x.size().width))
+        });
+        let hicon = match image {
+            Some(image) => util::create_icon(image.rgba(), image.size(), (0, 0), true),
+            None => ptr::null_mut(),
+        };
+        let _ = SendMessageW(hwnd, WM_SETICON, kind, hicon as LPARAM);
+        let old = mem::replace(&mut user_data.icons[i], hicon);
+        if !old.is_null() {
+            let _ = DestroyIcon(old);
+        }
+    }
+}
+
+/// Places the IME's composition and candidate windows at the area set with `set_ime_cursor_area`, if any.
+unsafe fn set_ime_cursor_area(hwnd: HWND, user_data: &WindowImplUserData) {
+    let (position, size) = match *mutex_lock(&user_data.shared.ime_cursor_area) {
+        Some(area) => area,
+        None => return,
+    };
+    let himc = ImmGetContext(hwnd);
+    if himc.is_null() {
+        return
+    }
+    let point = POINT {
+        x: position.x,
+        y: position.y,
+    };
+    let rect = RECT {
+        left: position.x,
+        top: position.y,
+        right: position.x.saturating_add(size.width as LONG),
+        bottom: position.y.saturating_add(size.height as LONG),
+    };
+    let mut composition = COMPOSITIONFORM {
+        dwStyle: CFS_POINT,
+        ptCurrentPos: point,
+        rcArea: rect,
+    };
+    let _ = ImmSetCompositionWindow(himc, &mut composition);
+    // Excluding the area keeps the candidate list from covering the text being typed
+    let mut candidate = CANDIDATEFORM {
+        dwIndex: 0,
+        dwStyle: CFS_EXCLUDE,
+        ptCurrentPos: point,
+        rcArea: rect,
+    };
+    let _ = ImmSetCandidateWindow(himc, &mut candidate);
+    let _ = ImmReleaseContext(hwnd, himc);
+}
+
+/// Reads one of the strings of an input context, such as `GCS_COMPSTR`.
+unsafe fn composition_string(himc: HIMC, index: DWORD) -> Vec<WCHAR> {
+    // The lengths are in bytes, and it's not null terminated
+    let size = ImmGetCompositionStringW(himc, index, ptr::null_mut(), 0);
+    let mut buffer: Vec<WCHAR> = vec![0; size.max(0) as usize / mem::size_of::<WCHAR>()];
+    let size = ImmGetCompositionStringW(
+        himc,
+        index,
+        buffer.as_mut_ptr().cast(),
+        (buffer.len() * mem::size_of::<WCHAR>()) as DWORD,
+    );
+    buffer.truncate(size.max(0) as usize / mem::size_of::<WCHAR>());
+    buffer
+}
+
+/// Retrieves the user data of one of our windows, which is null until `WM_NCCREATE`.
+unsafe fn user_data<'a>(hwnd: HWND) -> Option<&'a mut WindowImplUserData> {
+    (util::get_window_data(hwnd, 0) as *mut WindowImplUserData).as_mut()
 }
 
 unsafe extern "system" fn hcbt_destroywnd_hookproc(code: c_int, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
@@ -157,10 +977,9 @@ unsafe extern "system" fn hcbt_destroywnd_hookproc(code: c_int, wparam: WPARAM,
             (util::get_class_data(hwnd, 0) as u32) == RAMEN_WINDOW_MARKER
         {
             // Note that nothing is forwarded here, we decide for ramen's windows
-            if user_data(hwnd).destroy_flag.load(atomic::Ordering::Acquire) {
-                0 // Allow
-            } else {
-                1 // Prevent
+            match user_data(hwnd) {
+                Some(user_data) if !user_data.shared.destroy_flag.load(atomic::Ordering::Acquire) => 1, // Prevent
+                _ => 0,                                                                                 // Allow
             }
         } else {
             // Unrelated window, forward
@@ -173,5 +992,342 @@ unsafe extern "system" fn hcbt_destroywnd_hookproc(code: c_int, wparam: WPARAM,
 }
 
 pub unsafe extern "system" fn window_proc(hwnd: HWND, msg: UINT, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
-    DefWindowProcW(hwnd, msg, wparam, lparam)
+    // Store the user data pointer from `CreateWindowExW` as early as possible
+    // Note that a few messages such as `WM_GETMINMAXINFO` are sent even before this one
+    if msg == WM_NCCREATE {
+        let create_struct = &*(lparam as *const CREATESTRUCTW);
+        let _ = util::set_window_data(hwnd, 0, create_struct.lpCreateParams as usize);
+        return DefWindowProcW(hwnd, msg, wparam, lparam)
+    }
+    let user_data = match user_data(hwnd) {
+        Some(user_data) => user_data,
+        None => return DefWindowProcW(hwnd, msg, wparam, lparam),
+    };
+    let events = &user_data.shared.events;
+
+    // Signed coordinates packed into `lparam`, like `GET_X_LPARAM` and `GET_Y_LPARAM`
+    let lparam_point = || {
+        PhysicalPosition::new(
+            (lparam & 0xFFFF) as SHORT as i32,
+            ((lparam >> 16) & 0xFFFF) as SHORT as i32,
+        )
+    };
+
+    match msg {
+        WM_CLOSE => {
+            // `DefWindowProcW` would call `DestroyWindow`, which isn't up to anyone but the `Window` owner
+            events.push(Event::CloseRequested);
+            0
+        },
+        RAMEN_WM_DESTROY => {
+            let _ = DestroyWindow(hwnd);
+            0
+        },
+        RAMEN_WM_SET_FULLSCREEN => {
+            let fullscreen = mutex_lock(&user_data.shared.fullscreen).clone();
+            set_fullscreen(hwnd, user_data, fullscreen);
+            0
+        },
+        RAMEN_WM_SET_IME_CURSOR_AREA => {
+            set_ime_cursor_area(hwnd, user_data);
+            0
+        },
+        RAMEN_WM_SET_CURSOR_GRAB => {
+            update_cursor_clip(hwnd, user_data);
+            0
+        },
+        RAMEN_WM_SET_CURSOR => {
+            update_cursor(user_data);
+            0
+        },
+        RAMEN_WM_SET_ICON => {
+            update_icon(hwnd, user_data);
+            0
+        },
+        RAMEN_WM_SET_TITLE => {
+            // Already set if this was posted more than once
+            let title = mem::take(&mut *mutex_lock(&user_data.shared.title));
+            if !title.is_empty() {
+                let _ = SetWindowTextW(hwnd, title.as_ptr());
+            }
+            0
+        },
+        WM_SETCURSOR => {
+            // Outside the client area, it's up to Windows (resize arrows on the borders and so on)
+            if (lparam & 0xFFFF) as LRESULT == HTCLIENT {
+                show_cursor(user_data);
+                TRUE as LRESULT
+            } else {
+                DefWindowProcW(hwnd, msg, wparam, lparam)
+            }
+        },
+        WM_DESTROY => {
+            // This frees the drop target, which OLE holds on to
+            let _ = RevokeDragDrop(hwnd);
+            restore_video_mode(user_data);
+            if user_data.cursor_clipped {
+                let _ = ClipCursor(ptr::null());
+            }
+            if user_data.cursor_custom {
+                let _ = DestroyIcon(user_data.cursor);
+            }
+            for icon in user_data.icons.iter().copied().filter(|x| !x.is_null()) {
+                let _ = DestroyIcon(icon);
+            }
+            PostQuitMessage(0);
+            0
+        },
+        WM_MOVE => {
+            // This has the position of the client area, but we report the outer position
+            // Minimized windows are moved far offscreen, which isn't really a move either
+            let mut rect = RECT {
+                left: 0,
+                top: 0,
+                right: 0,
+                bottom: 0,
+            };
+            if IsIconic(hwnd) == FALSE && GetWindowRect(hwnd, &mut rect) != FALSE {
+                events.push(Event::Moved(PhysicalPosition::new(rect.left, rect.top)));
+            }
+            update_cursor_clip(hwnd, user_data);
+            0
+        },
+        WM_SIZE => {
+            // Minimizing sets the client area to 0x0, which isn't really a resize
+            if wparam != SIZE_MINIMIZED {
+                events.push(Event::Resized(PhysicalSize::new(
+                    (lparam & 0xFFFF) as u32,
+                    ((lparam >> 16) & 0xFFFF) as u32,
+                )));
+            }
+            update_cursor_clip(hwnd, user_data);
+            0
+        },
+        WM_SETFOCUS | WM_KILLFOCUS => {
+            // Exclusive fullscreen only keeps its video mode while the window is focused,
+            // and it's minimized otherwise so it's not left covering part of the desktop
+            if let Some(Fullscreen::Exclusive(..)) = user_data.fullscreen {
+                if msg == WM_SETFOCUS && user_data.video_mode_device.is_none() {
+                    set_fullscreen(hwnd, user_data, user_data.fullscreen.clone());
+                } else if msg == WM_KILLFOCUS && user_data.video_mode_device.is_some() {
+                    restore_video_mode(user_data);
+                    let _ = ShowWindow(hwnd, SW_MINIMIZE);
+                }
+            }
+            // `GetFocus` is already updated, so this clips or unclips the cursor as appropriate
+            update_cursor_clip(hwnd, user_data);
+            if msg == WM_KILLFOCUS {
+                user_data.held_keys.release_all(&user_data.shared.events);
+            }
+            user_data.shared.events.push(Event::Focus(msg == WM_SETFOCUS));
+            0
+        },
+        WM_KEYDOWN | WM_KEYUP | WM_SYSKEYDOWN | WM_SYSKEYUP => {
+            // Bits 16-23 are the scancode, bit 24 is set for the "extended" keys
+            let scancode = ((lparam >> 16) & 0x1FF) as u32;
+            let pressed = msg == WM_KEYDOWN || msg == WM_SYSKEYDOWN;
+            let (key_code, logical_key) = (keyboard::key_code(scancode), keyboard::logical_key(wparam));
+            let repeat = pressed && user_data.held_keys.update(scancode, key_code, logical_key, pressed);
+            events.push(Event::Key {
+                scancode,
+                key_code,
+                logical_key,
+                pressed,
+                repeat,
+                modifiers: keyboard::modifiers(),
+            });
+
+            // The system keys (Alt & F10 combinations) control things like the window menu and Alt+F4
+            if msg == WM_SYSKEYDOWN || msg == WM_SYSKEYUP {
+                DefWindowProcW(hwnd, msg, wparam, lparam)
+            } else {
+                0
+            }
+        },
+        WM_CHAR => {
+            let unit = wparam as WCHAR;
+            let decoded = match unit {
+                0xD800..=0xDBFF => {
+                    user_data.high_surrogate = Some(unit);
+                    None
+                },
+                0xDC00..=0xDFFF => user_data
+                    .high_surrogate
+                    .take()
+                    .and_then(|high| char::decode_utf16([high, unit].iter().copied()).next()?.ok()),
+                _ => char::from_u32(unit.into()),
+            };
+            if let Some(ch) = decoded.filter(|x| !x.is_control()) {
+                events.push(Event::Text(ch.to_string()));
+            }
+            0
+        },
+        WM_IME_SETCONTEXT => {
+            // The pre-edit text is drawn by the user from `Event::Preedit`, so the IME shouldn't draw it too
+            let lparam = if wparam != 0 {
+                lparam & !ISC_SHOWUICOMPOSITIONWINDOW
+            } else {
+                lparam
+            };
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        },
+        WM_IME_STARTCOMPOSITION => {
+            // Not passed on, as that would open the IME's own composition window
+            set_ime_cursor_area(hwnd, user_data);
+            0
+        },
+        WM_IME_COMPOSITION => {
+            // Not passed on either, as that would send the committed text again as `WM_IME_CHAR` and `WM_CHAR`
+            let himc = ImmGetContext(hwnd);
+            if himc.is_null() {
+                return 0
+            }
+            let flags = lparam as DWORD;
+            if flags & GCS_RESULTSTR != 0 {
+                if user_data.preedit_shown {
+                    user_data.preedit_shown = false;
+                    events.push(Event::Preedit {
+                        text: String::new(),
+                        cursor: None,
+                    });
+                }
+                let text: String = String::from_utf16_lossy(&composition_string(himc, GCS_RESULTSTR))
+                    .chars()
+                    .filter(|x| !x.is_control())
+                    .collect();
+                if !text.is_empty() {
+                    events.push(Event::Text(text));
+                }
+            }
+            if flags & GCS_COMPSTR != 0 {
+                let text = composition_string(himc, GCS_COMPSTR);
+                // The cursor position is in UTF-16 code units, which are counted up into UTF-8 bytes
+                let cursor = if flags & GCS_CURSORPOS != 0 {
+                    let position = ImmGetCompositionStringW(himc, GCS_CURSORPOS, ptr::null_mut(), 0).max(0) as usize;
+                    let bytes = char::decode_utf16(text[..position.min(text.len())].iter().copied())
+                        .map(|x| x.map_or(3, char::len_utf8))
+                        .sum();
+                    Some((bytes, bytes))
+                } else {
+                    None
+                };
+                let text = String::from_utf16_lossy(&text);
+                if !text.is_empty() || user_data.preedit_shown {
+                    user_data.preedit_shown = !text.is_empty();
+                    events.push(Event::Preedit { text, cursor });
+                }
+            }
+            let _ = ImmReleaseContext(hwnd, himc);
+            0
+        },
+        WM_IME_ENDCOMPOSITION => {
+            if user_data.preedit_shown {
+                user_data.preedit_shown = false;
+                events.push(Event::Preedit {
+                    text: String::new(),
+                    cursor: None,
+                });
+            }
+            0
+        },
+        WM_MOUSEMOVE => {
+            if !user_data.cursor_inside {
+                let mut track = TRACKMOUSEEVENT {
+                    cbSize: mem::size_of::<TRACKMOUSEEVENT>() as DWORD,
+                    dwFlags: TME_LEAVE,
+                    hwndTrack: hwnd,
+                    dwHoverTime: 0,
+                };
+                if TrackMouseEvent(&mut track) != FALSE {
+                    user_data.cursor_inside = true;
+                    events.push(Event::CursorEntered);
+                }
+            }
+            events.push(Event::CursorMoved(lparam_point()));
+            0
+        },
+        WM_INPUT => {
+            let mut input: RAWINPUT = mem::zeroed();
+            let mut size = mem::size_of::<RAWINPUT>() as UINT;
+            let read = GetRawInputData(
+                lparam as HRAWINPUT,
+                RID_INPUT,
+                (&mut input as *mut RAWINPUT).cast(),
+                &mut size,
+                mem::size_of::<RAWINPUTHEADER>() as UINT,
+            );
+            // Tablets and remote desktop report absolute positions, which aren't motion
+            let is_relative_mouse = read != UINT::MAX &&
+                read as usize >= mem::size_of::<RAWINPUTHEADER>() &&
+                input.header.dwType == RIM_TYPEMOUSE &&
+                input.mouse.usFlags & MOUSE_MOVE_ABSOLUTE == 0;
+            let (x, y) = (input.mouse.lLastX, input.mouse.lLastY);
+            if is_relative_mouse && (x, y) != (0, 0) {
+                events.push(Event::RawMouseMotion(x.into(), y.into()));
+            }
+            // `DefWindowProcW` does the cleanup for the input
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        },
+        WM_MOUSELEAVE => {
+            user_data.cursor_inside = false;
+            events.push(Event::CursorLeft);
+            0
+        },
+        WM_LBUTTONDOWN | WM_LBUTTONUP | WM_RBUTTONDOWN | WM_RBUTTONUP | WM_MBUTTONDOWN | WM_MBUTTONUP |
+        WM_XBUTTONDOWN | WM_XBUTTONUP => {
+            let button = match msg {
+                WM_LBUTTONDOWN | WM_LBUTTONUP => Some(MouseButton::Left),
+                WM_RBUTTONDOWN | WM_RBUTTONUP => Some(MouseButton::Right),
+                WM_MBUTTONDOWN | WM_MBUTTONUP => Some(MouseButton::Middle),
+                // The high word of `wparam` is `XBUTTON1` or `XBUTTON2`, they're mouse buttons 4 & 5
+                _ => match ((wparam >> 16) & 0xFFFF) as WORD {
+                    XBUTTON1 => Some(MouseButton::Other(4)),
+                    XBUTTON2 => Some(MouseButton::Other(5)),
+                    _ => None,
+                },
+            };
+            let pressed = matches!(msg, WM_LBUTTONDOWN | WM_RBUTTONDOWN | WM_MBUTTONDOWN | WM_XBUTTONDOWN);
+            if let Some(button) = button {
+                events.push(Event::MouseButton { button, pressed });
+            }
+            // Returning `TRUE` is what's documented for the X buttons specifically
+            (msg == WM_XBUTTONDOWN || msg == WM_XBUTTONUP) as LRESULT
+        },
+        WM_MOUSEWHEEL | WM_MOUSEHWHEEL => {
+            // The high word of `wparam` is the distance in multiples (or fractions) of `WHEEL_DELTA`
+            let delta = f32::from(((wparam >> 16) & 0xFFFF) as SHORT) / f32::from(WHEEL_DELTA);
+            events.push(Event::MouseWheel(if msg == WM_MOUSEWHEEL {
+                ScrollDelta::Lines(0.0, delta)
+            } else {
+                ScrollDelta::Lines(delta, 0.0)
+            }));
+            0
+        },
+        WM_DISPLAYCHANGE => {
+            // Sent to every top-level window when monitors are added, removed, or change resolution
+            monitor::update_monitors(&mut user_data.monitors, enumerate_monitors(), events);
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        },
+        WM_DPICHANGED => {
+            // The low and high word are the X and Y DPI, which are always the same
+            // `lparam` points to a suggested window rectangle that keeps the logical size the same
+            let dpi = UINT::from((wparam & 0xFFFF) as WORD);
+            let rect = &*(lparam as *const RECT);
+            let (width, height) = (rect.right - rect.left, rect.bottom - rect.top);
+            let style = util::get_window_data(hwnd, GWL_STYLE) as DWORD;
+            let style_ex = util::get_window_data(hwnd, GWL_EXSTYLE) as DWORD;
+            let (frame_width, frame_height) = util::adjust_window_size(PhysicalSize::new(0, 0), style, style_ex, dpi);
+            events.push(Event::ScaleFactorChanged {
+                scale_factor: f64::from(dpi) / f64::from(USER_DEFAULT_SCREEN_DPI),
+                suggested_size: PhysicalSize::new(
+                    (width - frame_width).max(0) as u32,
+                    (height - frame_height).max(0) as u32,
+                ),
+            });
+            let flags = SWP_NOZORDER | SWP_NOACTIVATE;
+            let _ = SetWindowPos(hwnd, ptr::null_mut(), rect.left, rect.top, width, height, flags);
+            0
+        },
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
 }
@@ -0,0 +1,269 @@
+//! Translating Win32 scancodes and virtual keys.
+
+use crate::{
+    keyboard::{Key, KeyCode, Modifiers, NamedKey},
+    platform::win32::ffi::*,
+};
+
+/// Looks up the physical key for a scancode from `WM_KEYDOWN` and friends,
+/// which has `0x100` set for the "extended" keys that are sent with an `0xE0` prefix.
+pub fn key_code(scancode: u32) -> KeyCode {
+    SCANCODES
+        .iter()
+        .find(|&&(x, _)| u32::from(x) == scancode)
+        .map_or(KeyCode::Unidentified, |&(_, key_code)| key_code)
+}
+
+/// Looks up what a virtual key is in the current layout, without any modifiers.
+pub unsafe fn logical_key(virtual_key: WPARAM) -> Key {
+    if let Some(&(_, named)) = VIRTUAL_KEYS.iter().find(|&&(x, _)| WPARAM::from(x) == virtual_key) {
+        return Key::Named(named)
+    }
+
+    // This gives the unshifted character, except letters which are uppercase, and sets the top bit for dead keys
+    let mapped = MapVirtualKeyW(virtual_key as UINT, MAPVK_VK_TO_CHAR);
+    if mapped & 0x8000_0000 != 0 {
+        return Key::Dead
+    }
+    match char::from_u32(mapped) {
+        Some(ch) if mapped != 0 && !ch.is_control() => Key::Character(ch.to_lowercase().next().unwrap_or(ch)),
+        _ => Key::Unidentified,
+    }
+}
+
+/// Reads the modifiers as of the message being processed.
+pub unsafe fn modifiers() -> Modifiers {
+    // The top bit is whether the key is held down, the bottom bit whether it's toggled on
+    let down = |virtual_key| GetKeyState(virtual_key) < 0;
+    Modifiers {
+        shift: down(VK_SHIFT),
+        control: down(VK_CONTROL),
+        alt: down(VK_MENU),
+        logo: down(VK_LWIN) || down(VK_RWIN),
+        caps_lock: GetKeyState(VK_CAPITAL) & 1 != 0,
+        num_lock: GetKeyState(VK_NUMLOCK) & 1 != 0,
+    }
+}
+
+/// Scancode set 1, which is what Windows uses regardless of the keyboard, by physical key.
+const SCANCODES: &[(u16, KeyCode)] = {
+    use KeyCode::*;
+    &[
+        (0x001, Escape),
+        (0x002, Digit1),
+        (0x003, Digit2),
+        (0x004, Digit3),
+        (0x005, Digit4),
+        (0x006, Digit5),
+        (0x007, Digit6),
+        (0x008, Digit7),
+        (0x009, Digit8),
+        (0x00A, Digit9),
+        (0x00B, Digit0),
+        (0x00C, Minus),
+        (0x00D, Equal),
+        (0x00E, Backspace),
+        (0x00F, Tab),
+        (0x010, KeyQ),
+        (0x011, KeyW),
+        (0x012, KeyE),
+        (0x013, KeyR),
+        (0x014, KeyT),
+        (0x015, KeyY),
+        (0x016, KeyU),
+        (0x017, KeyI),
+        (0x018, KeyO),
+        (0x019, KeyP),
+        (0x01A, BracketLeft),
+        (0x01B, BracketRight),
+        (0x01C, Enter),
+        (0x01D, ControlLeft),
+        (0x01E, KeyA),
+        (0x01F, KeyS),
+        (0x020, KeyD),
+        (0x021, KeyF),
+        (0x022, KeyG),
+        (0x023, KeyH),
+        (0x024, KeyJ),
+        (0x025, KeyK),
+        (0x026, KeyL),
+        (0x027, Semicolon),
+        (0x028, Quote),
+        (0x029, Backquote),
+        (0x02A, ShiftLeft),
+        (0x02B, Backslash),
+        (0x02C, KeyZ),
+        (0x02D, KeyX),
+        (0x02E, KeyC),
+        (0x02F, KeyV),
+        (0x030, KeyB),
+        (0x031, KeyN),
+        (0x032, KeyM),
+        (0x033, Comma),
+        (0x034, Period),
+        (0x035, Slash),
+        (0x036, ShiftRight),
+        (0x037, NumpadMultiply),
+        (0x038, AltLeft),
+        (0x039, Space),
+        (0x03A, CapsLock),
+        (0x03B, F1),
+        (0x03C, F2),
+        (0x03D, F3),
+        (0x03E, F4),
+        (0x03F, F5),
+        (0x040, F6),
+        (0x041, F7),
+        (0x042, F8),
+        (0x043, F9),
+        (0x044, F10),
+        (0x045, Pause), // Sent with an `0xE1` prefix, which isn't reported
+        (0x046, ScrollLock),
+        (0x047, Numpad7),
+        (0x048, Numpad8),
+        (0x049, Numpad9),
+        (0x04A, NumpadSubtract),
+        (0x04B, Numpad4),
+        (0x04C, Numpad5),
+        (0x04D, Numpad6),
+        (0x04E, NumpadAdd),
+        (0x04F, Numpad1),
+        (0x050, Numpad2),
+        (0x051, Numpad3),
+        (0x052, Numpad0),
+        (0x053, NumpadDecimal),
+        (0x056, IntlBackslash),
+        (0x057, F11),
+        (0x058, F12),
+        (0x059, NumpadEqual),
+        (0x064, F13),
+        (0x065, F14),
+        (0x066, F15),
+        (0x067, F16),
+        (0x068, F17),
+        (0x069, F18),
+        (0x06A, F19),
+        (0x06B, F20),
+        (0x06C, F21),
+        (0x06D, F22),
+        (0x06E, F23),
+        (0x070, KanaMode),
+        (0x073, IntlRo),
+        (0x076, F24),
+        (0x079, Convert),
+        (0x07B, NonConvert),
+        (0x07D, IntlYen),
+        (0x07E, NumpadComma),
+        (0x0F1, Lang2),
+        (0x0F2, Lang1),
+        (0x11C, NumpadEnter),
+        (0x11D, ControlRight),
+        (0x120, AudioVolumeMute),
+        (0x12E, AudioVolumeDown),
+        (0x130, AudioVolumeUp),
+        (0x135, NumpadDivide),
+        (0x137, PrintScreen),
+        (0x138, AltRight),
+        (0x145, NumLock),
+        (0x147, Home),
+        (0x148, ArrowUp),
+        (0x149, PageUp),
+        (0x14B, ArrowLeft),
+        (0x14D, ArrowRight),
+        (0x14F, End),
+        (0x150, ArrowDown),
+        (0x151, PageDown),
+        (0x152, Insert),
+        (0x153, Delete),
+        (0x15B, SuperLeft),
+        (0x15C, SuperRight),
+        (0x15D, ContextMenu),
+        (0x15E, Power),
+    ]
+};
+
+/// Virtual keys that don't type anything, by the key they name.
+const VIRTUAL_KEYS: &[(u8, NamedKey)] = {
+    use NamedKey::*;
+    &[
+        (0x08, Backspace),
+        (0x09, Tab),
+        (0x0D, Enter),
+        (0x10, Shift),
+        (0x11, Control),
+        (0x12, Alt),
+        (0x13, Pause),
+        (0x14, CapsLock),
+        (0x1B, Escape),
+        (0x21, PageUp),
+        (0x22, PageDown),
+        (0x23, End),
+        (0x24, Home),
+        (0x25, ArrowLeft),
+        (0x26, ArrowUp),
+        (0x27, ArrowRight),
+        (0x28, ArrowDown),
+        (0x2C, PrintScreen),
+        (0x2D, Insert),
+        (0x2E, Delete),
+        (0x5B, Super),
+        (0x5C, Super),
+        (0x5D, ContextMenu),
+        (0x70, F1),
+        (0x71, F2),
+        (0x72, F3),
+        (0x73, F4),
+        (0x74, F5),
+        (0x75, F6),
+        (0x76, F7),
+        (0x77, F8),
+        (0x78, F9),
+        (0x79, F10),
+        (0x7A, F11),
+        (0x7B, F12),
+        (0x7C, F13),
+        (0x7D, F14),
+        (0x7E, F15),
+        (0x7F, F16),
+        (0x80, F17),
+        (0x81, F18),
+        (0x82, F19),
+        (0x83, F20),
+        (0x84, F21),
+        (0x85, F22),
+        (0x86, F23),
+        (0x87, F24),
+        (0x90, NumLock),
+        (0x91, ScrollLock),
+        (0xAD, AudioVolumeMute),
+        (0xAE, AudioVolumeDown),
+        (0xAF, AudioVolumeUp),
+    ]
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn tables_have_no_duplicates() {
+        let scancodes: HashSet<u16> = SCANCODES.iter().map(|&(x, _)| x).collect();
+        assert_eq!(scancodes.len(), SCANCODES.len());
+        let key_codes: HashSet<KeyCode> = SCANCODES.iter().map(|&(_, x)| x).collect();
+        assert_eq!(key_codes.len(), SCANCODES.len());
+        let virtual_keys: HashSet<u8> = VIRTUAL_KEYS.iter().map(|&(x, _)| x).collect();
+        assert_eq!(virtual_keys.len(), VIRTUAL_KEYS.len());
+    }
+
+    #[test]
+    fn scancodes_become_key_codes() {
+        assert_eq!(key_code(0x01E), KeyCode::KeyA);
+        assert_eq!(key_code(0x011), KeyCode::KeyW);
+        assert_eq!(key_code(0x01C), KeyCode::Enter);
+        assert_eq!(key_code(0x11C), KeyCode::NumpadEnter);
+        assert_eq!(key_code(0x048), KeyCode::Numpad8);
+        assert_eq!(key_code(0x148), KeyCode::ArrowUp);
+        assert_eq!(key_code(0x1FF), KeyCode::Unidentified);
+    }
+}
@@ -1,8 +1,73 @@
 //! Utility functions for interacting with the Win32 API.
 
 // TODO deglob
-use crate::platform::win32::ffi::*;
-use std::{mem, ptr};
+use crate::{
+    dpi::PhysicalSize,
+    error::{Error, ErrorKind},
+    platform::win32::ffi::*,
+    sync::LazyCell,
+};
+use std::{mem, ptr, slice};
+
+type AdjustWindowRectExForDpi = unsafe extern "system" fn(*mut RECT, DWORD, BOOL, DWORD, UINT) -> BOOL;
+type GetDpiForWindow = unsafe extern "system" fn(HWND) -> UINT;
+type SetThreadDpiAwarenessContext = unsafe extern "system" fn(DPI_AWARENESS_CONTEXT) -> DPI_AWARENESS_CONTEXT;
+type GetDpiForMonitor = unsafe extern "system" fn(HMONITOR, c_int, *mut UINT, *mut UINT) -> HRESULT;
+
+/// Functions from `user32.dll` that are too new to link to, looked up at runtime instead.
+/// All of them are from Windows 10 1607 and up.
+pub struct User32 {
+    pub adjust_window_rect_ex_for_dpi: Option<AdjustWindowRectExForDpi>,
+    pub get_dpi_for_window: Option<GetDpiForWindow>,
+    pub set_thread_dpi_awareness_context: Option<SetThreadDpiAwarenessContext>,
+}
+
+pub static USER32: LazyCell<User32> = LazyCell::new(load_user32);
+
+/// `GetDpiForMonitor` from `shcore.dll`, which is from Windows 8.1 and up.
+pub static GET_DPI_FOR_MONITOR: LazyCell<Option<GetDpiForMonitor>> = LazyCell::new(load_get_dpi_for_monitor);
+
+fn load_user32() -> User32 {
+    unsafe {
+        let mut name = Vec::new();
+        let module = GetModuleHandleW(str_to_wstr("user32.dll", &mut name));
+        let get = |symbol: &'static [u8]| -> *mut c_void {
+            if module.is_null() {
+                ptr::null_mut()
+            } else {
+                GetProcAddress(module, symbol.as_ptr().cast())
+            }
+        };
+
+        // A null pointer is `None`, the rest are function pointers with the right signature
+        User32 {
+            adjust_window_rect_ex_for_dpi: mem::transmute::<*mut c_void, Option<AdjustWindowRectExForDpi>>(get(
+                b"AdjustWindowRectExForDpi\0",
+            )),
+            get_dpi_for_window: mem::transmute::<*mut c_void, Option<GetDpiForWindow>>(get(b"GetDpiForWindow\0")),
+            set_thread_dpi_awareness_context: mem::transmute::<*mut c_void, Option<SetThreadDpiAwarenessContext>>(get(
+                b"SetThreadDpiAwarenessContext\0",
+            )),
+        }
+    }
+}
+
+fn load_get_dpi_for_monitor() -> Option<GetDpiForMonitor> {
+    unsafe {
+        // Unlike `user32.dll`, nothing guarantees this is loaded already
+        let mut name = Vec::new();
+        let module = LoadLibraryExW(
+            str_to_wstr("shcore.dll", &mut name),
+            ptr::null_mut(),
+            LOAD_LIBRARY_SEARCH_SYSTEM32,
+        );
+        if module.is_null() {
+            return None
+        }
+        let symbol = GetProcAddress(module, b"GetDpiForMonitor\0".as_ptr().cast());
+        mem::transmute::<*mut c_void, Option<GetDpiForMonitor>>(symbol)
+    }
+}
 
 /// Retrieves the base module [`HINSTANCE`].
 #[inline]
@@ -61,6 +126,102 @@ pub unsafe fn set_window_data(hwnd: HWND, offset: c_int, data: usize) -> usize {
     SetWindowLongPtrW(hwnd, offset, data as LONG_PTR) as usize
 }
 
+/// Works out the size of a window including decorations, from its client area size, styles and DPI.
+pub unsafe fn adjust_window_size(inner_size: PhysicalSize, style: DWORD, style_ex: DWORD, dpi: UINT) -> (c_int, c_int) {
+    let mut rect = RECT {
+        left: 0,
+        top: 0,
+        right: inner_size.width as LONG,
+        bottom: inner_size.height as LONG,
+    };
+    let _ = match USER32.adjust_window_rect_ex_for_dpi {
+        Some(adjust_window_rect_ex_for_dpi) => adjust_window_rect_ex_for_dpi(&mut rect, style, FALSE, style_ex, dpi),
+        None => AdjustWindowRectEx(&mut rect, style, FALSE, style_ex),
+    };
+    (rect.right - rect.left, rect.bottom - rect.top)
+}
+
+/// The DPI of a window, which is always 96 where Windows doesn't support per-monitor DPI awareness.
+pub unsafe fn window_dpi(hwnd: HWND) -> UINT {
+    match USER32.get_dpi_for_window {
+        Some(get_dpi_for_window) => get_dpi_for_window(hwnd),
+        None => USER_DEFAULT_SCREEN_DPI,
+    }
+}
+
+/// Makes an icon or cursor from 8-bit RGBA pixels without premultiplied alpha, or null if it couldn't be made.
+///
+/// The hotspot is ignored for icons. Either must be freed with `DestroyIcon`.
+pub unsafe fn create_icon(rgba: &[u8], size: PhysicalSize, hotspot: (u32, u32), is_icon: bool) -> HICON {
+    // A negative height makes the bitmap top-down, like the pixels
+    let mut info: BITMAPINFO = mem::zeroed();
+    info.bmiHeader.biSize = mem::size_of::<BITMAPINFOHEADER>() as DWORD;
+    info.bmiHeader.biWidth = size.width as LONG;
+    info.bmiHeader.biHeight = -(size.height as LONG);
+    info.bmiHeader.biPlanes = 1;
+    info.bmiHeader.biBitCount = 32;
+    info.bmiHeader.biCompression = BI_RGB;
+    let mut bits = ptr::null_mut();
+    let color = CreateDIBSection(ptr::null_mut(), &info, DIB_RGB_COLORS, &mut bits, ptr::null_mut(), 0);
+    if color.is_null() {
+        return ptr::null_mut()
+    }
+    // 32-bit DIBs are BGRA in memory
+    let pixels = slice::from_raw_parts_mut(bits.cast::<u8>(), rgba.len());
+    for (dest, src) in pixels.chunks_exact_mut(4).zip(rgba.chunks_exact(4)) {
+        dest.copy_from_slice(&[src[2], src[1], src[0], src[3]]);
+    }
+
+    // The mask still has to be there even though the alpha channel is used instead
+    let mask = CreateBitmap(size.width as c_int, size.height as c_int, 1, 1, ptr::null());
+    if mask.is_null() {
+        let _ = DeleteObject(color.cast());
+        return ptr::null_mut()
+    }
+    let mut icon_info = ICONINFO {
+        fIcon: is_icon.into(),
+        xHotspot: hotspot.0,
+        yHotspot: hotspot.1,
+        hbmMask: mask,
+        hbmColor: color,
+    };
+    // The icon gets copies of the bitmaps
+    let icon = CreateIconIndirect(&mut icon_info);
+    let _ = DeleteObject(mask.cast());
+    let _ = DeleteObject(color.cast());
+    icon
+}
+
+/// Creates an [`Error`] from the thread's last error code (`GetLastError`), with a description from the system.
+pub fn last_error(kind: ErrorKind, message: &str) -> Error {
+    unsafe {
+        let code = GetLastError();
+        let kind = match code {
+            ERROR_NOT_ENOUGH_MEMORY | ERROR_OUTOFMEMORY => ErrorKind::OutOfMemory,
+            _ => kind,
+        };
+
+        // A language ID of 0 picks the most appropriate language for the user
+        let mut buffer = [0 as WCHAR; 512];
+        let length = FormatMessageW(
+            FORMAT_MESSAGE_FROM_SYSTEM | FORMAT_MESSAGE_IGNORE_INSERTS,
+            ptr::null(),
+            code,
+            0,
+            buffer.as_mut_ptr(),
+            buffer.len() as DWORD,
+            ptr::null_mut(),
+        ) as usize;
+        let description = String::from_utf16_lossy(&buffer[..length]);
+        let description = description.trim_end(); // these end in "\r\n"
+        if description.is_empty() {
+            Error::with_code(kind, code as i32, message.to_owned())
+        } else {
+            Error::with_code(kind, code as i32, format!("{}: {}", message, description))
+        }
+    }
+}
+
 /// Converts a `&str` to an `LPCWSTR` compatible wide string.
 ///
 /// If the length is 0 (aka `*ret == 0x00`) then no allocation was made (it points to a static empty string).
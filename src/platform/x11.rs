@@ -0,0 +1,16 @@
+//! X11-specific definitions and API extensions.
+
+mod clipboard;
+mod cursor;
+mod dnd;
+mod ffi;
+mod ime;
+mod imp;
+mod util;
+mod xinput;
+
+// public re-exports
+pub use self::ffi::{Display, Window};
+
+// platform `imp` glue
+pub(crate) use imp::WindowImpl;
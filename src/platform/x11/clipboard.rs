@@ -0,0 +1,599 @@
+//! The clipboard and primary selection, which X11 calls selections.
+//!
+//! Whoever owns a selection hands it over to whoever asks, by writing it to a property on their window in the
+//! format (target) they asked for, then telling them with a `SelectionNotify`. Anything bigger than a request can
+//! carry is sent in chunks, which ICCCM calls `INCR`: each chunk is written once the last one was read and deleted,
+//! and an empty one marks the end.
+//!
+//! What's dropped through XDND is handed over the same way, as the `XdndSelection` selection.
+
+use crate::{
+    clipboard::{self, Selection, TEXT_MIME, TRANSFER_TIMEOUT},
+    error::{Error, ErrorKind},
+    platform::{
+        posix::str_to_cstring,
+        x11::{ffi::*, util},
+    },
+    sync::Sender,
+};
+use std::{collections::VecDeque, mem, rc::Rc, slice, time::Instant};
+
+/// A question about what's on a selection, answered once whoever owns it has handed it over.
+pub enum Request {
+    Formats(Selection, Sender<Result<Vec<String>, Error>>),
+    Get(Selection, String, Sender<Result<Option<Vec<u8>>, Error>>),
+
+    /// What's being dropped on the window, as a target, which is answered through [`Clipboard::take_dropped`].
+    Drop(Atom),
+}
+
+/// Everything to do with selections, for one window.
+pub struct Clipboard {
+    display: *mut Display,
+    window: Window,
+
+    // The largest chunk of data that fits in one request, with room to spare for the request itself
+    max_chunk: usize,
+
+    clipboard: Atom,
+    incr: Atom,
+    property: Atom,
+    targets: Atom,
+    timestamp: Atom,
+    utf8_string: Atom,
+    xdnd_selection: Atom,
+
+    // The server's time as of the last event that had it, which selections are changed and asked for at
+    time: Time,
+
+    // What's on each selection, if it came from us, and then what's being dragged out of the window
+    owned: [Option<Owned>; 3],
+
+    // Chunked transfers to other apps
+    outgoing: Vec<Outgoing>,
+
+    // Requests waiting their turn, as there's only the one property for answers to go in
+    requests: VecDeque<Request>,
+    incoming: Option<Incoming>,
+
+    // What was dropped, once it's been handed over (or not)
+    dropped: Option<Option<Vec<u8>>>,
+}
+
+/// Where what's being dragged out of the window goes in `owned`, after the selections that are public.
+const XDND_INDEX: usize = 2;
+
+/// The contents of a selection that's owned by us.
+struct Owned {
+    time: Time,
+    formats: Vec<(String, Rc<[u8]>)>,
+    targets: Vec<(Atom, Rc<[u8]>)>,
+}
+
+/// Data being sent to another app in chunks.
+struct Outgoing {
+    requestor: Window,
+    property: Atom,
+    target: Atom,
+    data: Rc<[u8]>,
+    offset: usize,
+    deadline: Instant,
+}
+
+/// A request that's waiting on the owner of a selection, with the other targets to try if it's refused.
+struct Incoming {
+    request: Request,
+    targets: Vec<Atom>,
+    chunks: Option<Vec<u8>>,
+    deadline: Instant,
+}
+
+impl Clipboard {
+    pub unsafe fn new(display: *mut Display, window: Window) -> Self {
+        // The size is in 4-byte units, and a `ChangeProperty` request has a 24 byte header
+        let max_chunk = (XMaxRequestSize(display).max(0) as usize * 4)
+            .saturating_sub(256)
+            .max(4096);
+        Self {
+            display,
+            window,
+            max_chunk,
+            clipboard: util::intern_atom(display, b"CLIPBOARD\0"),
+            incr: util::intern_atom(display, b"INCR\0"),
+            property: util::intern_atom(display, b"RAMEN_SELECTION\0"),
+            targets: util::intern_atom(display, b"TARGETS\0"),
+            timestamp: util::intern_atom(display, b"TIMESTAMP\0"),
+            utf8_string: util::intern_atom(display, b"UTF8_STRING\0"),
+            xdnd_selection: util::intern_atom(display, b"XdndSelection\0"),
+            time: CurrentTime,
+            owned: [None, None, None],
+            outgoing: Vec::new(),
+            requests: VecDeque::new(),
+            incoming: None,
+            dropped: None,
+        }
+    }
+
+    /// Keeps track of the server's time, from an event that was timestamped with it.
+    pub fn update_time(&mut self, time: Time) {
+        self.time = time;
+    }
+
+    /// The server's time as of the last event that had it.
+    pub fn time(&self) -> Time {
+        self.time
+    }
+
+    /// When the next transfer gives up, if any are going.
+    pub fn deadline(&self) -> Option<Instant> {
+        let outgoing = self.outgoing.iter().map(|x| x.deadline);
+        outgoing.chain(self.incoming.as_ref().map(|x| x.deadline)).min()
+    }
+
+    /// Gives up on any transfers that stalled, answering a request that was waiting on one with an error.
+    pub unsafe fn expire(&mut self) {
+        let now = Instant::now();
+        self.outgoing.retain(|x| x.deadline > now);
+        if self.incoming.as_ref().is_some_and(|x| x.deadline <= now) {
+            if let Some(incoming) = self.incoming.take() {
+                let error = Error::new(ErrorKind::TimedOut, "timed out waiting for the clipboard's owner");
+                self.fail(incoming.request, error);
+            }
+            self.next_request();
+        }
+    }
+
+    /// Puts data on a selection, taking ownership of it, or gives it up if there's nothing to put there.
+    pub unsafe fn set(&mut self, selection: Selection, formats: Vec<(String, Vec<u8>)>) -> Result<(), Error> {
+        let atom = self.selection_atom(selection);
+        if formats.is_empty() {
+            if self.owned[selection as usize].take().is_some() {
+                let _ = XSetSelectionOwner(self.display, atom, 0, self.time);
+            }
+            return Ok(())
+        }
+        self.own(atom, formats).map(|_| ())
+    }
+
+    /// Offers what's being dragged out of the window as `XdndSelection`, returning the targets it's offered as.
+    pub unsafe fn set_dragged(&mut self, formats: Vec<(String, Vec<u8>)>) -> Result<Vec<Atom>, Error> {
+        self.own(self.xdnd_selection, formats)
+    }
+
+    /// Takes ownership of a selection to put data on it, returning the targets it's offered as.
+    unsafe fn own(&mut self, atom: Atom, formats: Vec<(String, Vec<u8>)>) -> Result<Vec<Atom>, Error> {
+        let formats: Vec<(String, Rc<[u8]>)> = formats.into_iter().map(|(mime, data)| (mime, data.into())).collect();
+        let mut offered = formats.clone();
+        if let Some((_, text)) = formats.iter().find(|x| x.0 == TEXT_MIME) {
+            for alias in clipboard::text_aliases(text) {
+                if !offered.iter().any(|x| x.0 == *alias) {
+                    offered.push(((*alias).to_owned(), Rc::clone(text)));
+                }
+            }
+        }
+        let targets: Vec<(Atom, Rc<[u8]>)> = offered
+            .into_iter()
+            .map(|(mime, data)| {
+                let name = str_to_cstring(&mime);
+                (XInternAtom(self.display, name.as_ptr(), False), data)
+            })
+            .collect();
+
+        let _ = XSetSelectionOwner(self.display, atom, self.window, self.time);
+        if XGetSelectionOwner(self.display, atom) != self.window {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "failed to take ownership of the selection",
+            ))
+        }
+        let atoms = targets.iter().map(|x| x.0).collect();
+        if let Some(index) = self.owned_index(atom) {
+            self.owned[index] = Some(Owned {
+                time: self.time,
+                formats,
+                targets,
+            });
+        }
+        Ok(atoms)
+    }
+
+    /// Queues up a request, which is answered right away if the selection is ours.
+    pub unsafe fn request(&mut self, request: Request) {
+        self.requests.push_back(request);
+        if self.incoming.is_none() {
+            self.next_request();
+        }
+    }
+
+    /// What was dropped, if it's been handed over since this was last called, or `Some(None)` if it couldn't be.
+    pub fn take_dropped(&mut self) -> Option<Option<Vec<u8>>> {
+        self.dropped.take()
+    }
+
+    /// Handles selection events, and property changes that are part of a transfer.
+    #[allow(non_upper_case_globals)] // Xlib's names for event types
+    pub unsafe fn process_event(&mut self, event: &XEvent) {
+        match event.type_ {
+            SelectionClear => {
+                let event = &event.selection_clear;
+                if let Some(index) = self.owned_index(event.selection) {
+                    self.owned[index] = None;
+                }
+            },
+            SelectionRequest => self.send(&event.selection_request),
+            SelectionNotify => self.receive(&event.selection),
+            PropertyNotify => {
+                let event = &event.property;
+                if event.window == self.window && event.atom == self.property && event.state == PropertyNewValue {
+                    self.receive_chunk();
+                } else if event.state == PropertyDelete {
+                    self.send_chunk(event.window, event.atom);
+                }
+            },
+            _ => (),
+        }
+    }
+
+    fn selection_atom(&self, selection: Selection) -> Atom {
+        match selection {
+            Selection::Clipboard => self.clipboard,
+            Selection::Primary => XA_PRIMARY,
+        }
+    }
+
+    /// The selection a request is about.
+    fn request_selection(&self, request: &Request) -> Atom {
+        match request {
+            Request::Formats(selection, _) | Request::Get(selection, ..) => self.selection_atom(*selection),
+            Request::Drop(_) => self.xdnd_selection,
+        }
+    }
+
+    /// Where what's on a selection goes in `owned`, which for `XdndSelection` is [`XDND_INDEX`].
+    fn owned_index(&self, atom: Atom) -> Option<usize> {
+        match atom {
+            XA_PRIMARY => Some(Selection::Primary as usize),
+            _ if atom == self.clipboard => Some(Selection::Clipboard as usize),
+            _ if atom == self.xdnd_selection => Some(XDND_INDEX),
+            _ => None,
+        }
+    }
+
+    /// Starts on the next request, answering any that are about a selection that's ours along the way.
+    unsafe fn next_request(&mut self) {
+        while let Some(request) = self.requests.pop_front() {
+            let owned = match &request {
+                Request::Formats(selection, _) | Request::Get(selection, ..) => &self.owned[*selection as usize],
+                Request::Drop(_) => &None,
+            };
+            if let Some(owned) = owned {
+                match request {
+                    Request::Formats(_, reply) => {
+                        let _ = reply.send(Ok(owned.formats.iter().map(|(mime, _)| mime.clone()).collect()));
+                    },
+                    Request::Get(_, mime, reply) => {
+                        let _ = reply.send(Ok(owned.formats.iter().find(|x| x.0 == mime).map(|x| x.1.to_vec())));
+                    },
+                    Request::Drop(_) => (),
+                }
+                continue
+            }
+
+            // Atoms nobody's interned can't be on offer, so they're not created just to ask
+            let mut targets = match &request {
+                Request::Formats(..) => vec![self.targets],
+                Request::Get(_, mime, _) => {
+                    let name = str_to_cstring(mime);
+                    let atom = XInternAtom(self.display, name.as_ptr(), True);
+                    let mut targets: Vec<Atom> = Some(atom).filter(|&x| x != 0).into_iter().collect();
+                    if mime == TEXT_MIME {
+                        targets.push(self.utf8_string);
+                    }
+                    targets
+                },
+                Request::Drop(target) => vec![*target],
+            };
+            if targets.is_empty() {
+                self.finish(request, None);
+                continue
+            }
+            let target = targets.remove(0);
+            self.convert(self.request_selection(&request), target);
+            self.incoming = Some(Incoming {
+                request,
+                targets,
+                chunks: None,
+                deadline: Instant::now() + TRANSFER_TIMEOUT,
+            });
+            break
+        }
+    }
+
+    /// Asks the owner of a selection to write it to our property as `target`.
+    unsafe fn convert(&self, selection: Atom, target: Atom) {
+        let _ = XDeleteProperty(self.display, self.window, self.property);
+        let _ = XConvertSelection(self.display, selection, target, self.property, self.window, self.time);
+    }
+
+    /// Answers a request with an error.
+    fn fail(&mut self, request: Request, error: Error) {
+        match request {
+            Request::Formats(_, reply) => {
+                let _ = reply.send(Err(error));
+            },
+            Request::Get(_, _, reply) => {
+                let _ = reply.send(Err(error));
+            },
+            Request::Drop(_) => self.dropped = Some(None),
+        }
+    }
+
+    /// Answers a request with what the owner handed over, as the format it's in and the data.
+    unsafe fn finish(&mut self, request: Request, data: Option<(c_int, Vec<u8>)>) {
+        match request {
+            Request::Formats(_, reply) => {
+                let formats = match data {
+                    Some((32, data)) => self.format_names(&data),
+                    _ => Vec::new(),
+                };
+                let _ = reply.send(Ok(formats));
+            },
+            Request::Get(_, _, reply) => {
+                let _ = reply.send(Ok(data.map(|x| x.1)));
+            },
+            Request::Drop(_) => self.dropped = Some(data.map(|x| x.1)),
+        }
+    }
+
+    /// Turns a `TARGETS` list into MIME types, skipping anything that isn't one.
+    unsafe fn format_names(&self, data: &[u8]) -> Vec<String> {
+        let atoms = data.chunks_exact(mem::size_of::<c_ulong>()).map(|x| {
+            let mut bytes = [0; mem::size_of::<c_ulong>()];
+            bytes.copy_from_slice(x);
+            c_ulong::from_ne_bytes(bytes) as Atom
+        });
+        let names = atoms.filter_map(|atom| util::atom_name(self.display, atom));
+        clipboard::mime_types(names)
+    }
+
+    /// Handles the owner of a selection saying it's written it to our property, or that it couldn't.
+    unsafe fn receive(&mut self, event: &XSelectionEvent) {
+        let mut incoming = match self.incoming.take() {
+            Some(incoming) if event.requestor == self.window && incoming.chunks.is_none() => incoming,
+            incoming => {
+                self.incoming = incoming;
+                return
+            },
+        };
+        let data = if event.property == 0 {
+            if !incoming.targets.is_empty() {
+                let target = incoming.targets.remove(0);
+                self.convert(self.request_selection(&incoming.request), target);
+                self.incoming = Some(incoming);
+                return
+            }
+            None
+        } else {
+            match read_property(self.display, self.window, self.property) {
+                // Deleting the property, which reading it did, tells the owner to write the first chunk
+                Some((type_, ..)) if type_ == self.incr => {
+                    incoming.chunks = Some(Vec::new());
+                    incoming.deadline = Instant::now() + TRANSFER_TIMEOUT;
+                    self.incoming = Some(incoming);
+                    return
+                },
+                property => property.map(|(_, format, data)| (format, data)),
+            }
+        };
+        self.finish(incoming.request, data);
+        self.next_request();
+    }
+
+    /// Reads the next chunk of a chunked transfer to us, finishing it if it's the empty one at the end.
+    unsafe fn receive_chunk(&mut self) {
+        let incoming = match &mut self.incoming {
+            Some(incoming) => incoming,
+            None => return,
+        };
+        let chunks = match &mut incoming.chunks {
+            Some(chunks) => chunks,
+            None => return,
+        };
+        match read_property(self.display, self.window, self.property) {
+            Some((_, _, data)) if !data.is_empty() => {
+                chunks.extend_from_slice(&data);
+                incoming.deadline = Instant::now() + TRANSFER_TIMEOUT;
+            },
+            Some((_, format, _)) => {
+                if let Some(incoming) = self.incoming.take() {
+                    let data = incoming.chunks.unwrap_or_default();
+                    self.finish(incoming.request, Some((format, data)));
+                }
+                self.next_request();
+            },
+            None => (),
+        }
+    }
+
+    /// Answers another app asking for a selection that's ours.
+    unsafe fn send(&mut self, request: &XSelectionRequestEvent) {
+        // Ancient clients leave the property out, and expect it to be written to the target instead
+        let property = if request.property == 0 {
+            request.target
+        } else {
+            request.property
+        };
+        let owned = self
+            .owned_index(request.selection)
+            .and_then(|x| self.owned[x].as_ref())
+            .filter(|x| request.time == CurrentTime || request.time >= x.time);
+
+        let mut sent = false;
+        if let Some(owned) = owned {
+            if request.target == self.targets {
+                let mut atoms: Vec<c_ulong> = vec![self.targets as c_ulong, self.timestamp as c_ulong];
+                atoms.extend(owned.targets.iter().map(|x| x.0 as c_ulong));
+                self.change_property(
+                    request.requestor,
+                    property,
+                    XA_ATOM,
+                    32,
+                    atoms.as_ptr().cast(),
+                    atoms.len(),
+                );
+                sent = true;
+            } else if request.target == self.timestamp {
+                let time = owned.time as c_ulong;
+                self.change_property(
+                    request.requestor,
+                    property,
+                    XA_INTEGER,
+                    32,
+                    (&time as *const c_ulong).cast(),
+                    1,
+                );
+                sent = true;
+            } else if let Some((target, data)) = owned.targets.iter().find(|x| x.0 == request.target) {
+                let (target, data) = (*target, Rc::clone(data));
+                if data.len() > self.max_chunk {
+                    // They'll delete the property to ask for the first chunk, which is when it's noticed
+                    let length = data.len() as c_ulong;
+                    let _ = XSelectInput(self.display, request.requestor, PropertyChangeMask);
+                    let incr = self.incr;
+                    self.change_property(
+                        request.requestor,
+                        property,
+                        incr,
+                        32,
+                        (&length as *const c_ulong).cast(),
+                        1,
+                    );
+                    self.outgoing
+                        .retain(|x| (x.requestor, x.property) != (request.requestor, property));
+                    self.outgoing.push(Outgoing {
+                        requestor: request.requestor,
+                        property,
+                        target,
+                        data,
+                        offset: 0,
+                        deadline: Instant::now() + TRANSFER_TIMEOUT,
+                    });
+                } else {
+                    self.change_property(request.requestor, property, target, 8, data.as_ptr(), data.len());
+                }
+                sent = true;
+            }
+        }
+
+        // A property of `None` means it was refused, which is also the answer to `MULTIPLE` as it's not supported
+        let mut event: XEvent = mem::zeroed();
+        event.selection = XSelectionEvent {
+            type_: SelectionNotify,
+            serial: 0,
+            send_event: True,
+            display: self.display,
+            requestor: request.requestor,
+            selection: request.selection,
+            target: request.target,
+            property: if sent { property } else { 0 },
+            time: request.time,
+        };
+        let _ = XSendEvent(self.display, request.requestor, False, 0, &mut event);
+    }
+
+    /// Writes the next chunk of a chunked transfer, after the last one was read.
+    unsafe fn send_chunk(&mut self, requestor: Window, property: Atom) {
+        let index = match self
+            .outgoing
+            .iter()
+            .position(|x| x.requestor == requestor && x.property == property)
+        {
+            Some(index) => index,
+            None => return,
+        };
+        let outgoing = &mut self.outgoing[index];
+        let end = outgoing.data.len().min(outgoing.offset + self.max_chunk);
+        let (target, data) = (outgoing.target, Rc::clone(&outgoing.data));
+        let chunk = &data[outgoing.offset..end];
+        outgoing.offset = end;
+        outgoing.deadline = Instant::now() + TRANSFER_TIMEOUT;
+        // The empty chunk at the end is the last thing written, after which their window is left alone
+        if chunk.is_empty() {
+            let _ = self.outgoing.remove(index);
+            if !self.outgoing.iter().any(|x| x.requestor == requestor) {
+                let _ = XSelectInput(self.display, requestor, 0);
+            }
+        }
+        self.change_property(requestor, property, target, 8, chunk.as_ptr(), chunk.len());
+    }
+
+    /// Writes to a property, where `length` is in items of `format` bits (which are `c_ulong` for 32).
+    unsafe fn change_property(
+        &self,
+        window: Window,
+        property: Atom,
+        type_: Atom,
+        format: c_int,
+        data: *const c_uchar,
+        length: usize,
+    ) {
+        let _ = XChangeProperty(
+            self.display,
+            window,
+            property,
+            type_,
+            format,
+            PropModeReplace,
+            data,
+            length as c_int,
+        );
+    }
+}
+
+/// Reads all of a property and deletes it, as its type, its format, and the data.
+///
+/// For a format of 32, the data is an array of `c_ulong` like Xlib hands out.
+unsafe fn read_property(display: *mut Display, window: Window, property: Atom) -> Option<(Atom, c_int, Vec<u8>)> {
+    // This is in 4-byte units, so this reads 4 MiB at a time
+    const CHUNK: c_long = 1 << 20;
+
+    let mut data = Vec::new();
+    let (mut type_, mut format) = (0, 0);
+    let mut offset = 0;
+    loop {
+        let (mut nitems, mut bytes_after) = (0, 0);
+        let mut chunk = std::ptr::null_mut();
+        let status = XGetWindowProperty(
+            display,
+            window,
+            property,
+            offset,
+            CHUNK,
+            False,
+            AnyPropertyType,
+            &mut type_,
+            &mut format,
+            &mut nitems,
+            &mut bytes_after,
+            &mut chunk,
+        );
+        if status != Success {
+            return None
+        }
+        if !chunk.is_null() {
+            let item_size = match format {
+                16 => mem::size_of::<c_short>(),
+                32 => mem::size_of::<c_ulong>(),
+                _ => 1,
+            };
+            data.extend_from_slice(slice::from_raw_parts(chunk, nitems as usize * item_size));
+            let _ = XFree(chunk.cast());
+        }
+        if bytes_after == 0 || nitems == 0 {
+            break
+        }
+        offset += (nitems as c_long * c_long::from(format) / 8) / 4;
+    }
+    let _ = XDeleteProperty(display, window, property);
+    Some((type_, format, data))
+}
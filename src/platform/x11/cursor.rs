@@ -0,0 +1,150 @@
+//! Cursors, either from the user's cursor theme or made from pixels with XRender.
+//!
+//! Without XRender or a theme, the core protocol's cursor font is used for shapes instead.
+
+use crate::{
+    cursor::{self, CursorShape},
+    dpi::PhysicalSize,
+    platform::{x11::ffi::*, xcursor},
+};
+use std::ptr;
+
+/// Makes a cursor for `window`'s screen, which is 0 if it couldn't be made.
+///
+/// Theme cursors are made at the theme's size for the scale factor.
+pub unsafe fn create(display: *mut Display, window: Window, cursor: &cursor::Cursor, scale_factor: f64) -> Cursor {
+    match cursor {
+        cursor::Cursor::Shape(shape) => create_themed(display, window, *shape, scale_factor)
+            .unwrap_or_else(|| XCreateFontCursor(display, font_glyph(*shape))),
+        cursor::Cursor::Custom(custom) => {
+            let mut pixels: Vec<u32> = custom.premultiplied_argb().collect();
+            create_argb(display, window, custom.size, &mut pixels, custom.hotspot).unwrap_or(0)
+        },
+    }
+}
+
+/// Makes a cursor with nothing in it, which is how X11 hides the cursor.
+pub unsafe fn create_blank(display: *mut Display, window: Window) -> Cursor {
+    let data: c_char = 0;
+    let pixmap = XCreateBitmapFromData(display, window, &data, 1, 1);
+    let mut color = XColor {
+        pixel: 0,
+        red: 0,
+        green: 0,
+        blue: 0,
+        flags: 0,
+        pad: 0,
+    };
+    // The pixmap is its own mask, so not a single pixel is drawn
+    let cursor = XCreatePixmapCursor(display, pixmap, pixmap, &mut color, &mut color, 0, 0);
+    let _ = XFreePixmap(display, pixmap);
+    cursor
+}
+
+/// The glyph in the cursor font (`cursorfont.h`) that's closest to a shape.
+///
+/// There's no diagonal resizing arrows, so the corners are used instead, like most themes do.
+fn font_glyph(shape: CursorShape) -> c_uint {
+    match shape {
+        CursorShape::Arrow => 68,                         // XC_left_ptr
+        CursorShape::Hand => 60,                          // XC_hand2
+        CursorShape::Text => 152,                         // XC_xterm
+        CursorShape::Crosshair => 34,                     // XC_crosshair
+        CursorShape::Wait | CursorShape::Progress => 150, // XC_watch
+        CursorShape::Help => 92,                          // XC_question_arrow
+        CursorShape::NotAllowed => 0,                     // XC_X_cursor
+        CursorShape::Move => 52,                          // XC_fleur
+        CursorShape::ResizeHorizontal => 108,             // XC_sb_h_double_arrow
+        CursorShape::ResizeVertical => 116,               // XC_sb_v_double_arrow
+        CursorShape::ResizeTopLeftBottomRight => 14,      // XC_bottom_right_corner
+        CursorShape::ResizeTopRightBottomLeft => 12,      // XC_bottom_left_corner
+    }
+}
+
+/// Makes a cursor from the theme, with XRender stringing the images together if it's animated.
+unsafe fn create_themed(
+    display: *mut Display,
+    window: Window,
+    shape: CursorShape,
+    scale_factor: f64,
+) -> Option<Cursor> {
+    let size = (f64::from(xcursor::default_size()) * scale_factor).round() as u32;
+    let images = xcursor::load(shape, size)?;
+    let mut frames = Vec::with_capacity(images.len());
+    for mut image in images {
+        let size = PhysicalSize::new(image.width, image.height);
+        match create_argb(display, window, size, &mut image.pixels, image.hotspot) {
+            Some(cursor) => frames.push(XAnimCursor {
+                cursor,
+                delay: image.delay.into(),
+            }),
+            None => break,
+        }
+    }
+    let cursor = match frames.len() {
+        0 => return None,
+        1 => return Some(frames[0].cursor),
+        n => ((*XRENDER).as_ref()?.XRenderCreateAnimCursor)(display, n as c_int, frames.as_mut_ptr()),
+    };
+    // The animated cursor holds onto its frames
+    for frame in frames {
+        let _ = XFreeCursor(display, frame.cursor);
+    }
+    Some(cursor)
+}
+
+/// Uploads premultiplied ARGB pixels to a 32-bit pixmap, which XRender can make a cursor from.
+unsafe fn create_argb(
+    display: *mut Display,
+    window: Window,
+    size: PhysicalSize,
+    pixels: &mut [u32],
+    hotspot: (u32, u32),
+) -> Option<Cursor> {
+    let xrender = (*XRENDER).as_ref()?;
+    let (mut event_base, mut error_base) = (0, 0);
+    if (xrender.XRenderQueryExtension)(display, &mut event_base, &mut error_base) == False {
+        return None
+    }
+    let format = (xrender.XRenderFindStandardFormat)(display, PictStandardARGB32);
+    if format.is_null() {
+        return None
+    }
+
+    let (width, height) = (size.width, size.height);
+    let image = XCreateImage(
+        display,
+        ptr::null_mut(),
+        32,
+        ZPixmap,
+        0,
+        pixels.as_mut_ptr().cast(),
+        width,
+        height,
+        32,
+        (width * 4) as c_int,
+    );
+    if image.is_null() {
+        return None
+    }
+    // The pixels are in our byte order, which Xlib swaps for the server if it has to
+    (*image).byte_order = if cfg!(target_endian = "little") {
+        LSBFirst
+    } else {
+        MSBFirst
+    };
+    let pixmap = XCreatePixmap(display, window, width, height, 32);
+    let gc = XCreateGC(display, pixmap, 0, ptr::null_mut());
+    let _ = XPutImage(display, pixmap, gc, image, 0, 0, 0, 0, width, height);
+    let _ = XFreeGC(display, gc);
+    // The pixels are ours to free, not Xlib's
+    (*image).data = ptr::null_mut();
+    let _ = XDestroyImage(image);
+
+    let picture = (xrender.XRenderCreatePicture)(display, pixmap, format, 0, ptr::null());
+    let _ = XFreePixmap(display, pixmap);
+    let (x, y) = hotspot;
+    let cursor = (xrender.XRenderCreateCursor)(display, picture, x, y);
+    (xrender.XRenderFreePicture)(display, picture);
+    Some(cursor)
+}
@@ -0,0 +1,540 @@
+//! Drag and drop, which X11 does with the XDND protocol.
+//!
+//! Windows that take drops say so with the `XdndAware` property. Whoever's dragging something over them sends
+//! client messages as it enters, moves and leaves, and every move is answered with whether it would be accepted
+//! there. Once it's dropped, the data is handed over as the `XdndSelection` selection, like the clipboard is.
+//!
+//! Dragging something out of the window is the other side of that, with the pointer grabbed to follow it around
+//! and find the windows under it that take drops.
+
+use crate::{
+    clipboard::TRANSFER_TIMEOUT,
+    dpi::PhysicalPosition,
+    drag::{DragData, DragFormat, DropAction},
+    error::{Error, ErrorKind},
+    event::{Event, EventQueue},
+    keyboard::KeyCode,
+    platform::{
+        x11::{
+            clipboard::{Clipboard, Request as ClipboardRequest},
+            ffi::*,
+            util,
+        },
+        xkb,
+    },
+};
+use std::{mem, time::Instant};
+
+/// The version of XDND that's spoken, which is the latest one.
+const XDND_VERSION: c_ulong = 5;
+
+/// Everything to do with drag and drop, for one window.
+pub struct Dnd {
+    display: *mut Display,
+    root: Window,
+    window: Window,
+
+    action_copy: Atom,
+    action_move: Atom,
+    aware: Atom,
+    drop: Atom,
+    enter: Atom,
+    finished: Atom,
+    leave: Atom,
+    position: Atom,
+    status: Atom,
+    type_list: Atom,
+
+    // What's being dragged over the window, if anything, and what it's accepted as
+    drag: Option<Drag>,
+    accepted: Option<(DragFormat, DropAction)>,
+
+    // What's being dragged out of the window, if anything
+    drag_out: Option<DragOut>,
+}
+
+/// Something being dragged over the window.
+struct Drag {
+    source: Window,
+    // The targets it's offered as, and their names
+    targets: Vec<Atom>,
+    names: Vec<String>,
+    position: Option<PhysicalPosition>,
+    // Once it's dropped, what it was accepted as, while waiting for it to be handed over
+    dropped: Option<(DragFormat, DropAction)>,
+}
+
+/// Something being dragged out of the window.
+struct DragOut {
+    // The targets it's offered as
+    targets: Vec<Atom>,
+    // The window it's over that takes drops, if any, and the version of XDND they have in common
+    target: Option<(Window, c_ulong)>,
+    // What it would be dropped as, and what the target said it'd do with it there, if it'd take it
+    action: DropAction,
+    accepted: Option<DropAction>,
+    // Targets answer each position before they're sent the next, so only the latest one waits its turn
+    waiting: bool,
+    next_position: Option<(c_int, c_int, Time)>,
+    // Once it's dropped, when to give up on hearing back about it
+    dropped: Option<Instant>,
+}
+
+impl DragOut {
+    fn is_over(&self, window: Window) -> bool {
+        self.target.is_some_and(|x| x.0 == window)
+    }
+}
+
+impl Dnd {
+    pub unsafe fn new(display: *mut Display, root: Window, window: Window) -> Self {
+        let version = XDND_VERSION;
+        let aware = util::intern_atom(display, b"XdndAware\0");
+        let _ = XChangeProperty(
+            display,
+            window,
+            aware,
+            XA_ATOM,
+            32,
+            PropModeReplace,
+            (&version as *const c_ulong).cast(),
+            1,
+        );
+        Self {
+            display,
+            root,
+            window,
+            action_copy: util::intern_atom(display, b"XdndActionCopy\0"),
+            action_move: util::intern_atom(display, b"XdndActionMove\0"),
+            aware,
+            drop: util::intern_atom(display, b"XdndDrop\0"),
+            enter: util::intern_atom(display, b"XdndEnter\0"),
+            finished: util::intern_atom(display, b"XdndFinished\0"),
+            leave: util::intern_atom(display, b"XdndLeave\0"),
+            position: util::intern_atom(display, b"XdndPosition\0"),
+            status: util::intern_atom(display, b"XdndStatus\0"),
+            type_list: util::intern_atom(display, b"XdndTypeList\0"),
+            drag: None,
+            accepted: None,
+            drag_out: None,
+        }
+    }
+
+    /// Handles a client message if it's part of XDND, returning whether it was.
+    pub unsafe fn process_client_message(
+        &mut self,
+        message: &XClientMessageEvent,
+        clipboard: &mut Clipboard,
+        events: &EventQueue,
+    ) -> bool {
+        let data = message.data;
+        let source = data[0] as Window;
+        let type_ = message.message_type;
+        if type_ == self.enter {
+            // Something that was dropped keeps the drag going until it's been handed over
+            if self.drag.as_ref().is_some_and(|x| x.dropped.is_some()) {
+                return true
+            }
+            if self.drag.take().is_some() {
+                events.push(Event::DragLeft);
+            }
+            // The first three targets are in the message, and if there's more, they're all in a property
+            let targets: Vec<Atom> = if data[1] & 1 != 0 {
+                util::get_property32(self.display, source, self.type_list, XA_ATOM, 0x10000)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|x| x as Atom)
+                    .collect()
+            } else {
+                data[2..5].iter().filter(|&&x| x != 0).map(|&x| x as Atom).collect()
+            };
+            let names: Vec<String> = targets
+                .iter()
+                .map(|&x| util::atom_name(self.display, x).unwrap_or_default())
+                .collect();
+            self.accepted = None;
+            events.push(Event::DragEntered(DragFormat::from_names(&names)));
+            self.drag = Some(Drag {
+                source,
+                targets,
+                names,
+                position: None,
+                dropped: None,
+            });
+        } else if type_ == self.position {
+            // The position is on the root window, packed into one number
+            let (x_root, y_root) = ((data[2] >> 16) as c_int & 0xFFFF, data[2] as c_int & 0xFFFF);
+            let (mut x, mut y, mut child) = (0, 0, 0);
+            let (display, root, window) = (self.display, self.root, self.window);
+            let _ = XTranslateCoordinates(display, root, window, x_root, y_root, &mut x, &mut y, &mut child);
+            let position = PhysicalPosition::new(x, y);
+            let drag = match self.dragging(source) {
+                Some(drag) => drag,
+                None => return true,
+            };
+            if drag.position != Some(position) {
+                drag.position = Some(position);
+                events.push(Event::DragMoved(position));
+            }
+            self.send_status();
+        } else if type_ == self.leave {
+            if self.dragging(source).is_some() {
+                self.drag = None;
+                events.push(Event::DragLeft);
+            }
+        } else if type_ == self.drop {
+            if self.dragging(source).is_none() {
+                return true
+            }
+            match self.accepted_target() {
+                Some(target) => {
+                    if let Some(drag) = &mut self.drag {
+                        drag.dropped = self.accepted.clone();
+                    }
+                    // The selection has to be asked for as of when it was dropped
+                    clipboard.update_time(data[2] as Time);
+                    clipboard.request(ClipboardRequest::Drop(target));
+                },
+                None => {
+                    self.drag = None;
+                    self.send_finished(source, None);
+                    events.push(Event::DragLeft);
+                },
+            }
+        } else if type_ == self.status {
+            let accepted = (data[1] & 1 != 0).then(|| self.action_from_atom(data[4] as Atom));
+            let drag_out = match self
+                .drag_out
+                .as_mut()
+                .filter(|x| x.is_over(source) && x.dropped.is_none())
+            {
+                Some(drag_out) => drag_out,
+                None => return true,
+            };
+            drag_out.waiting = false;
+            drag_out.accepted = accepted;
+            self.send_position();
+        } else if type_ == self.finished {
+            let version = match &self.drag_out {
+                Some(drag_out) if drag_out.is_over(source) && drag_out.dropped.is_some() => drag_out.target.unwrap().1,
+                _ => return true,
+            };
+            let drag_out = self.drag_out.take().unwrap();
+            // Before version 5, there's no saying whether it was taken, so it's whatever was last accepted
+            let action = if version >= 5 {
+                (data[1] & 1 != 0).then(|| self.action_from_atom(data[2] as Atom))
+            } else {
+                drag_out.accepted
+            };
+            events.push(Event::DragFinished(action));
+        } else {
+            return false
+        }
+        true
+    }
+
+    /// Starts dragging something out of the window, offered as `targets`, grabbing the pointer to follow it.
+    ///
+    /// The cursor is shown for as long as it's dragged. The keyboard's grabbed too, so it can be cancelled with escape.
+    pub unsafe fn start_drag_out(&mut self, targets: Vec<Atom>, cursor: Cursor, time: Time) -> Result<(), Error> {
+        let list: Vec<c_ulong> = targets.iter().map(|&x| x as c_ulong).collect();
+        let _ = XChangeProperty(
+            self.display,
+            self.window,
+            self.type_list,
+            XA_ATOM,
+            32,
+            PropModeReplace,
+            list.as_ptr().cast(),
+            list.len() as c_int,
+        );
+        let mask = (ButtonReleaseMask | PointerMotionMask) as c_uint;
+        let grab = XGrabPointer(
+            self.display,
+            self.window,
+            False,
+            mask,
+            GrabModeAsync,
+            GrabModeAsync,
+            0,
+            cursor,
+            time,
+        );
+        if grab != GrabSuccess {
+            return Err(Error::new(ErrorKind::Other, "failed to grab the pointer to drag with"))
+        }
+        // Escape cancelling it is a nicety, so it doesn't matter if that can't be grabbed
+        let _ = XGrabKeyboard(self.display, self.window, False, GrabModeAsync, GrabModeAsync, time);
+        self.drag_out = Some(DragOut {
+            targets,
+            target: None,
+            action: DropAction::Copy,
+            accepted: None,
+            waiting: false,
+            next_position: None,
+            dropped: None,
+        });
+        Ok(())
+    }
+
+    /// Whether something's being dragged out of the window, or was dropped and hasn't been heard back about.
+    pub fn dragging_out(&self) -> bool {
+        self.drag_out.is_some()
+    }
+
+    /// Whether something's being dragged out of the window, with the pointer grabbed for it.
+    pub fn grabbing(&self) -> bool {
+        self.drag_out.as_ref().is_some_and(|x| x.dropped.is_none())
+    }
+
+    /// Handles pointer and keyboard events while they're grabbed for dragging, returning whether it was one of them.
+    ///
+    /// Anything else, such as scrolling or other keys, goes to the window as usual.
+    #[allow(non_upper_case_globals)] // Xlib's names for event types
+    pub unsafe fn process_grabbed_event(&mut self, event: &XEvent, events: &EventQueue) -> bool {
+        match event.type_ {
+            MotionNotify => {
+                let event = &event.motion;
+                self.drag_moved(event.x_root, event.y_root, event.time, event.state);
+            },
+            // Buttons 4 to 7 are scrolling
+            ButtonRelease if !(4..=7).contains(&event.button.button) => {
+                let event = &event.button;
+                self.drag_moved(event.x_root, event.y_root, event.time, event.state);
+                self.ungrab(event.time);
+                self.drop_out(event.time, events);
+            },
+            KeyPress if xkb::key_code(event.key.keycode) == KeyCode::Escape => {
+                self.ungrab(event.key.time);
+                self.cancel(events);
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    /// When to give up on hearing back about what was done with something that was dragged out and dropped.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.drag_out.as_ref()?.dropped
+    }
+
+    /// Gives up on hearing back about something that was dropped, if it's been too long.
+    pub fn expire(&mut self, events: &EventQueue) {
+        if self.deadline().is_some_and(|x| x <= Instant::now()) {
+            self.drag_out = None;
+            events.push(Event::DragFinished(None));
+        }
+    }
+
+    /// Follows what's being dragged out of the window to a point on the screen, telling whatever's under it.
+    unsafe fn drag_moved(&mut self, x_root: c_int, y_root: c_int, time: Time, state: c_uint) {
+        let target = self.find_target(x_root, y_root);
+        let mut drag_out = match self.drag_out.take() {
+            Some(drag_out) => drag_out,
+            None => return,
+        };
+        drag_out.action = match xkb::modifiers(state).shift {
+            true => DropAction::Move,
+            false => DropAction::Copy,
+        };
+        if target.map(|x| x.0) != drag_out.target.map(|x| x.0) {
+            if let Some((window, _)) = drag_out.target {
+                self.send(window, self.leave, [self.window as c_long, 0, 0, 0, 0]);
+            }
+            if let Some((window, version)) = target {
+                // The first three targets go in the message, and bit 0 says whether there's more in the property
+                let mut data = [self.window as c_long, (version << 24) as c_long, 0, 0, 0];
+                data[1] |= c_long::from(drag_out.targets.len() > 3);
+                for (slot, &atom) in data[2..].iter_mut().zip(&drag_out.targets) {
+                    *slot = atom as c_long;
+                }
+                self.send(window, self.enter, data);
+            }
+            drag_out.target = target;
+            drag_out.accepted = None;
+            drag_out.waiting = false;
+        }
+        drag_out.next_position = drag_out.target.map(|_| (x_root, y_root, time));
+        self.drag_out = Some(drag_out);
+        self.send_position();
+    }
+
+    unsafe fn ungrab(&self, time: Time) {
+        let _ = XUngrabPointer(self.display, time);
+        let _ = XUngrabKeyboard(self.display, time);
+    }
+
+    /// Drops what's being dragged out of the window, or cancels it if where it is wouldn't take it.
+    unsafe fn drop_out(&mut self, time: Time, events: &EventQueue) {
+        match self.drag_out.as_mut() {
+            Some(drag_out) if drag_out.accepted.is_some() && drag_out.dropped.is_none() => {
+                let (window, _) = drag_out.target.unwrap();
+                drag_out.dropped = Some(Instant::now() + TRANSFER_TIMEOUT);
+                self.send(window, self.drop, [self.window as c_long, 0, time as c_long, 0, 0]);
+            },
+            _ => self.cancel(events),
+        }
+    }
+
+    /// Cancels dragging something out of the window, telling whatever it's over.
+    unsafe fn cancel(&mut self, events: &EventQueue) {
+        match self.drag_out.take() {
+            Some(drag_out) if drag_out.dropped.is_none() => {
+                if let Some((window, _)) = drag_out.target {
+                    self.send(window, self.leave, [self.window as c_long, 0, 0, 0, 0]);
+                }
+                events.push(Event::DragFinished(None));
+            },
+            // Once it's been dropped, it's up to the target
+            drag_out => self.drag_out = drag_out,
+        }
+    }
+
+    /// Sends the latest position of what's being dragged out, if the target's answered the last one.
+    unsafe fn send_position(&mut self) {
+        let drag_out = match self.drag_out.as_mut().filter(|x| !x.waiting) {
+            Some(drag_out) => drag_out,
+            None => return,
+        };
+        let ((window, _), (x, y, time)) = match (drag_out.target, drag_out.next_position.take()) {
+            (Some(target), Some(position)) => (target, position),
+            _ => return,
+        };
+        drag_out.waiting = true;
+        let action = drag_out.action;
+        let action = self.action_atom(action);
+        // The position is on the root window, packed into one number
+        let position = ((x as c_long & 0xFFFF) << 16) | (y as c_long & 0xFFFF);
+        let data = [self.window as c_long, 0, position, time as c_long, action as c_long];
+        self.send(window, self.position, data);
+    }
+
+    /// The window that takes drops at a point on the screen, if there is one, and the version of XDND to speak to it.
+    unsafe fn find_target(&self, x_root: c_int, y_root: c_int) -> Option<(Window, c_ulong)> {
+        // Top-level windows are usually inside a frame from the window manager, so it's looked for all the way down
+        let mut window = self.root;
+        loop {
+            let (mut x, mut y, mut child) = (0, 0, 0);
+            let found = XTranslateCoordinates(
+                self.display,
+                self.root,
+                window,
+                x_root,
+                y_root,
+                &mut x,
+                &mut y,
+                &mut child,
+            );
+            if found == False || child == 0 {
+                return None
+            }
+            window = child;
+            // Version 3 is the oldest that's still around
+            let version = util::get_property32(self.display, window, self.aware, XA_ATOM, 1)
+                .and_then(|x| x.first().copied())
+                .filter(|&x| x >= 3);
+            if let Some(version) = version {
+                return Some((window, version.min(XDND_VERSION)))
+            }
+        }
+    }
+
+    /// Accepts what's being dragged over the window as a format, or rejects it, and tells whoever's dragging it.
+    pub unsafe fn set_accepted(&mut self, accepted: Option<(DragFormat, DropAction)>) {
+        self.accepted = accepted;
+        // They're told in answer to every move, but anything since the last one would go unnoticed until the next
+        if self
+            .drag
+            .as_ref()
+            .is_some_and(|x| x.position.is_some() && x.dropped.is_none())
+        {
+            self.send_status();
+        }
+    }
+
+    /// Finishes a drop with what was handed over, if anything, sending the event and telling whoever dragged it.
+    pub unsafe fn finish_drop(&mut self, data: Option<Vec<u8>>, events: &EventQueue) {
+        let (source, (format, action)) = match &mut self.drag {
+            Some(drag) if drag.dropped.is_some() => (drag.source, drag.dropped.take().unwrap()),
+            _ => return,
+        };
+        self.drag = None;
+        match data {
+            Some(data) => {
+                events.push(Event::Dropped(DragData::from_bytes(format, data)));
+                self.send_finished(source, Some(action));
+            },
+            None => {
+                events.push(Event::DragLeft);
+                self.send_finished(source, None);
+            },
+        }
+    }
+
+    /// The drag that's going, if it's from `source` and hasn't been dropped.
+    fn dragging(&mut self, source: Window) -> Option<&mut Drag> {
+        self.drag.as_mut().filter(|x| x.source == source && x.dropped.is_none())
+    }
+
+    /// The target to ask for what's being dragged as, if it's accepted as a format it's offered in.
+    fn accepted_target(&self) -> Option<Atom> {
+        let (drag, (format, _)) = (self.drag.as_ref()?, self.accepted.as_ref()?);
+        let name = format.pick_name(&drag.names)?;
+        let index = drag.names.iter().position(|x| x == name)?;
+        Some(drag.targets[index])
+    }
+
+    fn action_atom(&self, action: DropAction) -> Atom {
+        match action {
+            DropAction::Copy => self.action_copy,
+            DropAction::Move => self.action_move,
+        }
+    }
+
+    /// Anything other than moving, such as linking, is taken as copying.
+    fn action_from_atom(&self, atom: Atom) -> DropAction {
+        match atom {
+            _ if atom == self.action_move => DropAction::Move,
+            _ => DropAction::Copy,
+        }
+    }
+
+    /// Tells whoever's dragging whether it would be accepted where it is.
+    unsafe fn send_status(&self) {
+        let drag = match &self.drag {
+            Some(drag) => drag,
+            None => return,
+        };
+        let action = self
+            .accepted
+            .as_ref()
+            .filter(|_| self.accepted_target().is_some())
+            .map(|(_, action)| self.action_atom(*action));
+        // Bit 0 is whether it's accepted, and bit 1 asks for every move, rather than only outside a rectangle
+        let flags = c_long::from(action.is_some()) | 0b10;
+        let data = [self.window as c_long, flags, 0, 0, action.unwrap_or(0) as c_long];
+        self.send(drag.source, self.status, data);
+    }
+
+    /// Tells whoever dragged something that it's been dropped, and what was done with it if it was accepted.
+    unsafe fn send_finished(&self, source: Window, action: Option<DropAction>) {
+        let action = action.map_or(0, |x| self.action_atom(x));
+        let data = [self.window as c_long, c_long::from(action != 0), action as c_long, 0, 0];
+        self.send(source, self.finished, data);
+    }
+
+    unsafe fn send(&self, window: Window, message_type: Atom, data: [c_long; 5]) {
+        let mut event: XEvent = mem::zeroed();
+        event.client_message = XClientMessageEvent {
+            type_: ClientMessage,
+            serial: 0,
+            send_event: True,
+            display: self.display,
+            window,
+            message_type,
+            format: 32,
+            data,
+        };
+        let _ = XSendEvent(self.display, window, False, 0, &mut event);
+    }
+}
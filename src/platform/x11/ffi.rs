@@ -0,0 +1,998 @@
+//! Bindings to Xlib.
+
+#![allow(bad_style, clippy::upper_case_acronyms, dead_code)]
+
+/* c scalars & xlib scalars */
+
+// Unlike Win32, the widths of these follow the platform's C ABI, so `std` is the source of truth.
+use crate::{
+    platform::posix::{dynamic_library, dynamic_library_fns},
+    sync::LazyCell,
+};
+pub use core::ffi::c_void;
+pub use std::os::raw::{c_char, c_int, c_long, c_short, c_uchar, c_uint, c_ulong, c_ushort};
+
+pub type Atom = XID;
+pub type Bool = c_int;
+pub type Cursor = XID;
+pub type Drawable = XID;
+pub type KeySym = XID;
+pub type Picture = XID;
+pub type Pixmap = XID;
+pub type Status = c_int;
+pub type Time = c_ulong;
+pub type RRCrtc = XID;
+pub type RRMode = XID;
+pub type RROutput = XID;
+pub type Rotation = c_ushort;
+pub type XID = c_ulong;
+/// Opaque handle to a window (an `XID` on the server).
+pub type Window = XID;
+pub type XIMFeedback = c_ulong;
+pub type XIMStyle = c_ulong;
+pub type XPointer = *mut c_char;
+pub type XVaNestedList = *mut c_void;
+
+/* opaque types */
+
+/// Opaque handle to a connection to the X server.
+pub enum Display {}
+
+/// Opaque handle to a connection to an input method.
+pub enum XIMRec {}
+pub type XIM = *mut XIMRec;
+
+/// Opaque handle to an input context, which is an input method's state for one window.
+pub enum XICRec {}
+pub type XIC = *mut XICRec;
+
+/// Opaque handle to a graphics context, which drawing requests take their settings from.
+pub enum XGCRec {}
+pub type GC = *mut XGCRec;
+
+pub enum Visual {}
+pub enum XRenderPictFormat {}
+
+/* function pointers */
+
+pub type XErrorHandler = Option<unsafe extern "C" fn(display: *mut Display, error_event: *mut XErrorEvent) -> c_int>;
+/// Input context callbacks. Only the start callback's return value is used, the rest return `void` in C.
+pub type XICProc = Option<unsafe extern "C" fn(ic: XIC, client_data: XPointer, call_data: XPointer) -> c_int>;
+
+/* structs */
+
+#[repr(C)]
+pub struct XAnimCursor {
+    pub cursor: Cursor,
+    pub delay: c_ulong,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XAnyEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+}
+
+#[repr(C)]
+pub struct XColor {
+    pub pixel: c_ulong,
+    pub red: c_ushort,
+    pub green: c_ushort,
+    pub blue: c_ushort,
+    pub flags: c_char,
+    pub pad: c_char,
+}
+
+/// Only the start is described, the rest being more fields and a table of functions that are only used by Xlib.
+#[repr(C)]
+pub struct XImage {
+    pub width: c_int,
+    pub height: c_int,
+    pub xoffset: c_int,
+    pub format: c_int,
+    pub data: *mut c_char,
+    pub byte_order: c_int,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XButtonEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub root: Window,
+    pub subwindow: Window,
+    pub time: Time,
+    pub x: c_int,
+    pub y: c_int,
+    pub x_root: c_int,
+    pub y_root: c_int,
+    pub state: c_uint,
+    pub button: c_uint,
+    pub same_screen: Bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XClientMessageEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub message_type: Atom,
+    pub format: c_int,
+    pub data: [c_long; 5],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XConfigureEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub event: Window,
+    pub window: Window,
+    pub x: c_int,
+    pub y: c_int,
+    pub width: c_int,
+    pub height: c_int,
+    pub border_width: c_int,
+    pub above: Window,
+    pub override_redirect: Bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XCrossingEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub root: Window,
+    pub subwindow: Window,
+    pub time: Time,
+    pub x: c_int,
+    pub y: c_int,
+    pub x_root: c_int,
+    pub y_root: c_int,
+    pub mode: c_int,
+    pub detail: c_int,
+    pub same_screen: Bool,
+    pub focus: Bool,
+    pub state: c_uint,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XErrorEvent {
+    pub type_: c_int,
+    pub display: *mut Display,
+    pub resourceid: XID,
+    pub serial: c_ulong,
+    pub error_code: c_uchar,
+    pub request_code: c_uchar,
+    pub minor_code: c_uchar,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XFocusChangeEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub mode: c_int,
+    pub detail: c_int,
+}
+
+/// An event from an extension, whose data is fetched separately with `XGetEventData`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XGenericEventCookie {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub extension: c_int,
+    pub evtype: c_int,
+    pub cookie: c_uint,
+    pub data: *mut c_void,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XKeyEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub root: Window,
+    pub subwindow: Window,
+    pub time: Time,
+    pub x: c_int,
+    pub y: c_int,
+    pub x_root: c_int,
+    pub y_root: c_int,
+    pub state: c_uint,
+    pub keycode: c_uint,
+    pub same_screen: Bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XMotionEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub root: Window,
+    pub subwindow: Window,
+    pub time: Time,
+    pub x: c_int,
+    pub y: c_int,
+    pub x_root: c_int,
+    pub y_root: c_int,
+    pub state: c_uint,
+    pub is_hint: c_char,
+    pub same_screen: Bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XPropertyEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub atom: Atom,
+    pub time: Time,
+    pub state: c_int,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XSelectionClearEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub selection: Atom,
+    pub time: Time,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XSelectionEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub requestor: Window,
+    pub selection: Atom,
+    pub target: Atom,
+    pub property: Atom,
+    pub time: Time,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XSelectionRequestEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub owner: Window,
+    pub requestor: Window,
+    pub selection: Atom,
+    pub target: Atom,
+    pub property: Atom,
+    pub time: Time,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union XEvent {
+    pub type_: c_int,
+    pub any: XAnyEvent,
+    pub button: XButtonEvent,
+    pub client_message: XClientMessageEvent,
+    pub configure: XConfigureEvent,
+    pub crossing: XCrossingEvent,
+    pub focus_change: XFocusChangeEvent,
+    pub generic_event_cookie: XGenericEventCookie,
+    pub key: XKeyEvent,
+    pub motion: XMotionEvent,
+    pub property: XPropertyEvent,
+    pub selection: XSelectionEvent,
+    pub selection_clear: XSelectionClearEvent,
+    pub selection_request: XSelectionRequestEvent,
+    pub pad: [c_long; 24],
+}
+
+#[repr(C)]
+pub struct XClassHint {
+    pub res_name: *mut c_char,
+    pub res_class: *mut c_char,
+}
+
+#[repr(C)]
+pub struct XAspect {
+    pub x: c_int,
+    pub y: c_int,
+}
+
+#[repr(C)]
+pub struct XSizeHints {
+    pub flags: c_long,
+    pub x: c_int,
+    pub y: c_int,
+    pub width: c_int,
+    pub height: c_int,
+    pub min_width: c_int,
+    pub min_height: c_int,
+    pub max_width: c_int,
+    pub max_height: c_int,
+    pub width_inc: c_int,
+    pub height_inc: c_int,
+    pub min_aspect: XAspect,
+    pub max_aspect: XAspect,
+    pub base_width: c_int,
+    pub base_height: c_int,
+    pub win_gravity: c_int,
+}
+
+#[repr(C)]
+pub struct XPoint {
+    pub x: c_short,
+    pub y: c_short,
+}
+
+#[repr(C)]
+pub struct XIMCallback {
+    pub client_data: XPointer,
+    pub callback: XICProc,
+}
+
+#[repr(C)]
+pub struct XIMStyles {
+    pub count_styles: c_ushort,
+    pub supported_styles: *mut XIMStyle,
+}
+
+/// The text of an `XIMText`, which is only wide if `encoding_is_wchar` is set.
+#[repr(C)]
+pub union XIMTextString {
+    pub multi_byte: *mut c_char,
+    pub wide_char: *mut c_int,
+}
+
+#[repr(C)]
+pub struct XIMText {
+    pub length: c_ushort,
+    pub feedback: *mut XIMFeedback,
+    pub encoding_is_wchar: Bool,
+    pub string: XIMTextString,
+}
+
+#[repr(C)]
+pub struct XIMPreeditDrawCallbackStruct {
+    pub caret: c_int,
+    pub chg_first: c_int,
+    pub chg_length: c_int,
+    pub text: *mut XIMText,
+}
+
+#[repr(C)]
+pub struct XIMPreeditCaretCallbackStruct {
+    pub position: c_int,
+    pub direction: c_int,
+    pub style: c_int,
+}
+
+#[repr(C)]
+pub struct XIEventMask {
+    pub deviceid: c_int,
+    pub mask_len: c_int,
+    pub mask: *mut c_uchar,
+}
+
+#[repr(C)]
+pub struct XIDeviceInfo {
+    pub deviceid: c_int,
+    pub name: *mut c_char,
+    pub use_: c_int,
+    pub attachment: c_int,
+    pub enabled: Bool,
+    pub num_classes: c_int,
+    pub classes: *mut *mut XIAnyClassInfo,
+}
+
+#[repr(C)]
+pub struct XIAnyClassInfo {
+    pub type_: c_int,
+    pub sourceid: c_int,
+}
+
+#[repr(C)]
+pub struct XIValuatorClassInfo {
+    pub type_: c_int,
+    pub sourceid: c_int,
+    pub number: c_int,
+    pub label: Atom,
+    pub min: f64,
+    pub max: f64,
+    pub value: f64,
+    pub resolution: c_int,
+    pub mode: c_int,
+}
+
+#[repr(C)]
+pub struct XIScrollClassInfo {
+    pub type_: c_int,
+    pub sourceid: c_int,
+    pub number: c_int,
+    pub scroll_type: c_int,
+    pub increment: f64,
+    pub flags: c_int,
+}
+
+#[repr(C)]
+pub struct XIButtonState {
+    pub mask_len: c_int,
+    pub mask: *mut c_uchar,
+}
+
+#[repr(C)]
+pub struct XIValuatorState {
+    pub mask_len: c_int,
+    pub mask: *mut c_uchar,
+    pub values: *mut f64,
+}
+
+#[repr(C)]
+pub struct XIRawEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub extension: c_int,
+    pub evtype: c_int,
+    pub time: Time,
+    pub deviceid: c_int,
+    pub sourceid: c_int,
+    pub detail: c_int,
+    pub flags: c_int,
+    pub valuators: XIValuatorState,
+    pub raw_values: *mut f64,
+}
+
+#[repr(C)]
+pub struct XIModifierState {
+    pub base: c_int,
+    pub latched: c_int,
+    pub locked: c_int,
+    pub effective: c_int,
+}
+
+pub type XIGroupState = XIModifierState;
+
+#[repr(C)]
+pub struct XIDeviceEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub extension: c_int,
+    pub evtype: c_int,
+    pub time: Time,
+    pub deviceid: c_int,
+    pub sourceid: c_int,
+    pub detail: c_int,
+    pub root: Window,
+    pub event: Window,
+    pub child: Window,
+    pub root_x: f64,
+    pub root_y: f64,
+    pub event_x: f64,
+    pub event_y: f64,
+    pub flags: c_int,
+    pub buttons: XIButtonState,
+    pub valuators: XIValuatorState,
+    pub mods: XIModifierState,
+    pub group: XIGroupState,
+}
+
+#[repr(C)]
+pub struct XRRModeInfo {
+    pub id: RRMode,
+    pub width: c_uint,
+    pub height: c_uint,
+    pub dotClock: c_ulong,
+    pub hSyncStart: c_uint,
+    pub hSyncEnd: c_uint,
+    pub hTotal: c_uint,
+    pub hSkew: c_uint,
+    pub vSyncStart: c_uint,
+    pub vSyncEnd: c_uint,
+    pub vTotal: c_uint,
+    pub name: *mut c_char,
+    pub nameLength: c_uint,
+    pub modeFlags: c_ulong,
+}
+
+#[repr(C)]
+pub struct XRRScreenResources {
+    pub timestamp: Time,
+    pub configTimestamp: Time,
+    pub ncrtc: c_int,
+    pub crtcs: *mut RRCrtc,
+    pub noutput: c_int,
+    pub outputs: *mut RROutput,
+    pub nmode: c_int,
+    pub modes: *mut XRRModeInfo,
+}
+
+#[repr(C)]
+pub struct XRROutputInfo {
+    pub timestamp: Time,
+    pub crtc: RRCrtc,
+    pub name: *mut c_char,
+    pub nameLen: c_int,
+    pub mm_width: c_ulong,
+    pub mm_height: c_ulong,
+    pub connection: c_ushort,
+    pub subpixel_order: c_ushort,
+    pub ncrtc: c_int,
+    pub crtcs: *mut RRCrtc,
+    pub nclone: c_int,
+    pub clones: *mut RROutput,
+    pub nmode: c_int,
+    pub npreferred: c_int,
+    pub modes: *mut RRMode,
+}
+
+#[repr(C)]
+pub struct XRRCrtcInfo {
+    pub timestamp: Time,
+    pub x: c_int,
+    pub y: c_int,
+    pub width: c_uint,
+    pub height: c_uint,
+    pub mode: RRMode,
+    pub rotation: Rotation,
+    pub noutput: c_int,
+    pub outputs: *mut RROutput,
+    pub rotations: Rotation,
+    pub npossible: c_int,
+    pub possible: *mut RROutput,
+}
+
+/* constants */
+
+pub const False: Bool = 0;
+pub const True: Bool = 1;
+pub const PropModeReplace: c_int = 0;
+pub const PropertyNewValue: c_int = 0;
+pub const PropertyDelete: c_int = 1;
+pub const AnyPropertyType: Atom = 0;
+pub const Success: c_int = 0;
+pub const CurrentTime: Time = 0;
+pub const XA_PRIMARY: Atom = 1;
+pub const XA_ATOM: Atom = 4;
+pub const XA_CARDINAL: Atom = 6;
+pub const XA_INTEGER: Atom = 19;
+pub const XA_STRING: Atom = 31;
+
+// Error codes
+pub const BadAlloc: c_uchar = 11;
+
+// Event types
+pub const KeyPress: c_int = 2;
+pub const KeyRelease: c_int = 3;
+pub const ButtonPress: c_int = 4;
+pub const ButtonRelease: c_int = 5;
+pub const MotionNotify: c_int = 6;
+pub const EnterNotify: c_int = 7;
+pub const LeaveNotify: c_int = 8;
+pub const FocusIn: c_int = 9;
+pub const FocusOut: c_int = 10;
+pub const ConfigureNotify: c_int = 22;
+pub const PropertyNotify: c_int = 28;
+pub const SelectionClear: c_int = 29;
+pub const SelectionRequest: c_int = 30;
+pub const SelectionNotify: c_int = 31;
+pub const ClientMessage: c_int = 33;
+pub const GenericEvent: c_int = 35;
+
+// Event masks
+pub const KeyPressMask: c_long = 1 << 0;
+pub const KeyReleaseMask: c_long = 1 << 1;
+pub const ButtonPressMask: c_long = 1 << 2;
+pub const ButtonReleaseMask: c_long = 1 << 3;
+pub const EnterWindowMask: c_long = 1 << 4;
+pub const LeaveWindowMask: c_long = 1 << 5;
+pub const PointerMotionMask: c_long = 1 << 6;
+pub const StructureNotifyMask: c_long = 1 << 17;
+pub const SubstructureNotifyMask: c_long = 1 << 19;
+pub const SubstructureRedirectMask: c_long = 1 << 20;
+pub const FocusChangeMask: c_long = 1 << 21;
+pub const PropertyChangeMask: c_long = 1 << 22;
+
+// Size hint flags
+pub const USPosition: c_long = 1 << 0;
+pub const USSize: c_long = 1 << 1;
+
+// Images
+pub const LSBFirst: c_int = 0;
+pub const MSBFirst: c_int = 1;
+pub const ZPixmap: c_int = 2;
+
+// Standard XRender picture formats
+pub const PictStandardARGB32: c_int = 0;
+
+// Pointer and keyboard grabs
+pub const GrabModeAsync: c_int = 1;
+pub const GrabSuccess: c_int = 0;
+
+// Focus event details
+pub const NotifyPointer: c_int = 5;
+
+// Input method styles
+pub const XIMPreeditCallbacks: XIMStyle = 0x0002;
+pub const XIMPreeditNothing: XIMStyle = 0x0008;
+pub const XIMPreeditNone: XIMStyle = 0x0010;
+pub const XIMStatusNothing: XIMStyle = 0x0400;
+pub const XIMStatusNone: XIMStyle = 0x0800;
+
+// Input method caret directions, of which only the ones that don't need layout information are handled
+pub const XIMForwardChar: c_int = 0;
+pub const XIMBackwardChar: c_int = 1;
+pub const XIMLineStart: c_int = 8;
+pub const XIMLineEnd: c_int = 9;
+pub const XIMAbsolutePosition: c_int = 10;
+
+// Input method lookup results
+pub const XBufferOverflow: Status = -1;
+pub const XLookupChars: Status = 2;
+pub const XLookupBoth: Status = 4;
+
+// Input method value names
+pub const XNClientWindow: &[u8] = b"clientWindow\0";
+pub const XNFilterEvents: &[u8] = b"filterEvents\0";
+pub const XNFocusWindow: &[u8] = b"focusWindow\0";
+pub const XNInputStyle: &[u8] = b"inputStyle\0";
+pub const XNPreeditAttributes: &[u8] = b"preeditAttributes\0";
+pub const XNPreeditCaretCallback: &[u8] = b"preeditCaretCallback\0";
+pub const XNPreeditDoneCallback: &[u8] = b"preeditDoneCallback\0";
+pub const XNPreeditDrawCallback: &[u8] = b"preeditDrawCallback\0";
+pub const XNPreeditStartCallback: &[u8] = b"preeditStartCallback\0";
+pub const XNQueryInputStyle: &[u8] = b"queryInputStyle\0";
+pub const XNSpotLocation: &[u8] = b"spotLocation\0";
+
+// XInput2, where event types are `evtype`s of generic events from the extension
+pub const XIAllDevices: c_int = 0;
+pub const XIAllMasterDevices: c_int = 1;
+pub const XI_DeviceChanged: c_int = 1;
+pub const XI_ButtonPress: c_int = 4;
+pub const XI_ButtonRelease: c_int = 5;
+pub const XI_Motion: c_int = 6;
+pub const XI_RawMotion: c_int = 17;
+pub const XIValuatorClass: c_int = 2;
+pub const XIScrollClass: c_int = 3;
+pub const XIScrollTypeHorizontal: c_int = 2;
+pub const XIPointerEmulated: c_int = 1 << 16;
+
+// XRandR, where event types are relative to the extension's event base
+pub const RR_Connected: c_ushort = 0;
+pub const RR_Interlace: c_ulong = 0x00000010;
+pub const RR_DoubleScan: c_ulong = 0x00000020;
+pub const RRScreenChangeNotify: c_int = 0;
+pub const RRNotify: c_int = 1;
+pub const RRSetConfigSuccess: Status = 0;
+pub const RRScreenChangeNotifyMask: c_int = 1 << 0;
+pub const RRCrtcChangeNotifyMask: c_int = 1 << 1;
+pub const RROutputChangeNotifyMask: c_int = 1 << 2;
+
+/* dynamically loaded functions */
+
+dynamic_library_fns! {
+    /// Functions from `libX11`, loaded at runtime so that Wayland-only systems don't need it installed to run ramen.
+    pub struct Xlib("libX11.so.6") in XLIB {
+        // Connection management
+        fn XInitThreads() -> Status;
+        fn XOpenDisplay(display_name: *const c_char) -> *mut Display;
+        fn XCloseDisplay(display: *mut Display) -> c_int;
+        fn XDefaultScreen(display: *mut Display) -> c_int;
+        fn XRootWindow(display: *mut Display, screen_number: c_int) -> Window;
+        fn XBlackPixel(display: *mut Display, screen_number: c_int) -> c_ulong;
+        fn XDefaultDepth(display: *mut Display, screen_number: c_int) -> c_int;
+        fn XDisplayWidth(display: *mut Display, screen_number: c_int) -> c_int;
+        fn XDisplayHeight(display: *mut Display, screen_number: c_int) -> c_int;
+        fn XDisplayWidthMM(display: *mut Display, screen_number: c_int) -> c_int;
+        fn XDisplayHeightMM(display: *mut Display, screen_number: c_int) -> c_int;
+        fn XFlush(display: *mut Display) -> c_int;
+        fn XSync(display: *mut Display, discard: Bool) -> c_int;
+        fn XConnectionNumber(display: *mut Display) -> c_int;
+        fn XMaxRequestSize(display: *mut Display) -> c_long;
+        fn XQueryExtension(
+            display: *mut Display,
+            name: *const c_char,
+            major_opcode_return: *mut c_int,
+            first_event_return: *mut c_int,
+            first_error_return: *mut c_int,
+        ) -> Bool;
+
+        // Error handling
+        fn XSetErrorHandler(handler: XErrorHandler) -> XErrorHandler;
+        fn XGetErrorText(display: *mut Display, code: c_int, buffer_return: *mut c_char, length: c_int) -> c_int;
+
+        // Window management
+        fn XCreateSimpleWindow(
+            display: *mut Display,
+            parent: Window,
+            x: c_int,
+            y: c_int,
+            width: c_uint,
+            height: c_uint,
+            border_width: c_uint,
+            border: c_ulong,
+            background: c_ulong,
+        ) -> Window;
+        fn XDestroyWindow(display: *mut Display, w: Window) -> c_int;
+        fn XMapWindow(display: *mut Display, w: Window) -> c_int;
+        fn XUnmapWindow(display: *mut Display, w: Window) -> c_int;
+        fn XMoveWindow(display: *mut Display, w: Window, x: c_int, y: c_int) -> c_int;
+        fn XResizeWindow(display: *mut Display, w: Window, width: c_uint, height: c_uint) -> c_int;
+        fn XTranslateCoordinates(
+            display: *mut Display,
+            src_w: Window,
+            dest_w: Window,
+            src_x: c_int,
+            src_y: c_int,
+            dest_x_return: *mut c_int,
+            dest_y_return: *mut c_int,
+            child_return: *mut Window,
+        ) -> Bool;
+
+        // Event queue
+        fn XPending(display: *mut Display) -> c_int;
+        fn XNextEvent(display: *mut Display, event_return: *mut XEvent) -> c_int;
+        fn XSelectInput(display: *mut Display, w: Window, event_mask: c_long) -> c_int;
+        fn XGetEventData(display: *mut Display, cookie: *mut XGenericEventCookie) -> Bool;
+        fn XFreeEventData(display: *mut Display, cookie: *mut XGenericEventCookie);
+        fn XSendEvent(
+            display: *mut Display,
+            w: Window,
+            propagate: Bool,
+            event_mask: c_long,
+            event_send: *mut XEvent,
+        ) -> Status;
+
+        // Cursors & images
+        fn XCreateFontCursor(display: *mut Display, shape: c_uint) -> Cursor;
+        fn XCreatePixmapCursor(
+            display: *mut Display,
+            source: Pixmap,
+            mask: Pixmap,
+            foreground_color: *mut XColor,
+            background_color: *mut XColor,
+            x: c_uint,
+            y: c_uint,
+        ) -> Cursor;
+        fn XDefineCursor(display: *mut Display, w: Window, cursor: Cursor) -> c_int;
+        fn XFreeCursor(display: *mut Display, cursor: Cursor) -> c_int;
+        fn XCreateBitmapFromData(
+            display: *mut Display,
+            d: Drawable,
+            data: *const c_char,
+            width: c_uint,
+            height: c_uint,
+        ) -> Pixmap;
+        fn XCreatePixmap(display: *mut Display, d: Drawable, width: c_uint, height: c_uint, depth: c_uint) -> Pixmap;
+        fn XFreePixmap(display: *mut Display, pixmap: Pixmap) -> c_int;
+        fn XCreateGC(display: *mut Display, d: Drawable, valuemask: c_ulong, values: *mut c_void) -> GC;
+        fn XFreeGC(display: *mut Display, gc: GC) -> c_int;
+        fn XCreateImage(
+            display: *mut Display,
+            visual: *mut Visual,
+            depth: c_uint,
+            format: c_int,
+            offset: c_int,
+            data: *mut c_char,
+            width: c_uint,
+            height: c_uint,
+            bitmap_pad: c_int,
+            bytes_per_line: c_int,
+        ) -> *mut XImage;
+        fn XDestroyImage(ximage: *mut XImage) -> c_int;
+        fn XPutImage(
+            display: *mut Display,
+            d: Drawable,
+            gc: GC,
+            image: *mut XImage,
+            src_x: c_int,
+            src_y: c_int,
+            dest_x: c_int,
+            dest_y: c_int,
+            width: c_uint,
+            height: c_uint,
+        ) -> c_int;
+
+        // Input
+        fn XGrabPointer(
+            display: *mut Display,
+            grab_window: Window,
+            owner_events: Bool,
+            event_mask: c_uint,
+            pointer_mode: c_int,
+            keyboard_mode: c_int,
+            confine_to: Window,
+            cursor: Cursor,
+            time: Time,
+        ) -> c_int;
+        fn XUngrabPointer(display: *mut Display, time: Time) -> c_int;
+        fn XGrabKeyboard(
+            display: *mut Display,
+            grab_window: Window,
+            owner_events: Bool,
+            pointer_mode: c_int,
+            keyboard_mode: c_int,
+            time: Time,
+        ) -> c_int;
+        fn XUngrabKeyboard(display: *mut Display, time: Time) -> c_int;
+        fn XWarpPointer(
+            display: *mut Display,
+            src_w: Window,
+            dest_w: Window,
+            src_x: c_int,
+            src_y: c_int,
+            src_width: c_uint,
+            src_height: c_uint,
+            dest_x: c_int,
+            dest_y: c_int,
+        ) -> c_int;
+        fn XLookupString(
+            event_struct: *mut XKeyEvent,
+            buffer_return: *mut c_char,
+            bytes_buffer: c_int,
+            keysym_return: *mut KeySym,
+            status_in_out: *mut c_void,
+        ) -> c_int;
+        fn Xutf8LookupString(
+            ic: XIC,
+            event: *mut XKeyEvent,
+            buffer_return: *mut c_char,
+            bytes_buffer: c_int,
+            keysym_return: *mut KeySym,
+            status_return: *mut Status,
+        ) -> c_int;
+        fn XkbKeycodeToKeysym(display: *mut Display, kc: c_uchar, group: c_int, level: c_int) -> KeySym;
+        fn XkbSetDetectableAutoRepeat(display: *mut Display, detectable: Bool, supported_rtrn: *mut Bool) -> Bool;
+
+        // Input methods, where the variadic arguments are name & value pairs ending with a null name
+        fn XSupportsLocale() -> Bool;
+        fn XSetLocaleModifiers(modifier_list: *const c_char) -> *mut c_char;
+        fn XOpenIM(display: *mut Display, db: *mut c_void, res_name: *mut c_char, res_class: *mut c_char) -> XIM;
+        fn XCloseIM(im: XIM) -> Status;
+        fn XGetIMValues(im: XIM, ...) -> *mut c_char;
+        fn XCreateIC(im: XIM, ...) -> XIC;
+        fn XDestroyIC(ic: XIC);
+        fn XGetICValues(ic: XIC, ...) -> *mut c_char;
+        fn XSetICValues(ic: XIC, ...) -> *mut c_char;
+        fn XSetICFocus(ic: XIC);
+        fn XUnsetICFocus(ic: XIC);
+        fn XVaCreateNestedList(unused: c_int, ...) -> XVaNestedList;
+        fn XFilterEvent(event: *mut XEvent, w: Window) -> Bool;
+
+        // Window properties
+        fn XInternAtom(display: *mut Display, atom_name: *const c_char, only_if_exists: Bool) -> Atom;
+        fn XChangeProperty(
+            display: *mut Display,
+            w: Window,
+            property: Atom,
+            type_: Atom,
+            format: c_int,
+            mode: c_int,
+            data: *const c_uchar,
+            nelements: c_int,
+        ) -> c_int;
+        fn XGetWindowProperty(
+            display: *mut Display,
+            w: Window,
+            property: Atom,
+            long_offset: c_long,
+            long_length: c_long,
+            delete: Bool,
+            req_type: Atom,
+            actual_type_return: *mut Atom,
+            actual_format_return: *mut c_int,
+            nitems_return: *mut c_ulong,
+            bytes_after_return: *mut c_ulong,
+            prop_return: *mut *mut c_uchar,
+        ) -> c_int;
+        fn XDeleteProperty(display: *mut Display, w: Window, property: Atom) -> c_int;
+        fn XGetAtomName(display: *mut Display, atom: Atom) -> *mut c_char;
+        fn XFree(data: *mut c_void) -> c_int;
+        fn XSetClassHint(display: *mut Display, w: Window, class_hints: *mut XClassHint) -> c_int;
+        fn XStoreName(display: *mut Display, w: Window, window_name: *const c_char) -> c_int;
+        fn XSetWMProtocols(display: *mut Display, w: Window, protocols: *mut Atom, count: c_int) -> Status;
+        fn XSetWMNormalHints(display: *mut Display, w: Window, hints: *mut XSizeHints);
+
+        // Selections
+        fn XSetSelectionOwner(display: *mut Display, selection: Atom, owner: Window, time: Time) -> c_int;
+        fn XGetSelectionOwner(display: *mut Display, selection: Atom) -> Window;
+        fn XConvertSelection(
+            display: *mut Display,
+            selection: Atom,
+            target: Atom,
+            property: Atom,
+            requestor: Window,
+            time: Time,
+        ) -> c_int;
+    }
+}
+
+dynamic_library! {
+    /// Functions from `libXrandr`, which is only needed for multi-monitor support.
+    pub struct Xrandr("libXrandr.so.2") {
+        fn XRRQueryExtension(display: *mut Display, event_base_return: *mut c_int, error_base_return: *mut c_int) -> Bool;
+        fn XRRQueryVersion(display: *mut Display, major_version_return: *mut c_int, minor_version_return: *mut c_int) -> Status;
+        fn XRRSelectInput(display: *mut Display, window: Window, mask: c_int);
+        fn XRRUpdateConfiguration(event: *mut XEvent) -> c_int;
+        fn XRRGetScreenResourcesCurrent(display: *mut Display, window: Window) -> *mut XRRScreenResources;
+        fn XRRFreeScreenResources(resources: *mut XRRScreenResources);
+        fn XRRGetOutputInfo(display: *mut Display, resources: *mut XRRScreenResources, output: RROutput) -> *mut XRROutputInfo;
+        fn XRRFreeOutputInfo(output_info: *mut XRROutputInfo);
+        fn XRRGetCrtcInfo(display: *mut Display, resources: *mut XRRScreenResources, crtc: RRCrtc) -> *mut XRRCrtcInfo;
+        fn XRRFreeCrtcInfo(crtc_info: *mut XRRCrtcInfo);
+        fn XRRGetOutputPrimary(display: *mut Display, window: Window) -> RROutput;
+        fn XRRSetCrtcConfig(display: *mut Display, resources: *mut XRRScreenResources, crtc: RRCrtc, timestamp: Time, x: c_int, y: c_int, mode: RRMode, rotation: Rotation, outputs: *mut RROutput, noutputs: c_int) -> Status;
+    }
+}
+
+pub static XRANDR: LazyCell<Option<Xrandr>> = LazyCell::new(|| unsafe { Xrandr::load() });
+
+dynamic_library! {
+    /// Functions from `libXi`, which is only needed for smooth scrolling.
+    pub struct Xi("libXi.so.6") {
+        fn XIQueryVersion(display: *mut Display, major_version_inout: *mut c_int, minor_version_inout: *mut c_int) -> Status;
+        fn XISelectEvents(display: *mut Display, win: Window, masks: *mut XIEventMask, num_masks: c_int) -> Status;
+        fn XIQueryDevice(display: *mut Display, deviceid: c_int, ndevices_return: *mut c_int) -> *mut XIDeviceInfo;
+        fn XIFreeDeviceInfo(info: *mut XIDeviceInfo);
+    }
+}
+
+pub static XI: LazyCell<Option<Xi>> = LazyCell::new(|| unsafe { Xi::load() });
+
+dynamic_library! {
+    /// Functions from `libXrender`, which is only needed for cursors made from pixels and cursor themes.
+    pub struct Xrender("libXrender.so.1") {
+        fn XRenderQueryExtension(display: *mut Display, event_base_return: *mut c_int, error_base_return: *mut c_int) -> Bool;
+        fn XRenderFindStandardFormat(display: *mut Display, format: c_int) -> *mut XRenderPictFormat;
+        fn XRenderCreatePicture(display: *mut Display, drawable: Drawable, format: *const XRenderPictFormat, valuemask: c_ulong, attributes: *const c_void) -> Picture;
+        fn XRenderFreePicture(display: *mut Display, picture: Picture);
+        fn XRenderCreateCursor(display: *mut Display, source: Picture, x: c_uint, y: c_uint) -> Cursor;
+        fn XRenderCreateAnimCursor(display: *mut Display, ncursor: c_int, cursors: *mut XAnimCursor) -> Cursor;
+    }
+}
+
+pub static XRENDER: LazyCell<Option<Xrender>> = LazyCell::new(|| unsafe { Xrender::load() });
+
+/// Whether `libX11` could be loaded.
+pub fn is_available() -> bool {
+    XLIB.is_some()
+}
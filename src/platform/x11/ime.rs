@@ -0,0 +1,283 @@
+//! Input methods through XIM, which is how IBus, Fcitx and friends talk to X11 clients.
+
+use crate::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{Event, EventQueue},
+    platform::{posix, x11::ffi::*},
+};
+use std::{ffi::CStr, mem, ptr, slice, sync::Once};
+
+/// An input context for one window, along with the input method connection it belongs to.
+pub struct Ime {
+    im: XIM,
+    ic: XIC,
+
+    // The XIM functions taking attributes are variadic, so they're called through the library itself
+    xlib: &'static Xlib,
+
+    // Owned, but only accessed through the pointer the callbacks are given, as they can run during any Xlib call
+    preedit: *mut Preedit,
+}
+
+/// The pre-edit text as the input method has drawn it so far, in characters as that's what XIM counts in.
+#[derive(Default)]
+struct Preedit {
+    text: Vec<char>,
+    caret: usize,
+
+    // Callbacks run in the middle of Xlib calls, so events are held here until the caller picks them up
+    events: Vec<Event>,
+}
+
+impl Preedit {
+    fn changed(&mut self) {
+        let caret = self.text[..self.caret].iter().map(|x| x.len_utf8()).sum();
+        self.events.push(Event::Preedit {
+            text: self.text.iter().collect(),
+            cursor: Some((caret, caret)),
+        });
+    }
+}
+
+/// Xlib picks the encoding of input methods from the C locale, which is ASCII-only until the program sets it.
+/// `LC_CTYPE` is all that matters to it, so that's set from the environment unless the program already picked one.
+fn init_locale() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| unsafe {
+        let current = posix::setlocale(posix::LC_CTYPE, ptr::null());
+        if current.is_null() || CStr::from_ptr(current).to_bytes() == b"C" {
+            let _ = posix::setlocale(posix::LC_CTYPE, b"\0".as_ptr().cast());
+        }
+    });
+}
+
+impl Ime {
+    /// Connects to the input method named in `XMODIFIERS`, and makes an input context for `window`.
+    ///
+    /// This is `None` if there's no input method running, which is common and not an error.
+    pub unsafe fn new(display: *mut Display, window: Window) -> Option<Self> {
+        init_locale();
+        let xlib = (*XLIB).as_ref()?;
+        if XSupportsLocale() == False {
+            return None
+        }
+        let _ = XSetLocaleModifiers(b"\0".as_ptr().cast());
+        let im = XOpenIM(display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut());
+        if im.is_null() {
+            return None
+        }
+
+        // Pre-edit text is ours to draw if the input method allows it, otherwise it draws it in a window of its own
+        let mut styles: *mut XIMStyles = ptr::null_mut();
+        let mut supported: &[XIMStyle] = &[];
+        if (xlib.XGetIMValues)(im, XNQueryInputStyle.as_ptr(), &mut styles, ptr::null::<c_char>()).is_null() &&
+            !styles.is_null()
+        {
+            supported = slice::from_raw_parts((*styles).supported_styles, (*styles).count_styles.into());
+        }
+        let style = [
+            XIMPreeditCallbacks | XIMStatusNothing,
+            XIMPreeditNothing | XIMStatusNothing,
+            XIMPreeditNone | XIMStatusNone,
+        ]
+        .iter()
+        .copied()
+        .find(|x| supported.contains(x));
+        if !styles.is_null() {
+            let _ = XFree(styles.cast());
+        }
+        let style = match style {
+            Some(style) => style,
+            None => {
+                let _ = XCloseIM(im);
+                return None
+            },
+        };
+
+        let preedit = Box::into_raw(Box::<Preedit>::default());
+        let ic = if style & XIMPreeditCallbacks != 0 {
+            let callback = |callback: XICProc| XIMCallback {
+                client_data: preedit.cast(),
+                callback,
+            };
+            let (start, done, draw, caret) = (
+                callback(Some(preedit_start)),
+                callback(Some(preedit_done)),
+                callback(Some(preedit_draw)),
+                callback(Some(preedit_caret)),
+            );
+            let attributes = (xlib.XVaCreateNestedList)(
+                0,
+                XNPreeditStartCallback.as_ptr(),
+                &start,
+                XNPreeditDoneCallback.as_ptr(),
+                &done,
+                XNPreeditDrawCallback.as_ptr(),
+                &draw,
+                XNPreeditCaretCallback.as_ptr(),
+                &caret,
+                ptr::null::<c_char>(),
+            );
+            let ic = (xlib.XCreateIC)(
+                im,
+                XNInputStyle.as_ptr(),
+                style,
+                XNClientWindow.as_ptr(),
+                window,
+                XNFocusWindow.as_ptr(),
+                window,
+                XNPreeditAttributes.as_ptr(),
+                attributes,
+                ptr::null::<c_char>(),
+            );
+            let _ = XFree(attributes);
+            ic
+        } else {
+            (xlib.XCreateIC)(
+                im,
+                XNInputStyle.as_ptr(),
+                style,
+                XNClientWindow.as_ptr(),
+                window,
+                XNFocusWindow.as_ptr(),
+                window,
+                ptr::null::<c_char>(),
+            )
+        };
+        if ic.is_null() {
+            let _ = XCloseIM(im);
+            drop(Box::from_raw(preedit));
+            return None
+        }
+        Some(Self { im, ic, xlib, preedit })
+    }
+
+    /// The events the input method needs the window to select, on top of its own.
+    pub unsafe fn filter_events(&self) -> c_long {
+        let mut mask: c_ulong = 0;
+        let _ = (self.xlib.XGetICValues)(self.ic, XNFilterEvents.as_ptr(), &mut mask, ptr::null::<c_char>());
+        mask as c_long
+    }
+
+    /// Moves events from the pre-edit callbacks into `events`, which should be done after every `XFilterEvent`.
+    pub unsafe fn flush_events(&self, events: &EventQueue) {
+        for event in mem::take(&mut (*self.preedit).events) {
+            events.push(event);
+        }
+    }
+
+    pub unsafe fn set_focus(&self, focused: bool) {
+        if focused {
+            XSetICFocus(self.ic);
+        } else {
+            XUnsetICFocus(self.ic);
+        }
+    }
+
+    /// Places the candidate window, which goes below the spot location as that's where text's baseline would be.
+    pub unsafe fn set_cursor_area(&self, position: PhysicalPosition, size: PhysicalSize) {
+        let clamp = |x: i64| x.clamp(c_short::MIN.into(), c_short::MAX.into()) as c_short;
+        let spot = XPoint {
+            x: clamp(position.x.into()),
+            y: clamp(i64::from(position.y) + i64::from(size.height)),
+        };
+        let attributes = (self.xlib.XVaCreateNestedList)(0, XNSpotLocation.as_ptr(), &spot, ptr::null::<c_char>());
+        let _ = (self.xlib.XSetICValues)(self.ic, XNPreeditAttributes.as_ptr(), attributes, ptr::null::<c_char>());
+        let _ = XFree(attributes);
+    }
+
+    /// Reads the text a key press typed, which is also how the input method hands over committed text.
+    #[allow(non_upper_case_globals)] // Xlib's names for lookup statuses
+    pub unsafe fn lookup_string(&self, event: &mut XKeyEvent) -> String {
+        let mut buffer = vec![0u8; 64];
+        let mut status = 0;
+        loop {
+            let count = Xutf8LookupString(
+                self.ic,
+                event,
+                buffer.as_mut_ptr().cast(),
+                buffer.len() as c_int,
+                ptr::null_mut(),
+                &mut status,
+            );
+            match status {
+                XBufferOverflow => buffer.resize(count as usize, 0),
+                XLookupChars | XLookupBoth => {
+                    buffer.truncate(count.max(0) as usize);
+                    return String::from_utf8_lossy(&buffer).into_owned()
+                },
+                _ => return String::new(),
+            }
+        }
+    }
+}
+
+impl Drop for Ime {
+    fn drop(&mut self) {
+        unsafe {
+            XDestroyIC(self.ic);
+            let _ = XCloseIM(self.im);
+            drop(Box::from_raw(self.preedit));
+        }
+    }
+}
+
+unsafe extern "C" fn preedit_start(_ic: XIC, client_data: XPointer, _call_data: XPointer) -> c_int {
+    let preedit = &mut *(client_data as *mut Preedit);
+    preedit.text.clear();
+    preedit.caret = 0;
+    -1 // No limit on the length of the pre-edit text
+}
+
+unsafe extern "C" fn preedit_done(_ic: XIC, client_data: XPointer, _call_data: XPointer) -> c_int {
+    let preedit = &mut *(client_data as *mut Preedit);
+    if !preedit.text.is_empty() {
+        preedit.text.clear();
+        preedit.caret = 0;
+        preedit.events.push(Event::Preedit {
+            text: String::new(),
+            cursor: None,
+        });
+    }
+    0
+}
+
+unsafe extern "C" fn preedit_draw(_ic: XIC, client_data: XPointer, call_data: XPointer) -> c_int {
+    let preedit = &mut *(client_data as *mut Preedit);
+    let draw = &*(call_data as *const XIMPreeditDrawCallbackStruct);
+
+    // The characters from `chg_first` to `chg_first + chg_length` are replaced with `text`, which is null for deletions
+    // It's in the locale's encoding, which `init_locale` has made UTF-8 on any reasonable system
+    let first = (draw.chg_first.max(0) as usize).min(preedit.text.len());
+    let last = (first + draw.chg_length.max(0) as usize).min(preedit.text.len());
+    let mut text = Vec::new();
+    if let Some(new) = draw.text.as_ref() {
+        if new.encoding_is_wchar == False && !new.string.multi_byte.is_null() {
+            text.extend(CStr::from_ptr(new.string.multi_byte).to_string_lossy().chars());
+        }
+    }
+    let _ = preedit.text.splice(first..last, text);
+    preedit.caret = (draw.caret.max(0) as usize).min(preedit.text.len());
+    preedit.changed();
+    0
+}
+
+#[allow(non_upper_case_globals)] // Xlib's names for caret directions
+unsafe extern "C" fn preedit_caret(_ic: XIC, client_data: XPointer, call_data: XPointer) -> c_int {
+    let preedit = &mut *(client_data as *mut Preedit);
+    let caret = &mut *(call_data as *mut XIMPreeditCaretCallbackStruct);
+    preedit.caret = match caret.direction {
+        XIMForwardChar => preedit.caret + 1,
+        XIMBackwardChar => preedit.caret.saturating_sub(1),
+        XIMLineStart => 0,
+        XIMLineEnd => preedit.text.len(),
+        XIMAbsolutePosition => caret.position.max(0) as usize,
+        _ => preedit.caret,
+    }
+    .min(preedit.text.len());
+
+    // The input method is told where the caret ended up
+    caret.position = preedit.caret as c_int;
+    preedit.changed();
+    0
+}
@@ -0,0 +1,1352 @@
+//! The X11 backend, where every window has a thread of its own with its own Xlib connection.
+
+use crate::{
+    clipboard::Selection,
+    cursor,
+    dpi::{PhysicalPosition, PhysicalSize},
+    drag::{DragData, DragFormat, DragIcon, DropAction},
+    error::{Error, ErrorKind},
+    event::{Event, EventQueue, MouseButton, ScrollDelta},
+    icon::Icon,
+    keyboard::HeldKeys,
+    monitor::{self, Monitor, VideoMode},
+    platform::{
+        posix::{self, str_to_cstring, WakePipe, WatchedFds},
+        x11::{
+            clipboard::{Clipboard, Request as ClipboardRequest},
+            cursor as x11_cursor,
+            dnd::Dnd,
+            ffi::*,
+            ime::Ime,
+            util,
+            xinput::XInput,
+        },
+        xkb,
+    },
+    sync::{self, mutex_lock, Mutex, Sender},
+    window::{CursorGrab, Fullscreen, WindowBuilder},
+};
+use std::{
+    mem, ptr,
+    sync::{
+        atomic::{self, AtomicBool},
+        Arc,
+    },
+    thread,
+    time::Instant,
+};
+
+pub(crate) struct WindowImpl {
+    // What `raw-window-handle` hands out
+    #[cfg_attr(not(feature = "raw-window-handle"), allow(dead_code))]
+    display: *mut Display,
+    #[cfg_attr(not(feature = "raw-window-handle"), allow(dead_code))]
+    screen: c_int,
+    #[cfg_attr(not(feature = "raw-window-handle"), allow(dead_code))]
+    window: Window,
+    shared: Arc<WindowImplShared>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+// Only the window thread makes requests on the display, other than renderers it's handed out to, which is safe as
+// Xlib locks its connections once `XInitThreads` has been called, before any of them were opened
+unsafe impl Send for WindowImpl {}
+unsafe impl Sync for WindowImpl {}
+
+/// State shared between a [`WindowImpl`] and its window thread.
+struct WindowImplShared {
+    commands: Mutex<Vec<Command>>,
+    destroy_flag: AtomicBool,
+    events: Arc<EventQueue>,
+    fullscreen: Mutex<Option<Fullscreen>>,
+    geometry: Mutex<Geometry>,
+    monitors: Mutex<Vec<Monitor>>,
+    scale_factor: Mutex<f64>,
+    wake: WakePipe,
+    watched_fds: Mutex<WatchedFds>,
+}
+
+/// Requests from the user's thread, carried out by the window thread as Xlib connections aren't thread safe.
+enum Command {
+    AcceptDrag(Option<(DragFormat, DropAction)>),
+    ClipboardRequest(ClipboardRequest),
+    ClipboardSet(Selection, Vec<(String, Vec<u8>)>, Sender<Result<(), Error>>),
+    Resize(PhysicalSize),
+    ResizeOuter(PhysicalSize),
+    Move(PhysicalPosition),
+    SetCursor(cursor::Cursor),
+    SetCursorGrab(CursorGrab),
+    SetCursorPosition(PhysicalPosition),
+    SetCursorVisible(bool),
+    SetFullscreen(Option<Fullscreen>),
+    SetIcon(Option<Icon>),
+    SetImeCursorArea(PhysicalPosition, PhysicalSize),
+    SetTitle(String),
+    SetVisible(bool),
+    StartDrag(DragData, DragIcon, Sender<Result<(), Error>>),
+}
+
+/// The window's last known geometry, where the position and outer size include the window manager's frame.
+#[derive(Clone, Copy, Default, PartialEq)]
+struct Geometry {
+    inner_size: PhysicalSize,
+    outer_size: PhysicalSize,
+    position: PhysicalPosition,
+}
+
+/// State of the window thread, living on its stack.
+struct WindowImplState {
+    display: *mut Display,
+    root: Window,
+    window: Window,
+    shared: Arc<WindowImplShared>,
+
+    // Where XRandR's events start, if it's usable
+    randr_event_base: Option<c_int>,
+
+    net_frame_extents: Atom,
+    net_wm_state: Atom,
+    net_wm_state_fullscreen: Atom,
+    resource_manager: Atom,
+    wm_protocols: Atom,
+    wm_delete_window: Atom,
+
+    // What's held down, to tell repeats apart and to release it when focus is lost
+    held_keys: HeldKeys,
+
+    // The input context, if IMEs are enabled and an input method is running
+    ime: Option<Ime>,
+
+    // XInput2, if it's there, which takes over from the core pointer events
+    xinput: Option<XInput>,
+
+    clipboard: Clipboard,
+    dnd: Dnd,
+
+    // The cursor as requested, what it was made into (theme cursors depend on the scale factor),
+    // and the empty cursor shown while it's hidden, made the first time it's needed
+    requested_cursor: cursor::Cursor,
+    cursor: Cursor,
+    cursor_visible: bool,
+    blank_cursor: Cursor,
+
+    // The cursor grab as requested, which only holds while focused, and where a locked cursor is held
+    // There's no locking the cursor in X11, so it's moved back to where it was locked whenever it moves
+    focused: bool,
+    cursor_grab: CursorGrab,
+    cursor_position: Option<PhysicalPosition>,
+    lock_position: Option<PhysicalPosition>,
+
+    // Where the client area is on the screen, and how much the frame adds to each side (left, right, top, bottom)
+    frame_extents: [u32; 4],
+    inner_position: PhysicalPosition,
+    inner_size: PhysicalSize,
+    scale_factor: f64,
+
+    // The fullscreen state last carried out, where the window was before it, and the CRTC it changed the mode of
+    fullscreen: Option<Fullscreen>,
+    windowed_position: Option<PhysicalPosition>,
+    saved_crtc: Option<SavedCrtc>,
+}
+
+/// A CRTC's configuration from before its video mode was changed, so that it can be put back.
+struct SavedCrtc {
+    crtc: RRCrtc,
+    mode: RRMode,
+    x: c_int,
+    y: c_int,
+    rotation: Rotation,
+    outputs: Vec<RROutput>,
+}
+
+/// Sent to `thread::spawn` as a nice package.
+struct WindowImplThreadParams {
+    builder: WindowBuilder,
+    shared: Arc<WindowImplShared>,
+    response: Sender<Result<WindowImpl, Error>>,
+}
+
+impl WindowImpl {
+    pub(crate) fn new(builder: &WindowBuilder) -> Result<Self, Error> {
+        if !is_available() {
+            return Err(Error::new(ErrorKind::Unsupported, "libX11 is not available"))
+        }
+
+        util::init_threads();
+        let (response, receiver) = sync::oneshot();
+        let shared = Arc::new(WindowImplShared {
+            commands: Mutex::new(Vec::new()),
+            destroy_flag: AtomicBool::new(false),
+            events: Arc::new(EventQueue::new()),
+            fullscreen: Mutex::new(builder.fullscreen.clone()),
+            geometry: Mutex::new(Geometry::default()),
+            monitors: Mutex::new(Vec::new()),
+            scale_factor: Mutex::new(1.0),
+            wake: WakePipe::new()?,
+            watched_fds: Mutex::new(WatchedFds::default()),
+        });
+
+        let thread_params = WindowImplThreadParams {
+            // Owned by the thread, as the caller might stop waiting on it (see `creation_timeout`)
+            builder: builder.clone(),
+            shared,
+            response,
+        };
+        let thread = thread::spawn(move || unsafe {
+            let WindowImplThreadParams {
+                builder,
+                shared,
+                response,
+            } = thread_params;
+            let respond = |result| {
+                // If the caller gave up waiting, the unclaimed `WindowImpl` is dropped here
+                // That sets the destroy flag, so the event loop exits as soon as it's entered
+                let _ = response.send(result);
+            };
+
+            // Every window gets its own connection, so that each window thread can block on its own socket
+            // `NULL` means "use the `DISPLAY` environment variable"
+            util::init_error_handling();
+            let display = XOpenDisplay(ptr::null());
+            if display.is_null() {
+                return respond(Err(Error::new(
+                    ErrorKind::ConnectionFailed,
+                    "failed to connect to the X server",
+                )))
+            }
+
+            let screen = XDefaultScreen(display);
+            let root = XRootWindow(display, screen);
+            let black = XBlackPixel(display, screen);
+
+            // The scale factor is needed to place the window, and changes to it are announced on the root window
+            let resource_manager = util::intern_atom(display, b"RESOURCE_MANAGER\0");
+            let _ = XSelectInput(display, root, PropertyChangeMask);
+            let scale_factor = read_scale_factor(display, root, resource_manager);
+            let size = builder.inner_size.to_physical(scale_factor);
+            let (width, height) = (size.width.max(1), size.height.max(1));
+            let mut position = builder.position.map(|x| x.to_physical(scale_factor));
+            let windowed_position = position;
+
+            // Monitors being plugged in and out is announced on the root window too
+            let randr_event_base = init_randr(display, root);
+            let monitors = read_monitors(display, root, randr_event_base.is_some(), scale_factor);
+
+            // A fullscreen window is made on the monitor it's going to cover, as window managers keep it there
+            if let Some(Fullscreen::Borderless(Some(monitor)) | Fullscreen::Exclusive(monitor, _)) = &builder.fullscreen
+            {
+                position = Some(monitor.position);
+            }
+            let PhysicalPosition { x, y } = position.unwrap_or_default();
+            let geometry = Geometry {
+                inner_size: PhysicalSize::new(width, height),
+                outer_size: PhysicalSize::new(width, height),
+                position: PhysicalPosition::new(x, y),
+            };
+            *mutex_lock(&shared.geometry) = geometry;
+            *mutex_lock(&shared.scale_factor) = scale_factor;
+            *mutex_lock(&shared.monitors) = monitors;
+
+            let window = XCreateSimpleWindow(display, root, x, y, width, height, 0, black, black);
+            let ime = if builder.ime { Ime::new(display, window) } else { None };
+            let ime_events = ime.as_ref().map_or(0, |x| x.filter_events());
+            let _ = XSelectInput(
+                display,
+                window,
+                ime_events |
+                    KeyPressMask |
+                    KeyReleaseMask |
+                    ButtonPressMask |
+                    ButtonReleaseMask |
+                    EnterWindowMask |
+                    LeaveWindowMask |
+                    PointerMotionMask |
+                    StructureNotifyMask |
+                    FocusChangeMask |
+                    PropertyChangeMask,
+            );
+
+            // By default, holding a key down sends a release & press pair for every repetition
+            // With this, it's just presses, so repeats can be told apart from releasing the key
+            let _ = XkbSetDetectableAutoRepeat(display, True, ptr::null_mut());
+
+            let mut state = WindowImplState {
+                display,
+                root,
+                window,
+                shared: Arc::clone(&shared),
+                randr_event_base,
+                net_frame_extents: util::intern_atom(display, b"_NET_FRAME_EXTENTS\0"),
+                net_wm_state: util::intern_atom(display, b"_NET_WM_STATE\0"),
+                net_wm_state_fullscreen: util::intern_atom(display, b"_NET_WM_STATE_FULLSCREEN\0"),
+                resource_manager,
+                wm_protocols: util::intern_atom(display, b"WM_PROTOCOLS\0"),
+                wm_delete_window: util::intern_atom(display, b"WM_DELETE_WINDOW\0"),
+                held_keys: HeldKeys::default(),
+                ime,
+                xinput: XInput::new(display, root, window),
+                clipboard: Clipboard::new(display, window),
+                dnd: Dnd::new(display, root, window),
+                requested_cursor: cursor::Cursor::default(),
+                cursor: x11_cursor::create(display, window, &cursor::Cursor::default(), scale_factor),
+                cursor_visible: true,
+                blank_cursor: 0,
+                focused: false,
+                cursor_grab: CursorGrab::None,
+                cursor_position: None,
+                lock_position: None,
+                frame_extents: [0; 4],
+                inner_position: geometry.position,
+                inner_size: geometry.inner_size,
+                scale_factor,
+                fullscreen: builder.fullscreen.clone(),
+                windowed_position,
+                saved_crtc: None,
+            };
+            let _ = XDefineCursor(display, window, state.cursor);
+
+            // Window managers place windows wherever they like, unless they're told the user asked for a position
+            let mut size_hints: XSizeHints = mem::zeroed();
+            size_hints.flags = USSize;
+            size_hints.width = width as c_int;
+            size_hints.height = height as c_int;
+            if position.is_some() {
+                size_hints.flags |= USPosition;
+                size_hints.x = x;
+                size_hints.y = y;
+            }
+            XSetWMNormalHints(display, window, &mut size_hints);
+
+            // Without this, the window manager just kills the connection when the user tries to close the window
+            // With it, it sends a `WM_PROTOCOLS` client message with `WM_DELETE_WINDOW` instead
+            let _ = XSetWMProtocols(display, window, &mut state.wm_delete_window, 1);
+
+            // `WM_CLASS` is two strings: the instance name and the class name
+            // Window managers use these to group windows, and match them against `.desktop` files
+            let class_name = str_to_cstring(builder.class_name.as_ref());
+            let mut class_hint = XClassHint {
+                res_name: class_name.as_ptr() as *mut c_char,
+                res_class: class_name.as_ptr() as *mut c_char,
+            };
+            let _ = XSetClassHint(display, window, &mut class_hint);
+            set_title(display, window, builder.title.as_ref());
+            set_icon(display, window, builder.icon.as_ref());
+
+            // Before the window is mapped, its state is set directly instead of asking the window manager
+            if state.fullscreen.is_some() {
+                let _ = XChangeProperty(
+                    display,
+                    window,
+                    state.net_wm_state,
+                    XA_ATOM,
+                    32,
+                    PropModeReplace,
+                    (&state.net_wm_state_fullscreen as *const Atom).cast(),
+                    1,
+                );
+            }
+            let _ = XMapWindow(display, window);
+
+            // The ID is allocated on our side, so whether creating the window worked is only known after a sync
+            let _ = XSync(display, False);
+            if let Some(err) = util::take_error(ErrorKind::WindowCreationFailed, "failed to create the window") {
+                let _ = XDestroyWindow(display, window);
+                let _ = XCloseDisplay(display);
+                return respond(Err(err))
+            }
+
+            respond(Ok(WindowImpl {
+                display,
+                screen,
+                window,
+                shared: Arc::clone(&shared),
+                thread: None,
+            }));
+            if let Some(Fullscreen::Exclusive(monitor, mode)) = &builder.fullscreen {
+                let _ = set_video_mode(&mut state, monitor, mode);
+            }
+
+            let mut event = mem::MaybeUninit::<XEvent>::uninit();
+            // The connection and the wake pipe come first, then whatever the user's watching
+            let own_fds = [
+                posix::pollfd {
+                    fd: XConnectionNumber(display),
+                    events: posix::POLLIN,
+                    revents: 0,
+                },
+                posix::pollfd {
+                    fd: shared.wake.fd(),
+                    events: posix::POLLIN,
+                    revents: 0,
+                },
+            ];
+            let mut fds = Vec::with_capacity(own_fds.len());
+            loop {
+                // `XPending` flushes the output buffer, and reads anything that's arrived on the socket
+                // Events can end up queued without the socket being readable, so always check before polling
+                while XPending(display) > 0 {
+                    let _ = XNextEvent(display, event.as_mut_ptr());
+
+                    // Input methods get first dibs on every event, and swallow the keys that go into composing text
+                    let filtered = XFilterEvent(event.as_mut_ptr(), 0) != False;
+                    if let Some(ime) = &state.ime {
+                        ime.flush_events(&shared.events);
+                    }
+                    if !filtered {
+                        process_event(&mut state, &mut *event.as_mut_ptr());
+                    }
+                }
+                if let Some(data) = state.clipboard.take_dropped() {
+                    state.dnd.finish_drop(data, &shared.events);
+                }
+                if shared.destroy_flag.load(atomic::Ordering::Acquire) {
+                    break
+                }
+                // Clipboard transfers and drops give up if the other end stops responding, so they need waking up for
+                let deadline = state.clipboard.deadline().into_iter().chain(state.dnd.deadline()).min();
+                let timeout = deadline.map_or(-1, |deadline| {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    remaining.as_millis().saturating_add(1).min(c_int::MAX as u128) as c_int
+                });
+                fds.clear();
+                fds.extend_from_slice(&own_fds);
+                let tokens = mutex_lock(&shared.watched_fds).extend_pollfds(&mut fds);
+                let _ = posix::poll(fds.as_mut_ptr(), fds.len() as _, timeout);
+                mutex_lock(&shared.watched_fds).dispatch(&fds[own_fds.len()..], &tokens, &shared.events);
+                state.clipboard.expire();
+                state.dnd.expire(&shared.events);
+                if fds[1].revents != 0 {
+                    shared.wake.drain();
+                    let commands = mem::take(&mut *mutex_lock(&shared.commands));
+                    for command in commands {
+                        process_command(&mut state, command);
+                    }
+                }
+            }
+
+            restore_video_mode(&mut state);
+            state.ime = None;
+            let _ = XDestroyWindow(display, window);
+            let _ = XCloseDisplay(display);
+        });
+
+        /* Wait for the thread to return the window or an error */
+        let (mut window, thread) = receiver.recv_from_thread(thread, builder.creation_timeout)?;
+        window.thread = Some(thread);
+        Ok(window)
+    }
+
+    #[cfg(feature = "raw-window-handle")]
+    pub(crate) fn raw_window_handle(
+        &self,
+    ) -> Result<raw_window_handle::RawWindowHandle, raw_window_handle::HandleError> {
+        Ok(raw_window_handle::XlibWindowHandle::new(self.window).into())
+    }
+
+    #[cfg(feature = "raw-window-handle")]
+    pub(crate) fn raw_display_handle(
+        &self,
+    ) -> Result<raw_window_handle::RawDisplayHandle, raw_window_handle::HandleError> {
+        Ok(raw_window_handle::XlibDisplayHandle::new(ptr::NonNull::new(self.display.cast()), self.screen).into())
+    }
+
+    #[inline]
+    pub(crate) fn events(&self) -> &Arc<EventQueue> {
+        &self.shared.events
+    }
+
+    pub(crate) fn register_fd(&self, fd: c_int, events: c_short, token: u64) {
+        mutex_lock(&self.shared.watched_fds).register(fd, events, token);
+        self.shared.wake.wake();
+    }
+
+    pub(crate) fn deregister_fd(&self, token: u64) {
+        mutex_lock(&self.shared.watched_fds).deregister(token);
+        // The window thread stops polling it straight away, so it can be closed
+        self.shared.wake.wake();
+    }
+
+    pub(crate) fn available_monitors(&self) -> Vec<Monitor> {
+        mutex_lock(&self.shared.monitors).clone()
+    }
+
+    pub(crate) fn current_monitor(&self) -> Option<Monitor> {
+        let geometry = *mutex_lock(&self.shared.geometry);
+        let monitors = mutex_lock(&self.shared.monitors);
+        monitor::monitor_for_rect(&monitors, geometry.position, geometry.outer_size).cloned()
+    }
+
+    pub(crate) fn fullscreen(&self) -> Option<Fullscreen> {
+        mutex_lock(&self.shared.fullscreen).clone()
+    }
+
+    pub(crate) fn inner_size(&self) -> PhysicalSize {
+        mutex_lock(&self.shared.geometry).inner_size
+    }
+
+    pub(crate) fn outer_size(&self) -> PhysicalSize {
+        mutex_lock(&self.shared.geometry).outer_size
+    }
+
+    pub(crate) fn position(&self) -> Option<PhysicalPosition> {
+        Some(mutex_lock(&self.shared.geometry).position)
+    }
+
+    pub(crate) fn scale_factor(&self) -> f64 {
+        *mutex_lock(&self.shared.scale_factor)
+    }
+
+    pub(crate) fn accept_drag(&self, accept: Option<(DragFormat, DropAction)>) {
+        self.command(Command::AcceptDrag(accept));
+    }
+
+    pub(crate) fn start_drag(&self, data: DragData, icon: DragIcon) -> Result<(), Error> {
+        let (reply, receiver) = sync::oneshot();
+        self.command(Command::StartDrag(data, icon, reply));
+        receiver.recv_reply()
+    }
+
+    pub(crate) fn clipboard_formats(&self, selection: Selection) -> Result<Vec<String>, Error> {
+        let (reply, receiver) = sync::oneshot();
+        self.command(Command::ClipboardRequest(ClipboardRequest::Formats(selection, reply)));
+        receiver.recv_reply()
+    }
+
+    pub(crate) fn clipboard_get(&self, selection: Selection, mime: &str) -> Result<Option<Vec<u8>>, Error> {
+        let (reply, receiver) = sync::oneshot();
+        self.command(Command::ClipboardRequest(ClipboardRequest::Get(
+            selection,
+            mime.into(),
+            reply,
+        )));
+        receiver.recv_reply()
+    }
+
+    pub(crate) fn clipboard_set(&self, selection: Selection, data: Vec<(String, Vec<u8>)>) -> Result<(), Error> {
+        let (reply, receiver) = sync::oneshot();
+        self.command(Command::ClipboardSet(selection, data, reply));
+        receiver.recv_reply()
+    }
+
+    pub(crate) fn set_cursor(&self, cursor: cursor::Cursor) {
+        self.command(Command::SetCursor(cursor));
+    }
+
+    pub(crate) fn set_cursor_grab(&self, grab: CursorGrab) {
+        self.command(Command::SetCursorGrab(grab));
+    }
+
+    pub(crate) fn set_cursor_position(&self, position: PhysicalPosition) {
+        self.command(Command::SetCursorPosition(position));
+    }
+
+    pub(crate) fn set_cursor_visible(&self, visible: bool) {
+        self.command(Command::SetCursorVisible(visible));
+    }
+
+    pub(crate) fn set_fullscreen(&self, fullscreen: Option<Fullscreen>) {
+        // The state is reported as requested right away, like the other backends
+        *mutex_lock(&self.shared.fullscreen) = fullscreen.clone();
+        self.command(Command::SetFullscreen(fullscreen));
+    }
+
+    pub(crate) fn set_icon(&self, icon: Option<Icon>) {
+        self.command(Command::SetIcon(icon));
+    }
+
+    pub(crate) fn set_ime_cursor_area(&self, position: PhysicalPosition, size: PhysicalSize) {
+        self.command(Command::SetImeCursorArea(position, size));
+    }
+
+    pub(crate) fn set_inner_size(&self, size: PhysicalSize) {
+        self.command(Command::Resize(size));
+    }
+
+    pub(crate) fn set_outer_size(&self, size: PhysicalSize) {
+        self.command(Command::ResizeOuter(size));
+    }
+
+    pub(crate) fn set_position(&self, position: PhysicalPosition) {
+        self.command(Command::Move(position));
+    }
+
+    pub(crate) fn set_title(&self, title: &str) {
+        self.command(Command::SetTitle(title.into()));
+    }
+
+    pub(crate) fn set_visible(&self, visible: bool) {
+        self.command(Command::SetVisible(visible));
+    }
+
+    fn command(&self, command: Command) {
+        mutex_lock(&self.shared.commands).push(command);
+        self.shared.wake.wake();
+    }
+}
+
+impl Drop for WindowImpl {
+    fn drop(&mut self) {
+        self.shared.destroy_flag.store(true, atomic::Ordering::Release);
+        self.shared.wake.wake();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+unsafe fn set_title(display: *mut Display, window: Window, title: &str) {
+    // `WM_NAME` is `STRING` (Latin-1) or `COMPOUND_TEXT`, which nobody should have to deal with
+    // EWMH added `_NET_WM_NAME` which is plain UTF-8, and every modern window manager prefers it
+    // We set both, so that ancient window managers at least get something readable for ASCII
+    let title = str_to_cstring(title);
+    let _ = XStoreName(display, window, title.as_ptr());
+    let _ = XChangeProperty(
+        display,
+        window,
+        util::intern_atom(display, b"_NET_WM_NAME\0"),
+        util::intern_atom(display, b"UTF8_STRING\0"),
+        8,
+        PropModeReplace,
+        title.as_ptr().cast(),
+        title.as_bytes().len() as c_int,
+    );
+}
+
+/// Sets `_NET_WM_ICON`, which is every image one after the other as a width, a height, then ARGB pixels.
+///
+/// Like any property with a format of 32, each of those is a `long` on our side, whatever size that is.
+unsafe fn set_icon(display: *mut Display, window: Window, icon: Option<&Icon>) {
+    let property = util::intern_atom(display, b"_NET_WM_ICON\0");
+    let icon = match icon {
+        Some(icon) => icon,
+        None => {
+            let _ = XDeleteProperty(display, window, property);
+            return
+        },
+    };
+    let mut data: Vec<c_ulong> = Vec::new();
+    for image in icon.images() {
+        let size = image.size();
+        data.push(size.width.into());
+        data.push(size.height.into());
+        data.extend(image.rgba().chunks_exact(4).map(|pixel| {
+            let [r, g, b, a] = [pixel[0], pixel[1], pixel[2], pixel[3]].map(c_ulong::from);
+            a << 24 | r << 16 | g << 8 | b
+        }));
+    }
+    let _ = XChangeProperty(
+        display,
+        window,
+        property,
+        XA_CARDINAL,
+        32,
+        PropModeReplace,
+        data.as_ptr().cast(),
+        data.len() as c_int,
+    );
+}
+
+/// Carries out a [`Command`], whose effects show up as events later on.
+unsafe fn process_command(state: &mut WindowImplState, command: Command) {
+    let [left, right, top, bottom] = state.frame_extents;
+    match command {
+        Command::AcceptDrag(accept) => state.dnd.set_accepted(accept),
+        Command::ClipboardRequest(request) => state.clipboard.request(request),
+        Command::ClipboardSet(selection, data, reply) => {
+            let _ = reply.send(state.clipboard.set(selection, data));
+        },
+        Command::Resize(size) => {
+            let _ = XResizeWindow(state.display, state.window, size.width.max(1), size.height.max(1));
+        },
+        Command::ResizeOuter(size) => {
+            let width = size.width.saturating_sub(left + right).max(1);
+            let height = size.height.saturating_sub(top + bottom).max(1);
+            let _ = XResizeWindow(state.display, state.window, width, height);
+        },
+        Command::Move(PhysicalPosition { x, y }) => {
+            // With the default gravity (`NorthWestGravity`), the window manager moves the frame's corner here
+            let _ = XMoveWindow(state.display, state.window, x, y);
+        },
+        Command::SetCursor(cursor) => {
+            // If it can't be made, the old one stays
+            let x_cursor = x11_cursor::create(state.display, state.window, &cursor, state.scale_factor);
+            state.requested_cursor = cursor;
+            if x_cursor != 0 {
+                let _ = XFreeCursor(state.display, state.cursor);
+                state.cursor = x_cursor;
+                update_cursor(state);
+            }
+        },
+        Command::SetCursorPosition(position) => {
+            // A locked cursor would only be moved back, so it's locked here instead
+            if state.lock_position.is_some() {
+                state.lock_position = Some(position);
+            }
+            let _ = XWarpPointer(state.display, 0, state.window, 0, 0, 0, 0, position.x, position.y);
+        },
+        Command::SetCursorVisible(visible) => {
+            state.cursor_visible = visible;
+            update_cursor(state);
+        },
+        Command::SetCursorGrab(grab) => {
+            state.cursor_grab = grab;
+            update_cursor_grab(state);
+        },
+        Command::SetFullscreen(fullscreen) => set_fullscreen(state, fullscreen),
+        Command::SetIcon(icon) => set_icon(state.display, state.window, icon.as_ref()),
+        Command::SetImeCursorArea(position, size) => {
+            if let Some(ime) = &state.ime {
+                ime.set_cursor_area(position, size);
+            }
+        },
+        Command::SetTitle(title) => set_title(state.display, state.window, &title),
+        Command::SetVisible(true) => {
+            let _ = XMapWindow(state.display, state.window);
+        },
+        Command::SetVisible(false) => {
+            let _ = XUnmapWindow(state.display, state.window);
+        },
+        Command::StartDrag(data, icon, reply) => {
+            let _ = reply.send(start_drag(state, data, icon));
+        },
+    }
+}
+
+/// Offers something as `XdndSelection`, and starts dragging it out of the window.
+unsafe fn start_drag(state: &mut WindowImplState, data: DragData, icon: DragIcon) -> Result<(), Error> {
+    if state.dnd.dragging_out() {
+        return Err(Error::new(
+            ErrorKind::Other,
+            "something is already being dragged out of the window",
+        ))
+    }
+    let targets = state.clipboard.set_dragged(data.into_formats())?;
+    let cursor = match icon {
+        DragIcon::None => 0,
+        DragIcon::Image(image) => x11_cursor::create(
+            state.display,
+            state.window,
+            &cursor::Cursor::Custom(image),
+            state.scale_factor,
+        ),
+    };
+    let result = state.dnd.start_drag_out(targets, cursor, state.clipboard.time());
+    // The grab keeps the cursor around for as long as it needs it
+    if cursor != 0 {
+        let _ = XFreeCursor(state.display, cursor);
+    }
+    result
+}
+
+/// Shows the current cursor over the window, or the blank one if it's hidden.
+unsafe fn update_cursor(state: &mut WindowImplState) {
+    let cursor = if state.cursor_visible {
+        state.cursor
+    } else {
+        if state.blank_cursor == 0 {
+            state.blank_cursor = x11_cursor::create_blank(state.display, state.window);
+        }
+        state.blank_cursor
+    };
+    let _ = XDefineCursor(state.display, state.window, cursor);
+}
+
+/// Grabs the pointer as [`WindowImplState::cursor_grab`] says if the window is focused, or lets go of it.
+///
+/// Grabs are let go of when the window loses focus, otherwise the cursor couldn't get to other windows.
+unsafe fn update_cursor_grab(state: &mut WindowImplState) {
+    // Dragging something out of the window has its own grab, and this is called again once it's let go of
+    if state.dnd.grabbing() {
+        return
+    }
+    let _ = XUngrabPointer(state.display, CurrentTime);
+    state.lock_position = None;
+    if !state.focused || state.cursor_grab == CursorGrab::None {
+        return
+    }
+
+    // With `owner_events`, the window gets its events as usual, the grab only keeps the pointer in it
+    let mask = ButtonPressMask | ButtonReleaseMask | EnterWindowMask | LeaveWindowMask | PointerMotionMask;
+    let result = XGrabPointer(
+        state.display,
+        state.window,
+        True,
+        mask as c_uint,
+        GrabModeAsync,
+        GrabModeAsync,
+        state.window,
+        0,
+        CurrentTime,
+    );
+    if result == GrabSuccess && state.cursor_grab == CursorGrab::Locked {
+        let size = state.inner_size;
+        let inside =
+            |p: &PhysicalPosition| p.x >= 0 && p.y >= 0 && (p.x as u32) < size.width && (p.y as u32) < size.height;
+        let position = state
+            .cursor_position
+            .filter(inside)
+            .unwrap_or_else(|| PhysicalPosition::new((size.width / 2) as i32, (size.height / 2) as i32));
+        state.lock_position = Some(position);
+        let _ = XWarpPointer(state.display, 0, state.window, 0, 0, 0, 0, position.x, position.y);
+    }
+}
+
+/// Sends the cursor's new position if it changed, or puts it back if it's locked.
+unsafe fn cursor_moved(state: &mut WindowImplState, position: PhysicalPosition) {
+    if let Some(lock_position) = state.lock_position {
+        // Moving it back sends motion to where it was locked, which is skipped here like any other
+        if position != lock_position {
+            let (x, y) = (lock_position.x, lock_position.y);
+            let _ = XWarpPointer(state.display, 0, state.window, 0, 0, 0, 0, x, y);
+        }
+        return
+    }
+    if state.cursor_position != Some(position) {
+        state.cursor_position = Some(position);
+        state.shared.events.push(Event::CursorMoved(position));
+    }
+}
+
+/// Makes the window fullscreen or not, switching the video mode for exclusive fullscreen.
+unsafe fn set_fullscreen(state: &mut WindowImplState, fullscreen: Option<Fullscreen>) {
+    restore_video_mode(state);
+    let monitor = match &fullscreen {
+        Some(Fullscreen::Borderless(monitor)) => monitor.as_ref(),
+        Some(Fullscreen::Exclusive(monitor, _)) => Some(monitor),
+        None => None,
+    };
+
+    // Window managers keep fullscreen windows where they are, so going to another monitor means leaving first
+    if state.fullscreen.is_some() && (monitor.is_some() || fullscreen.is_none()) {
+        send_fullscreen_state(state, false);
+    }
+    if state.fullscreen.is_none() && fullscreen.is_some() {
+        state.windowed_position = Some(mutex_lock(&state.shared.geometry).position);
+    }
+    if let Some(Fullscreen::Exclusive(monitor, mode)) = &fullscreen {
+        // If the mode can't be set, this carries on as borderless
+        let _ = set_video_mode(state, monitor, mode);
+    }
+    let position = match monitor {
+        Some(monitor) => Some(monitor.position),
+        None if fullscreen.is_none() => state.windowed_position.take(),
+        None => None,
+    };
+    if let Some(PhysicalPosition { x, y }) = position {
+        let _ = XMoveWindow(state.display, state.window, x, y);
+    }
+    if fullscreen.is_some() {
+        send_fullscreen_state(state, true);
+    }
+    state.fullscreen = fullscreen;
+}
+
+/// Asks the window manager to add or remove `_NET_WM_STATE_FULLSCREEN`, as it owns the state of mapped windows.
+unsafe fn send_fullscreen_state(state: &WindowImplState, fullscreen: bool) {
+    let mut event: XEvent = mem::zeroed();
+    event.client_message = XClientMessageEvent {
+        type_: ClientMessage,
+        serial: 0,
+        send_event: True,
+        display: state.display,
+        window: state.window,
+        message_type: state.net_wm_state,
+        format: 32,
+        // The action is 0 to remove or 1 to add, and the 1 at the end means it's from a normal application
+        data: [
+            c_long::from(fullscreen),
+            state.net_wm_state_fullscreen as c_long,
+            0,
+            1,
+            0,
+        ],
+    };
+    let _ = XSendEvent(
+        state.display,
+        state.root,
+        False,
+        SubstructureRedirectMask | SubstructureNotifyMask,
+        &mut event,
+    );
+}
+
+/// Switches the CRTC showing a monitor to one of its video modes, saving how it was first.
+unsafe fn set_video_mode(state: &mut WindowImplState, monitor: &Monitor, mode: &VideoMode) -> bool {
+    let xrandr = match (*XRANDR).as_ref().filter(|_| state.randr_event_base.is_some()) {
+        Some(xrandr) => xrandr,
+        None => return false,
+    };
+    let display = state.display;
+    let resources = (xrandr.XRRGetScreenResourcesCurrent)(display, state.root);
+    if resources.is_null() {
+        return false
+    }
+    let mut switched = false;
+    let info = (xrandr.XRRGetOutputInfo)(display, resources, monitor.id as RROutput);
+    if !info.is_null() {
+        let crtc_info = if (*info).crtc != 0 {
+            (xrandr.XRRGetCrtcInfo)(display, resources, (*info).crtc)
+        } else {
+            ptr::null_mut()
+        };
+        if !crtc_info.is_null() {
+            let crtc = &*crtc_info;
+            let saved = SavedCrtc {
+                crtc: (*info).crtc,
+                mode: crtc.mode,
+                x: crtc.x,
+                y: crtc.y,
+                rotation: crtc.rotation,
+                // Xrandr hands out null rather than an empty array for a CRTC with no outputs
+                outputs: util::array(crtc.outputs, crtc.noutput).to_vec(),
+            };
+            switched = (xrandr.XRRSetCrtcConfig)(
+                display,
+                resources,
+                saved.crtc,
+                CurrentTime,
+                crtc.x,
+                crtc.y,
+                mode.id as RRMode,
+                crtc.rotation,
+                crtc.outputs,
+                crtc.noutput,
+            ) == RRSetConfigSuccess;
+            if switched && state.saved_crtc.is_none() {
+                state.saved_crtc = Some(saved);
+            }
+            (xrandr.XRRFreeCrtcInfo)(crtc_info);
+        }
+        (xrandr.XRRFreeOutputInfo)(info);
+    }
+    (xrandr.XRRFreeScreenResources)(resources);
+    switched
+}
+
+/// Puts back the video mode that [`set_video_mode`] changed, if it changed one.
+unsafe fn restore_video_mode(state: &mut WindowImplState) {
+    if let (Some(mut saved), Some(xrandr)) = (state.saved_crtc.take(), &*XRANDR) {
+        let resources = (xrandr.XRRGetScreenResourcesCurrent)(state.display, state.root);
+        if !resources.is_null() {
+            let _ = (xrandr.XRRSetCrtcConfig)(
+                state.display,
+                resources,
+                saved.crtc,
+                CurrentTime,
+                saved.x,
+                saved.y,
+                saved.mode,
+                saved.rotation,
+                saved.outputs.as_mut_ptr(),
+                saved.outputs.len() as c_int,
+            );
+            (xrandr.XRRFreeScreenResources)(resources);
+        }
+    }
+}
+
+/// Works out the outer geometry from the client area and frame extents, sending events for whatever changed.
+fn update_geometry(state: &mut WindowImplState) {
+    let [left, right, top, bottom] = state.frame_extents;
+    let PhysicalSize { width, height } = state.inner_size;
+    let PhysicalPosition { x, y } = state.inner_position;
+    let geometry = Geometry {
+        inner_size: state.inner_size,
+        outer_size: PhysicalSize::new(width + left + right, height + top + bottom),
+        position: PhysicalPosition::new(x - left as i32, y - top as i32),
+    };
+
+    let mut shared_geometry = mutex_lock(&state.shared.geometry);
+    if *shared_geometry != geometry {
+        let events = &state.shared.events;
+        if shared_geometry.inner_size != geometry.inner_size {
+            events.push(Event::Resized(geometry.inner_size));
+        }
+        if shared_geometry.position != geometry.position {
+            events.push(Event::Moved(geometry.position));
+        }
+        *shared_geometry = geometry;
+    }
+}
+
+/// Reads the scale factor from the `Xft.dpi` resource, which is what desktop environments set for HiDPI.
+///
+/// The `RESOURCE_MANAGER` property is read directly, as Xlib only reads it once when connecting.
+unsafe fn read_scale_factor(display: *mut Display, root: Window, resource_manager: Atom) -> f64 {
+    util::get_property8(display, root, resource_manager, XA_STRING)
+        .and_then(|resources| util::parse_xft_dpi(&resources))
+        .map_or(1.0, |dpi| dpi / 96.0)
+}
+
+/// Checks that XRandR 1.3 or newer is available, and if so subscribes to monitor changes and returns its event base.
+unsafe fn init_randr(display: *mut Display, root: Window) -> Option<c_int> {
+    let xrandr = (*XRANDR).as_ref()?;
+    let (mut event_base, mut error_base) = (0, 0);
+    let (mut major, mut minor) = (0, 0);
+    if (xrandr.XRRQueryExtension)(display, &mut event_base, &mut error_base) == False ||
+        (xrandr.XRRQueryVersion)(display, &mut major, &mut minor) == 0 ||
+        (major, minor) < (1, 3)
+    {
+        return None
+    }
+    (xrandr.XRRSelectInput)(
+        display,
+        root,
+        RRScreenChangeNotifyMask | RRCrtcChangeNotifyMask | RROutputChangeNotifyMask,
+    );
+    Some(event_base)
+}
+
+/// Lists the monitors that are turned on, or pretends the whole screen is one monitor without XRandR.
+unsafe fn read_monitors(display: *mut Display, root: Window, randr: bool, scale_factor: f64) -> Vec<Monitor> {
+    let screen = XDefaultScreen(display);
+    let bit_depth = XDefaultDepth(display, screen) as u16;
+    let mut monitors = Vec::new();
+    if let Some(xrandr) = (*XRANDR).as_ref().filter(|_| randr) {
+        let resources = (xrandr.XRRGetScreenResourcesCurrent)(display, root);
+        if resources.is_null() {
+            return monitors
+        }
+        let primary = (xrandr.XRRGetOutputPrimary)(display, root);
+        let modes = util::array((*resources).modes, (*resources).nmode);
+        let outputs = util::array((*resources).outputs, (*resources).noutput);
+        for &output in outputs {
+            let info = (xrandr.XRRGetOutputInfo)(display, resources, output);
+            if info.is_null() {
+                continue
+            }
+
+            // Outputs without a CRTC are connected, but turned off
+            if (*info).connection == RR_Connected && (*info).crtc != 0 {
+                let crtc = (xrandr.XRRGetCrtcInfo)(display, resources, (*info).crtc);
+                if !crtc.is_null() {
+                    let name = util::array((*info).name as *const u8, (*info).nameLen);
+                    let video_modes = util::array((*info).modes, (*info).nmode)
+                        .iter()
+                        .filter_map(|&id| modes.iter().find(|x| x.id == id))
+                        .filter_map(|mode| {
+                            Some(VideoMode {
+                                id: mode.id as usize,
+                                size: PhysicalSize::new(mode.width, mode.height),
+                                bit_depth,
+                                refresh_rate_millihertz: util::mode_refresh_rate(mode)?,
+                            })
+                        })
+                        .collect();
+                    let (mm_width, mm_height) = ((*info).mm_width as u32, (*info).mm_height as u32);
+                    monitors.push(Monitor {
+                        id: output as usize,
+                        name: String::from_utf8_lossy(name).into_owned(),
+                        position: PhysicalPosition::new((*crtc).x, (*crtc).y),
+                        size: PhysicalSize::new((*crtc).width, (*crtc).height),
+                        physical_size_mm: (mm_width != 0 && mm_height != 0).then_some((mm_width, mm_height)),
+                        refresh_rate_millihertz: modes
+                            .iter()
+                            .find(|x| x.id == (*crtc).mode)
+                            .and_then(util::mode_refresh_rate),
+                        scale_factor,
+                        primary: output == primary,
+                        video_modes,
+                    });
+                    (xrandr.XRRFreeCrtcInfo)(crtc);
+                }
+            }
+            (xrandr.XRRFreeOutputInfo)(info);
+        }
+        (xrandr.XRRFreeScreenResources)(resources);
+    } else {
+        let (mm_width, mm_height) = (XDisplayWidthMM(display, screen), XDisplayHeightMM(display, screen));
+        monitors.push(Monitor {
+            id: 0,
+            name: String::from("default"),
+            position: PhysicalPosition::new(0, 0),
+            size: PhysicalSize::new(
+                XDisplayWidth(display, screen) as u32,
+                XDisplayHeight(display, screen) as u32,
+            ),
+            physical_size_mm: (mm_width > 0 && mm_height > 0).then_some((mm_width as u32, mm_height as u32)),
+            refresh_rate_millihertz: None,
+            scale_factor,
+            primary: true,
+            video_modes: Vec::new(),
+        });
+    }
+    monitors
+}
+
+/// Re-reads the monitors, sending events for the ones that were connected or disconnected.
+unsafe fn update_monitors(state: &mut WindowImplState) {
+    let monitors = read_monitors(
+        state.display,
+        state.root,
+        state.randr_event_base.is_some(),
+        state.scale_factor,
+    );
+    monitor::update_monitors(&mut mutex_lock(&state.shared.monitors), monitors, &state.shared.events);
+}
+
+/// Translates an X event into ramen events, if it has any meaning to us.
+#[allow(non_upper_case_globals)] // Xlib's names for event types
+unsafe fn process_event(state: &mut WindowImplState, event: &mut XEvent) {
+    let events = &state.shared.events;
+    let randr_event = state
+        .randr_event_base
+        .map(|base| event.type_ - base)
+        .filter(|&x| x == RRScreenChangeNotify || x == RRNotify);
+
+    // Selections are changed and asked for as of the last time the user did something
+    match event.type_ {
+        KeyPress | KeyRelease | ButtonPress | ButtonRelease | MotionNotify | EnterNotify | LeaveNotify => {
+            // These all have the time in the same place
+            state.clipboard.update_time(event.key.time);
+        },
+        PropertyNotify => state.clipboard.update_time(event.property.time),
+        _ => (),
+    }
+
+    // While something's being dragged out of the window, the pointer's grabbed to follow it around
+    if state.dnd.grabbing() && state.dnd.process_grabbed_event(event, events) {
+        if !state.dnd.grabbing() {
+            update_cursor_grab(state);
+        }
+        return
+    }
+
+    match event.type_ {
+        _ if randr_event.is_some() => {
+            // This keeps Xlib's idea of the screen size up to date, which is never used, but it's good manners
+            if let Some(xrandr) = &*XRANDR {
+                let _ = (xrandr.XRRUpdateConfiguration)(event);
+            }
+            update_monitors(state);
+        },
+        ClientMessage => {
+            let message = &event.client_message;
+            if message.message_type == state.wm_protocols {
+                if message.data[0] as Atom == state.wm_delete_window {
+                    events.push(Event::CloseRequested);
+                }
+            } else {
+                let _ = state.dnd.process_client_message(message, &mut state.clipboard, events);
+            }
+        },
+        ConfigureNotify => {
+            let configure = &event.configure;
+            state.inner_size = PhysicalSize::new(configure.width as u32, configure.height as u32);
+
+            // The coordinates in the event are relative to the parent, which is usually a frame that
+            // the window manager has put around us, so we ask for the coordinates relative to the screen
+            let (mut x, mut y, mut child) = (0, 0, 0);
+            let (display, window, root) = (state.display, state.window, state.root);
+            if XTranslateCoordinates(display, window, root, 0, 0, &mut x, &mut y, &mut child) != False {
+                state.inner_position = PhysicalPosition::new(x, y);
+            }
+            update_geometry(state);
+        },
+        PropertyNotify if event.property.atom == state.net_frame_extents => {
+            // EWMH window managers set this on our window, as the frame isn't visible to us otherwise
+            state.frame_extents =
+                util::get_property32(state.display, state.window, state.net_frame_extents, XA_CARDINAL, 4)
+                    .filter(|x| x.len() == 4)
+                    .map(|x| [x[0] as u32, x[1] as u32, x[2] as u32, x[3] as u32])
+                    .unwrap_or([0; 4]);
+            update_geometry(state);
+        },
+        PropertyNotify if event.property.atom == state.resource_manager => {
+            let root = event.property.window;
+            let scale_factor = read_scale_factor(state.display, root, state.resource_manager);
+            if scale_factor != state.scale_factor {
+                // Keep the logical size the same, unless the user decides otherwise when they get the event
+                let suggested_size = state
+                    .inner_size
+                    .to_logical(state.scale_factor)
+                    .to_physical(scale_factor);
+                state.scale_factor = scale_factor;
+                *mutex_lock(&state.shared.scale_factor) = scale_factor;
+                events.push(Event::ScaleFactorChanged {
+                    scale_factor,
+                    suggested_size,
+                });
+                process_command(state, Command::Resize(suggested_size));
+                if let cursor::Cursor::Shape(_) = state.requested_cursor {
+                    process_command(state, Command::SetCursor(state.requested_cursor.clone()));
+                }
+                update_monitors(state);
+            }
+        },
+        PropertyNotify | SelectionClear | SelectionNotify | SelectionRequest => state.clipboard.process_event(event),
+        // `NotifyPointer` is about the pointer being in the window that's focused, ignore that
+        FocusIn | FocusOut if event.focus_change.detail != NotifyPointer => {
+            // Exclusive fullscreen only keeps its video mode while the window is focused
+            let focused = event.type_ == FocusIn;
+            if !focused {
+                restore_video_mode(state);
+            } else if let (Some(Fullscreen::Exclusive(monitor, mode)), None) = (&state.fullscreen, &state.saved_crtc) {
+                let (monitor, mode) = (monitor.clone(), mode.clone());
+                let _ = set_video_mode(state, &monitor, &mode);
+            }
+            if let Some(ime) = &state.ime {
+                ime.set_focus(focused);
+            }
+            if !focused {
+                state.held_keys.release_all(&state.shared.events);
+            }
+            state.focused = focused;
+            update_cursor_grab(state);
+            state.shared.events.push(Event::Focus(focused));
+        },
+        KeyPress | KeyRelease => {
+            let pressed = event.type_ == KeyPress;
+            let keycode = event.key.keycode;
+
+            // Input methods hand over committed text as key presses with no keycode, which aren't real keys
+            if keycode != 0 {
+                // With XKB, the layout group is in bits 13 and 14 of the state, the modifiers are in the low byte
+                let group = (event.key.state >> 13) & 0b11;
+                let keysym = XkbKeycodeToKeysym(state.display, keycode as c_uchar, group as c_int, 0);
+                let (key_code, logical_key) = (xkb::key_code(keycode), xkb::keysym_to_key(keysym as u32));
+                let repeat = pressed && state.held_keys.update(keycode, key_code, logical_key, pressed);
+                events.push(Event::Key {
+                    scancode: keycode,
+                    key_code,
+                    logical_key,
+                    pressed,
+                    repeat,
+                    modifiers: xkb::modifiers(event.key.state),
+                });
+            }
+
+            if pressed {
+                let text: String = match &state.ime {
+                    Some(ime) => ime.lookup_string(&mut event.key),
+                    None => {
+                        // This is Latin-1, which maps one-to-one onto the first 256 Unicode code points
+                        let mut buffer = [0 as c_char; 32];
+                        let count = XLookupString(
+                            &mut event.key,
+                            buffer.as_mut_ptr(),
+                            buffer.len() as c_int,
+                            ptr::null_mut(),
+                            ptr::null_mut(),
+                        );
+                        buffer[..count.max(0) as usize]
+                            .iter()
+                            .map(|&x| char::from(x as u8))
+                            .collect()
+                    },
+                };
+                let text: String = text.chars().filter(|x| !x.is_control()).collect();
+                if !text.is_empty() {
+                    events.push(Event::Text(text));
+                }
+            }
+        },
+        GenericEvent => {
+            let focused = state.focused;
+            let position = match &mut state.xinput {
+                Some(xinput) => xinput.process_event(state.display, event, events, focused),
+                None => None,
+            };
+            if let Some(position) = position {
+                cursor_moved(state, position);
+            }
+        },
+        EnterNotify | LeaveNotify => {
+            if event.type_ == EnterNotify {
+                // Scroll valuators that moved while the cursor was elsewhere would look like one big scroll
+                if let Some(xinput) = &mut state.xinput {
+                    xinput.query_scroll_valuators(state.display);
+                }
+                events.push(Event::CursorEntered);
+                cursor_moved(state, PhysicalPosition::new(event.crossing.x, event.crossing.y));
+            } else {
+                state.cursor_position = None;
+                events.push(Event::CursorLeft);
+            }
+        },
+        ButtonPress | ButtonRelease => {
+            if let Some(event) = button_event(event.button.button, event.type_ == ButtonPress) {
+                events.push(event);
+            }
+        },
+        MotionNotify => cursor_moved(state, PhysicalPosition::new(event.motion.x, event.motion.y)),
+        _ => (),
+    }
+}
+
+/// Translates a core or XInput2 button number into an event.
+pub(super) fn button_event(button: c_uint, pressed: bool) -> Option<Event> {
+    let button = match button {
+        1 => MouseButton::Left,
+        2 => MouseButton::Middle,
+        3 => MouseButton::Right,
+        // The wheel is buttons 4 to 7, which are pressed and released for every notch
+        4..=7 if pressed => {
+            let delta = match button {
+                4 => ScrollDelta::Lines(0.0, 1.0),
+                5 => ScrollDelta::Lines(0.0, -1.0),
+                6 => ScrollDelta::Lines(-1.0, 0.0),
+                _ => ScrollDelta::Lines(1.0, 0.0),
+            };
+            return Some(Event::MouseWheel(delta))
+        },
+        4..=7 => return None,
+        // Back and forward are 8 and 9, right after the wheel
+        other => MouseButton::Other((other - 4) as u16),
+    };
+    Some(Event::MouseButton { button, pressed })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{button_event, WindowImpl};
+    use crate::{
+        event::{Event, MouseButton, ScrollDelta},
+        window::Window,
+    };
+    use std::env;
+
+    #[test]
+    fn buttons_skip_the_wheel() {
+        let button = |button| Some(Event::MouseButton { button, pressed: true });
+        assert_eq!(button_event(1, true), button(MouseButton::Left));
+        assert_eq!(button_event(3, true), button(MouseButton::Right));
+        assert_eq!(
+            button_event(4, true),
+            Some(Event::MouseWheel(ScrollDelta::Lines(0.0, 1.0)))
+        );
+        assert_eq!(button_event(4, false), None);
+        assert_eq!(button_event(8, true), button(MouseButton::Other(4)));
+        assert_eq!(button_event(9, true), button(MouseButton::Other(5)));
+    }
+
+    // Needs an X server, such as `Xvfb :99 & DISPLAY=:99 cargo test`
+    #[test]
+    fn create_and_drop() {
+        if env::var_os("DISPLAY").is_none() {
+            return
+        }
+        let mut builder = Window::builder();
+        builder.title("ramen x11 test").class_name("ramen_test");
+        assert!(WindowImpl::new(&builder).is_ok());
+    }
+
+    #[cfg(feature = "raw-window-handle")]
+    #[test]
+    fn raw_handles_are_xlib() {
+        use raw_window_handle::{RawDisplayHandle, RawWindowHandle};
+
+        if env::var_os("DISPLAY").is_none() {
+            return
+        }
+        let window = WindowImpl::new(&Window::builder()).unwrap();
+        match window.raw_window_handle() {
+            Ok(RawWindowHandle::Xlib(handle)) => assert_ne!(handle.window, 0),
+            other => panic!("expected an Xlib window handle, got {:?}", other),
+        }
+        match window.raw_display_handle() {
+            Ok(RawDisplayHandle::Xlib(handle)) => assert!(handle.display.is_some()),
+            other => panic!("expected an Xlib display handle, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! Utility functions for interacting with Xlib.
+
+use crate::{
+    error::{Error, ErrorKind},
+    platform::x11::ffi::*,
+};
+use std::{
+    cell::Cell,
+    ffi::CStr,
+    ptr, slice,
+    sync::{Once, OnceLock},
+};
+
+/// The error handler that was installed before ours, which is called for errors on threads that aren't ours.
+static PREVIOUS_ERROR_HANDLER: OnceLock<XErrorHandler> = OnceLock::new();
+
+thread_local! {
+    /// Whether this is a window thread, for which we deal with errors ourselves.
+    static IS_WINDOW_THREAD: Cell<bool> = const { Cell::new(false) };
+
+    /// The first error that occurred on this thread since the last [`take_error`].
+    static FIRST_ERROR: Cell<Option<(*mut Display, c_uchar)>> = const { Cell::new(None) };
+}
+
+/// Makes Xlib lock its connections, so that a display handed out to a renderer can be used alongside the window thread.
+///
+/// This has to happen before any connection is opened, so it's done before the first window thread is spawned.
+pub fn init_threads() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| unsafe {
+        let _ = XInitThreads();
+    });
+}
+
+/// Installs our error handler, and makes it handle errors from requests made by the current thread.
+///
+/// Xlib's default error handler prints the error and exits the process, which is rather unhelpful.
+/// Errors are reported asynchronously, so after `XSync`, [`take_error`] says whether anything failed.
+pub fn init_error_handling() {
+    let _ = PREVIOUS_ERROR_HANDLER.get_or_init(|| unsafe { XSetErrorHandler(Some(error_handler)) });
+    IS_WINDOW_THREAD.with(|x| x.set(true));
+}
+
+unsafe extern "C" fn error_handler(display: *mut Display, error_event: *mut XErrorEvent) -> c_int {
+    if IS_WINDOW_THREAD.with(Cell::get) {
+        FIRST_ERROR.with(|x| {
+            if x.get().is_none() {
+                x.set(Some((display, (*error_event).error_code)));
+            }
+        });
+        0
+    } else {
+        match PREVIOUS_ERROR_HANDLER.get() {
+            Some(Some(handler)) => handler(display, error_event),
+            _ => 0,
+        }
+    }
+}
+
+/// Takes the first error reported on this thread since the last call, converting it into an [`Error`].
+///
+/// Remember to `XSync` first, so that every request made has actually been answered.
+pub unsafe fn take_error(kind: ErrorKind, message: &str) -> Option<Error> {
+    let (display, code) = FIRST_ERROR.with(Cell::take)?;
+    let mut buffer = [0 as c_char; 256];
+    let _ = XGetErrorText(display, code.into(), buffer.as_mut_ptr(), buffer.len() as c_int);
+    let description = CStr::from_ptr(buffer.as_ptr()).to_string_lossy();
+    let kind = if code == BadAlloc { ErrorKind::OutOfMemory } else { kind };
+    Some(Error::with_code(
+        kind,
+        code.into(),
+        format!("{}: {}", message, description),
+    ))
+}
+
+/// Looks up (or creates) the atom for a static null-terminated name such as `b"WM_CLASS\0"`.
+pub unsafe fn intern_atom(display: *mut Display, name: &'static [u8]) -> Atom {
+    debug_assert_eq!(name.last(), Some(&0x00));
+    XInternAtom(display, name.as_ptr().cast(), False)
+}
+
+/// The name of an atom, if it is one.
+pub unsafe fn atom_name(display: *mut Display, atom: Atom) -> Option<String> {
+    let name = XGetAtomName(display, atom);
+    if name.is_null() {
+        return None
+    }
+    let string = CStr::from_ptr(name).to_string_lossy().into_owned();
+    let _ = XFree(name.cast());
+    Some(string)
+}
+
+/// Reads up to `max_length` items of a property with a format of 32, such as a `CARDINAL` list.
+///
+/// Xlib hands out 32-bit property data as an array of `c_ulong`, even where that's 64 bits wide.
+pub unsafe fn get_property32(
+    display: *mut Display,
+    window: Window,
+    property: Atom,
+    type_: Atom,
+    max_length: c_long,
+) -> Option<Vec<c_ulong>> {
+    get_property(display, window, property, type_, 32, max_length)
+}
+
+/// Reads a property with a format of 8, such as a `STRING`.
+pub unsafe fn get_property8(display: *mut Display, window: Window, property: Atom, type_: Atom) -> Option<Vec<u8>> {
+    // The length is in 32-bit units, this is plenty for anything that's not an image
+    get_property(display, window, property, type_, 8, 0x10000)
+}
+
+unsafe fn get_property<T: Copy>(
+    display: *mut Display,
+    window: Window,
+    property: Atom,
+    type_: Atom,
+    format: c_int,
+    max_length: c_long,
+) -> Option<Vec<T>> {
+    let (mut actual_type, mut actual_format) = (0, 0);
+    let (mut nitems, mut bytes_after) = (0, 0);
+    let mut data = ptr::null_mut();
+    let status = XGetWindowProperty(
+        display,
+        window,
+        property,
+        0,
+        max_length,
+        False,
+        type_,
+        &mut actual_type,
+        &mut actual_format,
+        &mut nitems,
+        &mut bytes_after,
+        &mut data,
+    );
+    if status != Success || data.is_null() {
+        return None
+    }
+    let items = (actual_type == type_ && actual_format == format)
+        .then(|| slice::from_raw_parts(data as *const T, nitems as usize).to_vec());
+    let _ = XFree(data.cast());
+    items
+}
+
+/// Borrows an array handed out by Xlib or one of its extensions, which may be null if it's empty.
+pub unsafe fn array<'a, T>(data: *const T, len: c_int) -> &'a [T] {
+    if data.is_null() || len <= 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data, len as usize)
+    }
+}
+
+/// Finds the value of `Xft.dpi` in the contents of the `RESOURCE_MANAGER` property.
+///
+/// The format is one `name: value` pair per line, as in `.Xresources` after preprocessing.
+pub fn parse_xft_dpi(resources: &[u8]) -> Option<f64> {
+    resources.split(|&x| x == b'\n').find_map(|line| {
+        let line = std::str::from_utf8(line).ok()?;
+        let (name, value) = line.split_once(':')?;
+        (name.trim() == "Xft.dpi")
+            .then(|| value.trim().parse::<f64>().ok())
+            .flatten()
+            .filter(|&dpi| dpi > 0.0)
+    })
+}
+
+/// Works out the refresh rate of an XRandR mode in millihertz, from its timings.
+pub fn mode_refresh_rate(mode: &XRRModeInfo) -> Option<u32> {
+    // Interlaced modes draw every other line per refresh, and double scanned ones draw every line twice
+    let mut pixels_per_second = u128::from(mode.dotClock) * 1000;
+    let mut pixels = u128::from(mode.hTotal) * u128::from(mode.vTotal);
+    if mode.modeFlags & RR_Interlace != 0 {
+        pixels_per_second *= 2;
+    }
+    if mode.modeFlags & RR_DoubleScan != 0 {
+        pixels *= 2;
+    }
+    (pixels != 0).then(|| (pixels_per_second / pixels) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mode_refresh_rate, parse_xft_dpi};
+    use crate::platform::x11::ffi::*;
+    use std::ptr;
+
+    #[test]
+    fn xft_dpi() {
+        assert_eq!(
+            parse_xft_dpi(b"Xft.antialias:\t1\nXft.dpi:\t192\nXft.hinting:\t1\n"),
+            Some(192.0)
+        );
+        assert_eq!(parse_xft_dpi(b"  Xft.dpi : 120.5"), Some(120.5));
+        assert_eq!(parse_xft_dpi(b"Xft.dpi:\tnope\nXft.dpi:\t0\n"), None);
+        assert_eq!(parse_xft_dpi(b"*background:\tblack\n"), None);
+    }
+
+    #[test]
+    fn refresh_rates() {
+        // Timings of the standard 1920x1080 @ 60 Hz and 1024x768 @ 87 Hz interlaced modes
+        let mut mode = XRRModeInfo {
+            id: 0,
+            width: 1920,
+            height: 1080,
+            dotClock: 148_500_000,
+            hSyncStart: 2008,
+            hSyncEnd: 2052,
+            hTotal: 2200,
+            hSkew: 0,
+            vSyncStart: 1084,
+            vSyncEnd: 1089,
+            vTotal: 1125,
+            name: ptr::null_mut(),
+            nameLength: 0,
+            modeFlags: 0,
+        };
+        assert_eq!(mode_refresh_rate(&mode), Some(60_000));
+        mode.dotClock = 44_900_000;
+        mode.hTotal = 1264;
+        mode.vTotal = 817;
+        mode.modeFlags = RR_Interlace;
+        assert_eq!(mode_refresh_rate(&mode), Some(86_957));
+        mode.hTotal = 0;
+        assert_eq!(mode_refresh_rate(&mode), None);
+    }
+}
@@ -0,0 +1,221 @@
+//! Pointer input through XInput2, which unlike the core protocol can scroll smoothly.
+//!
+//! Once a window selects XInput2's button and motion events, the core ones stop arriving for it,
+//! so everything the core events did is done here too.
+//! It also has raw motion, straight from the device, which is only sent to the root window.
+
+use crate::{
+    dpi::PhysicalPosition,
+    event::{Event, EventQueue, ScrollDelta},
+    platform::x11::{ffi::*, imp::button_event},
+};
+use std::{ptr, slice};
+
+/// The XInput2 extension, and what's needed to make sense of its events.
+pub struct XInput {
+    opcode: c_int,
+    scroll_valuators: Vec<ScrollValuator>,
+}
+
+/// An axis of a device that scrolls, such as a touchpad or a free-spinning wheel.
+///
+/// Its position is absolute and keeps going, so only how much it changed since last time means anything.
+struct ScrollValuator {
+    deviceid: c_int,
+    number: c_int,
+    horizontal: bool,
+    increment: f64,
+    position: f64,
+}
+
+impl XInput {
+    /// Selects pointer events from XInput2 for `window`, and raw motion on `root`,
+    /// if the server supports version 2.1 or newer.
+    pub unsafe fn new(display: *mut Display, root: Window, window: Window) -> Option<Self> {
+        let xi = (*XI).as_ref()?;
+        let (mut opcode, mut event_base, mut error_base) = (0, 0, 0);
+        let name = b"XInputExtension\0".as_ptr().cast();
+        if XQueryExtension(display, name, &mut opcode, &mut event_base, &mut error_base) == False {
+            return None
+        }
+
+        // Scroll valuators are new in 2.1, and the server answers with what it supports up to that
+        let (mut major, mut minor) = (2, 1);
+        if (xi.XIQueryVersion)(display, &mut major, &mut minor) != Success || (major, minor) < (2, 1) {
+            return None
+        }
+
+        // Device changes are selected for every device, as that's where switching between mice shows up
+        let mut pointer_mask = [(1 << XI_ButtonPress) | (1 << XI_ButtonRelease) | (1 << XI_Motion) as c_uchar];
+        let mut device_mask = [(1 << XI_DeviceChanged) as c_uchar];
+        let mut masks = [
+            XIEventMask {
+                deviceid: XIAllMasterDevices,
+                mask_len: pointer_mask.len() as c_int,
+                mask: pointer_mask.as_mut_ptr(),
+            },
+            XIEventMask {
+                deviceid: XIAllDevices,
+                mask_len: device_mask.len() as c_int,
+                mask: device_mask.as_mut_ptr(),
+            },
+        ];
+        if (xi.XISelectEvents)(display, window, masks.as_mut_ptr(), masks.len() as c_int) != Success {
+            return None
+        }
+
+        // Raw motion is bit 17, which is in the third byte of the mask
+        // Failing to select it only means `RawMouseMotion` isn't sent, so that's not worth giving up on the rest
+        let mut raw_mask: [c_uchar; 3] = [0, 0, 1 << (XI_RawMotion - 16)];
+        let mut raw_mask = XIEventMask {
+            deviceid: XIAllMasterDevices,
+            mask_len: raw_mask.len() as c_int,
+            mask: raw_mask.as_mut_ptr(),
+        };
+        let _ = (xi.XISelectEvents)(display, root, &mut raw_mask, 1);
+
+        let mut xinput = Self {
+            opcode,
+            scroll_valuators: Vec::new(),
+        };
+        xinput.query_scroll_valuators(display);
+        Some(xinput)
+    }
+
+    /// Reads every device's scroll valuators and where they are now.
+    ///
+    /// This is also how scrolling is kept from jumping after the valuators moved while the cursor was elsewhere.
+    pub unsafe fn query_scroll_valuators(&mut self, display: *mut Display) {
+        self.scroll_valuators.clear();
+        let xi = match &*XI {
+            Some(xi) => xi,
+            None => return,
+        };
+        let mut count = 0;
+        let devices = (xi.XIQueryDevice)(display, XIAllDevices, &mut count);
+        if devices.is_null() {
+            return
+        }
+        for device in slice::from_raw_parts(devices, count.max(0) as usize) {
+            let classes = slice::from_raw_parts(device.classes, device.num_classes.max(0) as usize);
+            for &class in classes {
+                if (*class).type_ != XIScrollClass {
+                    continue
+                }
+                let scroll = &*(class as *const XIScrollClassInfo);
+                // The position is in the valuator class with the same number
+                let position = classes
+                    .iter()
+                    .filter(|&&x| (*x).type_ == XIValuatorClass)
+                    .map(|&x| &*(x as *const XIValuatorClassInfo))
+                    .find(|x| x.number == scroll.number)
+                    .map_or(0.0, |x| x.value);
+                if scroll.increment != 0.0 {
+                    self.scroll_valuators.push(ScrollValuator {
+                        deviceid: device.deviceid,
+                        number: scroll.number,
+                        horizontal: scroll.scroll_type == XIScrollTypeHorizontal,
+                        increment: scroll.increment,
+                        position,
+                    });
+                }
+            }
+        }
+        (xi.XIFreeDeviceInfo)(devices);
+    }
+
+    /// Translates an event if it's from XInput2, returning where the cursor moved to if it did.
+    ///
+    /// Cursor motion is left to the caller, which handles it the same way as core motion events.
+    /// Raw motion is only sent while `focused`, as it's selected on the root window and would arrive regardless.
+    #[allow(non_upper_case_globals)] // Xlib's names for event types
+    pub unsafe fn process_event(
+        &mut self,
+        display: *mut Display,
+        event: &mut XEvent,
+        events: &EventQueue,
+        focused: bool,
+    ) -> Option<PhysicalPosition> {
+        let cookie = &mut event.generic_event_cookie;
+        if cookie.extension != self.opcode || XGetEventData(display, cookie) == False {
+            return None
+        }
+        let mut position = None;
+        match cookie.evtype {
+            XI_ButtonPress | XI_ButtonRelease => {
+                // Devices that scroll smoothly also send scroll wheel buttons for older clients, which are skipped
+                let device_event = &*(cookie.data as *const XIDeviceEvent);
+                if device_event.flags & XIPointerEmulated == 0 {
+                    if let Some(event) = button_event(device_event.detail as c_uint, cookie.evtype == XI_ButtonPress) {
+                        events.push(event);
+                    }
+                }
+            },
+            XI_Motion => {
+                let device_event = &*(cookie.data as *const XIDeviceEvent);
+                position = Some(PhysicalPosition::new(
+                    device_event.event_x.floor() as i32,
+                    device_event.event_y.floor() as i32,
+                ));
+                if let Some(delta) = self.scroll_delta(device_event) {
+                    events.push(Event::MouseWheel(delta));
+                }
+            },
+            XI_RawMotion if focused => {
+                let raw_event = &*(cookie.data as *const XIRawEvent);
+                let (mut x, mut y) = (0.0, 0.0);
+                for (number, value) in valuator_values(&raw_event.valuators, raw_event.raw_values) {
+                    match number {
+                        0 => x = value,
+                        1 => y = value,
+                        _ => (),
+                    }
+                }
+                if (x, y) != (0.0, 0.0) {
+                    events.push(Event::RawMouseMotion(x, y));
+                }
+            },
+            XI_DeviceChanged => self.query_scroll_valuators(display),
+            _ => (),
+        }
+        XFreeEventData(display, cookie);
+        position
+    }
+
+    /// Works out how far a motion event scrolled, which is in increments of its device's scroll valuators.
+    unsafe fn scroll_delta(&mut self, device_event: &XIDeviceEvent) -> Option<ScrollDelta> {
+        let (mut x, mut y) = (0.0, 0.0);
+        for (number, value) in valuator_values(&device_event.valuators, device_event.valuators.values) {
+            if let Some(valuator) = self
+                .scroll_valuators
+                .iter_mut()
+                .find(|x| x.deviceid == device_event.sourceid && x.number == number as c_int)
+            {
+                let delta = (value - valuator.position) / valuator.increment;
+                valuator.position = value;
+                // Vertical scrolling is positive downwards, which is the opposite of ramen's convention
+                if valuator.horizontal {
+                    x += delta;
+                } else {
+                    y -= delta;
+                }
+            }
+        }
+        ((x, y) != (0.0, 0.0)).then_some(ScrollDelta::Lines(x as f32, y as f32))
+    }
+}
+
+/// Pairs up valuator numbers with their values, which are packed with one for each bit set in the mask.
+///
+/// Raw events have their own values alongside the mask, so the values are passed separately.
+unsafe fn valuator_values(valuators: &XIValuatorState, values: *const f64) -> impl Iterator<Item = (usize, f64)> + '_ {
+    let mask = if valuators.mask.is_null() {
+        &[][..]
+    } else {
+        slice::from_raw_parts(valuators.mask, valuators.mask_len.max(0) as usize)
+    };
+    (0..mask.len() * 8)
+        .filter(move |number| mask[number / 8] & (1 << (number % 8)) != 0)
+        .enumerate()
+        .map(move |(index, number)| (number, ptr::read(values.add(index))))
+}
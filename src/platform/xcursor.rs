@@ -0,0 +1,264 @@
+//! Cursor themes shared by the X11 and Wayland backends, read straight from Xcursor files without `libXcursor`.
+//!
+//! Themes are directories with a `cursors` folder of Xcursor files, named after what they're for,
+//! and an `index.theme` that can name other themes to fall back on. Which theme is used and where themes are
+//! searched for is taken from `XCURSOR_THEME` and `XCURSOR_PATH`, the same as `libXcursor` does.
+
+use crate::cursor::CursorShape;
+use std::{
+    env,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// One image of a cursor, of which animated cursors have several.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub hotspot: (u32, u32),
+
+    /// How long to show this image for in an animation, in milliseconds.
+    pub delay: u32,
+
+    /// ARGB pixels with premultiplied alpha, row by row from the top-left.
+    pub pixels: Vec<u32>,
+}
+
+/// The size cursors are drawn at when the scale factor is 1.
+pub fn default_size() -> u32 {
+    env::var("XCURSOR_SIZE")
+        .ok()
+        .and_then(|x| x.trim().parse().ok())
+        .filter(|&x| x > 0)
+        .unwrap_or(24)
+}
+
+/// Loads the images for a shape from the user's theme, as near to `size` pixels as the theme has.
+pub fn load(shape: CursorShape, size: u32) -> Option<Vec<Image>> {
+    let theme = env::var_os("XCURSOR_THEME").filter(|x| !x.is_empty());
+    let theme = theme.as_deref().and_then(|x| x.to_str()).unwrap_or("default");
+    let path = search_path();
+    let file = find(&path, theme, names(shape)).or_else(|| find(&path, "default", names(shape)))?;
+    parse(&fs::read(file).ok()?, size)
+}
+
+/// The directories to look for themes in, from `XCURSOR_PATH` or else the same defaults as `libXcursor`.
+fn search_path() -> Vec<PathBuf> {
+    let home = env::var_os("HOME").filter(|x| !x.is_empty());
+    let expand = |dir: &str| -> Option<PathBuf> {
+        match dir.strip_prefix('~') {
+            Some(rest) => Some(Path::new(home.as_ref()?).join(rest.trim_start_matches('/'))),
+            None => Some(PathBuf::from(dir)),
+        }
+    };
+    let mut path = Vec::new();
+    match env::var("XCURSOR_PATH") {
+        Ok(var) => path.extend(var.split(':').filter(|x| !x.is_empty()).filter_map(expand)),
+        Err(_) => {
+            let data_home = env::var_os("XDG_DATA_HOME").filter(|x| !x.is_empty());
+            match data_home {
+                Some(data_home) => path.push(Path::new(&data_home).join("icons")),
+                None => path.extend(expand("~/.local/share/icons")),
+            }
+            path.extend(
+                ["~/.icons", "/usr/share/icons", "/usr/share/pixmaps"]
+                    .iter()
+                    .copied()
+                    .filter_map(expand),
+            );
+        },
+    }
+    path
+}
+
+/// The names a shape goes by in themes, best first.
+///
+/// Newer themes use the CSS names, while older ones only have the names from the X11 cursor font.
+fn names(shape: CursorShape) -> &'static [&'static str] {
+    match shape {
+        CursorShape::Arrow => &["default", "left_ptr"],
+        CursorShape::Hand => &["pointer", "hand2", "hand1"],
+        CursorShape::Text => &["text", "xterm"],
+        CursorShape::Crosshair => &["crosshair", "cross"],
+        CursorShape::Wait => &["wait", "watch"],
+        CursorShape::Progress => &["progress", "left_ptr_watch", "half-busy"],
+        CursorShape::Help => &["help", "question_arrow", "left_ptr_help"],
+        CursorShape::NotAllowed => &["not-allowed", "crossed_circle", "forbidden"],
+        CursorShape::Move => &["move", "fleur", "all-scroll"],
+        CursorShape::ResizeHorizontal => &["ew-resize", "sb_h_double_arrow", "h_double_arrow"],
+        CursorShape::ResizeVertical => &["ns-resize", "sb_v_double_arrow", "v_double_arrow"],
+        CursorShape::ResizeTopLeftBottomRight => &["nwse-resize", "bd_double_arrow", "size_fdiag"],
+        CursorShape::ResizeTopRightBottomLeft => &["nesw-resize", "fd_double_arrow", "size_bdiag"],
+    }
+}
+
+/// Finds the file for the first of `names` that's in a theme or the themes it inherits from.
+fn find(path: &[PathBuf], theme: &str, names: &[&str]) -> Option<PathBuf> {
+    names.iter().find_map(|name| {
+        let mut visited = Vec::new();
+        find_in_theme(path, theme, name, &mut visited)
+    })
+}
+
+/// Looks for a cursor in a theme, then each theme it inherits from, skipping any that were already searched.
+fn find_in_theme(path: &[PathBuf], theme: &str, name: &str, visited: &mut Vec<OsString>) -> Option<PathBuf> {
+    if visited.iter().any(|x| x == theme) {
+        return None
+    }
+    visited.push(theme.into());
+
+    let file = path
+        .iter()
+        .map(|dir| dir.join(theme).join("cursors").join(name))
+        .find(|file| file.is_file());
+    if file.is_some() {
+        return file
+    }
+    for dir in path {
+        let index = match fs::read_to_string(dir.join(theme).join("index.theme")) {
+            Ok(index) => index,
+            Err(_) => continue,
+        };
+        for parent in inherits(&index) {
+            if let Some(file) = find_in_theme(path, parent, name, visited) {
+                return Some(file)
+            }
+        }
+    }
+    None
+}
+
+/// The themes named on the `Inherits` line of an `index.theme`, which is a list separated by commas or semicolons.
+fn inherits(index: &str) -> impl Iterator<Item = &str> {
+    index
+        .lines()
+        .filter_map(|line| line.trim_start().strip_prefix("Inherits"))
+        .filter_map(|rest| rest.trim_start().strip_prefix('='))
+        .flat_map(|list| list.split(|c: char| c == ',' || c == ';' || c.is_whitespace()))
+        .filter(|x| !x.is_empty())
+}
+
+/// Reads the images in an Xcursor file whose nominal size is closest to `size`, in the order they're animated.
+///
+/// Files can hold images for any number of sizes, listed in a table of contents at the start.
+/// Everything's little-endian, with the header and each image's header starting with their own length.
+pub fn parse(data: &[u8], size: u32) -> Option<Vec<Image>> {
+    const MAGIC: u32 = u32::from_le_bytes(*b"Xcur");
+    const IMAGE_TYPE: u32 = 0xFFFD0002;
+    const MAX_DIMENSION: u32 = 0x7FFF;
+
+    let read = |offset: usize| -> Option<u32> {
+        let bytes = data.get(offset..offset.checked_add(4)?)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    if read(0)? != MAGIC {
+        return None
+    }
+    let header_len = read(4)? as usize;
+    let toc_len = read(12)? as usize;
+    let toc = (0..toc_len)
+        .map(|i| {
+            let entry = header_len.checked_add(i.checked_mul(12)?)?;
+            Some((read(entry)?, read(entry + 4)?, read(entry + 8)? as usize))
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    // The first size that's nearest wins, the same as `libXcursor`
+    let nominal = toc
+        .iter()
+        .filter(|&&(kind, ..)| kind == IMAGE_TYPE)
+        .map(|&(_, nominal, _)| nominal)
+        .min_by_key(|&nominal| (i64::from(nominal) - i64::from(size)).abs())?;
+
+    let image = |position: usize| -> Option<Image> {
+        // header, type, nominal size, version, width, height, x hotspot, y hotspot, delay, pixels
+        let field = |i: usize| read(position.checked_add(i * 4)?);
+        let (width, height) = (field(4)?, field(5)?);
+        let hotspot = (field(6)?, field(7)?);
+        if width == 0 || height == 0 || width > MAX_DIMENSION || height > MAX_DIMENSION {
+            return None
+        }
+        if hotspot.0 >= width || hotspot.1 >= height {
+            return None
+        }
+        let start = position.checked_add(field(0)? as usize)?;
+        let pixel_count = width as usize * height as usize;
+        let pixels = (0..pixel_count).map(|i| read(start + i * 4)).collect::<Option<_>>()?;
+        Some(Image {
+            width,
+            height,
+            hotspot,
+            delay: field(8)?,
+            pixels,
+        })
+    };
+    let images = toc
+        .iter()
+        .filter(|&&(kind, entry_nominal, _)| kind == IMAGE_TYPE && entry_nominal == nominal)
+        .map(|&(_, _, position)| image(position))
+        .collect::<Option<Vec<_>>>()?;
+    Some(images)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn icons() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/xcursor/icons")
+    }
+
+    fn read(theme: &str, name: &str) -> Vec<u8> {
+        fs::read(icons().join(theme).join("cursors").join(name)).unwrap()
+    }
+
+    #[test]
+    fn nearest_size_is_picked() {
+        let data = read("base", "left_ptr");
+        let small = parse(&data, 16).unwrap();
+        assert_eq!(small.len(), 1);
+        assert_eq!((small[0].width, small[0].height, small[0].hotspot), (1, 1, (0, 0)));
+        assert_eq!(small[0].pixels, [0xFF000000]);
+        let large = parse(&data, 40).unwrap();
+        assert_eq!((large[0].width, large[0].height, large[0].hotspot), (2, 2, (1, 0)));
+        assert_eq!(large[0].pixels, [0xFF000000, 0x80808080, 0x00000000, 0xFFFFFFFF]);
+    }
+
+    #[test]
+    fn animations_keep_their_frames() {
+        let images = parse(&read("base", "watch"), 24).unwrap();
+        let delays: Vec<u32> = images.iter().map(|x| x.delay).collect();
+        assert_eq!(delays, [50, 100, 150]);
+        assert_eq!(images[2].pixels, [0xFF0000FF]);
+    }
+
+    #[test]
+    fn broken_files_are_rejected() {
+        let data = read("base", "left_ptr");
+        assert!(parse(&data[..data.len() - 1], 48).is_none());
+        assert!(parse(b"Xcur", 24).is_none());
+        assert!(parse(&[0; 16], 24).is_none());
+    }
+
+    #[test]
+    fn themes_inherit() {
+        let path = [icons()];
+        let find = |theme, shape| find(&path, theme, names(shape)).map(|x| x.strip_prefix(icons()).unwrap().to_owned());
+        assert_eq!(
+            find("derived", CursorShape::Text),
+            Some(PathBuf::from("derived/cursors/xterm"))
+        );
+        assert_eq!(
+            find("derived", CursorShape::Arrow),
+            Some(PathBuf::from("base/cursors/left_ptr"))
+        );
+        assert_eq!(
+            find("base", CursorShape::Text),
+            Some(PathBuf::from("derived/cursors/xterm"))
+        );
+        assert_eq!(find("derived", CursorShape::Hand), None);
+        assert_eq!(find("missing", CursorShape::Arrow), None);
+    }
+}
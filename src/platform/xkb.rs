@@ -0,0 +1,430 @@
+//! Keyboard handling shared by the X11 and Wayland backends, which both describe keyboards with XKB.
+//!
+//! Keycodes on both are evdev codes plus 8, so one table of physical keys covers both.
+//! Wayland compositors send a keymap which is compiled with `libxkbcommon`, while X11 looks up keysyms with
+//! Xlib's own XKB support, only using `libxkbcommon` (when it's there) to turn keysyms into characters.
+
+#![allow(non_camel_case_types)]
+
+use crate::{
+    keyboard::{Key, KeyCode, Modifiers, NamedKey},
+    platform::posix::{c_char, c_int, dynamic_library},
+    sync::LazyCell,
+};
+use std::ptr;
+
+pub type xkb_keycode_t = u32;
+pub type xkb_keysym_t = u32;
+pub type xkb_layout_index_t = u32;
+pub type xkb_mod_mask_t = u32;
+
+pub enum xkb_context {}
+pub enum xkb_keymap {}
+pub enum xkb_state {}
+
+const XKB_KEYMAP_FORMAT_TEXT_V1: c_int = 1;
+
+dynamic_library! {
+    /// Functions from `libxkbcommon`, which is only needed to tell what keys are in the user's layout.
+    pub struct Xkbcommon("libxkbcommon.so.0") {
+        fn xkb_context_new(flags: c_int) -> *mut xkb_context;
+        fn xkb_context_unref(context: *mut xkb_context);
+        fn xkb_keymap_new_from_string(context: *mut xkb_context, string: *const c_char, format: c_int, flags: c_int) -> *mut xkb_keymap;
+        fn xkb_keymap_unref(keymap: *mut xkb_keymap);
+        fn xkb_keymap_key_get_syms_by_level(keymap: *mut xkb_keymap, key: xkb_keycode_t, layout: xkb_layout_index_t, level: u32, syms_out: *mut *const xkb_keysym_t) -> c_int;
+        fn xkb_state_new(keymap: *mut xkb_keymap) -> *mut xkb_state;
+        fn xkb_state_unref(state: *mut xkb_state);
+        fn xkb_state_update_mask(state: *mut xkb_state, depressed_mods: xkb_mod_mask_t, latched_mods: xkb_mod_mask_t, locked_mods: xkb_mod_mask_t, depressed_layout: xkb_layout_index_t, latched_layout: xkb_layout_index_t, locked_layout: xkb_layout_index_t) -> c_int;
+        fn xkb_state_key_get_layout(state: *mut xkb_state, key: xkb_keycode_t) -> xkb_layout_index_t;
+        fn xkb_state_key_get_utf32(state: *mut xkb_state, key: xkb_keycode_t) -> u32;
+        fn xkb_keysym_to_utf32(keysym: xkb_keysym_t) -> u32;
+    }
+}
+
+pub static XKBCOMMON: LazyCell<Option<Xkbcommon>> = LazyCell::new(|| unsafe { Xkbcommon::load() });
+
+/// A compiled keymap, and the state of the modifiers and layout on it.
+pub struct Keymap {
+    context: *mut xkb_context,
+    keymap: *mut xkb_keymap,
+    state: *mut xkb_state,
+}
+
+impl Keymap {
+    /// Compiles a keymap from its text form, which has to be null terminated.
+    pub unsafe fn from_text(text: &[u8]) -> Option<Self> {
+        let xkb = (*XKBCOMMON).as_ref()?;
+        if text.last() != Some(&0) {
+            return None
+        }
+        let context = (xkb.xkb_context_new)(0);
+        if context.is_null() {
+            return None
+        }
+        let keymap = (xkb.xkb_keymap_new_from_string)(context, text.as_ptr().cast(), XKB_KEYMAP_FORMAT_TEXT_V1, 0);
+        let state = if keymap.is_null() {
+            ptr::null_mut()
+        } else {
+            (xkb.xkb_state_new)(keymap)
+        };
+        let keymap = Self { context, keymap, state };
+        (!state.is_null()).then_some(keymap)
+    }
+
+    /// Updates the modifiers and layout, as sent by `wl_keyboard.modifiers`.
+    pub unsafe fn update_mask(&mut self, depressed: u32, latched: u32, locked: u32, layout: u32) {
+        if let Some(xkb) = &*XKBCOMMON {
+            let _ = (xkb.xkb_state_update_mask)(self.state, depressed, latched, locked, 0, 0, layout);
+        }
+    }
+
+    /// Looks up a key in the current layout, without any modifiers.
+    pub unsafe fn logical_key(&self, keycode: xkb_keycode_t) -> Key {
+        let xkb = match &*XKBCOMMON {
+            Some(xkb) => xkb,
+            None => return Key::Unidentified,
+        };
+        let layout = (xkb.xkb_state_key_get_layout)(self.state, keycode);
+        let mut syms = ptr::null();
+        if (xkb.xkb_keymap_key_get_syms_by_level)(self.keymap, keycode, layout, 0, &mut syms) < 1 {
+            return Key::Unidentified
+        }
+        keysym_to_key(*syms)
+    }
+
+    /// The character a key types with the current modifiers, if it types one.
+    ///
+    /// Dead keys and compose sequences aren't handled here, that's up to an input method.
+    pub unsafe fn text(&self, keycode: xkb_keycode_t) -> Option<char> {
+        let xkb = (*XKBCOMMON).as_ref()?;
+        let code_point = (xkb.xkb_state_key_get_utf32)(self.state, keycode);
+        char::from_u32(code_point).filter(|x| code_point != 0 && !x.is_control())
+    }
+}
+
+impl Drop for Keymap {
+    fn drop(&mut self) {
+        if let Some(xkb) = &*XKBCOMMON {
+            unsafe {
+                if !self.state.is_null() {
+                    (xkb.xkb_state_unref)(self.state);
+                }
+                if !self.keymap.is_null() {
+                    (xkb.xkb_keymap_unref)(self.keymap);
+                }
+                (xkb.xkb_context_unref)(self.context);
+            }
+        }
+    }
+}
+
+/// Looks up the physical key for an XKB keycode, which is an evdev code plus 8.
+pub fn key_code(keycode: xkb_keycode_t) -> KeyCode {
+    keycode
+        .checked_sub(8)
+        .and_then(|code| EVDEV_CODES.iter().find(|&&(x, _)| u32::from(x) == code))
+        .map_or(KeyCode::Unidentified, |&(_, key_code)| key_code)
+}
+
+/// Works out what a keysym means as a [`Key`].
+pub fn keysym_to_key(keysym: xkb_keysym_t) -> Key {
+    if let Some(&(_, named)) = NAMED_KEYSYMS.iter().find(|&&(x, _)| x == keysym) {
+        return Key::Named(named)
+    }
+    if (0xFE50..=0xFE93).contains(&keysym) {
+        return Key::Dead
+    }
+    let code_point = match &*XKBCOMMON {
+        Some(xkb) => unsafe { (xkb.xkb_keysym_to_utf32)(keysym) },
+        None => latin1_or_unicode_keysym(keysym),
+    };
+    match char::from_u32(code_point) {
+        Some(ch) if code_point != 0 && !ch.is_control() => Key::Character(ch),
+        _ => Key::Unidentified,
+    }
+}
+
+/// Converts the keysyms that directly encode a code point, which is what's left without `libxkbcommon`.
+fn latin1_or_unicode_keysym(keysym: xkb_keysym_t) -> u32 {
+    match keysym {
+        0x20..=0x7E | 0xA0..=0xFF => keysym,
+        0x0100_0100..=0x0110_FFFF => keysym - 0x0100_0000,
+        _ => 0,
+    }
+}
+
+/// Reads a core modifier mask, where XKB puts the standard modifiers on the usual bits.
+pub fn modifiers(mask: u32) -> Modifiers {
+    Modifiers {
+        shift: mask & (1 << 0) != 0,
+        caps_lock: mask & (1 << 1) != 0,
+        control: mask & (1 << 2) != 0,
+        alt: mask & (1 << 3) != 0,
+        num_lock: mask & (1 << 4) != 0,
+        logo: mask & (1 << 6) != 0,
+    }
+}
+
+/// Evdev codes from `linux/input-event-codes.h`, by physical key.
+const EVDEV_CODES: &[(u16, KeyCode)] = {
+    use KeyCode::*;
+    &[
+        (1, Escape),
+        (2, Digit1),
+        (3, Digit2),
+        (4, Digit3),
+        (5, Digit4),
+        (6, Digit5),
+        (7, Digit6),
+        (8, Digit7),
+        (9, Digit8),
+        (10, Digit9),
+        (11, Digit0),
+        (12, Minus),
+        (13, Equal),
+        (14, Backspace),
+        (15, Tab),
+        (16, KeyQ),
+        (17, KeyW),
+        (18, KeyE),
+        (19, KeyR),
+        (20, KeyT),
+        (21, KeyY),
+        (22, KeyU),
+        (23, KeyI),
+        (24, KeyO),
+        (25, KeyP),
+        (26, BracketLeft),
+        (27, BracketRight),
+        (28, Enter),
+        (29, ControlLeft),
+        (30, KeyA),
+        (31, KeyS),
+        (32, KeyD),
+        (33, KeyF),
+        (34, KeyG),
+        (35, KeyH),
+        (36, KeyJ),
+        (37, KeyK),
+        (38, KeyL),
+        (39, Semicolon),
+        (40, Quote),
+        (41, Backquote),
+        (42, ShiftLeft),
+        (43, Backslash),
+        (44, KeyZ),
+        (45, KeyX),
+        (46, KeyC),
+        (47, KeyV),
+        (48, KeyB),
+        (49, KeyN),
+        (50, KeyM),
+        (51, Comma),
+        (52, Period),
+        (53, Slash),
+        (54, ShiftRight),
+        (55, NumpadMultiply),
+        (56, AltLeft),
+        (57, Space),
+        (58, CapsLock),
+        (59, F1),
+        (60, F2),
+        (61, F3),
+        (62, F4),
+        (63, F5),
+        (64, F6),
+        (65, F7),
+        (66, F8),
+        (67, F9),
+        (68, F10),
+        (69, NumLock),
+        (70, ScrollLock),
+        (71, Numpad7),
+        (72, Numpad8),
+        (73, Numpad9),
+        (74, NumpadSubtract),
+        (75, Numpad4),
+        (76, Numpad5),
+        (77, Numpad6),
+        (78, NumpadAdd),
+        (79, Numpad1),
+        (80, Numpad2),
+        (81, Numpad3),
+        (82, Numpad0),
+        (83, NumpadDecimal),
+        (85, Lang5),
+        (86, IntlBackslash),
+        (87, F11),
+        (88, F12),
+        (89, IntlRo),
+        (90, Lang3),
+        (91, Lang4),
+        (92, Convert),
+        (93, KanaMode),
+        (94, NonConvert),
+        (96, NumpadEnter),
+        (97, ControlRight),
+        (98, NumpadDivide),
+        (99, PrintScreen),
+        (100, AltRight),
+        (102, Home),
+        (103, ArrowUp),
+        (104, PageUp),
+        (105, ArrowLeft),
+        (106, ArrowRight),
+        (107, End),
+        (108, ArrowDown),
+        (109, PageDown),
+        (110, Insert),
+        (111, Delete),
+        (113, AudioVolumeMute),
+        (114, AudioVolumeDown),
+        (115, AudioVolumeUp),
+        (116, Power),
+        (117, NumpadEqual),
+        (119, Pause),
+        (121, NumpadComma),
+        (122, Lang1),
+        (123, Lang2),
+        (124, IntlYen),
+        (125, SuperLeft),
+        (126, SuperRight),
+        (127, ContextMenu),
+        (183, F13),
+        (184, F14),
+        (185, F15),
+        (186, F16),
+        (187, F17),
+        (188, F18),
+        (189, F19),
+        (190, F20),
+        (191, F21),
+        (192, F22),
+        (193, F23),
+        (194, F24),
+    ]
+};
+
+/// Keysyms from `xkbcommon-keysyms.h` that don't type anything, by the key they name.
+const NAMED_KEYSYMS: &[(xkb_keysym_t, NamedKey)] = {
+    use NamedKey::*;
+    &[
+        (0xFE03, AltGraph), // ISO_Level3_Shift
+        (0xFE20, Tab),      // ISO_Left_Tab, which is Shift+Tab on some layouts
+        (0xFF08, Backspace),
+        (0xFF09, Tab),
+        (0xFF0D, Enter),
+        (0xFF13, Pause),
+        (0xFF14, ScrollLock),
+        (0xFF15, PrintScreen), // Sys_Req
+        (0xFF1B, Escape),
+        (0xFF50, Home),
+        (0xFF51, ArrowLeft),
+        (0xFF52, ArrowUp),
+        (0xFF53, ArrowRight),
+        (0xFF54, ArrowDown),
+        (0xFF55, PageUp),
+        (0xFF56, PageDown),
+        (0xFF57, End),
+        (0xFF61, PrintScreen),
+        (0xFF63, Insert),
+        (0xFF67, ContextMenu),
+        (0xFF7F, NumLock),
+        (0xFF8D, Enter), // KP_Enter
+        (0xFF95, Home),  // The keypad's keys without Num Lock
+        (0xFF96, ArrowLeft),
+        (0xFF97, ArrowUp),
+        (0xFF98, ArrowRight),
+        (0xFF99, ArrowDown),
+        (0xFF9A, PageUp),
+        (0xFF9B, PageDown),
+        (0xFF9C, End),
+        (0xFF9E, Insert),
+        (0xFF9F, Delete),
+        (0xFFBE, F1),
+        (0xFFBF, F2),
+        (0xFFC0, F3),
+        (0xFFC1, F4),
+        (0xFFC2, F5),
+        (0xFFC3, F6),
+        (0xFFC4, F7),
+        (0xFFC5, F8),
+        (0xFFC6, F9),
+        (0xFFC7, F10),
+        (0xFFC8, F11),
+        (0xFFC9, F12),
+        (0xFFCA, F13),
+        (0xFFCB, F14),
+        (0xFFCC, F15),
+        (0xFFCD, F16),
+        (0xFFCE, F17),
+        (0xFFCF, F18),
+        (0xFFD0, F19),
+        (0xFFD1, F20),
+        (0xFFD2, F21),
+        (0xFFD3, F22),
+        (0xFFD4, F23),
+        (0xFFD5, F24),
+        (0xFFE1, Shift),
+        (0xFFE2, Shift),
+        (0xFFE3, Control),
+        (0xFFE4, Control),
+        (0xFFE5, CapsLock),
+        (0xFFE9, Alt),
+        (0xFFEA, Alt),
+        (0xFFEB, Super),
+        (0xFFEC, Super),
+        (0xFFFF, Delete),
+        (0x1008_FF11, AudioVolumeDown),
+        (0x1008_FF12, AudioVolumeMute),
+        (0x1008_FF13, AudioVolumeUp),
+        (0x1008_FF2A, Power),
+    ]
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn tables_have_no_duplicates() {
+        let codes: HashSet<u16> = EVDEV_CODES.iter().map(|&(x, _)| x).collect();
+        assert_eq!(codes.len(), EVDEV_CODES.len());
+        let key_codes: HashSet<KeyCode> = EVDEV_CODES.iter().map(|&(_, x)| x).collect();
+        assert_eq!(key_codes.len(), EVDEV_CODES.len());
+        let keysyms: HashSet<u32> = NAMED_KEYSYMS.iter().map(|&(x, _)| x).collect();
+        assert_eq!(keysyms.len(), NAMED_KEYSYMS.len());
+    }
+
+    #[test]
+    fn keycodes_are_evdev_plus_8() {
+        assert_eq!(key_code(38), KeyCode::KeyA);
+        assert_eq!(key_code(25), KeyCode::KeyW);
+        assert_eq!(key_code(9), KeyCode::Escape);
+        assert_eq!(key_code(111), KeyCode::ArrowUp);
+        assert_eq!(key_code(133), KeyCode::SuperLeft);
+        assert_eq!(key_code(3), KeyCode::Unidentified);
+        assert_eq!(key_code(255), KeyCode::Unidentified);
+    }
+
+    #[test]
+    fn keysyms_become_keys() {
+        assert_eq!(keysym_to_key(0xFF0D), Key::Named(NamedKey::Enter));
+        assert_eq!(keysym_to_key(0xFFC9), Key::Named(NamedKey::F12));
+        assert_eq!(keysym_to_key(0xFE51), Key::Dead);
+        assert_eq!(keysym_to_key(0x61), Key::Character('a'));
+        assert_eq!(keysym_to_key(0xE9), Key::Character('é'));
+        assert_eq!(keysym_to_key(0x0100_20AC), Key::Character('€'));
+        assert_eq!(keysym_to_key(0), Key::Unidentified);
+        assert_eq!(latin1_or_unicode_keysym(0x06C6), 0); // Cyrillic_ef, which needs `libxkbcommon`
+    }
+
+    #[test]
+    fn modifier_masks() {
+        assert_eq!(modifiers(0), Modifiers::default());
+        let held = modifiers(0b0101_0101);
+        assert!(held.shift && held.control && held.num_lock && held.logo);
+        assert!(!held.caps_lock && !held.alt);
+    }
+}
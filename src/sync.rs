@@ -3,9 +3,10 @@
 //! None of these functions should panic when used correctly as they're used in FFI.
 
 #[cfg(not(feature = "parking-lot"))]
+#[allow(clippy::module_inception)]
 mod sync {
-    use std::ptr;
     pub use std::sync::{Condvar, Mutex, MutexGuard};
+    use std::{ptr, time::Duration};
 
     #[inline]
     pub fn cvar_notify_one(cvar: &Condvar) {
@@ -22,14 +23,29 @@ mod sync {
         }
     }
 
-    pub fn mutex_lock<T>(mtx: &Mutex<T>) -> MutexGuard<T> {
+    /// Returns `true` if the wait timed out.
+    pub fn cvar_wait_for<T>(cvar: &Condvar, guard: &mut MutexGuard<T>, timeout: Duration) -> bool {
+        // HACK: See `cvar_wait`.
+        unsafe {
+            let guard_copy = ptr::read(guard);
+            let (result, wait) = cvar
+                .wait_timeout(guard_copy, timeout)
+                .expect("cvar mutex poisoned (this is a bug)");
+            ptr::write(guard, result);
+            wait.timed_out()
+        }
+    }
+
+    pub fn mutex_lock<T>(mtx: &Mutex<T>) -> MutexGuard<'_, T> {
         mtx.lock().expect("mutex poisoned (this is a bug)")
     }
 }
 
 #[cfg(feature = "parking-lot")]
+#[allow(clippy::module_inception)]
 mod sync {
     pub use parking_lot::{Condvar, Mutex, MutexGuard};
+    use std::time::Duration;
 
     #[inline]
     pub fn cvar_notify_one(cvar: &Condvar) {
@@ -41,13 +57,27 @@ mod sync {
         cvar.wait(guard);
     }
 
+    /// Returns `true` if the wait timed out.
+    #[inline]
+    pub fn cvar_wait_for<T>(cvar: &Condvar, guard: &mut MutexGuard<T>, timeout: Duration) -> bool {
+        cvar.wait_for(guard, timeout).timed_out()
+    }
+
     #[inline]
-    pub fn mutex_lock<T>(mtx: &Mutex<T>) -> MutexGuard<T> {
+    pub fn mutex_lock<T>(mtx: &Mutex<T>) -> MutexGuard<'_, T> {
         mtx.lock()
     }
 }
 
-use std::{cell::UnsafeCell, ops, ptr, sync::Once};
+use crate::error::{Error, ErrorKind};
+use std::{
+    any::Any,
+    cell::UnsafeCell,
+    mem, ops, ptr,
+    sync::{Arc, Once},
+    thread,
+    time::{Duration, Instant},
+};
 
 /// Minimal lazily initialized type, similar to the one in `once_cell`.
 ///
@@ -97,4 +127,182 @@ impl<T, F: FnOnce() -> T> ops::Deref for LazyCell<T, F> {
     }
 }
 
+/// Creates a single-use channel, for a spawned thread to hand one value back to whoever spawned it.
+///
+/// If the [`Sender`] is dropped without sending (the thread returned early or panicked),
+/// the [`Receiver`] is woken up instead of waiting forever.
+pub(crate) fn oneshot<T>() -> (Sender<T>, Receiver<T>) {
+    let inner = Arc::new(Oneshot {
+        cvar: Condvar::new(),
+        state: Mutex::new(OneshotState::Empty),
+    });
+    (
+        Sender {
+            inner: Arc::clone(&inner),
+        },
+        Receiver { inner },
+    )
+}
+
+struct Oneshot<T> {
+    cvar: Condvar,
+    state: Mutex<OneshotState<T>>,
+}
+
+enum OneshotState<T> {
+    Empty,
+    Full(T),
+    Disconnected,
+}
+
+pub(crate) struct Sender<T> {
+    inner: Arc<Oneshot<T>>,
+}
+
+pub(crate) struct Receiver<T> {
+    inner: Arc<Oneshot<T>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RecvError {
+    /// The sender was dropped without sending anything.
+    Disconnected,
+    /// Nothing was sent before the timeout elapsed.
+    Timeout,
+}
+
+impl<T> Sender<T> {
+    /// Sends the value, handing it back if the receiver has stopped waiting for it.
+    pub(crate) fn send(self, value: T) -> Result<(), T> {
+        let mut state = mutex_lock(&self.inner.state);
+        if let OneshotState::Disconnected = *state {
+            return Err(value)
+        }
+        *state = OneshotState::Full(value);
+        cvar_notify_one(&self.inner.cvar);
+        Ok(())
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = mutex_lock(&self.inner.state);
+        if let OneshotState::Empty = *state {
+            *state = OneshotState::Disconnected;
+            cvar_notify_one(&self.inner.cvar);
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Blocks until a value is sent, the sender is dropped, or `timeout` elapses.
+    pub(crate) fn recv(self, timeout: Option<Duration>) -> Result<T, RecvError> {
+        // A deadline too far out to represent may as well be no deadline
+        let deadline = timeout.and_then(|x| Instant::now().checked_add(x));
+        let mut state = mutex_lock(&self.inner.state);
+        loop {
+            match mem::replace(&mut *state, OneshotState::Empty) {
+                OneshotState::Full(value) => break Ok(value),
+                OneshotState::Disconnected => break Err(RecvError::Disconnected),
+                OneshotState::Empty => (),
+            }
+            match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break Err(RecvError::Timeout)
+                    }
+                    let _ = cvar_wait_for(&self.inner.cvar, &mut state, deadline - now);
+                },
+                None => cvar_wait(&self.inner.cvar, &mut state),
+            }
+        }
+    }
+}
+
+impl<T> Receiver<Result<T, Error>> {
+    /// Waits for the result of a window thread's setup, turning a timeout, an early exit or a panic into an [`Error`].
+    ///
+    /// On success, the thread's handle is passed back to be joined later.
+    pub(crate) fn recv_from_thread(
+        self,
+        thread: thread::JoinHandle<()>,
+        timeout: Option<Duration>,
+    ) -> Result<(T, thread::JoinHandle<()>), Error> {
+        match self.recv(timeout) {
+            Ok(result) => result.map(|value| (value, thread)),
+            // The thread is left detached, and cleans up after itself when it finds nobody waiting
+            Err(RecvError::Timeout) => Err(Error::new(
+                ErrorKind::TimedOut,
+                "timed out waiting for the window thread",
+            )),
+            // The sender is only dropped while the thread is exiting, so this won't block for long
+            Err(RecvError::Disconnected) => Err(match thread.join() {
+                Ok(()) => Error::new(ErrorKind::Other, "the window thread exited without responding"),
+                Err(payload) => Error::new(
+                    ErrorKind::Other,
+                    format!("the window thread panicked: {}", panic_message(&*payload)),
+                ),
+            }),
+        }
+    }
+
+    /// Waits for a window thread to answer a request, which it always does unless it's exiting.
+    #[cfg_attr(any(not(target_os = "linux"), feature = "headless"), allow(dead_code))]
+    pub(crate) fn recv_reply(self) -> Result<T, Error> {
+        self.recv(None).unwrap_or_else(|_| {
+            Err(Error::new(
+                ErrorKind::Other,
+                "the window thread exited without responding",
+            ))
+        })
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut state = mutex_lock(&self.inner.state);
+        let value = mem::replace(&mut *state, OneshotState::Disconnected);
+
+        // Anything that arrived too late is dropped outside of the lock, as it might take a while
+        mem::drop(state);
+        mem::drop(value);
+    }
+}
+
+/// Extracts the message from a panic payload, which is almost always a string.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&'static str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.as_str()
+    } else {
+        "Box<dyn Any>"
+    }
+}
+
 pub(crate) use sync::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oneshot_reports_panics() {
+        let (sender, receiver) = oneshot::<Result<(), Error>>();
+        let thread = thread::spawn(move || {
+            let _sender = sender;
+            panic!("oh no");
+        });
+        let err = receiver.recv_from_thread(thread, None).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Other);
+        assert!(err.message().ends_with("oh no"));
+    }
+
+    #[test]
+    fn oneshot_times_out() {
+        let (sender, receiver) = oneshot::<()>();
+        assert_eq!(receiver.recv(Some(Duration::from_millis(10))), Err(RecvError::Timeout));
+        assert_eq!(sender.send(()), Err(()));
+    }
+}
@@ -1,8 +1,57 @@
-use crate::{error::Error, platform};
-use std::borrow::Cow;
+use crate::{
+    clipboard::{Clipboard, Selection},
+    cursor::Cursor,
+    dpi::{LogicalSize, PhysicalPosition, PhysicalSize, Position, Size},
+    drag::{DragData, DragFormat, DragIcon, DropAction},
+    error::Error,
+    event::{Event, EventLoopProxy, Events},
+    icon::Icon,
+    monitor::{Monitor, VideoMode},
+    platform,
+};
+use std::{
+    any::Any,
+    borrow::Cow,
+    collections::VecDeque,
+    mem,
+    time::{Duration, Instant},
+};
 
+/// How the cursor is held onto by a window, see [`Window::set_cursor_grab`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CursorGrab {
+    /// The cursor moves freely, which is the default.
+    None,
+
+    /// The cursor can't leave the client area.
+    Confined,
+
+    /// The cursor stays where it is, which is what first-person cameras want along with [`Event::RawMouseMotion`].
+    /// If it's not over the client area, it's moved to the middle of it.
+    Locked,
+}
+
+/// How a window covers a monitor, see [`Window::set_fullscreen`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fullscreen {
+    /// Covers a monitor without touching its video mode. `None` means the one the window is on.
+    Borderless(Option<Monitor>),
+
+    /// Switches a monitor to one of its video modes, then covers it.
+    ///
+    /// Wayland doesn't let clients change video modes, so there this is the same as borderless.
+    Exclusive(Monitor, VideoMode),
+}
+
+#[derive(Clone)]
 pub struct WindowBuilder {
     pub(crate) class_name: Cow<'static, str>,
+    pub(crate) creation_timeout: Option<Duration>,
+    pub(crate) fullscreen: Option<Fullscreen>,
+    pub(crate) icon: Option<Icon>,
+    pub(crate) ime: bool,
+    pub(crate) inner_size: Size,
+    pub(crate) position: Option<Position>,
     pub(crate) title: Cow<'static, str>,
 
     #[cfg(target_os = "windows")]
@@ -13,6 +62,12 @@ impl WindowBuilder {
     const fn new() -> Self {
         Self {
             class_name: Cow::Borrowed("ramen_window_class"),
+            creation_timeout: None,
+            fullscreen: None,
+            icon: None,
+            ime: true,
+            inner_size: Size::Logical(LogicalSize::new(800.0, 608.0)),
+            position: None,
             title: Cow::Borrowed("a nice window"),
 
             #[cfg(target_os = "windows")]
@@ -21,7 +76,10 @@ impl WindowBuilder {
     }
 
     pub fn build(&self) -> Result<Window, Error> {
-        platform::imp::WindowImpl::new(self).map(|imp| Window { imp })
+        platform::imp::WindowImpl::new(self).map(|imp| Window {
+            imp,
+            event_buffer: VecDeque::new(),
+        })
     }
 
     pub fn class_name(&mut self, class_name: impl Into<Cow<'static, str>>) -> &mut Self {
@@ -29,18 +87,311 @@ impl WindowBuilder {
         self
     }
 
+    /// Sets how long [`build`](Self::build) may wait for the window to be created before giving up.
+    ///
+    /// By default there's no limit. A window that shows up after the timeout is destroyed.
+    pub fn creation_timeout(&mut self, timeout: Option<Duration>) -> &mut Self {
+        self.creation_timeout = timeout;
+        self
+    }
+
+    /// Sets whether the window starts out fullscreen, see [`Window::set_fullscreen`].
+    ///
+    /// The default is `None`, which is a normal window.
+    pub fn fullscreen(&mut self, fullscreen: Option<Fullscreen>) -> &mut Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+
+    /// Sets the window's icon, see [`Window::set_icon`].
+    ///
+    /// The default is `None`, which leaves it up to the system.
+    pub fn icon(&mut self, icon: Option<Icon>) -> &mut Self {
+        self.icon = icon;
+        self
+    }
+
+    /// Sets whether input methods (IMEs) are used for typing text into the window.
+    ///
+    /// The default is `true`, so [`Event::Preedit`] arrives while text is being composed.
+    /// Without one, [`Event::Text`] only has what the keyboard types directly.
+    pub fn ime(&mut self, enabled: bool) -> &mut Self {
+        self.ime = enabled;
+        self
+    }
+
+    /// Sets the initial size of the client area, which is the window minus any decorations.
+    ///
+    /// The default is 800x608 logical units.
+    pub fn inner_size(&mut self, size: impl Into<Size>) -> &mut Self {
+        self.inner_size = size.into();
+        self
+    }
+
+    /// Sets the initial position of the window's top-left corner, including decorations.
+    ///
+    /// By default the system picks one. This is ignored on Wayland.
+    pub fn position(&mut self, position: impl Into<Position>) -> &mut Self {
+        self.position = Some(position.into());
+        self
+    }
+
     pub fn title(&mut self, title: impl Into<Cow<'static, str>>) -> &mut Self {
         self.title = title.into();
         self
     }
 }
 
+/// A window, which stays open for as long as it's owned.
+///
+/// Closing it is up to the owner: anything else trying to close it sends [`Event::CloseRequested`] instead.
 pub struct Window {
-    imp: platform::imp::WindowImpl,
+    pub(crate) imp: platform::imp::WindowImpl,
+
+    // Events are moved here in bulk, so the window thread is never held up by the user iterating them
+    event_buffer: VecDeque<Event>,
 }
 
 impl Window {
     pub const fn builder() -> WindowBuilder {
         WindowBuilder::new()
     }
+
+    /// Makes a proxy for sending messages of type `T` to the window from other threads, see [`EventLoopProxy`].
+    ///
+    /// Proxies can be made for any number of types, and each arrives as [`Event::User`].
+    pub fn create_proxy<T: Any + Send>(&self) -> EventLoopProxy<T> {
+        EventLoopProxy::new(self.imp.events())
+    }
+
+    /// Closes the window, which is the same as dropping it.
+    ///
+    /// This blocks until the window is destroyed and its thread has exited.
+    pub fn close(self) {
+        mem::drop(self);
+    }
+
+    /// The size of the client area, which is the window minus any decorations.
+    pub fn inner_size(&self) -> PhysicalSize {
+        self.imp.inner_size()
+    }
+
+    /// The size of the window including any decorations.
+    pub fn outer_size(&self) -> PhysicalSize {
+        self.imp.outer_size()
+    }
+
+    /// The position of the window's top-left corner (including decorations) on the desktop.
+    ///
+    /// This is `None` on Wayland, where windows can't know where they are.
+    pub fn position(&self) -> Option<PhysicalPosition> {
+        self.imp.position()
+    }
+
+    /// The system clipboard, for copying and pasting.
+    pub fn clipboard(&self) -> Clipboard<'_> {
+        Clipboard::new(self, Selection::Clipboard)
+    }
+
+    /// X11's primary selection, which is whatever text was last selected, and is pasted with the middle mouse button.
+    ///
+    /// Apps are expected to put text on it whenever the user selects some, and to paste from it on a middle click.
+    /// Nothing else has one, so everything on it fails with [`ErrorKind::Unsupported`](crate::error::ErrorKind::Unsupported) elsewhere.
+    pub fn primary_selection(&self) -> Clipboard<'_> {
+        Clipboard::new(self, Selection::Primary)
+    }
+
+    /// Accepts what's being dragged over the window, to be dropped as one of its formats with the given action,
+    /// or rejects it with `None`.
+    ///
+    /// This holds wherever it's dragged until it's called again, and goes back to `None` with every
+    /// [`Event::DragEntered`], so it's usually called in response to that and
+    /// [`Event::DragMoved`]. The app it's dragged from shows whether it would be
+    /// accepted, usually with the cursor.
+    pub fn accept_drag(&self, accept: Option<(DragFormat, DropAction)>) {
+        self.imp.accept_drag(accept)
+    }
+
+    /// Starts dragging something out of the window, showing `icon` under the cursor.
+    ///
+    /// It has to be called while a mouse button is held, usually once the cursor has moved a little after being
+    /// pressed on whatever's being dragged, and it's dropped when the button's released. How that went comes back
+    /// as [`Event::DragFinished`], unless this fails. Holding shift on X11 asks for it to be moved rather than
+    /// copied, which is up to the compositor on Wayland.
+    ///
+    /// Fails with [`ErrorKind::Unsupported`](crate::error::ErrorKind::Unsupported) on Win32.
+    pub fn start_drag(&self, data: DragData, icon: DragIcon) -> Result<(), Error> {
+        self.imp.start_drag(data, icon)
+    }
+
+    /// Every monitor connected to the system.
+    pub fn available_monitors(&self) -> Vec<Monitor> {
+        self.imp.available_monitors()
+    }
+
+    /// The monitor the window is on, or mostly on if it spans several.
+    ///
+    /// This is `None` if the system doesn't know, such as on Wayland before the window is first shown.
+    pub fn current_monitor(&self) -> Option<Monitor> {
+        self.imp.current_monitor()
+    }
+
+    /// The fullscreen state last set with [`set_fullscreen`](Self::set_fullscreen) or the builder.
+    pub fn fullscreen(&self) -> Option<Fullscreen> {
+        self.imp.fullscreen()
+    }
+
+    /// The ratio of physical pixels to logical units, where `1.0` is 96 DPI.
+    ///
+    /// Changes to this arrive as [`Event::ScaleFactorChanged`].
+    pub fn scale_factor(&self) -> f64 {
+        self.imp.scale_factor()
+    }
+
+    /// Sets what the cursor looks like while it's over the client area.
+    ///
+    /// This is [`CursorShape::Arrow`](crate::cursor::CursorShape::Arrow) to begin with.
+    pub fn set_cursor(&self, cursor: impl Into<Cursor>) {
+        self.imp.set_cursor(cursor.into())
+    }
+
+    /// Moves the cursor to a point relative to the client area.
+    ///
+    /// Wayland doesn't let windows move the cursor, so this does nothing there.
+    pub fn set_cursor_position(&self, position: impl Into<Position>) {
+        self.imp
+            .set_cursor_position(position.into().to_physical(self.scale_factor()))
+    }
+
+    /// Hides or shows the cursor while it's over the client area.
+    pub fn set_cursor_visible(&self, visible: bool) {
+        self.imp.set_cursor_visible(visible)
+    }
+
+    /// Confines or locks the cursor to the window, or lets it go again with [`CursorGrab::None`].
+    ///
+    /// This only holds while the window is focused, and comes back when it gets focus back.
+    /// On Wayland, it only kicks in once the cursor is over the window, and only if the compositor allows it.
+    pub fn set_cursor_grab(&self, grab: CursorGrab) {
+        self.imp.set_cursor_grab(grab)
+    }
+
+    /// Makes the window fullscreen, or a normal window again with `None`.
+    ///
+    /// The window's previous size and position come back when it leaves fullscreen.
+    /// An exclusive video mode is undone whenever the window loses focus or is dropped, and redone when
+    /// it gets focus back. If the mode can't be set, the window is borderless fullscreen instead.
+    pub fn set_fullscreen(&self, fullscreen: Option<Fullscreen>) {
+        self.imp.set_fullscreen(fullscreen)
+    }
+
+    /// Sets the icon shown in the title bar, task bar and so on, or goes back to the system's default with `None`.
+    ///
+    /// Each place picks whichever of the icon's images suits its size best.
+    /// On Wayland, this needs the compositor to support `xdg-toplevel-icon-v1`, which only takes square images.
+    pub fn set_icon(&self, icon: Option<Icon>) {
+        self.imp.set_icon(icon)
+    }
+
+    /// Sets the area where text is being entered, in client area coordinates, so an input method
+    /// can show its candidate window next to it rather than on top of it.
+    ///
+    /// This does nothing if the window was built without an IME.
+    pub fn set_ime_cursor_area(&self, position: impl Into<Position>, size: impl Into<Size>) {
+        let scale_factor = self.scale_factor();
+        self.imp.set_ime_cursor_area(
+            position.into().to_physical(scale_factor),
+            size.into().to_physical(scale_factor),
+        )
+    }
+
+    /// Requests a new size for the client area, which arrives as [`Event::Resized`] if it's granted.
+    pub fn set_inner_size(&self, size: impl Into<Size>) {
+        self.imp.set_inner_size(size.into().to_physical(self.scale_factor()))
+    }
+
+    /// Requests a new size for the window including decorations, see [`set_inner_size`](Self::set_inner_size).
+    pub fn set_outer_size(&self, size: impl Into<Size>) {
+        self.imp.set_outer_size(size.into().to_physical(self.scale_factor()))
+    }
+
+    /// Requests that the window be moved, which arrives as [`Event::Moved`] if it's granted.
+    ///
+    /// This does nothing on Wayland.
+    pub fn set_position(&self, position: impl Into<Position>) {
+        self.imp.set_position(position.into().to_physical(self.scale_factor()))
+    }
+
+    /// Sets the text in the window's title bar, replacing what was given to the builder.
+    pub fn set_title(&self, title: &str) {
+        self.imp.set_title(title)
+    }
+
+    /// Hides or shows the window, which starts out shown.
+    ///
+    /// Wayland has no hiding windows short of destroying them, so this does nothing there.
+    pub fn set_visible(&self, visible: bool) {
+        self.imp.set_visible(visible)
+    }
+
+    /// Returns every event that has arrived since the last call, without blocking.
+    pub fn poll_events(&mut self) -> Events<'_> {
+        self.imp.events().poll(&mut self.event_buffer)
+    }
+
+    /// Returns every event that has arrived since the last call, blocking until there's at least one.
+    pub fn wait_events(&mut self) -> Events<'_> {
+        self.imp.events().wait(&mut self.event_buffer, None)
+    }
+
+    /// Returns every event that has arrived since the last call, blocking until there's at least one or `deadline`
+    /// has passed, so there might be none.
+    ///
+    /// The thread sleeps while it waits, so this is fine for waiting on the next frame or the like.
+    pub fn wait_events_until(&mut self, deadline: Instant) -> Events<'_> {
+        self.imp.events().wait(&mut self.event_buffer, Some(deadline))
+    }
+
+    /// Returns every event that has arrived since the last call, blocking until there's at least one or `timeout`
+    /// has passed, so there might be none.
+    pub fn wait_events_timeout(&mut self, timeout: Duration) -> Events<'_> {
+        // A deadline too far out to represent may as well be no deadline
+        let deadline = Instant::now().checked_add(timeout);
+        self.imp.events().wait(&mut self.event_buffer, deadline)
+    }
+
+    /// Sends [`Event::Timer`] with `id` once `deadline` has passed, which could be straight away.
+    ///
+    /// Timers go off while events are being polled or waited for, waking up [`wait_events`](Self::wait_events)
+    /// and its variants, so they're late if nothing's picking up events at the time. Timers for the same time go
+    /// off in the order they were set, and ids needn't be unique.
+    pub fn schedule_timer(&self, deadline: Instant, id: u64) {
+        self.imp.events().schedule_timer(deadline, id)
+    }
+}
+
+// The handles are borrowed from the window, which stays open until it's dropped, so they can't outlive it
+
+#[cfg(feature = "raw-window-handle")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "raw-window-handle")))]
+impl raw_window_handle::HasWindowHandle for Window {
+    /// The window's `HWND` and `HINSTANCE` on Win32, its Xlib `Window` on X11, or its `wl_surface` on Wayland.
+    ///
+    /// This fails with [`HandleError::NotSupported`](raw_window_handle::HandleError::NotSupported) on the headless backend.
+    fn window_handle(&self) -> Result<raw_window_handle::WindowHandle<'_>, raw_window_handle::HandleError> {
+        let raw = self.imp.raw_window_handle()?;
+        Ok(unsafe { raw_window_handle::WindowHandle::borrow_raw(raw) })
+    }
+}
+
+#[cfg(feature = "raw-window-handle")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "raw-window-handle")))]
+impl raw_window_handle::HasDisplayHandle for Window {
+    /// The window's Xlib `Display` and screen on X11, or its `wl_display` on Wayland.
+    ///
+    /// Every window has its own connection to the display server, so this is only valid for this window.
+    fn display_handle(&self) -> Result<raw_window_handle::DisplayHandle<'_>, raw_window_handle::HandleError> {
+        let raw = self.imp.raw_display_handle()?;
+        Ok(unsafe { raw_window_handle::DisplayHandle::borrow_raw(raw) })
+    }
 }